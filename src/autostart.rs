@@ -0,0 +1,187 @@
+use log::info;
+use std::path::Path;
+use wallcore::settings::StartMode;
+
+/// Name the app is registered under with the platform
+const APP_ID: &str = "wall-a-bunga";
+
+/// Quotes an argument for the Exec key of a desktop entry
+fn desktop_quote(arg: &str) -> String {
+    match arg.contains(|c: char| c.is_whitespace() || "\"'\\$`".contains(c)) {
+        true => format!(
+            "\"{}\"",
+            arg.replace('\\', "\\\\\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "\\$")
+                .replace('`', "\\`")
+        ),
+        false => arg.to_string(),
+    }
+}
+
+/// https://specifications.freedesktop.org/autostart-spec/latest/
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn desktop_entry(exe: &Path, args: &[&str]) -> String {
+    let exec = std::iter::once(exe.to_string_lossy().to_string())
+        .chain(args.iter().map(|arg| arg.to_string()))
+        .map(|arg| desktop_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "[Desktop Entry]\nType=Application\nName=wall-a-bunga\nComment=Rotates wallpapers and downloads new ones\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        exec
+    )
+}
+
+/// https://developer.apple.com/library/archive/documentation/MacOSX/Conceptual/BPSystemStartup/Chapters/CreatingLaunchdJobs.html
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn launch_agent(exe: &Path, args: &[&str]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let arguments: String = std::iter::once(exe.to_string_lossy().to_string())
+        .chain(args.iter().map(|arg| arg.to_string()))
+        .map(|arg| format!("        <string>{}</string>\n", escape(&arg)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>com.akarras.{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        APP_ID, arguments
+    )
+}
+
+/// Writes `contents` to `path` when enabled, removes it otherwise
+#[cfg(not(windows))]
+fn write_or_remove(path: &Path, contents: Option<String>) -> Result<(), String> {
+    match contents {
+        Some(contents) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(path, contents).map_err(|e| e.to_string())
+        }
+        None => match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        },
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn register(exe: &Path, args: Option<&[&str]>) -> Result<(), String> {
+    let config = platform_dirs::AppDirs::new(None, false)
+        .map(|dirs| dirs.config_dir)
+        .ok_or("No config directory")?;
+    let path = config.join("autostart").join(format!("{}.desktop", APP_ID));
+    write_or_remove(&path, args.map(|args| desktop_entry(exe, args)))
+}
+
+#[cfg(target_os = "macos")]
+fn register(exe: &Path, args: Option<&[&str]>) -> Result<(), String> {
+    let home = std::env::var_os("HOME").ok_or("No home directory")?;
+    let path = Path::new(&home)
+        .join("Library/LaunchAgents")
+        .join(format!("com.akarras.{}.plist", APP_ID));
+    write_or_remove(&path, args.map(|args| launch_agent(exe, args)))
+}
+
+#[cfg(windows)]
+fn register(exe: &Path, args: Option<&[&str]>) -> Result<(), String> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::shared::minwindef::HKEY;
+    use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use winapi::um::winnt::{KEY_SET_VALUE, REG_SZ};
+    use winapi::um::winreg::{
+        RegCloseKey, RegDeleteValueW, RegOpenKeyExW, RegSetValueExW, HKEY_CURRENT_USER,
+    };
+
+    let wide = |s: &OsStr| s.encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+    let run_key = wide(OsStr::new(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\Run",
+    ));
+    let name = wide(OsStr::new(APP_ID));
+    let mut key: HKEY = ptr::null_mut();
+    // https://learn.microsoft.com/en-us/windows/win32/api/winreg/nf-winreg-regopenkeyexw
+    let status = unsafe {
+        RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            run_key.as_ptr(),
+            0,
+            KEY_SET_VALUE,
+            &mut key,
+        )
+    };
+    if status as u32 != ERROR_SUCCESS {
+        return Err(format!("Failed to open the Run key, error {}", status));
+    }
+    let status = match args {
+        Some(args) => {
+            let command = std::iter::once(format!("\"{}\"", exe.to_string_lossy()))
+                .chain(args.iter().map(|arg| arg.to_string()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let value = wide(OsStr::new(&command));
+            unsafe {
+                RegSetValueExW(
+                    key,
+                    name.as_ptr(),
+                    0,
+                    REG_SZ,
+                    value.as_ptr() as *const u8,
+                    (value.len() * 2) as u32,
+                )
+            }
+        }
+        None => match unsafe { RegDeleteValueW(key, name.as_ptr()) } as u32 {
+            // it wasn't registered
+            ERROR_FILE_NOT_FOUND => ERROR_SUCCESS as i32,
+            status => status as i32,
+        },
+    };
+    unsafe { RegCloseKey(key) };
+    match status as u32 {
+        ERROR_SUCCESS => Ok(()),
+        _ => Err(format!("Failed to update the Run key, error {}", status)),
+    }
+}
+
+/// Registers the app to start at login in `mode`, or unregisters it
+pub(crate) fn set_start_at_login(enabled: bool, mode: StartMode) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    info!("Start at login {} {:?} with {:?}", enabled, mode, exe);
+    register(&exe, enabled.then(|| mode.args()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::autostart::{desktop_entry, launch_agent};
+    use std::path::Path;
+
+    #[test]
+    fn login_entries() {
+        let entry = desktop_entry(
+            Path::new("/opt/wall a bunga/wall-a-bunga"),
+            &["--minimized"],
+        );
+        assert!(entry.contains("\nExec=\"/opt/wall a bunga/wall-a-bunga\" --minimized\n"));
+        let agent = launch_agent(Path::new("/Applications/wall-a-bunga"), &["--watch"]);
+        assert!(agent.contains("<string>/Applications/wall-a-bunga</string>"));
+        assert!(agent.contains("<string>--watch</string>"));
+    }
+}
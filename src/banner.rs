@@ -0,0 +1,129 @@
+use crate::gui::{Submenu, TabId, WallpaperMessage};
+use crate::style::{container_style, labeled, make_button, make_button_fa};
+use iced::widget::{Column, Container, Row, Text};
+use iced::{theme, Alignment, Length};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Severity {
+    /// News that isn't about anything going wrong
+    Info,
+    Warning,
+    Error,
+}
+
+/// What a banner is about, a newer banner about the same thing replaces the older one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum BannerSource {
+    /// The last search of the tab failed, it can be retried
+    Search(TabId),
+    Downloads,
+    Settings,
+    Wallpaper,
+    /// A newer version was released
+    Update,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Banner {
+    pub(crate) source: BannerSource,
+    pub(crate) severity: Severity,
+    pub(crate) message: String,
+}
+
+impl Banner {
+    fn can_retry(&self) -> bool {
+        matches!(self.source, BannerSource::Search(_))
+    }
+}
+
+/// Errors shown above the results until they're dismissed or stop applying
+#[derive(Debug, Default)]
+pub(crate) struct Banners {
+    banners: Vec<Banner>,
+}
+
+impl Banners {
+    pub(crate) fn show(&mut self, source: BannerSource, severity: Severity, message: String) {
+        self.dismiss(source);
+        self.banners.push(Banner {
+            source,
+            severity,
+            message,
+        });
+    }
+
+    pub(crate) fn dismiss(&mut self, source: BannerSource) {
+        self.banners.retain(|banner| banner.source != source);
+    }
+
+    pub(crate) fn get(&self, source: BannerSource) -> Option<&Banner> {
+        self.banners.iter().find(|banner| banner.source == source)
+    }
+
+    pub(crate) fn view(&self) -> Column<'_, WallpaperMessage> {
+        self.banners
+            .iter()
+            .fold(Column::new().spacing(5), |column, banner| {
+                let row = Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::new(&banner.message).width(Length::Fill));
+                let row = match banner.source {
+                    _ if banner.can_retry() => row.push(
+                        make_button("retry").on_press(WallpaperMessage::RetryBanner(banner.source)),
+                    ),
+                    BannerSource::Update => row.push(
+                        make_button("what's new")
+                            .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Update)),
+                    ),
+                    _ => row,
+                };
+                let row = row.push(labeled(
+                    make_button_fa("", "times")
+                        .on_press(WallpaperMessage::DismissBanner(banner.source)),
+                    "Dismiss",
+                ));
+                column.push(
+                    Container::new(row)
+                        .padding([5, 10])
+                        .width(Length::Fill)
+                        .style(theme::Container::Custom(Box::new(container_style::Banner(
+                            banner.severity,
+                        )))),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::banner::{BannerSource, Banners, Severity};
+
+    #[test]
+    fn replaces_and_dismisses_banners() {
+        let mut banners = Banners::default();
+        banners.show(
+            BannerSource::Search(0),
+            Severity::Error,
+            "timed out".to_string(),
+        );
+        banners.show(
+            BannerSource::Search(1),
+            Severity::Error,
+            "timed out".to_string(),
+        );
+        banners.show(
+            BannerSource::Search(0),
+            Severity::Warning,
+            "rate limited".to_string(),
+        );
+        assert_eq!(banners.banners.len(), 2);
+        let banner = banners.get(BannerSource::Search(0)).unwrap();
+        assert_eq!(banner.severity, Severity::Warning);
+        assert_eq!(banner.message, "rate limited");
+        assert!(banner.can_retry());
+        banners.dismiss(BannerSource::Search(0));
+        assert!(banners.get(BannerSource::Search(0)).is_none());
+        assert!(banners.get(BannerSource::Search(1)).is_some());
+    }
+}
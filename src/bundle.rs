@@ -0,0 +1,157 @@
+use crate::watch::WatchList;
+use log::info;
+use native_dialog::FileDialog;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use wallcore::settings::SavedSettings;
+
+/// Everything that configures the app in one file, so it can be moved to another machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SettingsBundle {
+    pub(crate) settings: SavedSettings,
+    /// Watched searches
+    #[serde(default)]
+    pub(crate) watches: WatchList,
+}
+
+impl SettingsBundle {
+    pub(crate) fn new(settings: &SavedSettings, watches: &WatchList) -> Self {
+        let mut settings = settings.clone();
+        // belongs to the screen it was saved on
+        settings.window = None;
+        // keys stay in this machine's credential store, a bundle is easily shared
+        settings.api_key = None;
+        settings.unsplash_access_key = None;
+        for profile in &mut settings.profiles {
+            profile.api_key = None;
+        }
+        Self {
+            settings,
+            watches: watches.clone(),
+        }
+    }
+
+    fn is_json(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+    }
+
+    /// JSON when the file ends in `.json`, TOML otherwise
+    fn to_text(&self, path: &Path) -> Result<String, String> {
+        match SettingsBundle::is_json(path) {
+            true => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            false => toml::to_string_pretty(self).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn from_text(text: &str, path: &Path) -> Result<Self, String> {
+        let bundle: SettingsBundle = match SettingsBundle::is_json(path) {
+            true => serde_json::from_str(text).map_err(|e| e.to_string())?,
+            false => toml::from_str(text).map_err(|e| e.to_string())?,
+        };
+        Ok(Self {
+            settings: bundle.settings.migrate(),
+            ..bundle
+        })
+    }
+}
+
+/// Bundles come without keys, so the imported settings keep the ones of this machine, profiles
+/// by their name
+pub(crate) fn keep_keys(settings: &mut SavedSettings, current: &SavedSettings) {
+    settings.api_key = settings.api_key.take().or(current.api_key.clone());
+    settings.unsplash_access_key = settings
+        .unsplash_access_key
+        .take()
+        .or(current.unsplash_access_key.clone());
+    for profile in &mut settings.profiles {
+        let key = current
+            .profiles
+            .iter()
+            .find(|current| current.name == profile.name)
+            .and_then(|current| current.api_key.clone());
+        profile.api_key = profile.api_key.take().or(key);
+    }
+}
+
+/// Asks where to save the bundle and writes it there, None when the dialog was cancelled
+pub(crate) async fn export_settings(bundle: SettingsBundle) -> Result<Option<PathBuf>, String> {
+    let path = FileDialog::new()
+        .set_filename("wall-a-bunga.toml")
+        .add_filter("TOML", &["toml"])
+        .add_filter("JSON", &["json"])
+        .show_save_single_file()
+        .map_err(|e| e.to_string())?;
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let text = bundle.to_text(&path)?;
+    tokio::fs::write(&path, text)
+        .await
+        .map_err(|e| format!("Failed to write {:?} {}", path, e))?;
+    info!("Exported settings to {:?}", path);
+    Ok(Some(path))
+}
+
+/// Asks for an exported bundle and reads it, None when the dialog was cancelled
+pub(crate) async fn import_settings() -> Result<Option<SettingsBundle>, String> {
+    let path = FileDialog::new()
+        .add_filter("Settings", &["toml", "json"])
+        .show_open_single_file()
+        .map_err(|e| e.to_string())?;
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {:?} {}", path, e))?;
+    let bundle = SettingsBundle::from_text(&text, &path)
+        .map_err(|e| format!("{:?} isn't a settings export, {}", path, e))?;
+    info!("Imported settings from {:?}", path);
+    Ok(Some(bundle))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bundle::{keep_keys, SettingsBundle};
+    use crate::watch::WatchList;
+    use std::path::Path;
+    use wallapi::types::SearchOptions;
+    use wallcore::settings::SavedSettings;
+
+    #[test]
+    fn bundle_round_trip() {
+        let mut settings: SavedSettings = toml::from_str(
+            "save_directory = \"/wallpapers\"\n[window]\nwidth = 1800\nheight = 800\n",
+        )
+        .unwrap();
+        assert!(settings.window.is_some());
+        settings.api_key = Some("secret".to_string());
+        assert!(settings.add_profile("home"));
+        let mut watches = WatchList::default();
+        watches.add(&SearchOptions {
+            query: Some("cats".to_string()),
+            ..Default::default()
+        });
+        let bundle = SettingsBundle::new(&settings, &watches);
+        for file in ["export.toml", "export.json"] {
+            let path = Path::new(file);
+            let text = bundle.to_text(path).unwrap();
+            let imported = SettingsBundle::from_text(&text, path).unwrap();
+            assert_eq!(
+                imported.settings.save_directory.as_deref(),
+                Some("/wallpapers")
+            );
+            assert!(imported.settings.window.is_none());
+            assert_eq!(imported.watches.watches.len(), 1);
+            assert!(!text.contains("secret"));
+            let mut imported = imported.settings;
+            keep_keys(&mut imported, &settings);
+            assert_eq!(imported.api_key.as_deref(), Some("secret"));
+            assert_eq!(imported.profiles[0].api_key.as_deref(), Some("secret"));
+        }
+        assert!(SettingsBundle::from_text("{}", Path::new("export.json")).is_err());
+    }
+}
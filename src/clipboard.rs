@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use arboard::{Clipboard, ImageData};
+use iced::advanced::image::Data;
+use iced::widget::image;
+use std::borrow::Cow;
+use std::sync::Mutex;
+use tokio::task::spawn_blocking;
+
+/// Kept for as long as the app runs. On Linux the image is only on the clipboard while the
+/// clipboard that set it is alive, other apps ask it for the image when pasting.
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+/// Places the decoded image on the system clipboard
+pub(crate) async fn copy_image(handle: image::Handle) -> Result<()> {
+    spawn_blocking(move || {
+        let (width, height, pixels) = match handle.data() {
+            Data::Rgba {
+                width,
+                height,
+                pixels,
+            } => (*width, *height, pixels.to_vec()),
+            // iced decodes these itself, so we have to do it here as well
+            Data::Bytes(bytes) => {
+                let image = image_rs::load_from_memory(bytes)?.to_rgba8();
+                (image.width(), image.height(), image.into_raw())
+            }
+            Data::Path(path) => {
+                let image = image_rs::open(path)?.to_rgba8();
+                (image.width(), image.height(), image.into_raw())
+            }
+        };
+        let mut kept = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+        let mut clipboard = match kept.take() {
+            Some(clipboard) => clipboard,
+            None => Clipboard::new()?,
+        };
+        let result = clipboard.set_image(ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::Owned(pixels),
+        });
+        *kept = Some(clipboard);
+        Ok(result?)
+    })
+    .await
+    .map_err(|e| anyhow!("Copy task failed {:?}", e))?
+}
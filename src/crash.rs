@@ -0,0 +1,139 @@
+use log::error;
+use native_dialog::{MessageDialog, MessageType};
+use platform_dirs::AppDirs;
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use wallcore::utils::format_utc;
+
+/// What the app was doing, written into the report when it crashes. Only counts and modes go in
+/// here, never keys or anything else from the settings.
+static STATE: RwLock<String> = RwLock::new(String::new());
+
+/// Where crash reports are written, like `~/.local/share/wall-a-bunga/crashes`
+fn crash_dir() -> Option<PathBuf> {
+    AppDirs::new(Some("wall-a-bunga"), true).map(|dirs| dirs.data_dir.join("crashes"))
+}
+
+/// Holds the path of the report that hasn't been offered yet
+fn pending_path(dir: &Path) -> PathBuf {
+    dir.join("pending")
+}
+
+pub(crate) fn set_state(state: String) {
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = state;
+}
+
+fn report(message: &str, location: &str, backtrace: &Backtrace, state: &str) -> String {
+    format!(
+        "wall-a-bunga {} crashed at {} on {} {}\n\n\
+         thread '{}' panicked at {}\n{}\n\n\
+         What it was doing\n{}\n\n\
+         Backtrace\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        format_utc(SystemTime::now()),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::thread::current().name().unwrap_or("unnamed"),
+        location,
+        message,
+        state,
+        backtrace
+    )
+}
+
+/// Writes the report and marks it as the one to offer on the next launch
+fn write_report(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let path = dir.join(format!("crash-{}.txt", now.as_secs()));
+    std::fs::write(&path, report)?;
+    std::fs::write(pending_path(dir), path.to_string_lossy().as_bytes())?;
+    Ok(path)
+}
+
+/// Writes a crash report whenever the app panics, so it doesn't just disappear. The default hook
+/// still prints the panic to the console.
+pub(crate) fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let dir = match crash_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let message = match (
+            info.payload().downcast_ref::<&str>(),
+            info.payload().downcast_ref::<String>(),
+        ) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "no message".to_string(),
+        };
+        let location = info
+            .location()
+            .map(|location| location.to_string())
+            .unwrap_or_default();
+        let state = STATE.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let report = report(&message, &location, &Backtrace::force_capture(), &state);
+        match write_report(&dir, &report) {
+            Ok(path) => error!("Crashed, the report is in {:?}", path),
+            Err(e) => eprintln!("Failed to write the crash report {}", e),
+        }
+    }));
+}
+
+/// The report of the last crash if it hasn't been offered yet, it's only offered once
+fn take_pending_report(dir: &Path) -> Option<PathBuf> {
+    let pending = pending_path(dir);
+    let report = PathBuf::from(std::fs::read_to_string(&pending).ok()?);
+    if let Err(e) = std::fs::remove_file(&pending) {
+        error!("Failed to remove {:?} {}", pending, e);
+    }
+    report.exists().then_some(report)
+}
+
+/// Asks whether to open the report when the app crashed the last time it ran
+pub(crate) fn offer_last_report() {
+    let report = match crash_dir().and_then(|dir| take_pending_report(&dir)) {
+        Some(report) => report,
+        None => return,
+    };
+    let text = format!(
+        "wall-a-bunga closed unexpectedly last time. A report of what happened was saved to\n{}\n\nOpen it?",
+        report.display()
+    );
+    let open = MessageDialog::new()
+        .set_type(MessageType::Warning)
+        .set_title("wall-a-bunga crashed")
+        .set_text(&text)
+        .show_confirm();
+    match open {
+        Ok(true) => {
+            if let Err(e) = open::that_detached(&report) {
+                error!("Failed to open {:?} {:?}", report, e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => error!("Failed to ask about the crash report {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crash::{take_pending_report, write_report};
+
+    #[test]
+    fn offers_reports_once() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-crash-{}", std::process::id()));
+        assert_eq!(take_pending_report(&dir), None);
+        let path = write_report(&dir, "panicked").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "panicked");
+        assert_eq!(take_pending_report(&dir), Some(path));
+        assert_eq!(take_pending_report(&dir), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
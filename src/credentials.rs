@@ -0,0 +1,94 @@
+use keyring::Entry;
+use log::error;
+use wallcore::settings::SavedSettings;
+use wallcore::source::SourceKind;
+
+/// Keys are filed under this in the platform's credential store, by the source they're for
+const SERVICE: &str = "wall-a-bunga";
+
+/// The setting holding the key of the source, None for sources that don't need one
+fn key_mut(settings: &mut SavedSettings, kind: SourceKind) -> Option<&mut Option<String>> {
+    match kind {
+        SourceKind::Wallhaven => Some(&mut settings.api_key),
+        SourceKind::Unsplash => Some(&mut settings.unsplash_access_key),
+        SourceKind::Reddit | SourceKind::Folder => None,
+    }
+}
+
+/// Wallhaven keys belong to a profile, so each profile has an account of its own. The other keys
+/// are shared by every profile.
+fn account(kind: SourceKind, profile: Option<&str>) -> String {
+    match (kind, profile) {
+        (SourceKind::Wallhaven, Some(profile)) => format!("{}/{}", kind, profile),
+        _ => kind.to_string(),
+    }
+}
+
+fn read(account: &str) -> Option<String> {
+    match Entry::new(SERVICE, account).and_then(|entry| entry.get_password()) {
+        Ok(stored) => Some(stored),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            error!("Failed to read {} from the credential store {}", account, e);
+            None
+        }
+    }
+}
+
+/// Moves the key into the store, it's kept where it was when the store can't take it
+fn write(account: &str, key: &mut Option<String>) {
+    let result = Entry::new(SERVICE, account).and_then(|entry| match key.as_deref() {
+        Some(value) => entry.set_password(value),
+        // a key that was cleared is forgotten by the store too
+        None => match entry.delete_password() {
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        },
+    });
+    match result {
+        Ok(()) => *key = None,
+        Err(e) => error!(
+            "Failed to store {}, it's kept in the settings {}",
+            account, e
+        ),
+    }
+}
+
+/// Fills in the keys that aren't in the settings file from the credential store
+pub(crate) fn load_keys(mut settings: SavedSettings) -> SavedSettings {
+    let active = settings.profile.clone();
+    for kind in SourceKind::LIST {
+        if let Some(key) = key_mut(&mut settings, kind).filter(|key| key.is_none()) {
+            *key = read(&account(kind, active.as_deref()));
+        }
+    }
+    for profile in &mut settings.profiles {
+        if profile.api_key.is_none() {
+            profile.api_key = read(&account(SourceKind::Wallhaven, Some(&profile.name)));
+        }
+    }
+    settings
+}
+
+/// Moves the keys, the profiles' too, into the credential store so neither the settings file nor
+/// an exported bundle has them. Keys that can't be stored, like on systems without a keyring,
+/// stay in the settings instead of being lost.
+pub(crate) fn store_keys(mut settings: SavedSettings) -> SavedSettings {
+    let active = settings.profile.clone();
+    for profile in &mut settings.profiles {
+        match Some(&profile.name) == active.as_ref() {
+            // the active profile's key is the one in the settings, stored below
+            true => profile.api_key = None,
+            false => write(
+                &account(SourceKind::Wallhaven, Some(&profile.name)),
+                &mut profile.api_key,
+            ),
+        }
+    }
+    for kind in SourceKind::LIST {
+        if let Some(key) = key_mut(&mut settings, kind) {
+            write(&account(kind, active.as_deref()), key);
+        }
+    }
+    settings
+}
@@ -0,0 +1,202 @@
+use crate::library::Library;
+use crate::wallpaper::set_wallpaper;
+use crate::watch::download_wallpaper;
+use log::info;
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use wallapi::types::{ListingData, SearchOptions, Sorting, TopRange};
+use wallapi::WallhavenClient;
+use wallcore::files::save_json;
+use wallcore::history::unix_now;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A wallpaper that was set as the wallpaper of the day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DailyPick {
+    pub(crate) listing: ListingData,
+    pub(crate) path: PathBuf,
+    /// Seconds since the unix epoch
+    pub(crate) picked_at: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct DailyWallpaper {
+    /// Filters the toplist is searched with, never contains the api key. Everything when None.
+    pub(crate) options: Option<SearchOptions>,
+    /// Newest first
+    pub(crate) picks: Vec<DailyPick>,
+    /// Ids of wallpapers that were skipped and won't be picked again
+    pub(crate) skipped: Vec<String>,
+}
+
+impl DailyWallpaper {
+    /// Picks from the toplist of the past day using the filters of `options`
+    pub(crate) fn set_filters(&mut self, options: &SearchOptions) {
+        self.options = Some(SearchOptions {
+            api_key: None,
+            page: None,
+            seed: None,
+            query: options.query.clone().filter(|query| !query.is_empty()),
+            ..options.clone()
+        });
+    }
+
+    fn search_options(&self, api_key: Option<String>) -> SearchOptions {
+        SearchOptions {
+            api_key,
+            sorting: Some(Sorting::TopList),
+            top_range: Some(TopRange::OneDay),
+            sorting_order: None,
+            ..self.options.clone().unwrap_or_default()
+        }
+    }
+
+    pub(crate) fn query(&self) -> &str {
+        match self.options.as_ref().and_then(|o| o.query.as_deref()) {
+            Some(query) if !query.is_empty() => query,
+            _ => "everything",
+        }
+    }
+
+    /// True when nothing has been picked yet on the day of `now`, days go by UTC
+    pub(crate) fn is_due(&self, now: u64) -> bool {
+        match self.picks.first() {
+            Some(pick) => pick.picked_at / SECONDS_PER_DAY != now / SECONDS_PER_DAY,
+            None => true,
+        }
+    }
+
+    /// The highest ranked wallpaper that hasn't been picked or skipped before
+    fn pick<'a>(&self, listings: &'a [ListingData]) -> Option<&'a ListingData> {
+        listings.iter().find(|listing| {
+            !self.skipped.contains(&listing.id)
+                && !self.picks.iter().any(|pick| pick.listing.id == listing.id)
+        })
+    }
+
+    /// Drops today's pick and makes sure it's never picked again, returns its id
+    pub(crate) fn skip(&mut self) -> Option<String> {
+        if self.picks.is_empty() {
+            return None;
+        }
+        let pick = self.picks.remove(0);
+        self.skipped.push(pick.listing.id.clone());
+        Some(pick.listing.id)
+    }
+
+    pub(crate) async fn save_daily(daily: DailyWallpaper) -> anyhow::Result<()> {
+        let daily_file = save_json("daily.json", &daily).await?;
+        info!("Saved daily picks to {:?}", daily_file);
+        Ok(())
+    }
+
+    // Function left sync intentionally, same as the settings
+    pub(crate) fn load_daily() -> Option<Self> {
+        let app_dirs = AppDirs::new(Some("wall-a-bunga"), true).unwrap();
+        let daily_file = app_dirs.config_dir.join("daily.json");
+        let json = std::fs::read_to_string(daily_file.clone()).ok()?;
+        info!("Loaded daily picks from {:?}", daily_file);
+        serde_json::from_str(&json).ok()
+    }
+}
+
+/// Downloads the top wallpaper of the past day that matches the filters and sets it as the
+/// desktop wallpaper. The pick isn't added to `daily`, that's up to the caller.
+pub(crate) async fn pick_daily_wallpaper(
+    client: WallhavenClient,
+    daily: DailyWallpaper,
+    api_key: Option<String>,
+    directory: PathBuf,
+    library: Library,
+    command: Option<String>,
+) -> Result<DailyPick, String> {
+    let response = client
+        .search(&daily.search_options(api_key))
+        .await
+        .map_err(|e| e.to_string())?;
+    let listings = response.data.ok_or_else(|| {
+        response
+            .error
+            .unwrap_or_else(|| "No error message".to_string())
+    })?;
+    let listing = daily
+        .pick(&listings)
+        .ok_or_else(|| format!("No new toplist wallpapers for {}", daily.query()))?
+        .clone();
+    download_wallpaper(client.http(), &listing, Path::new(&directory), &library).await?;
+    let path = library
+        .find(&listing.id)
+        .map_err(|e| e.to_string())?
+        .map(|entry| entry.path)
+        .ok_or_else(|| format!("{} wasn't added to the library", listing.id))?;
+    set_wallpaper(path.clone(), command).await?;
+    info!("Picked {} as the wallpaper of the day", listing.id);
+    Ok(DailyPick {
+        listing,
+        path,
+        picked_at: unix_now(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::daily::{DailyPick, DailyWallpaper, SECONDS_PER_DAY};
+    use std::path::PathBuf;
+    use wallapi::types::{ListingData, SearchOptions, Sorting, TopRange};
+
+    fn listing(id: &str) -> ListingData {
+        ListingData {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn picked(id: &str, picked_at: u64) -> DailyPick {
+        DailyPick {
+            listing: listing(id),
+            path: PathBuf::from(format!("{}.jpg", id)),
+            picked_at,
+        }
+    }
+
+    #[test]
+    fn picks_once_a_day() {
+        let noon = 20_000 * SECONDS_PER_DAY + SECONDS_PER_DAY / 2;
+        let mut daily = DailyWallpaper::default();
+        assert!(daily.is_due(noon));
+        daily.picks.insert(0, picked("a", noon));
+        assert!(!daily.is_due(noon + 60));
+        assert!(daily.is_due(noon + SECONDS_PER_DAY / 2));
+    }
+
+    #[test]
+    fn skips_picked_and_skipped_wallpapers() {
+        let listings = [listing("top"), listing("second"), listing("third")];
+        let mut daily = DailyWallpaper::default();
+        assert_eq!(daily.pick(&listings).unwrap().id, "top");
+        daily.picks.insert(0, picked("top", 0));
+        assert_eq!(daily.pick(&listings).unwrap().id, "second");
+        daily.picks.insert(0, picked("second", SECONDS_PER_DAY));
+        assert_eq!(daily.skip().as_deref(), Some("second"));
+        assert_eq!(daily.skipped, ["second"]);
+        assert_eq!(daily.pick(&listings).unwrap().id, "third");
+    }
+
+    #[test]
+    fn searches_the_days_toplist() {
+        let mut daily = DailyWallpaper::default();
+        daily.set_filters(&SearchOptions {
+            query: Some("cats".to_string()),
+            api_key: Some("secret".to_string()),
+            sorting: Some(Sorting::DateAdded),
+            ..Default::default()
+        });
+        assert_eq!(daily.options.as_ref().unwrap().api_key, None);
+        let options = daily.search_options(None);
+        assert_eq!(options.sorting, Some(Sorting::TopList));
+        assert_eq!(options.top_range, Some(TopRange::OneDay));
+        assert_eq!(daily.query(), "cats");
+    }
+}
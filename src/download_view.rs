@@ -0,0 +1,163 @@
+use crate::font_awesome::FAIcon;
+use crate::gui::WallpaperMessage;
+use crate::style::make_button_fa;
+use font_awesome_as_a_crate::Type;
+use iced::futures::stream::BoxStream;
+use iced::widget::{Column, ProgressBar, Row, Text};
+use iced::{Alignment, Element, Length, Subscription};
+use iced_futures::subscription::{EventStream, Recipe};
+use wallcore::download_manager::{DownloadManager, DownloadStatus, ImageDownload};
+use wallcore::utils::{file_size_format, remaining_time_format};
+
+/// Provides a subscriber for Iced to return messages
+struct DownloadRecipe(ImageDownload);
+
+impl Recipe for DownloadRecipe {
+    type Output = DownloadStatus;
+
+    fn hash(&self, state: &mut iced_futures::core::Hasher) {
+        use std::hash::Hash;
+
+        std::any::TypeId::of::<Self>().hash(state);
+        self.0.id().hash(state);
+        self.0.url().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: EventStream) -> BoxStream<'static, Self::Output> {
+        self.0.stream()
+    }
+}
+
+pub(crate) fn download_subscriptions(
+    downloads: &DownloadManager,
+) -> Vec<Subscription<DownloadStatus>> {
+    downloads
+        .running()
+        .map(|download| Subscription::from_recipe(DownloadRecipe(download.clone())))
+        .collect()
+}
+
+/// Lists every download with its progress, along with controls to pause, resume, cancel or retry
+/// it
+pub(crate) fn download_list(downloads: &DownloadManager) -> Column<'_, WallpaperMessage> {
+    if downloads.is_empty() {
+        return Column::new().push(Text::new("Nothing is downloading"));
+    }
+    let header = Text::new(format!(
+        "{} running, up to {} at a time",
+        downloads.running_count(),
+        downloads.concurrent_downloads()
+    ));
+    downloads.downloads().fold(
+        Column::new().spacing(5).push(header),
+        |column, (id, download)| {
+            let file_name = download
+                .save_path()
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| id.to_string());
+            let status = match (
+                download.failure(),
+                download.is_paused(),
+                download.progress(),
+            ) {
+                (Some(reason), _, _) => format!("failed: {}", reason),
+                (_, true, _) => "paused".to_string(),
+                (_, _, None) => "queued".to_string(),
+                (_, _, Some(progress)) => {
+                    let done = match download.expected_size() {
+                        Some(_) => format!("{:.0}%", progress),
+                        None => file_size_format(download.downloaded() as i64),
+                    };
+                    match download.speed() {
+                        Some(speed) => format!("{}  {}/s", done, file_size_format(speed as i64)),
+                        None => done,
+                    }
+                }
+            };
+            // started downloads without a known size can't show how far along they are
+            let progress: Element<WallpaperMessage> =
+                match (download.expected_size(), download.progress()) {
+                    (None, Some(_)) => Text::new("size unknown").width(Length::Fixed(200.0)).into(),
+                    (_, progress) => ProgressBar::new(0.0..=100.0, progress.unwrap_or_default())
+                        .width(Length::Fixed(200.0))
+                        .height(Length::Fixed(10.0))
+                        .into(),
+                };
+            let controls = match (download.is_failed(), download.is_paused()) {
+                (true, _) => Row::new().spacing(5).push(
+                    make_button_fa("retry", "redo")
+                        .on_press(WallpaperMessage::RetryDownload(id.to_string())),
+                ),
+                (false, true) => Row::new().spacing(5).push(
+                    make_button_fa("resume", "play")
+                        .on_press(WallpaperMessage::ResumeDownload(id.to_string())),
+                ),
+                (false, false) => Row::new().spacing(5).push(
+                    make_button_fa("pause", "pause")
+                        .on_press(WallpaperMessage::PauseDownload(id.to_string())),
+                ),
+            };
+            column.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::new(file_name).width(Length::Fixed(300.0)))
+                    .push(progress)
+                    .push(Text::new(status).width(Length::Fixed(160.0)))
+                    .push(
+                        controls
+                            .push(
+                                make_button_fa("cancel", "times")
+                                    .on_press(WallpaperMessage::CancelDownload(id.to_string())),
+                            )
+                            .push(make_button_fa("open folder", "folder-open").on_press(
+                                WallpaperMessage::RevealFile(download.save_path().to_path_buf()),
+                            )),
+                    ),
+            )
+        },
+    )
+}
+
+/// Counts of what's downloading, finished and failed, shown on the downloads button
+pub(crate) fn download_summary(downloads: &DownloadManager) -> Row<'_, WallpaperMessage> {
+    let download_icon = FAIcon::new(Type::Solid, "download").svg();
+    let complete_icon = FAIcon::new(Type::Solid, "check").svg();
+    let failed = downloads.failed_count();
+    if !downloads.is_empty() || downloads.finished_count() > 0 {
+        let row = Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(download_icon.height(Length::Fixed(26.0)))
+            .push(Text::new(format!("{}", downloads.len() - failed)).size(26))
+            .push(complete_icon.height(Length::Fixed(26.0)))
+            .push(Text::new(format!("{}", downloads.finished_count())).size(26));
+        let row = match failed {
+            0 => row,
+            _ => row
+                .push(
+                    FAIcon::new(Type::Solid, "exclamation-triangle")
+                        .svg()
+                        .height(Length::Fixed(26.0)),
+                )
+                .push(Text::new(format!("{}", failed)).size(26)),
+        };
+        let row = match downloads.is_offline() {
+            true => row.push(Text::new("offline").size(26)),
+            false => row,
+        };
+        match downloads.remaining_time() {
+            Some(remaining) => row.push(Text::new(format!(
+                "{}/s, {} left",
+                file_size_format(downloads.total_speed() as i64),
+                remaining_time_format(remaining)
+            ))),
+            None => row,
+        }
+    } else {
+        Row::new()
+            .push(download_icon.height(Length::Fixed(15.0)))
+            .push(Text::new("0"))
+    }
+}
@@ -0,0 +1,332 @@
+use crate::library::{file_hash, Library, LibraryEntry};
+use crate::thumbnails::cache_file_thumbnail;
+use image_rs::imageops::FilterType;
+use image_rs::DynamicImage;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::task::spawn_blocking;
+
+/// Most bits two image hashes may differ by for the images to count as the same wallpaper
+const SIMILARITY_THRESHOLD: u32 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DuplicateFile {
+    pub(crate) path: PathBuf,
+    pub(crate) size: u64,
+    /// Width and height, None when the image couldn't be read
+    pub(crate) dimensions: Option<(u32, u32)>,
+    /// Scaled down copy in the thumbnail cache, so the submenu doesn't decode full size images
+    pub(crate) thumbnail: Option<PathBuf>,
+}
+
+/// Files that are copies of the same wallpaper, the best copy comes first
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DuplicateGroup {
+    pub(crate) files: Vec<DuplicateFile>,
+    /// Every file has the same contents, otherwise the images only look alike
+    pub(crate) exact: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScannedFile {
+    file: DuplicateFile,
+    hash: String,
+    image_hash: Option<u64>,
+}
+
+/// Difference hash of an image, each bit says whether a pixel is brighter than the one to its
+/// right once the image is shrunk to 9x8. Survives resizing and recompression.
+pub(crate) fn image_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn is_image(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("jpg" | "jpeg" | "png")
+    )
+}
+
+fn parse_dimensions(resolution: &str) -> Option<(u32, u32)> {
+    let (width, height) = resolution.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Hashes a file, reusing what the library already knows about it
+fn scan_file(path: PathBuf, entry: Option<LibraryEntry>, library: &Library) -> Option<ScannedFile> {
+    let size = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            warn!("Skipping {:?} {:?}", path, e);
+            return None;
+        }
+    };
+    if let Some(entry) = &entry {
+        let dimensions = entry.resolution.as_deref().and_then(parse_dimensions);
+        if let (Some(hash), Some(image_hash), Some(dimensions)) =
+            (&entry.hash, entry.image_hash, dimensions)
+        {
+            return Some(ScannedFile {
+                file: DuplicateFile {
+                    path,
+                    size,
+                    dimensions: Some(dimensions),
+                    thumbnail: None,
+                },
+                hash: hash.clone(),
+                image_hash: Some(image_hash),
+            });
+        }
+    }
+    let hash = match file_hash(&path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            warn!("Failed to hash {:?} {:?}", path, e);
+            return None;
+        }
+    };
+    let (dimensions, image_hash) = match image_rs::open(&path) {
+        Ok(image) => (
+            Some((image.width(), image.height())),
+            Some(image_hash(&image)),
+        ),
+        Err(e) => {
+            warn!("Failed to read image {:?} {:?}", path, e);
+            (None, None)
+        }
+    };
+    // save the work for the next scan
+    if let Some(mut entry) = entry {
+        entry.hash = Some(hash.clone());
+        entry.image_hash = image_hash;
+        if let Some((width, height)) = dimensions {
+            entry.resolution = Some(format!("{}x{}", width, height));
+        }
+        if let Err(e) = library.record(&entry) {
+            error!("Failed to update {} in the library {:?}", entry.id, e);
+        }
+    }
+    Some(ScannedFile {
+        file: DuplicateFile {
+            path,
+            size,
+            dimensions,
+            thumbnail: None,
+        },
+        hash,
+        image_hash,
+    })
+}
+
+fn is_copy(a: &ScannedFile, b: &ScannedFile) -> bool {
+    let looks_alike = match (a.image_hash, b.image_hash) {
+        (Some(a), Some(b)) => (a ^ b).count_ones() <= SIMILARITY_THRESHOLD,
+        _ => false,
+    };
+    a.hash == b.hash || looks_alike
+}
+
+/// Groups files with the same contents or images that look alike. Each file is compared with
+/// the first file of a group, so a chain of images that each look a bit like the next isn't
+/// lumped together.
+fn group_duplicates(files: Vec<ScannedFile>) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<Vec<ScannedFile>> = Vec::new();
+    for file in files {
+        match groups.iter_mut().find(|group| is_copy(&group[0], &file)) {
+            Some(group) => group.push(file),
+            None => groups.push(vec![file]),
+        }
+    }
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            // keep the biggest image, then the biggest file
+            group.sort_by_key(|scanned| {
+                let pixels = scanned
+                    .file
+                    .dimensions
+                    .map(|(width, height)| width as u64 * height as u64);
+                std::cmp::Reverse((pixels, scanned.file.size))
+            });
+            DuplicateGroup {
+                exact: group.iter().all(|scanned| scanned.hash == group[0].hash),
+                files: group.into_iter().map(|scanned| scanned.file).collect(),
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.files[0].path.cmp(&b.files[0].path));
+    groups
+}
+
+/// Looks for copies of the same wallpaper among the images in the directory and the library
+pub(crate) async fn scan_duplicates(library: Library, directory: PathBuf) -> Vec<DuplicateGroup> {
+    let result = spawn_blocking(move || {
+        let mut entries: HashMap<PathBuf, LibraryEntry> = match library.entries() {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+            Err(e) => {
+                error!("Failed to read the library {:?}", e);
+                HashMap::new()
+            }
+        };
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&directory) {
+            Ok(read_dir) => read_dir
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| is_image(path))
+                .collect(),
+            Err(e) => {
+                error!("Failed to read {:?} {:?}", directory, e);
+                vec![]
+            }
+        };
+        // wallpapers saved before the save directory was changed
+        for path in entries.keys() {
+            if !paths.contains(path) && path.exists() {
+                paths.push(path.clone());
+            }
+        }
+        info!("Scanning {} images for duplicates", paths.len());
+        let files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let entry = entries.remove(&path);
+                scan_file(path, entry, &library)
+            })
+            .collect();
+        let mut groups = group_duplicates(files);
+        for file in groups.iter_mut().flat_map(|group| group.files.iter_mut()) {
+            file.thumbnail = match cache_file_thumbnail(&file.path) {
+                Ok(thumbnail) => Some(thumbnail),
+                Err(e) => {
+                    warn!("Failed to make a thumbnail of {:?} {}", file.path, e);
+                    None
+                }
+            };
+        }
+        groups
+    })
+    .await;
+    match result {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("Duplicate scan failed {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Whether the file is in the save directory, comparing the real paths
+async fn is_in_directory(path: &Path, directory: &Path) -> bool {
+    match (path.parent(), tokio::fs::canonicalize(directory).await) {
+        (Some(parent), Ok(directory)) => tokio::fs::canonicalize(parent)
+            .await
+            .is_ok_and(|parent| parent == directory),
+        _ => false,
+    }
+}
+
+/// Deletes a copy, a wallpaper the library knew by that copy is pointed at the one that's kept.
+/// Only wallpapers the app saved are deleted, the ones in the save directory or the library.
+pub(crate) async fn delete_duplicate(
+    library: Library,
+    path: PathBuf,
+    kept: PathBuf,
+    directory: PathBuf,
+) -> Result<(), String> {
+    let entry = library.find_path(&path).map_err(|e| e.to_string())?;
+    if entry.is_none() && !is_in_directory(&path, &directory).await {
+        return Err(format!(
+            "{:?} isn't in the save directory or the library, it's left alone",
+            path
+        ));
+    }
+    tokio::fs::remove_file(&path)
+        .await
+        .map_err(|e| format!("Failed to delete {:?} {}", path, e))?;
+    info!("Deleted duplicate {:?}", path);
+    if let Some(entry) = entry {
+        // the kept copy may be a different file, the next scan hashes it again
+        let entry = LibraryEntry {
+            path: kept,
+            hash: None,
+            image_hash: None,
+            resolution: None,
+            ..entry
+        };
+        library.record(&entry).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::duplicates::{group_duplicates, image_hash, DuplicateFile, ScannedFile};
+    use image_rs::{DynamicImage, GrayImage, Luma};
+    use std::path::PathBuf;
+
+    fn scanned(name: &str, hash: &str, image_hash: u64, width: u32) -> ScannedFile {
+        ScannedFile {
+            file: DuplicateFile {
+                path: PathBuf::from(name),
+                size: 1000,
+                dimensions: Some((width, width / 16 * 9)),
+                thumbnail: None,
+            },
+            hash: hash.to_string(),
+            image_hash: Some(image_hash),
+        }
+    }
+
+    #[test]
+    fn groups_exact_and_similar_copies() {
+        let groups = group_duplicates(vec![
+            scanned("a.jpg", "01", 0b1111, 1920),
+            scanned("b.jpg", "01", 0b1111, 1920),
+            scanned("c.jpg", "02", 0xff00, 1280),
+            scanned("d.jpg", "03", 0xff01, 3840),
+            scanned("e.jpg", "04", 0xf0f0_f0f0, 1920),
+            // each looks like the next, but the last doesn't look like the first
+            scanned("f.jpg", "05", 0xff00_0000, 1920),
+            scanned("g.jpg", "06", 0xff3f_0000, 1920),
+            scanned("h.jpg", "07", 0xff3f_0fc0, 1920),
+        ]);
+        assert_eq!(groups.len(), 3);
+        assert!(groups[0].exact);
+        assert_eq!(groups[0].files.len(), 2);
+        assert!(!groups[1].exact);
+        // the biggest copy comes first
+        let paths: Vec<_> = groups[1].files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("d.jpg"), PathBuf::from("c.jpg")]);
+        let paths: Vec<_> = groups[2].files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("f.jpg"), PathBuf::from("g.jpg")]);
+    }
+
+    #[test]
+    fn image_hash_ignores_size() {
+        let gradient = |width: u32, height: u32| {
+            DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, _| {
+                Luma([(255 - x * 255 / width) as u8])
+            }))
+        };
+        let large = image_hash(&gradient(1920, 1080));
+        let small = image_hash(&gradient(192, 108));
+        assert!((large ^ small).count_ones() <= 2);
+        let flipped = image_hash(&gradient(192, 108).fliph());
+        assert!((large ^ flipped).count_ones() > 32);
+    }
+}
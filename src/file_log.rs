@@ -0,0 +1,187 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use platform_dirs::AppDirs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+use wallcore::settings::LogLevel;
+use wallcore::utils::format_utc;
+
+/// The log starts over in a new file past this size
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+/// Older logs kept next to the current one, `wall-a-bunga.log.1` being the newest
+const KEPT_LOGS: usize = 3;
+
+/// Least severe messages written to the file, changed along with the settings
+static FILE_LEVEL: RwLock<LevelFilter> = RwLock::new(LevelFilter::Off);
+/// Least severe messages printed to the console, from `RUST_LOG`
+static CONSOLE_LEVEL: RwLock<LevelFilter> = RwLock::new(LevelFilter::Off);
+
+/// Where the log file is written, like `~/.local/share/wall-a-bunga/wall-a-bunga.log`
+pub(crate) fn log_path() -> Option<PathBuf> {
+    AppDirs::new(Some("wall-a-bunga"), true).map(|dirs| dirs.data_dir.join("wall-a-bunga.log"))
+}
+
+/// Appends to a file, moving it aside once it grows past [MAX_LOG_SIZE]
+struct RotatingFile {
+    path: PathBuf,
+    /// Opened on the first write, so nothing is created while file logging is off
+    file: Option<File>,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_size: u64) -> Self {
+        Self {
+            path,
+            file: None,
+            size: 0,
+            max_size,
+        }
+    }
+
+    fn rotated_path(path: &Path, number: usize) -> PathBuf {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(".{}", number));
+        path.with_file_name(file_name)
+    }
+
+    fn open(&mut self) -> std::io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = File::options().create(true).append(true).open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("opened above"))
+    }
+
+    /// Shifts every kept log up by one, dropping the oldest
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        for number in (1..KEPT_LOGS).rev() {
+            let from = RotatingFile::rotated_path(&self.path, number);
+            if from.exists() {
+                std::fs::rename(&from, RotatingFile::rotated_path(&self.path, number + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, RotatingFile::rotated_path(&self.path, 1))
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let length = line.len() as u64 + 1;
+        self.open()?;
+        if self.size > 0 && self.size + length > self.max_size {
+            self.rotate()?;
+        }
+        let file = self.open()?;
+        writeln!(file, "{}", line)?;
+        self.size += length;
+        Ok(())
+    }
+}
+
+/// Prints to the console like `pretty_env_logger` and also writes to the log file
+struct AppLogger {
+    console: Box<dyn Log>,
+    file: Mutex<RotatingFile>,
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata) || file_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.console.log(record);
+        if !file_enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {} {}",
+            format_utc(SystemTime::now()),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_line(&line) {
+            // logging it would come right back here
+            eprintln!("Failed to write the log file {:?} {}", file.path, e);
+            set_file_level(LogLevel::Off);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        if let Some(file) = &mut self.file.lock().unwrap_or_else(|e| e.into_inner()).file {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Dependencies are chatty below warnings, only the app's own messages go further than that
+fn file_enabled(metadata: &Metadata) -> bool {
+    let level = *FILE_LEVEL.read().unwrap_or_else(|e| e.into_inner());
+    match metadata.target().starts_with("wall") {
+        true => metadata.level() <= level,
+        false => metadata.level() <= level.min(LevelFilter::Warn),
+    }
+}
+
+/// Starts logging to the console, the file stays off until [set_file_level] is called with the
+/// level from the settings
+pub(crate) fn init() {
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    let console = builder.build();
+    *CONSOLE_LEVEL.write().unwrap_or_else(|e| e.into_inner()) = console.filter();
+    let logger = AppLogger {
+        console: Box::new(console),
+        file: Mutex::new(RotatingFile::new(
+            log_path().unwrap_or_else(|| PathBuf::from("wall-a-bunga.log")),
+            MAX_LOG_SIZE,
+        )),
+    };
+    match log::set_boxed_logger(Box::new(logger)) {
+        Ok(()) => set_file_level(LogLevel::Off),
+        Err(e) => eprintln!("Failed to start logging {}", e),
+    }
+}
+
+pub(crate) fn set_file_level(level: LogLevel) {
+    *FILE_LEVEL.write().unwrap_or_else(|e| e.into_inner()) = level.filter();
+    let console = *CONSOLE_LEVEL.read().unwrap_or_else(|e| e.into_inner());
+    log::set_max_level(console.max(level.filter()));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file_log::{RotatingFile, KEPT_LOGS};
+
+    #[test]
+    fn rotates_log_files() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-log-{}", std::process::id()));
+        let path = dir.join("test.log");
+        let mut file = RotatingFile::new(path.clone(), 20);
+        for line in 0..10 {
+            file.write_line(&format!("line number {}", line)).unwrap();
+        }
+        let read = |number: usize| match number {
+            0 => std::fs::read_to_string(&path).unwrap(),
+            number => std::fs::read_to_string(RotatingFile::rotated_path(&path, number)).unwrap(),
+        };
+        // every line is over half the limit, so each one gets a file
+        assert_eq!(read(0), "line number 9\n");
+        assert_eq!(read(1), "line number 8\n");
+        assert_eq!(read(KEPT_LOGS), "line number 6\n");
+        assert!(!RotatingFile::rotated_path(&path, KEPT_LOGS + 1).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
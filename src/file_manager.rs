@@ -0,0 +1,57 @@
+use log::info;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Program and arguments showing the file selected in the platform's file manager
+#[cfg(windows)]
+fn reveal_command(path: &Path) -> (&'static str, Vec<OsString>) {
+    let mut select = OsString::from("/select,");
+    select.push(path);
+    ("explorer", vec![select])
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(path: &Path) -> (&'static str, Vec<OsString>) {
+    ("open", vec!["-R".into(), path.into()])
+}
+
+/// There's no common way to select a file, so its folder is opened
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal_command(path: &Path) -> (&'static str, Vec<OsString>) {
+    let folder = match path.is_dir() {
+        true => path,
+        false => path.parent().unwrap_or(path),
+    };
+    ("xdg-open", vec![folder.into()])
+}
+
+/// Shows a file in the platform's file manager, a file that isn't there (yet) shows its folder
+pub(crate) async fn reveal_in_file_manager(path: PathBuf) -> Result<(), String> {
+    let path = match (path.exists(), path.parent()) {
+        (false, Some(parent)) => parent.to_path_buf(),
+        _ => path,
+    };
+    info!("Revealing {:?} in the file manager", path);
+    let (program, args) = reveal_command(&path);
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .spawn()
+        .map_err(|e| format!("Failed to run {} {}", program, e))?;
+    // explorer exits with an error even when it worked, so only starting it counts
+    let _ = child.wait().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn opens_containing_folder() {
+        use crate::file_manager::reveal_command;
+        use std::path::Path;
+
+        let (program, args) = reveal_command(Path::new("/w/a.jpg"));
+        assert_eq!(program, "xdg-open");
+        assert_eq!(args, ["/w"]);
+    }
+}
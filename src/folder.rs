@@ -0,0 +1,177 @@
+use crate::library::{Fnv1a, LibraryEntry};
+use anyhow::Result;
+use log::error;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use wallapi::types::{ListingData, SearchOptions};
+
+/// Files with other extensions aren't shown, the same ones wallpapers are downloaded as
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// When each image was modified and its resolution then, None when it couldn't be read
+type Resolutions = HashMap<PathBuf, (SystemTime, Option<String>)>;
+
+/// Resolutions of the images in searched folders. Reading them means opening every file, so
+/// they're kept for as long as the file isn't modified.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FolderIndex {
+    resolutions: Arc<Mutex<Resolutions>>,
+}
+
+impl FolderIndex {
+    /// Images in the folder and its subfolders that fit the search, newest first. They're
+    /// matched like the library, by file name and resolution.
+    pub(crate) fn search(
+        &self,
+        folder: &Path,
+        options: &SearchOptions,
+    ) -> Result<Vec<LibraryEntry>> {
+        let mut files = vec![];
+        find_images(folder, &mut files)?;
+        files.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let mut resolutions = self.resolutions.lock().unwrap();
+        Ok(files
+            .into_iter()
+            .map(|(path, modified)| {
+                let resolution = match resolutions.get(&path) {
+                    Some((seen, resolution)) if *seen == modified => resolution.clone(),
+                    _ => {
+                        let resolution = image_rs::image_dimensions(&path)
+                            .ok()
+                            .map(|(width, height)| format!("{}x{}", width, height));
+                        resolutions.insert(path.clone(), (modified, resolution.clone()));
+                        resolution
+                    }
+                };
+                folder_entry(path, modified, resolution)
+            })
+            .filter(|entry| entry.matches(options))
+            .collect())
+    }
+}
+
+/// Adds the images under `folder` with when they were modified, hidden folders are skipped
+fn find_images(folder: &Path, files: &mut Vec<(PathBuf, SystemTime)>) -> Result<()> {
+    for entry in std::fs::read_dir(folder)?.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                error!("Failed to read {:?} {:?}", path, e);
+                continue;
+            }
+        };
+        if metadata.is_dir() {
+            if hidden {
+                continue;
+            }
+            // one unreadable subfolder shouldn't hide the rest
+            if let Err(e) = find_images(&path, files) {
+                error!("Failed to read {:?} {:?}", path, e);
+            }
+            continue;
+        }
+        let is_image = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        if is_image {
+            files.push((path, metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+        }
+    }
+    Ok(())
+}
+
+/// The image as a library entry so it can be searched and shown like one. Its id changes when
+/// the file does, so cached thumbnails of the old file aren't shown for it.
+fn folder_entry(path: PathBuf, modified: SystemTime, resolution: Option<String>) -> LibraryEntry {
+    let mut hasher = Fnv1a::default();
+    hasher.write(path.to_string_lossy().as_bytes());
+    let modified = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write(&modified.as_secs().to_le_bytes());
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    LibraryEntry {
+        id: format!("folder-{:016x}", hasher.finish()),
+        path,
+        hash: None,
+        image_hash: None,
+        // searching by tag is searching by file name and resolution
+        tags: std::iter::once(name).chain(resolution.clone()).collect(),
+        resolution,
+        listing: None,
+        protected: false,
+    }
+}
+
+/// The entry as a search result, linking to the file instead of wallhaven
+pub(crate) fn to_listing(entry: &LibraryEntry) -> ListingData {
+    let url = reqwest::Url::from_file_path(&entry.path)
+        .map(|url| url.to_string())
+        .unwrap_or_default();
+    ListingData {
+        url: url.clone(),
+        short_url: url,
+        path: entry.path.to_string_lossy().to_string(),
+        ..entry.to_listing()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::folder::{to_listing, FolderIndex};
+    use std::collections::HashSet;
+    use wallapi::types::{SearchOptions, XYCombo};
+
+    #[test]
+    fn searches_images_in_folders() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-folder-{}", std::process::id()));
+        let nested = dir.join("space");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(dir.join(".cache")).unwrap();
+        let image = |path: std::path::PathBuf, width: u32, height: u32| {
+            image_rs::RgbImage::new(width, height).save(&path).unwrap();
+            path
+        };
+        let mountains = image(dir.join("Mountains.png"), 32, 18);
+        let nebula = image(nested.join("nebula.PNG"), 16, 16);
+        image(dir.join(".cache").join("thumbnail.png"), 4, 4);
+        std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+        let index = FolderIndex::default();
+        let search = |query: &str, resolutions: &[XYCombo]| {
+            let options = SearchOptions {
+                query: Some(query.to_string()),
+                resolutions: Some(resolutions.iter().copied().collect::<HashSet<_>>()),
+                ..Default::default()
+            };
+            let mut paths: Vec<_> = index
+                .search(&dir, &options)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(search("", &[]), [mountains.clone(), nebula.clone()]);
+        assert_eq!(search("mountain", &[]), vec![mountains.clone()]);
+        assert_eq!(search("16x16", &[]), vec![nebula.clone()]);
+        assert_eq!(search("", &[XYCombo { x: 32, y: 18 }]), vec![mountains]);
+
+        let entry = index.search(&dir, &SearchOptions::default()).unwrap();
+        let listing = to_listing(entry.iter().find(|e| e.path == nebula).unwrap());
+        assert!(listing.id.starts_with("folder-"));
+        assert!(listing.url.starts_with("file://"));
+        assert_eq!(listing.resolution, "16x16");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
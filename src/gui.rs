@@ -1,51 +1,421 @@
-use crate::download_manager::{DownloadManager, DownloadStatus};
-use crate::font_awesome::FAIcon;
-use crate::settings::SavedSettings;
-use crate::style::{button_style, inactive_style};
-use crate::style::{make_button, make_button_fa};
+mod downloads;
+mod preview;
+#[cfg(test)]
+mod scenarios;
+mod search;
+mod selection;
+mod settings;
+
+use crate::banner::{BannerSource, Banners, Severity};
+use crate::bundle::SettingsBundle;
+use crate::crash;
+use crate::credentials;
+use crate::daily::{DailyPick, DailyWallpaper};
+use crate::download_view::{download_list, download_subscriptions, download_summary};
+use crate::duplicates::{delete_duplicate, DuplicateGroup};
+use crate::file_log::log_path;
+use crate::folder::{self, FolderIndex};
+use crate::gui::downloads::{DownloadsState, FileConflict};
+use crate::gui::preview::PreviewState;
+use crate::gui::search::SearchState;
+use crate::gui::selection::SelectionState;
+use crate::gui::settings::SettingsPage;
+use crate::http::build_client;
+use crate::ipc::{self, IpcCommand};
+use crate::library::{file_hash, Library, LibraryEntry};
+use crate::monitors::{closest_aspect_ratio, detect_monitors, Monitor};
+use crate::onboarding::OnboardingStep;
+use crate::preview_download::{PreviewDownload, PreviewStatus};
+use crate::purity_lock::PurityLock;
+use crate::quota::{enforce_quota, QuotaReport};
+use crate::shortcuts::keyboard_shortcuts;
+use crate::style::{button_style, container_style, focus_outline, inactive_style, labeled};
+use crate::style::{density, set_appearance};
+use crate::style::{make_badge, make_button, make_button_fa};
 use crate::submenus::ratio_menu::RatioMenu;
 use crate::submenus::resolution_menu::ResolutionOptionsMenu;
-use crate::utils::trendy_number_format;
+use crate::submenus::selection_menu::{SelectionCriteria, SelectionMenu};
+use crate::submenus::{ComboKind, CustomCombos};
+use crate::taskbar::Taskbar;
+use crate::thumbnails::{load_thumbnail, ThumbnailSource, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+use crate::tray::{self, TrayAction, TrayIcon};
+use crate::virtual_rows::{skipped_height, visible_rows};
+use crate::watch::{Watch, WatchList};
 use anyhow::Result;
-use font_awesome_as_a_crate::Type;
 use iced::widget::image::Viewer;
-use iced::widget::scrollable::Viewport;
+use iced::widget::scrollable::{self, RelativeOffset, Viewport};
 use iced::widget::{
-    image, Button, Checkbox, Column, Container, Image, PickList, ProgressBar, Row, Scrollable,
-    Space, Text, TextInput,
+    image, tooltip, Button, Checkbox, Column, Container, Image, MouseArea, PickList, ProgressBar,
+    Row, Scrollable, Space, Text, TextInput, Tooltip,
+};
+use iced::{
+    alignment, executor, mouse, window, Alignment, Application, Color, Command, ContentFit,
+    Element, Length, Subscription,
 };
-use iced::{alignment, executor, Alignment, Application, Command, Element, Length, Subscription};
-use log::{debug, error, info, warn};
+use log::{debug, error, info};
 use native_dialog::FileDialog;
-use rand::{thread_rng, RngCore};
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::ControlFlow::{self, Break, Continue};
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::fs::metadata;
 use tokio::task::spawn_blocking;
+use wallapi::query::QueryBuilder;
+use wallapi::rate_limit::RateLimiter;
 use wallapi::types::{
-    Categories, Category, GenericResponse, ListingData, Purity, SearchMetaData, SearchOptions,
-    Sorting, XYCombo,
+    Categories, Category, Collection, GenericResponse, ListingData, Purity, PurityLevel,
+    SearchMetaData, SearchOptions, Sorting, Tag, Uploader, UserSettings, XYCombo,
 };
+use wallapi::url::WallhavenUrl;
 use wallapi::{WallhavenApiClientError, WallhavenClient};
+use wallcore::cart::DownloadCart;
+use wallcore::connectivity::{is_connection_error, is_search_connection_error, CONNECTION_ERROR};
+use wallcore::download_manager::{DownloadManager, DownloadStatus};
+use wallcore::history::{unix_now, SearchHistory};
+use wallcore::paginator::Paginator;
+use wallcore::reddit::fetch_subreddit;
+use wallcore::result_filter::{parse_minimum, FileType, LocalSort, ResultFilter};
+use wallcore::settings::{
+    DataSaver, Density, DownloadedFilter, ExistingFilePolicy, LogLevel, QuotaAction, Renderer,
+    ResultLayout, SavedSettings, StartMode, ThemeMode, WindowGeometry,
+};
+use wallcore::source::{Source, SourceKind};
+use wallcore::tags::TagCache;
+use wallcore::unsplash::{self, attribution, fetch_photos};
+use wallcore::updates::Release;
+use wallcore::utils::{
+    cropped_pixels, file_size_format, is_upscaled, is_valid_seed, parse_upload_date,
+    remaining_time_format, time_ago_format, trendy_number_format, MEGABYTE,
+};
+
+/// What a reducer did with a message, it hands back the messages that aren't about its part of the
+/// app so the next reducer gets them
+type Reduced = ControlFlow<Command<WallpaperMessage>, WallpaperMessage>;
+
+/// What the window is showing, written into crash reports. Only counts and modes, nothing
+/// private.
+#[derive(Debug, Clone, PartialEq)]
+struct CrashState {
+    tabs: usize,
+    /// None when the active tab is browsing the library
+    source: Option<Source>,
+    results: usize,
+    cart: usize,
+    downloads: usize,
+    submenu: Submenu,
+    previewing: bool,
+    onboarding: bool,
+    renderer: Renderer,
+    theme_mode: ThemeMode,
+    density: Density,
+}
+
+impl Display for CrashState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let location = match &self.source {
+            Some(source) => format!("on {}", source),
+            None => "in the library".to_string(),
+        };
+        write!(
+            f,
+            "{} tabs, the active one {} with {} results\n\
+             {} in the cart, {} downloads\n\
+             submenu {:?}, previewing {}, onboarding {}\n\
+             renderer {:?}, theme {:?}, density {:?}",
+            self.tabs,
+            location,
+            self.results,
+            self.cart,
+            self.downloads,
+            self.submenu,
+            self.previewing,
+            self.onboarding,
+            self.renderer,
+            self.theme_mode,
+            self.density,
+        )
+    }
+}
+
+/// What the window is started with
+#[derive(Debug)]
+pub(crate) struct StartupFlags {
+    pub(crate) settings: Result<Option<SavedSettings>, String>,
+    /// What the app was started with, like `wall-a-bunga open <id>`. It's handled once the
+    /// window is up the same as when it's forwarded to an app that's already running.
+    pub(crate) command: Option<IpcCommand>,
+}
 
 #[derive(Debug, Default)]
 pub(crate) struct WallpaperUi {
     controls: SearchControls,
-    search_value: String,
-    search_results: Vec<(ListingData, ImageView)>,
-    search_meta: Option<SearchMetaData>,
-    search_options: SearchOptions,
-    error_message: String,
+    /// Always contains at least one tab
+    tabs: Vec<SearchTab>,
+    active_tab: usize,
+    next_tab_id: TabId,
     settings: SavedSettings,
     api_key: String,
     resolution_menu: ResolutionOptionsMenu,
     aspect_menu: RatioMenu,
+    selection_menu: SelectionMenu,
     download_manager: DownloadManager,
-    concurrent_download_control: IncrementControl,
-    preview_mode: PreviewMode,
+    /// Wallpapers that were downloaded, used to mark results that don't need downloading again
+    library: Library,
+    /// Resolutions of the images in folders picked as a source
+    folder_index: FolderIndex,
+    /// Copies of the same wallpaper found by the last duplicate scan
+    duplicates: Vec<DuplicateGroup>,
+    /// Result of the last duplicate scan, or that one is running
+    duplicate_status: String,
+    /// Copies about to be deleted from the duplicate group at the index, once it's confirmed
+    deleting_duplicates: Option<(usize, Vec<PathBuf>)>,
+    /// Ids of downloaded wallpapers that are never pruned to stay under the disk quota
+    protected: HashSet<String>,
+    /// Disk usage of the save directory from the last quota check
+    quota: Option<QuotaReport>,
+    checking_quota: bool,
+    /// Monitors found by the last detection
+    monitors: Vec<Monitor>,
+    /// Wallpaper id and thumbnail picked for each monitor, by monitor name
+    monitor_assignments: HashMap<String, (String, image::Handle)>,
+    /// Id of the wallpaper last set, rotation moves on to a different one
+    current_wallpaper: Option<String>,
+    rotation_paused: bool,
+    /// The tray icon couldn't be shown, so closing the window quits
+    tray_failed: bool,
+    taskbar: Taskbar,
+    /// Last handed to the crash reporter, it's only formatted again when something changed
+    crash_state: Option<CrashState>,
+    /// Errors shown above the results
+    banners: Banners,
+    /// Step of the first launch wizard being shown, None once it's done
+    onboarding: Option<OnboardingStep>,
+    /// Whether the desktop was in dark mode when last checked, unknown counts as dark
+    system_dark: Option<bool>,
+    /// Whether the connection was metered when last checked, unknown counts as not metered
+    metered: bool,
+    /// Data saver before the status row toggled it, toggling again restores it
+    data_saver_toggled_from: Option<DataSaver>,
+    /// Size and position of the window, saved when the app quits
+    window_geometry: WindowGeometry,
+    /// Searches that are re-run to download what's new
+    watches: WatchList,
+    checking_watches: bool,
+    /// Filters and past picks of the wallpaper of the day
+    daily: DailyWallpaper,
+    picking_daily: bool,
+    /// Why picking the wallpaper of the day failed last time
+    daily_error: Option<String>,
+    /// The PIN was entered this session, sketchy and nsfw can be picked until the app closes
+    purity_unlocked: bool,
+    /// Newer release found by the update check
+    update: Option<Release>,
+    downloading_update: bool,
+    /// Why downloading the update's installer failed
+    update_error: Option<String>,
+    /// Whose collections to list, your own when left empty
+    collections_username: String,
+    collections: CollectionsState,
+    /// The full size preview and how it's shown
+    preview: PreviewState,
+    /// Past searches and the tags suggested while typing one
+    search: SearchState,
+    /// Wallpapers picked for download and the menus and drags that pick them
+    selection: SelectionState,
+    /// Inputs of the downloads page and the downloads waiting on something
+    downloads: DownloadsState,
+    /// Inputs of the settings page and what came of them
+    settings_page: SettingsPage,
+    /// Shared by every request to wallhaven, rebuilt when the proxy changes
+    client: WallhavenClient,
+}
+
+pub(crate) type TabId = u64;
+
+/// The search of a tab a page was asked for by, pages of a search that was replaced since are
+/// dropped when they arrive
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct SearchId {
+    tab: TabId,
+    generation: u64,
+}
+
+/// A single search, keeps its own options, results and scroll position so tabs can be switched
+/// without losing any state
+#[derive(Debug, Default)]
+pub(crate) struct SearchTab {
+    id: TabId,
+    search_value: String,
+    exclude_value: String,
+    /// Tags that are excluded from the search, shown as chips under the search box
+    excluded_tags: Vec<String>,
+    /// Contents of the go to page input
+    page_value: String,
+    /// Seed of the random order shown, or one typed in to bring an order back
+    seed_value: String,
+    search_results: Vec<(ListingData, ImageView)>,
+    search_meta: Option<SearchMetaData>,
+    search_options: SearchOptions,
+    scroll_offset: RelativeOffset,
+    /// How far down the results are scrolled in pixels, only the rows near it are built
+    scroll_top: f32,
+    paginator: Paginator,
+    /// Position of the keyboard focus within the visible results grid
+    focus: Option<usize>,
+    filter: ResultFilter,
+    /// Reorders the fetched results, they're shown in the order wallhaven sent them otherwise
+    local_sort: Option<LocalSort>,
+    /// Searches the downloaded wallpapers instead of wallhaven
+    local: bool,
+    /// Browses a collection instead of searching
+    collection: Option<OpenCollection>,
+    /// Set when the tab lists the uploads of a single user
+    uploader: Option<UploaderProfile>,
+    /// Opened by "surprise me", random wallpapers are previewed one at a time
+    roulette: bool,
+    /// Where the results come from, library tabs ignore it
+    source: Source,
+    /// Where the next page starts for sources that page by the last result instead of by number
+    cursor: Option<String>,
+}
+
+/// Shown above the uploads of a user
+#[derive(Debug, Clone)]
+struct UploaderProfile {
+    uploader: Uploader,
+    avatar: Option<image::Handle>,
+}
+
+/// A change to the selection or the blacklist that can be undone
+#[derive(Debug, Clone)]
+enum UndoableEdit {
+    /// The cart before and after the selection changed
+    Selection {
+        before: DownloadCart<image::Handle>,
+        after: DownloadCart<image::Handle>,
+    },
+    /// A blacklisted wallpaper, with the results it was taken out of by tab and index
+    Blacklist {
+        id: String,
+        removed: Vec<(TabId, usize, (ListingData, ImageView))>,
+    },
+}
+
+/// Actions for a single result, opened by right clicking it
+#[derive(Debug, Clone)]
+struct ContextMenu {
+    /// Where String == image.id
+    id: String,
+    /// Shows everything known about the wallpaper instead of the actions
+    details: bool,
+}
+
+/// A wallhaven collection shown in a tab
+#[derive(Debug, Clone)]
+struct OpenCollection {
+    username: String,
+    id: i64,
+    label: String,
+}
+
+impl SearchTab {
+    fn new(
+        id: TabId,
+        api_key: Option<String>,
+        purity: Option<Purity>,
+        categories: Option<Categories>,
+    ) -> Self {
+        Self {
+            id,
+            search_options: SearchOptions {
+                api_key,
+                purity,
+                categories,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Fills the search box and filters in from a remembered search, keeping the tab's api key
+    fn restore(&mut self, options: SearchOptions) {
+        let query = QueryBuilder::parse(options.query.as_deref().unwrap_or_default());
+        self.search_value = query.terms().join(" ");
+        self.excluded_tags = query.excluded_tags().to_vec();
+        self.search_options = SearchOptions {
+            api_key: self.search_options.api_key.clone(),
+            ..options
+        };
+    }
+
+    /// Empties the tab for a new search, pages still on their way for the one before are
+    /// dropped
+    fn clear_results(&mut self) {
+        self.search_results.clear();
+        self.search_meta = None;
+        self.scroll_offset = RelativeOffset::START;
+        self.scroll_top = 0.0;
+        self.focus = None;
+        self.paginator.restart();
+    }
+
+    /// Which search the pages asked for now belong to
+    fn search_id(&self) -> SearchId {
+        SearchId {
+            tab: self.id,
+            generation: self.paginator.generation(),
+        }
+    }
+
+    /// Searches wallhaven.cc, only those searches can be shared, watched or picked from daily
+    fn is_wallhaven(&self) -> bool {
+        !self.local && self.source == Source::Wallhaven
+    }
+
+    /// Results are files that are already on disk, the library's or those of a folder, so
+    /// there's nothing to download
+    fn is_on_disk(&self) -> bool {
+        self.local || matches!(self.source, Source::Folder(_))
+    }
+
+    /// Link to the same search on wallhaven.cc, the library and other sources have none
+    fn share_link(&self) -> Option<String> {
+        if let Some(collection) = &self.collection {
+            return Some(format!(
+                "https://wallhaven.cc/user/{}/favorites/{}",
+                collection.username, collection.id
+            ));
+        }
+        match self.is_wallhaven() {
+            true => Some(WallhavenUrl::Search(self.search_options.clone()).to_string()),
+            false => None,
+        }
+    }
+
+    fn title(&self) -> &str {
+        if let Some(collection) = &self.collection {
+            return &collection.label;
+        }
+        if self.roulette {
+            return "surprise me";
+        }
+        match (self.search_options.query.as_deref(), self.local) {
+            (None | Some(""), true) => "library",
+            (None | Some(""), false) => match &self.source {
+                Source::Reddit(subreddit) => subreddit.as_str(),
+                Source::Folder(folder) => folder
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or("folder"),
+                Source::Unsplash => "unsplash",
+                Source::Wallhaven => "new search",
+            },
+            (Some(query), _) => query,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -54,7 +424,7 @@ struct IncrementControl {
 }
 
 impl IncrementControl {
-    fn view(&self) -> Row<WallpaperMessage> {
+    fn view(&self) -> Row<'_, WallpaperMessage> {
         Row::new()
             .push(
                 make_button("-")
@@ -76,15 +446,43 @@ enum ImageState {
     Unselected,
     Selected,
     Queued,
-    // f32 measures progress
-    Downloading(f32),
+    // f32 measures progress, None when the size isn't known
+    Downloading(Option<f32>),
     Downloaded,
     Failed,
 }
+
+/// What wallhaven said about the api key that was typed in
+#[derive(Default, Debug, Clone)]
+pub(crate) enum ApiKeyStatus {
+    #[default]
+    Unchecked,
+    Checking,
+    Valid(UserSettings),
+    Invalid(String),
+    /// Wallhaven couldn't be asked or didn't answer, like when offline or rate limited, the key
+    /// may well be fine
+    CouldNotCheck(String),
+}
+
+/// Collections listed by the collections menu
+#[derive(Default, Debug, Clone)]
+enum CollectionsState {
+    #[default]
+    NotLoaded,
+    Loading,
+    /// Username the collections belong to, None when they were listed with the api key
+    Loaded(Option<String>, Vec<Collection>),
+    Failed(String),
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ImageView {
     state: ImageState,
+    /// Thumbnail scaled down to the size it's shown at
     image_handle: image::Handle,
+    /// The downloaded file of library results, shown full size in the preview
+    file: Option<PathBuf>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -94,6 +492,22 @@ pub(crate) enum PurityOptions {
     Nsfw,
 }
 
+impl PurityOptions {
+    fn toggle(self, purity: &mut Purity) {
+        match self {
+            PurityOptions::Sfw => {
+                purity.clean = !purity.clean;
+            }
+            PurityOptions::Sketchy => {
+                purity.sketchy = !purity.sketchy;
+            }
+            PurityOptions::Nsfw => {
+                purity.nsfw = !purity.nsfw;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum ContentTypes {
     Anime,
@@ -101,22 +515,76 @@ pub(crate) enum ContentTypes {
     People,
 }
 
+impl ContentTypes {
+    fn toggle(self, content: &mut Categories) {
+        match self {
+            ContentTypes::Anime => {
+                content.anime = !content.anime;
+            }
+            ContentTypes::General => {
+                content.general = !content.general;
+            }
+            ContentTypes::People => {
+                content.people = !content.people;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum SelectionUpdateType {
     Single(String),
     SelectAll,
     DeselectAll,
+    /// Selects every visible result matching the criteria
+    Matching(SelectionCriteria),
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum WallpaperMessage {
     Search(),
     SearchUpdated(String),
-    SearchReceived(GenericResponse<Vec<(ListingData, ImageView)>>),
+    /// Searches for the tag with the given id, picked from the suggestions under the search box
+    PickTagSuggestion(i64),
+    ExcludeUpdated(String),
+    /// Adds the tags typed in the exclude input
+    AddExcludedTags(),
+    RemoveExcludedTag(usize),
+    SearchReceived(SearchId, GenericResponse<Vec<(ListingData, ImageView)>>),
+    /// The search never reached wallhaven, downloads wait for the connection too
+    SearchOffline(SearchId),
+    /// A single wallpaper opened from a pasted link, previewed once it arrives
+    WallpaperLinkReceived(SearchId, GenericResponse<Vec<(ListingData, ImageView)>>),
+    /// Switches where the active tab's results come from and searches again
+    SetSource(Source),
+    /// Where Option<String> == where the next page starts
+    RedditPageReceived(
+        SearchId,
+        Option<String>,
+        GenericResponse<Vec<(ListingData, ImageView)>>,
+    ),
+    /// Asks for a folder and searches its images in the active tab
+    ChooseSourceFolder(),
+    SourceFolderChosen(Option<PathBuf>),
     /// Where String == image.id
     SelectionUpdate(SelectionUpdateType),
+    /// Downloads everything in the cart
     DownloadImages(),
+    /// Where String == image.id
+    RemoveFromCart(String),
+    ClearCart(),
     SortingTypeChanged(Sorting),
+    SeedUpdated(String),
+    /// Searches again in the random order of the typed seed
+    ApplySeed(),
     TogglePurity(PurityOptions),
     ToggleContentType(ContentTypes),
     ApiTokenSet(String),
@@ -127,17 +595,267 @@ pub(crate) enum WallpaperMessage {
     ResolutionIsSingleTargetChanged(bool),
     AspectRatioSelected(XYCombo),
     SaveSettings(),
-    SaveCompleted(()),
-    SetIgnoreDownloaded(bool),
+    SaveCompleted(Result<(), String>),
+    SettingsSaved(Result<(), String>),
+    /// Checks the key with wallhaven if it's still the one typed in
+    ValidateApiKey(String),
+    ApiKeyValidated(String, ApiKeyStatus),
+    DismissBanner(BannerSource),
+    RetryBanner(BannerSource),
+    /// Probes whether the connection is back while offline
+    CheckConnection(),
+    ConnectionChecked(bool),
+    SetDownloadedFilter(DownloadedFilter),
+    SetExistingFilePolicy(ExistingFilePolicy),
+    SetUseUploadDate(bool),
+    SetDownloadAllCount(String),
+    SetDownloadAllMegabytes(String),
+    SetMaxDiskUsage(String),
+    SetQuotaAction(QuotaAction),
+    SetLogLevel(LogLevel),
+    SetRenderer(Renderer),
+    /// Never shows the wallpaper in search results again
+    BlacklistWallpaper(String),
+    ClearBlacklistedWallpapers(),
+    BlacklistTagsUpdated(String),
+    AddBlacklistedTags(),
+    RemoveBlacklistedTag(usize),
+    QuotaChecked(QuotaReport),
+    /// Where String == image.id, protects the wallpaper from being pruned or stops protecting it
+    ToggleProtected(String),
+    SetWallpaperCommand(String),
+    SetProxy(String),
+    SetUnsplashAccessKey(String),
+    /// Offers the kind of source in the source dropdown or takes it out
+    SetSourceEnabled(SourceKind, bool),
+    /// Where String == image.id, makes the downloaded file the desktop wallpaper
+    SetAsWallpaper(String),
+    /// Where String == image.id, downloads the wallpaper if needed and sets it once it's done
+    SetWhenDownloaded(String),
+    WallpaperSet(Result<(), String>),
+    /// Previews a random wallpaper matching the active tab's filters
+    SurpriseMe(),
+    /// Moves on to the next random wallpaper
+    Reroll(),
+    DetectMonitors(),
+    MonitorsDetected(Vec<Monitor>),
+    CustomComboInput(ComboKind, String),
+    /// Adds what was typed into the input as a custom entry and selects it
+    AddCustomCombo(ComboKind),
+    RemoveCustomCombo(ComboKind, XYCombo),
+    /// Sets the filters of the active tab to fit the primary monitor
+    DetectResolution(),
+    ResolutionDetected(Vec<Monitor>),
+    ToggleDefaultContent(ContentTypes),
+    OnboardingStepChanged(OnboardingStep),
+    /// Closes the first launch wizard and searches with what was picked
+    FinishOnboarding(),
+    /// Where the first String == monitor name and the second == image.id, downloads the
+    /// wallpaper if needed
+    AssignToMonitor(String, String),
+    ApplyMonitorWallpapers(),
+    /// Searches for wallpapers in exactly the monitor's resolution
+    SearchMonitorResolution(XYCombo),
+    SetRotationMinutes(String),
+    SetMinimizeToTray(bool),
+    /// Keeps the active profile's settings and loads the named one
+    SwitchProfile(String),
+    ProfileNameChanged(String),
+    AddProfile(),
+    RemoveProfile(),
+    ToggleDefaultPurity(PurityOptions),
+    SetStartAtLogin(bool),
+    PinUpdated(String),
+    /// Checks the entered PIN, sketchy and nsfw can be picked for the rest of the session
+    UnlockPurity(),
+    /// Locks sketchy and nsfw away behind the entered PIN
+    SetPurityPin(),
+    RemovePurityPin(),
+    SetCheckForUpdates(bool),
+    CheckForUpdates(),
+    UpdateChecked(Result<Release, String>),
+    /// Downloads the installer of the newer release and starts it
+    DownloadUpdate(),
+    UpdateDownloaded(Result<PathBuf, String>),
+    SetStartMode(StartMode),
+    SetThemeMode(ThemeMode),
+    SetAccentColor(String),
+    SetDensity(Density),
+    SetResultLayout(ResultLayout),
+    SetDataSaver(DataSaver),
+    /// Turns data saver on or off from the status row and saves it right away
+    ToggleDataSaver(),
+    CheckMetered(),
+    MeteredChecked(Option<bool>),
+    /// Writes the settings and watches to a file picked by the user
+    ExportSettings(),
+    /// The file it was written to, None when cancelled
+    SettingsExported(Result<Option<PathBuf>, String>),
+    ImportSettings(),
+    SettingsImported(Result<Option<Box<SettingsBundle>>, String>),
+    CheckSystemTheme(),
+    SystemThemeChecked(Option<bool>),
+    /// Switches the desktop to another downloaded wallpaper
+    RotateWallpaper(),
+    Tray(TrayAction),
+    /// Sent by a script or keybinding over the control socket
+    Ipc(IpcCommand),
+    /// The window's close button was pressed
+    CloseRequested(),
+    /// Saves the window geometry and closes the app
+    Quit(),
+    Closed(),
+    /// Where (u32, u32) == (width, height)
+    WindowResized(u32, u32),
+    /// Where (i32, i32) == (x, y)
+    WindowMoved(i32, i32),
+    /// Starts watching the active tab's search for new wallpapers
+    WatchSearch(),
+    RemoveWatch(usize),
+    ToggleWatchNotify(usize),
+    CheckWatches(),
+    /// Watches that were checked along with the new wallpapers they found
+    WatchesChecked((Vec<Watch>, Vec<ListingData>)),
+    SetWatchInterval(String),
+    SetDailyWallpaper(bool),
+    /// Picks the wallpaper of the day using the active tab's filters
+    UseSearchForDaily(),
+    /// Picks the wallpaper of the day if it's on and nothing was picked today
+    CheckDailyWallpaper(),
+    PickDailyWallpaper(),
+    DailyWallpaperPicked(Result<Box<DailyPick>, String>),
+    /// Never picks today's wallpaper again and picks the next one
+    SkipDailyWallpaper(),
+    /// Where String == image.id, the full details fill in the uploader and tags of the result
+    WallpaperDetailsReceived(String, Option<Box<ListingData>>),
+    /// Lists the uploads of a user in a new tab
+    OpenUploader(Uploader),
+    UploaderAvatarReceived(TabId, Option<image::Handle>),
+    /// Where String == image.id, opens the right click menu of the result or closes it if open
+    OpenContextMenu(String),
+    CloseContextMenu(),
+    /// Closes the right click menu before passing the picked action on
+    ContextMenuAction(Box<WallpaperMessage>),
+    /// Where String == image.id, fetches the uploader and tags if they're missing
+    ShowDetails(String),
+    /// Where String == image.id, searches for similar wallpapers in a new tab
+    FindSimilar(String),
+    /// Where String == image.id, downloads the result without going through the cart
+    DownloadNow(String),
+    /// Where usize == position among the visible results, starts dragging a selection rectangle
+    DragSelectStarted(usize),
+    DragSelectMoved(usize),
+    /// Selects or deselects every result in the rectangle once the mouse button is let go
+    DragSelectEnded(),
+    /// Takes back the last selection or blacklist change
+    Undo(),
+    Redo(),
+    CollectionsUsernameUpdated(String),
+    LoadCollections(),
+    CollectionsLoaded(Result<(Option<String>, Vec<Collection>), String>),
+    /// Where usize == index into the listed collections, opens it in a new tab
+    OpenCollection(usize),
+    /// Opens the collection and queues all of it like "download all results"
+    DownloadCollection(usize),
+    /// Queues every result of the active search, page by page, up to the configured limits
+    DownloadAllResults(),
+    StopDownloadAll(),
+    ImportInputChanged(String),
+    LoadImportFile(),
+    ImportFileLoaded(Option<String>),
+    ImportList(),
+    /// Wallpapers that were found and the ids that weren't
+    ImportResolved((Vec<ListingData>, Vec<String>)),
+    /// Where String == image.id, decides what happens to a download that would replace a file
+    ResolveFileConflict(String, ExistingFilePolicy),
+    /// Wallpaper ids along with whether they have been downloaded
+    DownloadedStateRefreshed(Vec<(String, bool)>),
+    /// A finished download was added to the library
+    LibraryUpdated(Result<(), String>),
+    /// Unsplash was told one of its photos was downloaded
+    UnsplashDownloadTracked(Result<(), String>),
+    ScanDuplicates(),
+    DuplicatesScanned(Vec<DuplicateGroup>),
+    /// Asks before deleting a copy from the duplicate group at the index
+    DeleteDuplicate(usize, PathBuf),
+    /// Asks before deleting everything but the best copy in the duplicate group at the index
+    KeepBestDuplicate(usize),
+    ConfirmDeleteDuplicates(),
+    CancelDeleteDuplicates(),
+    /// Hides the duplicate group at the index without deleting anything
+    IgnoreDuplicates(usize),
+    DuplicateDeleted(Result<(), String>),
     DownloadUpdated(DownloadStatus),
+    /// Where String == image.id
+    PauseDownload(String),
+    ResumeDownload(String),
+    CancelDownload(String),
+    /// Redraws the countdown while downloads are throttled
+    ThrottleTick(),
+    RetryDownload(String),
     SetMinimumResolution(XYCombo),
     ChangeConcurrentDownloads(i32),
     Scroll(Viewport),
     NextPage(),
+    PageInputChanged(String),
+    /// Replaces the results with the page typed into the page input
+    GoToPage(),
     /// Downloads the preview, usize is an index into the currently downloaded results.
     DownloadPreview(usize),
+    /// Starts downloading the full size of a preview that data saver held back
+    LoadFullPreview(),
     CancelPreview(),
     UpdatePreviewMode(PreviewMode),
+    PreviewUpdated(PreviewStatus),
+    /// Large thumbnail shown while the preview with the given request downloads
+    PreviewThumbnailReceived(u64, Option<image::Handle>),
+    PreviousPreview(),
+    NextPreview(),
+    SetPreviewZoom(PreviewZoom),
+    ToggleMonitorFit(),
+    SetMonitorResolution(XYCombo),
+    SpeedLimitChanged(String),
+    ZoomIn(),
+    ZoomOut(),
+    /// Toggles the selection of the wallpaper being previewed
+    SelectPreviewed(),
+    /// Queues the wallpaper being previewed without waiting for the rest of the cart
+    DownloadPreviewed(),
+    /// Opens the given url in the default browser
+    OpenInBrowser(String),
+    CopyToClipboard(String),
+    /// Copies the active search as a wallhaven.cc link
+    CopySearchLink(),
+    /// Copies the full size image being previewed to the clipboard
+    CopyPreviewImage(),
+    /// Holds the error message if copying the image failed
+    ImageCopied(Result<(), String>),
+    /// Shows the file in the platform's file manager
+    RevealFile(PathBuf),
+    /// Where String == image.id, shows the downloaded file in the file manager
+    RevealDownloaded(String),
+    /// Where String == image.id, copies the downloaded image to paste it into other apps
+    CopyDownloadedImage(String),
+    FileRevealed(Result<(), String>),
+    ToggleHistory(),
+    /// Re-runs the search at the given index of the history
+    RecallSearch(usize),
+    DeleteHistoryEntry(usize),
+    MoveFocus(FocusDirection),
+    /// Moves the keyboard focus to the next text input, where bool == forward
+    FocusInput(bool),
+    ToggleFocusedSelection(),
+    PreviewFocused(),
+    /// Closes whatever is open, the preview or a submenu
+    Escape(),
+    ResultFilterChanged(ResultFilter),
+    SetLocalSort(Option<LocalSort>),
+    NewTab(),
+    /// Switches to the tab searching the downloaded wallpapers, opening one if needed
+    OpenLibrary(),
+    /// Index into the open tabs
+    SelectTab(usize),
+    CloseTab(usize),
 }
 
 #[derive(Default, Debug, Clone)]
@@ -146,15 +864,73 @@ pub(crate) enum PreviewMode {
     Disable,
     /// User has requested a full screen preview, but we don't have the full size downloaded
     PreviewRequestDownloading {
-        /// Image handle to the small thumbnail
+        /// Shown at the preview's size until it's there, the small thumbnail is swapped for the
+        /// large one once that's fetched
+        preview_handle: image::Handle,
+        /// Dropping the download by changing the preview mode cancels it
+        download: PreviewDownload,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// Data saver is on, so the small thumbnail is shown until the full size is asked for
+    PreviewThumbnail {
         preview_handle: image::Handle,
-        cancel_mechanism: tokio::sync::mpsc::Sender<()>,
+        download: PreviewDownload,
     },
     /// Handle to the downloaded image
     PreviewView(image::Handle),
     PreviewFailed,
 }
 
+/// How the full size preview is scaled
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub(crate) enum PreviewZoom {
+    /// Fits the window, the mouse wheel can still zoom and pan
+    #[default]
+    Fit,
+    /// Fixed scale where 1.0 shows every pixel of the wallpaper
+    Scale(f32),
+}
+
+impl PreviewZoom {
+    const STEPS: [f32; 9] = [0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0];
+
+    /// Zooming in from fit starts at 100%, since wallpapers are usually larger than the window
+    fn zoom_in(self) -> Self {
+        match self {
+            PreviewZoom::Fit => PreviewZoom::Scale(1.0),
+            PreviewZoom::Scale(scale) => PreviewZoom::Scale(
+                Self::STEPS
+                    .into_iter()
+                    .find(|step| *step > scale)
+                    .unwrap_or(scale),
+            ),
+        }
+    }
+
+    fn zoom_out(self) -> Self {
+        match self {
+            PreviewZoom::Fit => PreviewZoom::Scale(0.5),
+            PreviewZoom::Scale(scale) => PreviewZoom::Scale(
+                Self::STEPS
+                    .into_iter()
+                    .rev()
+                    .find(|step| *step < scale)
+                    .unwrap_or(scale),
+            ),
+        }
+    }
+}
+
+impl Display for PreviewZoom {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreviewZoom::Fit => write!(f, "fit"),
+            PreviewZoom::Scale(scale) => write!(f, "{:.0}%", scale * 100.0),
+        }
+    }
+}
+
 #[derive(Default, Debug, Eq, PartialEq, Clone)]
 pub(crate) enum Submenu {
     #[default]
@@ -162,6 +938,20 @@ pub(crate) enum Submenu {
     Settings,
     Resolution,
     AspectRatio,
+    SelectBy,
+    Filters,
+    Cart,
+    Downloads,
+    Import,
+    Duplicates,
+    Monitors,
+    Watches,
+    Daily,
+    Collections,
+    /// Changelog of the newer release
+    Update,
+    /// Asks for the PIN before sketchy or nsfw can be picked
+    PurityLock,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -183,65 +973,256 @@ pub enum WallGuiError {
 
 pub type GuiResult<T> = Result<T>;
 
+/// Color of a result, telling whether it's selected or how far along its download is
+fn result_style(state: ImageState) -> button_style::Button {
+    match state {
+        ImageState::Selected => button_style::Button::Primary,
+        ImageState::Unselected => button_style::Button::Inactive,
+        ImageState::Queued => button_style::Button::Downloading,
+        ImageState::Downloading(_) => button_style::Button::Downloading,
+        ImageState::Downloaded => button_style::Button::Downloaded,
+        ImageState::Failed => button_style::Button::Failed,
+    }
+}
+
+/// Warns that a wallpaper is smaller than the user's monitor, None when it isn't or no monitor
+/// resolution is set
+fn upscale_badge<'a>(
+    listing: &ListingData,
+    monitor: Option<XYCombo>,
+) -> Option<Container<'a, WallpaperMessage>> {
+    let image = XYCombo {
+        x: listing.dimension_x as i32,
+        y: listing.dimension_y as i32,
+    };
+    match monitor {
+        Some(monitor) if is_upscaled(image, monitor) => Some(make_badge(
+            "smaller than your monitor",
+            Color::from_rgb(0.992, 0.6, 0.3),
+        )),
+        _ => None,
+    }
+}
+
+fn category_name(category: &Category) -> &'static str {
+    match category {
+        Category::Anime => "Anime",
+        Category::People => "People",
+        Category::General => "General",
+    }
+}
+
+/// Like "up to 45 requests a minute"
+fn describe_rate_limit(limit: &RateLimiter) -> String {
+    let period = match limit.period().as_secs() {
+        60 => "a minute".to_string(),
+        3600 => "an hour".to_string(),
+        seconds => format!("every {} seconds", seconds),
+    };
+    format!("up to {} requests {}", limit.requests(), period)
+}
+
+/// Resolution, file, purity, category, popularity and upload date of a wallpaper, shown when
+/// hovering a result and in its details
+fn listing_metadata<'a>(listing: &ListingData) -> Column<'a, WallpaperMessage> {
+    let column = Column::new()
+        .spacing(2)
+        .push(Text::new(format!(
+            "{}x{} ({})",
+            listing.dimension_x, listing.dimension_y, listing.ratio
+        )))
+        .push(Text::new(format!(
+            "{} {}",
+            file_size_format(listing.file_size),
+            listing.file_type
+        )))
+        .push(
+            Row::new()
+                .spacing(5)
+                .push(Text::new(category_name(&listing.category)))
+                .push(purity_badge(listing.purity)),
+        )
+        .push(Text::new(format!(
+            "{} favorites, {} views",
+            trendy_number_format(listing.favorites as f64),
+            trendy_number_format(listing.views as f64)
+        )))
+        .push(Text::new(format!("uploaded {}", listing.created_at)));
+    match attribution(listing) {
+        Some(attribution) => column.push(Text::new(attribution)),
+        None => column,
+    }
+}
+
+/// Credits the photographer of an unsplash photo, `profile` is where their other photos are
+fn photographer_button<'a>(
+    photographer: &'a Uploader,
+    profile: &str,
+    attribution: String,
+) -> Tooltip<'a, WallpaperMessage> {
+    Tooltip::new(
+        make_button_fa(&photographer.username, "camera")
+            .width(Length::Fixed(110.0))
+            .on_press(WallpaperMessage::OpenInBrowser(profile.to_string())),
+        Text::new(attribution).size(14),
+        tooltip::Position::Bottom,
+    )
+    .gap(4)
+    .padding(6)
+    .style(iced::theme::Container::Box)
+}
+
+/// Colored label showing the purity of a single wallpaper
+fn purity_badge<'a>(purity: PurityLevel) -> Container<'a, WallpaperMessage> {
+    match purity {
+        PurityLevel::Sfw => make_badge("sfw", Color::from_rgb(0.467, 0.867, 0.467)),
+        PurityLevel::Sketchy => make_badge("sketchy", Color::from_rgb(0.992, 0.85, 0.3)),
+        PurityLevel::Nsfw => make_badge("nsfw", Color::from_rgb(0.95, 0.3, 0.3)),
+    }
+}
+
+/// Results on each page of a library search, the same as wallhaven
+const LIBRARY_PAGE_SIZE: usize = 24;
+
+/// Number of wallpapers in each row of the results grid
+const GRID_COLUMNS: usize = 5;
+
+/// Height of the buttons under each thumbnail without their padding
+const TILE_BUTTONS_HEIGHT: f32 = 28.0;
+
+/// Room under the buttons for the download's progress or why it failed
+const TILE_STATUS_HEIGHT: f32 = 30.0;
+
+/// Height of each line in the results list
+const LIST_ROW_HEIGHT: f32 = 32.0;
+
+/// Every tile is as tall, so the rows that aren't built can be stood in for by space of the same
+/// height
+fn grid_tile_height() -> f32 {
+    let density = density();
+    let thumbnail = THUMBNAIL_HEIGHT as f32 + 2.0 * density.spacing() as f32;
+    let buttons = TILE_BUTTONS_HEIGHT + 2.0 * density.padding() as f32;
+    // the focus border is padded on both sides
+    thumbnail + buttons + TILE_STATUS_HEIGHT + 6.0
+}
+
+/// Most tags suggested under the search box
+const TAG_SUGGESTIONS: usize = 8;
+
+/// How many viewport heights from the end of the results the next page starts loading
+const PREFETCH_VIEWPORTS: f32 = 1.5;
+
+/// Whether the results are scrolled close enough to the end to fetch the next page. Without
+/// `prefetch` only the end itself is.
+fn near_end(viewport: &Viewport, prefetch: bool) -> bool {
+    let remaining = viewport.content_bounds().height
+        - (viewport.absolute_offset().y + viewport.bounds().height);
+    match prefetch {
+        true => remaining <= viewport.bounds().height * PREFETCH_VIEWPORTS,
+        // fractional offsets can stop a pixel short of the end
+        false => remaining <= 1.0,
+    }
+}
+
+/// Id of the scrollable holding the search results, used to restore the scroll position of tabs
+fn results_scrollable_id() -> scrollable::Id {
+    scrollable::Id::new("results")
+}
+
 impl WallpaperUi {
     async fn fetch_image(
+        client: WallhavenClient,
         data: ListingData,
-        mut storage_directory: PathBuf,
-    ) -> Result<(ListingData, ImageView), reqwest::Error> {
-        let bytes = reqwest::get(&data.thumbs.small).await?.bytes().await?;
-        storage_directory.push(data.path.split('/').last().unwrap_or(""));
-        let state = match metadata(storage_directory).await {
-            Ok(_) => ImageState::Downloaded,
-            Err(_) => ImageState::Unselected,
+        library: Library,
+    ) -> Result<(ListingData, ImageView)> {
+        let source = ThumbnailSource::Url {
+            client: client.http().clone(),
+            url: data.thumbs.small.clone(),
+        };
+        let image_handle = load_thumbnail(&data.id, source).await?;
+        let state = match library.check_downloaded(data.id.clone()).await {
+            true => ImageState::Downloaded,
+            false => ImageState::Unselected,
         };
 
         let result = ImageView {
             state,
-            image_handle: image::Handle::from_memory(bytes.as_ref().to_vec()),
+            image_handle,
+            file: None,
         };
         Ok((data, result))
     }
 
-    async fn fetch_full_image(url: String) -> Result<image::Handle, anyhow::Error> {
-        let bytes = reqwest::get(url).await?.bytes().await?;
-        Ok(spawn_blocking(move || {
-            if let Ok(image) = image_rs::load_from_memory(&bytes) {
-                let pixels: Vec<_> = image.to_rgba8().pixels().flat_map(|m| m.0).collect();
-                image::Handle::from_pixels(image.width(), image.height(), pixels)
-            } else {
-                warn!("Failed to convert image ourselves, letting Iced try.");
-                image::Handle::from_memory(bytes.to_vec())
-            }
-        })
-        .await?)
-    }
-
+    /// None when wallhaven couldn't be reached, see [WallpaperMessage::SearchOffline]
     async fn search_command(
+        client: WallhavenClient,
         options: SearchOptions,
-        directory: PathBuf,
-    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
-        match WallpaperUi::do_search(options, directory).await {
-            Ok(search) => search,
+        library: Library,
+    ) -> Option<GenericResponse<Vec<(ListingData, ImageView)>>> {
+        match WallpaperUi::do_search(client, options, library).await {
+            Ok(search) => Some(search),
+            Err(e) if is_search_connection_error(&e) => {
+                info!("Lost the connection searching {:?}", e);
+                None
+            }
             Err(e) => {
                 error!("{:3?}", e);
-                GenericResponse {
+                Some(GenericResponse {
                     error: Some(e.to_string()),
                     ..Default::default()
-                }
+                })
+            }
+        }
+    }
+
+    /// Only wallhaven turning the key down makes it invalid, failing to ask leaves it unchecked
+    async fn validate_api_key(client: WallhavenClient, api_key: String) -> ApiKeyStatus {
+        match client.user_settings(&api_key).await {
+            Ok(settings) => ApiKeyStatus::Valid(settings),
+            Err(WallhavenApiClientError::InvalidApiKey) => {
+                ApiKeyStatus::Invalid("wallhaven.cc doesn't accept this key".to_string())
+            }
+            Err(WallhavenApiClientError::Reqwest(e)) if is_connection_error(&e) => {
+                ApiKeyStatus::CouldNotCheck(CONNECTION_ERROR.to_string())
+            }
+            Err(WallhavenApiClientError::Reqwest(e)) => {
+                error!("Failed to check the api key {:?}", e);
+                let reason = match e.status() {
+                    Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+                        "wallhaven.cc is busy".to_string()
+                    }
+                    Some(status) => format!("wallhaven.cc answered {}", status),
+                    None => e.to_string(),
+                };
+                ApiKeyStatus::CouldNotCheck(reason)
+            }
+            Err(e) => {
+                error!("Failed to check the api key {:?}", e);
+                ApiKeyStatus::CouldNotCheck(e.to_string())
             }
         }
     }
 
     async fn do_search(
+        client: WallhavenClient,
         options: SearchOptions,
-        directory: PathBuf,
+        library: Library,
+    ) -> GuiResult<GenericResponse<Vec<(ListingData, ImageView)>>> {
+        let response = client.search(&options).await?;
+        WallpaperUi::fetch_images(client, response, library).await
+    }
+
+    /// Downloads the thumbnails of a page of results
+    async fn fetch_images(
+        client: WallhavenClient,
+        response: GenericResponse<Vec<ListingData>>,
+        library: Library,
     ) -> GuiResult<GenericResponse<Vec<(ListingData, ImageView)>>> {
-        let response = WallhavenClient::search(&options).await?;
         if let Some(data) = response.data {
             info!("Received {} search results", &data.len());
             let images: Vec<_> = data
                 .into_iter()
-                .map(|listing| WallpaperUi::fetch_image(listing, directory.clone()))
+                .map(|listing| WallpaperUi::fetch_image(client.clone(), listing, library.clone()))
                 .collect();
             let joined = futures::future::join_all(images).await;
             let map: Vec<_> = joined.into_iter().filter_map(|m| m.ok()).collect();
@@ -261,454 +1242,1916 @@ impl WallpaperUi {
         .into())
     }
 
-    async fn choose_directory() -> Option<PathBuf> {
-        FileDialog::new().show_open_single_dir().ok().flatten()
+    /// A page of unsplash's photos with their thumbnails
+    async fn unsplash_command(
+        client: WallhavenClient,
+        access_key: Option<String>,
+        query: String,
+        page: i64,
+        library: Library,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        let access_key = match access_key {
+            Some(access_key) => access_key,
+            None => {
+                return GenericResponse {
+                    error: Some("Add an unsplash access key in the settings first".to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+        let (photos, meta) = match fetch_photos(client.http(), &access_key, &query, page).await {
+            Ok(photos) => photos,
+            Err(e) => {
+                error!("Failed to fetch unsplash photos {:?}", e);
+                let error = match is_search_connection_error(&e) {
+                    true => CONNECTION_ERROR.to_string(),
+                    false => e.to_string(),
+                };
+                return GenericResponse {
+                    error: Some(error),
+                    ..Default::default()
+                };
+            }
+        };
+        let response = GenericResponse {
+            meta: Some(meta),
+            data: Some(photos),
+            error: None,
+        };
+        WallpaperUi::fetch_images(client, response, library)
+            .await
+            .unwrap_or_else(|e| {
+                error!("{:3?}", e);
+                GenericResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            })
     }
 
-    /// guesstimate our loading status based on our page
-    fn get_loading_status(&self) -> Text {
-        let page = self.search_options.page.unwrap_or(1) as i64;
-        let is_loading = match &self.search_meta {
-            Some(meta) => meta.current_page != page,
-            None => true, // if this is none, we haven't received anything yet
+    /// A page of a subreddit's image posts with their thumbnails, along with where the page
+    /// after it starts
+    async fn reddit_command(
+        client: WallhavenClient,
+        subreddit: String,
+        query: String,
+        after: Option<String>,
+        page: i64,
+        library: Library,
+    ) -> (
+        Option<String>,
+        GenericResponse<Vec<(ListingData, ImageView)>>,
+    ) {
+        let reddit_page =
+            match fetch_subreddit(client.http(), &subreddit, &query, after.as_deref()).await {
+                Ok(reddit_page) => reddit_page,
+                Err(e) => {
+                    error!("Failed to fetch r/{} {:?}", subreddit, e);
+                    let error = match is_search_connection_error(&e) {
+                        true => CONNECTION_ERROR.to_string(),
+                        false => e.to_string(),
+                    };
+                    let response = GenericResponse {
+                        error: Some(error),
+                        ..Default::default()
+                    };
+                    return (None, response);
+                }
+            };
+        let response = GenericResponse {
+            meta: Some(reddit_page.meta(page)),
+            data: Some(reddit_page.wallpapers),
+            error: None,
         };
-        debug!(
-            "calculated loading status {:?} page {:?}",
-            self.search_meta, self.search_options.page
-        );
-        let loading_text = if is_loading { "Loading..." } else { "" };
-        Text::new(loading_text).size(42)
+        match WallpaperUi::fetch_images(client, response, library).await {
+            Ok(response) => (reddit_page.after, response),
+            Err(e) => {
+                error!("{:3?}", e);
+                let response = GenericResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                };
+                (None, response)
+            }
+        }
     }
-}
-
-impl Application for WallpaperUi {
-    type Executor = executor::Default;
-    type Message = WallpaperMessage;
-    type Flags = Option<SavedSettings>;
 
-    fn new(flags: Self::Flags) -> (Self, Command<WallpaperMessage>) {
-        let key = flags.clone().unwrap_or_default().api_key;
-        (
-            Self {
-                settings: flags.unwrap_or_default(),
-                search_options: SearchOptions {
-                    api_key: key.clone(),
-                    ..Default::default()
-                },
-                api_key: key.unwrap_or_default(),
-                concurrent_download_control: IncrementControl { value: 5 },
-                ..Self::default()
+    async fn collection_command(
+        client: WallhavenClient,
+        collection: OpenCollection,
+        options: SearchOptions,
+        library: Library,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        let response = client
+            .collection_wallpapers(&collection.username, collection.id, &options)
+            .await;
+        match response {
+            Ok(response) => match WallpaperUi::fetch_images(client, response, library).await {
+                Ok(page) => page,
+                Err(e) => {
+                    error!("{:3?}", e);
+                    GenericResponse {
+                        error: Some(e.to_string()),
+                        ..Default::default()
+                    }
+                }
             },
-            Command::perform(
-                WallpaperUi::search_command(SearchOptions::default(), "./".into()),
-                WallpaperMessage::SearchReceived,
-            ),
-        )
+            Err(WallhavenApiClientError::Reqwest(e)) if is_connection_error(&e) => {
+                GenericResponse {
+                    error: Some(CONNECTION_ERROR.to_string()),
+                    ..Default::default()
+                }
+            }
+            Err(e) => {
+                error!("Failed to fetch collection {:?}", e);
+                GenericResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        }
     }
 
-    fn title(&self) -> String {
-        "wall-a-bunga".to_string()
+    async fn collections_command(
+        client: WallhavenClient,
+        username: Option<String>,
+        api_key: Option<String>,
+    ) -> Result<(Option<String>, Vec<Collection>), String> {
+        match client
+            .collections(username.as_deref(), api_key.as_deref())
+            .await
+        {
+            Ok(response) => match (response.data, response.error) {
+                (Some(collections), _) => Ok((username, collections)),
+                (None, Some(e)) => Err(e),
+                (None, None) => Err("wallhaven.cc didn't send any collections".to_string()),
+            },
+            Err(WallhavenApiClientError::InvalidApiKey) => {
+                Err("wallhaven.cc doesn't accept the api key".to_string())
+            }
+            Err(WallhavenApiClientError::Reqwest(e)) if is_connection_error(&e) => {
+                Err(CONNECTION_ERROR.to_string())
+            }
+            Err(WallhavenApiClientError::Reqwest(e))
+                if e.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                Err("There's no user with that name".to_string())
+            }
+            Err(e) => {
+                error!("Failed to list collections {:?}", e);
+                Err(e.to_string())
+            }
+        }
     }
 
-    fn update(&mut self, message: WallpaperMessage) -> Command<WallpaperMessage> {
-        match message {
-            WallpaperMessage::Search() => {
-                self.search_options.set_query(self.search_value.clone());
-                self.search_options.page = None;
-                let mut rng = thread_rng();
-                self.search_options.seed = Some(rng.next_u64().to_string());
-                self.search_results.clear();
-                self.preview_mode = PreviewMode::Disable;
-                return Command::perform(
-                    WallpaperUi::search_command(
-                        self.search_options.clone(),
-                        self.settings
-                            .save_directory
-                            .as_ref()
-                            .unwrap_or(&"./".to_string())
-                            .into(),
-                    ),
-                    WallpaperMessage::SearchReceived,
-                );
-            }
-            WallpaperMessage::SearchUpdated(msg) => {
-                self.search_value = msg;
+    /// Details of a wallpaper that search results leave out, such as its uploader
+    async fn wallpaper_details(
+        client: WallhavenClient,
+        id: String,
+        api_key: Option<String>,
+    ) -> Option<Box<ListingData>> {
+        match client.wallpaper(&id, api_key.as_deref()).await {
+            Ok(response) => response.data.map(Box::new),
+            Err(e) => {
+                debug!("Failed to fetch the details of {} {:?}", id, e);
+                None
             }
-            WallpaperMessage::SearchReceived(mut values) => {
-                if let Some(data) = &mut values.data {
-                    info!("Updated search results");
-                    self.search_results.append(data);
-                } else if let Some(error) = values.error {
-                    self.error_message = error;
+        }
+    }
+
+    async fn fetch_avatar(client: WallhavenClient, url: String) -> Option<image::Handle> {
+        let request = client.http().get(&url);
+        let bytes = async { request.send().await?.error_for_status()?.bytes().await };
+        match bytes.await {
+            Ok(bytes) => Some(image::Handle::from_memory(bytes.to_vec())),
+            Err(e) => {
+                debug!("Failed to fetch avatar {} {:?}", url, e);
+                None
+            }
+        }
+    }
+
+    async fn wallpaper_command(
+        client: WallhavenClient,
+        id: String,
+        api_key: Option<String>,
+        library: Library,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        match WallpaperUi::do_fetch_wallpaper(client, id, api_key, library).await {
+            Ok(wallpaper) => wallpaper,
+            Err(e) => {
+                error!("{:3?}", e);
+                GenericResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
                 }
-                debug!("Updating search meta: {:?}", values.meta);
-                self.search_meta = values.meta;
-            }
-            WallpaperMessage::SelectionUpdate(option) => {
-                match option {
-                    SelectionUpdateType::Single(id) => {
-                        let image = self.search_results.iter_mut().find(|(l, _)| l.id == id);
-                        if let Some((_, result_data)) = image {
-                            // toggle checked
-                            result_data.state = match result_data.state {
-                                ImageState::Unselected => ImageState::Selected,
-                                ImageState::Selected => ImageState::Unselected,
-                                ImageState::Failed => ImageState::Selected,
-                                // default return same state
-                                _ => result_data.state,
-                            }
-                        }
-                    }
-                    SelectionUpdateType::SelectAll => {
-                        for (_, r) in &mut self.search_results {
-                            r.state = match r.state {
-                                ImageState::Unselected => ImageState::Selected,
-                                _ => r.state,
-                            }
-                        }
-                    }
-                    SelectionUpdateType::DeselectAll => {
-                        for (_, r) in &mut self.search_results {
-                            r.state = match r.state {
-                                ImageState::Selected => ImageState::Unselected,
-                                _ => r.state,
-                            }
-                        }
+            }
+        }
+    }
+
+    /// Scales the downloaded files down to thumbnails, the full size file is shown for those that
+    /// can't be
+    async fn library_thumbnails(
+        results: Vec<(ListingData, PathBuf)>,
+    ) -> Vec<(ListingData, ImageView)> {
+        let results = results.into_iter().map(|(listing, path)| async move {
+            let source = ThumbnailSource::File(path.clone());
+            let image_handle = match load_thumbnail(&listing.id, source).await {
+                Ok(thumbnail) => thumbnail,
+                Err(e) => {
+                    error!("Failed to load the thumbnail of {:?} {}", path, e);
+                    image::Handle::from_path(&path)
+                }
+            };
+            let image = ImageView {
+                state: ImageState::Downloaded,
+                image_handle,
+                file: Some(path),
+            };
+            (listing, image)
+        });
+        futures::future::join_all(results).await
+    }
+
+    /// Searches the downloaded wallpapers, shaped like an online search so the results can share
+    /// the grid
+    async fn library_search_command(
+        library: Library,
+        options: SearchOptions,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        WallpaperUi::files_search_command(
+            options,
+            // the library tab doesn't show wallpapers that were deleted since
+            move |options| {
+                library.prune_missing()?;
+                library.search(options)
+            },
+            LibraryEntry::to_listing,
+        )
+        .await
+    }
+
+    /// Searches the images in a folder the same way as the library
+    async fn folder_search_command(
+        index: FolderIndex,
+        folder: PathBuf,
+        options: SearchOptions,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        WallpaperUi::files_search_command(
+            options,
+            move |options| index.search(&folder, options),
+            folder::to_listing,
+        )
+        .await
+    }
+
+    /// Pages through the files `search` finds, `to_listing` turns the ones on the page into
+    /// results
+    async fn files_search_command(
+        options: SearchOptions,
+        search: impl FnOnce(&SearchOptions) -> Result<Vec<LibraryEntry>> + Send + 'static,
+        to_listing: fn(&LibraryEntry) -> ListingData,
+    ) -> GenericResponse<Vec<(ListingData, ImageView)>> {
+        let page = options.page.unwrap_or(1).max(1) as usize;
+        let result = spawn_blocking(move || {
+            search(&options).map(|entries| {
+                let total = entries.len();
+                let results: Vec<_> = entries
+                    .into_iter()
+                    .skip((page - 1) * LIBRARY_PAGE_SIZE)
+                    .take(LIBRARY_PAGE_SIZE)
+                    .map(|entry| (to_listing(&entry), entry.path))
+                    .collect();
+                (results, total)
+            })
+        })
+        .await;
+        match result {
+            Ok(Ok((results, total))) => GenericResponse {
+                data: Some(WallpaperUi::library_thumbnails(results).await),
+                error: None,
+                meta: Some(SearchMetaData {
+                    current_page: page as i64,
+                    last_page: total.div_ceil(LIBRARY_PAGE_SIZE).max(1) as i64,
+                    per_page: LIBRARY_PAGE_SIZE as i64,
+                    total: total as i64,
+                    ..Default::default()
+                }),
+            },
+            Ok(Err(e)) => GenericResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+            Err(e) => GenericResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Fetches a single wallpaper, shaped like a one page search so it can be shown as results
+    async fn do_fetch_wallpaper(
+        client: WallhavenClient,
+        id: String,
+        api_key: Option<String>,
+        library: Library,
+    ) -> GuiResult<GenericResponse<Vec<(ListingData, ImageView)>>> {
+        let response = client.wallpaper(&id, api_key.as_deref()).await?;
+        if let Some(listing) = response.data {
+            let wallpaper = WallpaperUi::fetch_image(client, listing, library).await?;
+            return Ok(GenericResponse {
+                data: Some(vec![wallpaper]),
+                error: response.error,
+                meta: Some(SearchMetaData {
+                    current_page: 1,
+                    last_page: 1,
+                    per_page: 1,
+                    total: 1,
+                    ..Default::default()
+                }),
+            });
+        }
+
+        Err(WallGuiError::BadResponse(
+            response
+                .error
+                .unwrap_or_else(|| "No error message".to_string()),
+        )
+        .into())
+    }
+
+    /// Forgets wallpapers that were deleted, then adds wallpapers already in the directory to the
+    /// library
+    async fn index_directory(library: Library, directory: PathBuf) {
+        let result = spawn_blocking(move || {
+            library.prune_missing()?;
+            library.import_directory(&directory)
+        })
+        .await;
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("Failed to index the save directory {:?}", e),
+            Err(e) => error!("Indexing task failed {:?}", e),
+        }
+    }
+
+    /// Indexes the directory, then checks which of the given wallpapers have been downloaded
+    async fn find_downloaded(
+        library: Library,
+        directory: PathBuf,
+        ids: Vec<String>,
+    ) -> Vec<(String, bool)> {
+        WallpaperUi::index_directory(library.clone(), directory).await;
+        let result = spawn_blocking(move || {
+            ids.into_iter()
+                .map(|id| {
+                    let downloaded = library.is_downloaded(&id);
+                    (id, downloaded)
+                })
+                .collect()
+        })
+        .await;
+        match result {
+            Ok(downloaded) => downloaded,
+            Err(e) => {
+                error!("Library lookup failed {:?}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// Hashes a finished download and adds it to the library
+    async fn record_download(library: Library, mut entry: LibraryEntry) -> Result<(), String> {
+        spawn_blocking(move || {
+            match file_hash(&entry.path) {
+                Ok(hash) => entry.hash = Some(hash),
+                Err(e) => error!("Failed to hash {:?} {:?}", entry.path, e),
+            }
+            library.record(&entry)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+    }
+
+    /// Where the wallpaper is on disk. Images of a folder source aren't in the library, so the
+    /// results are looked through for them.
+    fn downloaded_file(&self, id: &str) -> Result<Option<PathBuf>> {
+        if let Some(entry) = self.library.find(id)? {
+            return Ok(Some(entry.path));
+        }
+        Ok(self
+            .tabs
+            .iter()
+            .flat_map(|tab| &tab.search_results)
+            .find(|(listing, _)| listing.id == id)
+            .and_then(|(_, image)| image.file.clone()))
+    }
+
+    async fn choose_directory() -> Option<PathBuf> {
+        FileDialog::new().show_open_single_dir().ok().flatten()
+    }
+
+    fn preview_zoom_controls(&self) -> Row<'_, WallpaperMessage> {
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(labeled(
+                make_button_fa("", "search-minus").on_press(WallpaperMessage::ZoomOut()),
+                "Zoom out",
+            ))
+            .push(
+                Text::new(self.preview.zoom.to_string())
+                    .size(21)
+                    .width(Length::Fixed(50.0))
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            )
+            .push(labeled(
+                make_button_fa("", "search-plus").on_press(WallpaperMessage::ZoomIn()),
+                "Zoom in",
+            ))
+            .push(
+                make_button("100%")
+                    .style(inactive_style(self.preview.zoom == PreviewZoom::Scale(1.0)))
+                    .on_press(WallpaperMessage::SetPreviewZoom(PreviewZoom::Scale(1.0))),
+            )
+            .push(
+                make_button("fit")
+                    .style(inactive_style(self.preview.zoom == PreviewZoom::Fit))
+                    .on_press(WallpaperMessage::SetPreviewZoom(PreviewZoom::Fit)),
+            )
+            .push(match self.settings.monitor_resolution {
+                Some(_) => make_button_fa("monitor fit", "desktop")
+                    .style(inactive_style(self.preview.show_monitor_fit))
+                    .on_press(WallpaperMessage::ToggleMonitorFit()),
+                // nothing to compare against until a monitor is chosen in the settings
+                None => make_button_fa("monitor fit", "desktop").style(inactive_style(false)),
+            })
+    }
+
+    /// The preview cropped to the aspect ratio of the user's monitor, along with how much is lost
+    fn monitor_fit_preview<'a>(
+        image: &image::Handle,
+        listing: &ListingData,
+        monitor: XYCombo,
+    ) -> Column<'a, WallpaperMessage> {
+        // largest box with the monitor's aspect ratio that fits on screen
+        let scale = f32::min(1280.0 / monitor.x as f32, 720.0 / monitor.y as f32);
+        let cropped = cropped_pixels(
+            XYCombo {
+                x: listing.dimension_x as i32,
+                y: listing.dimension_y as i32,
+            },
+            monitor,
+        );
+        Column::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(
+                Container::new(
+                    Image::new(image.clone())
+                        .content_fit(ContentFit::Cover)
+                        .width(Length::Fixed(monitor.x as f32 * scale))
+                        .height(Length::Fixed(monitor.y as f32 * scale)),
+                )
+                .style(iced::theme::Container::Custom(Box::new(
+                    container_style::Focused,
+                ))),
+            )
+            .push(Text::new(format!(
+                "on {}: {}px cropped horizontally, {}px cropped vertically",
+                monitor, cropped.x, cropped.y
+            )))
+    }
+
+    /// The full size preview, scaled to the chosen zoom and scrollable in both directions
+    fn preview_image(&self, image: &image::Handle) -> Element<'_, WallpaperMessage> {
+        if let (true, Some(monitor), Some((listing, _))) = (
+            self.preview.show_monitor_fit,
+            self.settings.monitor_resolution,
+            self.previewed(),
+        ) {
+            return WallpaperUi::monitor_fit_preview(image, listing, monitor).into();
+        }
+        match (self.preview.zoom, self.previewed()) {
+            (PreviewZoom::Scale(scale), Some((listing, _))) => Scrollable::new(
+                Image::new(image.clone())
+                    .width(Length::Fixed(listing.dimension_x as f32 * scale))
+                    .height(Length::Fixed(listing.dimension_y as f32 * scale)),
+            )
+            .direction(scrollable::Direction::Both {
+                vertical: Default::default(),
+                horizontal: Default::default(),
+            })
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            _ => Viewer::new(image.clone()).width(Length::Fill).into(),
+        }
+    }
+
+    fn preview_subscription(&self) -> Subscription<WallpaperMessage> {
+        match &self.preview.mode {
+            PreviewMode::PreviewRequestDownloading { download, .. } => {
+                Subscription::from_recipe(download.clone()).map(WallpaperMessage::PreviewUpdated)
+            }
+            // the same download, so it carries on converting the full resolution
+            PreviewMode::PreviewView(_) => match &self.preview.scaled {
+                Some(download) if download.request == self.preview.request => {
+                    Subscription::from_recipe(download.clone())
+                        .map(WallpaperMessage::PreviewUpdated)
+                }
+                _ => Subscription::none(),
+            },
+            _ => Subscription::none(),
+        }
+    }
+
+    /// Previous and next buttons for the preview, disabled at either end of the results
+    fn preview_navigation(&self) -> Row<'_, WallpaperMessage> {
+        let previous = make_button_fa("previous", "chevron-left");
+        let next = make_button_fa("next", "chevron-right");
+        Row::new()
+            .spacing(5)
+            .push(match self.adjacent_preview(false) {
+                Some(_) => previous.on_press(WallpaperMessage::PreviousPreview()),
+                None => previous.style(inactive_style(false)),
+            })
+            .push(match self.adjacent_preview(true) {
+                Some(_) => next.on_press(WallpaperMessage::NextPreview()),
+                None => next.style(inactive_style(false)),
+            })
+    }
+
+    /// Select, download and open buttons for the wallpaper being previewed
+    fn preview_actions(&self) -> Row<'_, WallpaperMessage> {
+        let (listing, image) = match self.previewed() {
+            Some(previewed) => previewed,
+            None => return Row::new(),
+        };
+        let select = match image.state {
+            ImageState::Selected => make_button_fa("deselect", "check-square")
+                .on_press(WallpaperMessage::SelectPreviewed()),
+            ImageState::Unselected | ImageState::Failed => {
+                make_button_fa("select", "square").on_press(WallpaperMessage::SelectPreviewed())
+            }
+            _ => make_button_fa("select", "square").style(inactive_style(false)),
+        };
+        let download = match image.state {
+            ImageState::Queued | ImageState::Downloading(_) | ImageState::Downloaded => {
+                make_button_fa("download now", "download").style(inactive_style(false))
+            }
+            _ => make_button_fa("download now", "download")
+                .on_press(WallpaperMessage::DownloadPreviewed()),
+        };
+        let wallpaper = make_button_fa("set as wallpaper", "desktop");
+        let wallpaper = match image.state {
+            ImageState::Downloaded => {
+                wallpaper.on_press(WallpaperMessage::SetAsWallpaper(listing.id.clone()))
+            }
+            _ if self.tab().is_on_disk() => wallpaper.style(inactive_style(false)),
+            _ if self.downloads.set_when_downloaded.as_ref() == Some(&listing.id) => {
+                make_button_fa("set once downloaded", "desktop").style(inactive_style(false))
+            }
+            _ => wallpaper.on_press(WallpaperMessage::SetWhenDownloaded(listing.id.clone())),
+        };
+        let row = Row::new()
+            .spacing(5)
+            .push(select)
+            .push(download)
+            .push(wallpaper)
+            .push(
+                make_button_fa("copy image", "copy").on_press(WallpaperMessage::CopyPreviewImage()),
+            )
+            .push(
+                make_button_fa(
+                    match self.tab().is_wallhaven() {
+                        true => "open on wallhaven",
+                        false => "open in browser",
+                    },
+                    "external-link-alt",
+                )
+                .on_press(WallpaperMessage::OpenInBrowser(listing.url.clone())),
+            );
+        let row = match self.tab().roulette {
+            true => {
+                row.push(make_button_fa("reroll", "random").on_press(WallpaperMessage::Reroll()))
+            }
+            false => row,
+        };
+        match (&listing.uploader, attribution(listing)) {
+            // unsplash's photographers have their profile there instead of uploads to search
+            (Some(photographer), Some(attribution)) => row.push(photographer_button(
+                photographer,
+                &listing.source,
+                attribution,
+            )),
+            (Some(uploader), None) => row.push(
+                make_button_fa(&uploader.username, "user")
+                    .on_press(WallpaperMessage::OpenUploader(uploader.clone())),
+            ),
+            (None, _) => row,
+        }
+    }
+
+    /// Heading of the results with the buttons switching between the grid and the list
+    fn layout_toggle(&self) -> Row<'_, WallpaperMessage> {
+        let layout = self.settings.result_layout;
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(Text::new("Search results"))
+            .push(labeled(
+                make_button_fa("", "th")
+                    .padding([2, 6])
+                    .style(inactive_style(layout == ResultLayout::Grid))
+                    .on_press(WallpaperMessage::SetResultLayout(ResultLayout::Grid)),
+                "Show as a grid",
+            ))
+            .push(labeled(
+                make_button_fa("", "list")
+                    .padding([2, 6])
+                    .style(inactive_style(layout == ResultLayout::List))
+                    .on_press(WallpaperMessage::SetResultLayout(ResultLayout::List)),
+                "Show as a list",
+            ))
+    }
+
+    /// The visible results as a table, the resolution, favorites and size headers sort it
+    fn results_list(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let cell = |text: String, width: f32| Text::new(text).width(Length::Fixed(width));
+        let sort_header = |label: &'static str, sort: LocalSort, width: f32| {
+            let active = tab.local_sort == Some(sort);
+            make_button(label)
+                .width(Length::Fixed(width))
+                .padding([2, 6])
+                .style(inactive_style(active))
+                .on_press(WallpaperMessage::SetLocalSort(match active {
+                    true => None,
+                    false => Some(sort),
+                }))
+        };
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(cell("id".to_string(), 90.0))
+            .push(sort_header("resolution", LocalSort::Resolution, 110.0))
+            .push(cell("ratio".to_string(), 60.0))
+            .push(cell("purity".to_string(), 80.0))
+            .push(sort_header("favorites", LocalSort::Favorites, 100.0))
+            .push(sort_header("size", LocalSort::FileSize, 90.0))
+            .push(cell("state".to_string(), 250.0));
+        let column = Column::new()
+            .spacing(2)
+            .push(self.layout_toggle())
+            .push(header);
+        let results: Vec<_> = self.visible_results().collect();
+        let row_height = LIST_ROW_HEIGHT + 2.0;
+        let rows = visible_rows(
+            tab.scroll_top,
+            self.window_geometry.height as f32,
+            row_height,
+            results.len(),
+        );
+        let skipped_before = skipped_height(rows.start, row_height, 2.0);
+        let skipped_after = skipped_height(results.len() - rows.end, row_height, 2.0);
+        let column = match rows.start {
+            0 => column,
+            _ => column.push(Space::with_height(Length::Fixed(skipped_before))),
+        };
+        let column = results
+            .into_iter()
+            .enumerate()
+            .skip(rows.start)
+            .take(rows.len())
+            .fold(column, |column, (position, (index, (listing, image)))| {
+                let state = match image.state {
+                    ImageState::Unselected => String::new(),
+                    ImageState::Selected => "selected".to_string(),
+                    ImageState::Queued => "queued".to_string(),
+                    ImageState::Downloading(Some(progress)) => {
+                        format!("downloading {:.0}%", progress)
                     }
+                    ImageState::Downloading(None) => "downloading".to_string(),
+                    ImageState::Downloaded => "downloaded".to_string(),
+                    ImageState::Failed => match self.download_manager.failure(&listing.id) {
+                        Some(reason) => format!("failed: {}", reason),
+                        None => "failed".to_string(),
+                    },
+                };
+                let details = Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(cell(listing.id.clone(), 90.0))
+                    .push(cell(
+                        format!("{}x{}", listing.dimension_x, listing.dimension_y),
+                        110.0,
+                    ))
+                    .push(cell(listing.ratio.clone(), 60.0))
+                    .push(purity_badge(listing.purity).width(Length::Fixed(80.0)))
+                    .push(cell(trendy_number_format(listing.favorites as f64), 100.0))
+                    .push(cell(file_size_format(listing.file_size), 90.0))
+                    .push(cell(state, 250.0));
+                let details = match upscale_badge(listing, self.settings.monitor_resolution) {
+                    Some(badge) => details.push(badge),
+                    None => details,
+                };
+                let line = Row::new()
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(
+                        Button::new(details)
+                            .padding([2, 6])
+                            .style(iced::theme::Button::Custom(Box::new(result_style(
+                                image.state,
+                            ))))
+                            .on_press(WallpaperMessage::SelectionUpdate(
+                                SelectionUpdateType::Single(listing.id.clone()),
+                            )),
+                    )
+                    .push(labeled(
+                        make_button_fa("", "image")
+                            .padding([2, 6])
+                            .on_press(WallpaperMessage::DownloadPreview(index)),
+                        "Preview",
+                    ))
+                    .push(labeled(
+                        make_button_fa("", "ellipsis-h")
+                            .padding([2, 6])
+                            .on_press(WallpaperMessage::OpenContextMenu(listing.id.clone())),
+                        "More actions",
+                    ));
+                let line = MouseArea::new(line)
+                    .on_right_press(WallpaperMessage::OpenContextMenu(listing.id.clone()));
+                let line = Container::new(line)
+                    .height(Length::Fixed(LIST_ROW_HEIGHT))
+                    .center_y();
+                let column = match tab.focus == Some(position) {
+                    true => column.push(line.style(iced::theme::Container::Custom(Box::new(
+                        container_style::Focused,
+                    )))),
+                    false => column.push(line),
+                };
+                match self
+                    .selection
+                    .context_menu
+                    .as_ref()
+                    .filter(|m| m.id == listing.id)
+                {
+                    Some(menu) => column.push(self.context_menu_view(menu, index, listing, image)),
+                    None => column,
                 }
+            });
+        match skipped_after > 0.0 {
+            true => column.push(Space::with_height(Length::Fixed(skipped_after))),
+            false => column,
+        }
+    }
+
+    /// Avatar and upload count of the user whose uploads the tab lists
+    fn uploader_header(&self) -> Row<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let profile = match &tab.uploader {
+            Some(profile) => profile,
+            None => return Row::new(),
+        };
+        let row = Row::new().spacing(10).align_items(Alignment::Center);
+        let row = match &profile.avatar {
+            Some(avatar) => row.push(
+                Image::new(avatar.clone())
+                    .width(Length::Fixed(64.0))
+                    .height(Length::Fixed(64.0)),
+            ),
+            None => row,
+        };
+        let uploads = match &tab.search_meta {
+            Some(meta) => format!("{} uploads", meta.total),
+            None => String::new(),
+        };
+        row.push(
+            Column::new()
+                .push(Text::new(&profile.uploader.username).size(26))
+                .push(Text::new(format!(
+                    "{}  {}",
+                    profile.uploader.group, uploads
+                ))),
+        )
+    }
+
+    /// Right click menu of a result, with everything that can be done to it or its details
+    fn context_menu_view<'a>(
+        &'a self,
+        menu: &ContextMenu,
+        index: usize,
+        listing: &'a ListingData,
+        image: &ImageView,
+    ) -> Container<'a, WallpaperMessage> {
+        let style = iced::theme::Container::Custom(Box::new(container_style::Focused));
+        let close = make_button_fa("close", "times")
+            .padding([2, 6])
+            .on_press(WallpaperMessage::CloseContextMenu());
+        if menu.details {
+            let mut details = listing_metadata(listing);
+            if let Some(uploader) = &listing.uploader {
+                details = details.push(Text::new(format!("by {}", uploader.username)));
+            }
+            if !listing.tags.is_empty() {
+                let tags: Vec<&str> = listing.tags.iter().map(|tag| tag.name.as_str()).collect();
+                details = details.push(Text::new(tags.join(", ")));
+            }
+            if !listing.source.is_empty() {
+                details = details.push(Text::new(format!("source {}", listing.source)));
+            }
+            return Container::new(details.push(close))
+                .padding(5)
+                .width(Length::Fixed(256.0))
+                .style(style);
+        }
+        let action = |label: &'a str, icon: &str, message: WallpaperMessage| {
+            make_button_fa(label, icon)
+                .padding([2, 6])
+                .width(Length::Fill)
+                .on_press(WallpaperMessage::ContextMenuAction(Box::new(message)))
+        };
+        let select = SelectionUpdateType::Single(listing.id.clone());
+        let mut actions = Column::new().spacing(2);
+        actions = match image.state {
+            ImageState::Selected => actions.push(action(
+                "deselect",
+                "check-square",
+                WallpaperMessage::SelectionUpdate(select),
+            )),
+            ImageState::Unselected | ImageState::Failed => actions.push(action(
+                "select",
+                "square",
+                WallpaperMessage::SelectionUpdate(select),
+            )),
+            _ => actions,
+        };
+        actions = actions.push(action(
+            "preview",
+            "image",
+            WallpaperMessage::DownloadPreview(index),
+        ));
+        actions = match image.state {
+            ImageState::Queued | ImageState::Downloading(_) => actions,
+            ImageState::Downloaded => {
+                let protected = self.protected.contains(&listing.id);
+                actions
+                    .push(action(
+                        "set as wallpaper",
+                        "desktop",
+                        WallpaperMessage::SetAsWallpaper(listing.id.clone()),
+                    ))
+                    .push(action(
+                        if protected { "unprotect" } else { "protect" },
+                        if protected { "lock-open" } else { "lock" },
+                        WallpaperMessage::ToggleProtected(listing.id.clone()),
+                    ))
+                    .push(action(
+                        "open folder",
+                        "folder-open",
+                        WallpaperMessage::RevealDownloaded(listing.id.clone()),
+                    ))
+                    // winit can't start a drag out of the window, pasting gets the file across
+                    .push(action(
+                        "copy image",
+                        "copy",
+                        WallpaperMessage::CopyDownloadedImage(listing.id.clone()),
+                    ))
+            }
+            _ => actions.push(action(
+                "download now",
+                "download",
+                WallpaperMessage::DownloadNow(listing.id.clone()),
+            )),
+        };
+        let actions = actions
+            .push(action(
+                "find similar",
+                "clone",
+                WallpaperMessage::FindSimilar(listing.id.clone()),
+            ))
+            .push(action(
+                "open in browser",
+                "external-link-alt",
+                WallpaperMessage::OpenInBrowser(listing.url.clone()),
+            ))
+            .push(action(
+                "copy url",
+                "link",
+                WallpaperMessage::CopyToClipboard(listing.url.clone()),
+            ))
+            .push(action(
+                "copy image url",
+                "copy",
+                WallpaperMessage::CopyToClipboard(listing.path.clone()),
+            ))
+            .push(
+                action(
+                    "blacklist",
+                    "ban",
+                    WallpaperMessage::BlacklistWallpaper(listing.id.clone()),
+                )
+                .style(inactive_style(false)),
+            )
+            .push(
+                make_button_fa("show details", "info-circle")
+                    .padding([2, 6])
+                    .width(Length::Fill)
+                    .on_press(WallpaperMessage::ShowDetails(listing.id.clone())),
+            )
+            .push(close.width(Length::Fill));
+        Container::new(actions)
+            .padding(5)
+            .width(Length::Fixed(256.0))
+            .style(style)
+    }
+
+    /// guesstimate our loading status based on our page
+    fn get_loading_status(&self) -> Element<'_, WallpaperMessage> {
+        let tab = self.tab();
+        // the failed search can also be retried from where its results end
+        if let Some(banner) = self.banners.get(BannerSource::Search(tab.id)) {
+            return Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(Text::new("Search failed").size(26))
+                .push(
+                    make_button_fa("retry", "redo")
+                        .on_press(WallpaperMessage::RetryBanner(banner.source)),
+                )
+                .into();
+        }
+        let page = tab.search_options.page.unwrap_or(1) as i64;
+        let is_loading = match &tab.search_meta {
+            Some(meta) => meta.current_page != page,
+            None => true, // if this is none, we haven't received anything yet
+        };
+        debug!(
+            "calculated loading status {:?} page {:?}",
+            tab.search_meta, tab.search_options.page
+        );
+        let loading_text = if is_loading { "Loading..." } else { "" };
+        Text::new(loading_text).size(42).into()
+    }
+
+    /// Results of the active tab that pass the view filters in the order they're shown, along
+    /// with their index into the search results
+    fn visible_results(&self) -> impl Iterator<Item = (usize, &(ListingData, ImageView))> {
+        let downloaded_filter = self.settings.downloaded_filter;
+        let filter = &self.tab().filter;
+        // reddit and links don't follow the purity filters, so the lock hides what they bring
+        let lock = self.purity_lock();
+        // everything in the library or a folder is already on disk
+        let local = self.tab().is_on_disk();
+        let mut results: Vec<_> = self
+            .tab()
+            .search_results
+            .iter()
+            .enumerate()
+            .filter(move |(_, (listing, image))| {
+                (local || downloaded_filter.shows(image.state == ImageState::Downloaded))
+                    && filter.matches(listing)
+                    && lock.shows(listing.purity)
+            })
+            .collect();
+        if let Some(sort) = self.tab().local_sort {
+            results.sort_by(|(_, (a, _)), (_, (b, _))| sort.compare(a, b));
+        }
+        results.into_iter()
+    }
+
+    /// Previews stay small thumbnails and pages only load at the end of the results
+    fn saving_data(&self) -> bool {
+        self.settings.data_saver.is_saving(self.metered)
+    }
+
+    /// Sketchy and nsfw wallpapers are kept away until the PIN is entered
+    fn purity_locked(&self) -> bool {
+        self.settings.purity_pin.is_some() && !self.purity_unlocked
+    }
+
+    fn purity_lock(&self) -> PurityLock {
+        PurityLock {
+            locked: self.purity_locked(),
+        }
+    }
+
+    /// The purity new searches start with, kept clean while locked
+    fn default_purity(&self) -> Option<Purity> {
+        self.purity_lock()
+            .allowed(self.settings.default_purity.clone())
+    }
+
+    /// Whether the toggle would turn on sketchy or nsfw while they're locked away
+    fn needs_unlock(&self, toggle: PurityOptions, purity: Option<&Purity>) -> bool {
+        let purity = purity.cloned().unwrap_or_default();
+        self.purity_locked()
+            && match toggle {
+                PurityOptions::Sfw => false,
+                PurityOptions::Sketchy => !purity.sketchy,
+                PurityOptions::Nsfw => !purity.nsfw,
+            }
+    }
+
+    fn focused_result(&self) -> Option<(usize, &(ListingData, ImageView))> {
+        self.visible_results().nth(self.tab().focus?)
+    }
+
+    /// Tags matching what's typed in the search box of a wallhaven tab, none once a tag, link or
+    /// uploader is searched
+    fn tag_suggestions(&self) -> Vec<&Tag> {
+        let tab = self.tab();
+        if !tab.is_wallhaven() || self.search.show_history {
+            return vec![];
+        }
+        let query = QueryBuilder::parse(&tab.search_value);
+        let typed = query.terms().join(" ");
+        if typed.contains(':') || typed.starts_with('@') {
+            return vec![];
+        }
+        self.search.tags.suggest(&typed, TAG_SUGGESTIONS)
+    }
+
+    fn tab(&self) -> &SearchTab {
+        &self.tabs[self.active_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut SearchTab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    fn save_directory(&self) -> PathBuf {
+        self.settings
+            .save_directory
+            .as_ref()
+            .unwrap_or(&"./".to_string())
+            .into()
+    }
+
+    /// What the window is showing for crash reports
+    fn crash_state(&self) -> CrashState {
+        let tab = self.tab();
+        CrashState {
+            tabs: self.tabs.len(),
+            source: (!tab.local).then(|| tab.source.clone()),
+            results: tab.search_results.len(),
+            cart: self.selection.cart.len(),
+            downloads: self.download_manager.len(),
+            submenu: self.controls.submenu.clone(),
+            previewing: !matches!(self.preview.mode, PreviewMode::Disable),
+            onboarding: self.onboarding.is_some(),
+            renderer: self.settings.renderer,
+            theme_mode: self.settings.theme_mode,
+            density: self.settings.density,
+        }
+    }
+
+    /// Hands the crash reporter what the window is showing once it changed
+    fn update_crash_state(&mut self) {
+        let state = self.crash_state();
+        if self.crash_state.as_ref() == Some(&state) {
+            return;
+        }
+        crash::set_state(state.to_string());
+        self.crash_state = Some(state);
+    }
+
+    /// Says whether wallhaven accepts the api key, shown under its input
+    fn api_key_status_view(&self) -> Row<'_, WallpaperMessage> {
+        let row = Row::new()
+            .padding([5, 0])
+            .spacing(10)
+            .align_items(Alignment::Center);
+        match &self.settings_page.api_key_status {
+            ApiKeyStatus::Unchecked => row,
+            ApiKeyStatus::Checking => row.push(Text::new("Checking the key...")),
+            ApiKeyStatus::Valid(settings) => row
+                .push(make_badge(
+                    "✔ valid key",
+                    Color::from_rgb(0.467, 0.867, 0.467),
+                ))
+                .push(Text::new(format!(
+                    "Your account allows {}",
+                    settings.purity.join(", ")
+                ))),
+            ApiKeyStatus::Invalid(e) => row
+                .push(make_badge("invalid key", Color::from_rgb(0.95, 0.3, 0.3)))
+                .push(Text::new(e)),
+            ApiKeyStatus::CouldNotCheck(e) => row
+                .push(make_badge("not checked", Color::from_rgb(0.992, 0.85, 0.3)))
+                .push(Text::new(format!("Couldn't check the key, {}", e))),
+        }
+    }
+
+    /// Sets the PIN that keeps sketchy and nsfw away, changing it needs the current one
+    fn purity_lock_settings(&self) -> Row<'_, WallpaperMessage> {
+        let row = Row::new()
+            .padding([10, 5])
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(Text::new("Purity lock"));
+        let (placeholder, submit) = match (&self.settings.purity_pin, self.purity_unlocked) {
+            (None, _) => ("new PIN", WallpaperMessage::SetPurityPin()),
+            (Some(_), false) => ("PIN", WallpaperMessage::UnlockPurity()),
+            (Some(_), true) => ("new PIN", WallpaperMessage::SetPurityPin()),
+        };
+        let row = row.push(
+            TextInput::new(placeholder, &self.settings_page.pin_value)
+                .secure(true)
+                .style(focus_outline())
+                .on_input(WallpaperMessage::PinUpdated)
+                .on_submit(submit.clone())
+                .width(Length::Fixed(200.0)),
+        );
+        let row = match (&self.settings.purity_pin, self.purity_unlocked) {
+            (None, _) => row
+                .push(make_button("lock").on_press(submit))
+                .push(Text::new("sketchy and nsfw need a PIN to be shown")),
+            (Some(_), false) => row
+                .push(make_button("unlock").on_press(submit))
+                .push(Text::new("locked, enter the PIN to change it")),
+            (Some(_), true) => row
+                .push(make_button("change PIN").on_press(submit))
+                .push(make_button("remove").on_press(WallpaperMessage::RemovePurityPin())),
+        };
+        row.push(Text::new(
+            self.settings_page.pin_error.as_deref().unwrap_or_default(),
+        ))
+    }
+
+    /// Which sources are offered, how often each is asked for wallpapers and the keys they need
+    fn sources_settings(&self) -> Column<'_, WallpaperMessage> {
+        SourceKind::LIST.into_iter().fold(
+            Column::new()
+                .padding([10, 5])
+                .push(Text::new("Sources").size(20)),
+            |column, kind| {
+                let enabled = !self.settings.disabled_sources.contains(&kind);
+                let name: Element<WallpaperMessage> = match kind.can_disable() {
+                    true => Checkbox::new(kind.to_string(), enabled, move |enabled| {
+                        WallpaperMessage::SetSourceEnabled(kind, enabled)
+                    })
+                    .width(Length::Fixed(150.0))
+                    .into(),
+                    false => Text::new(kind.to_string())
+                        .width(Length::Fixed(150.0))
+                        .into(),
+                };
+                let limit = match kind.rate_limit() {
+                    Some(limit) => describe_rate_limit(limit),
+                    None => "no limit, nothing leaves the computer".to_string(),
+                };
+                let row = Row::new()
+                    .padding([5, 0])
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(name)
+                    .push(Text::new(limit).width(Length::Fixed(250.0)));
+                let row = match kind {
+                    SourceKind::Unsplash => row.push(
+                        TextInput::new(
+                            "access key, needed to search unsplash",
+                            self.settings
+                                .unsplash_access_key
+                                .as_deref()
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetUnsplashAccessKey)
+                        .width(Length::Fixed(300.0)),
+                    ),
+                    _ => row,
+                };
+                column.push(row)
+            },
+        )
+    }
+
+    /// The first launch wizard, one question at a time
+    fn onboarding_view(&self, step: OnboardingStep) -> Element<'_, WallpaperMessage> {
+        let content = match step {
+            OnboardingStep::SaveDirectory => Column::new()
+                .push(Text::new(
+                    self.settings.save_directory.as_deref().unwrap_or("./"),
+                ))
+                .push(make_button("Choose Directory").on_press(WallpaperMessage::ChooseDirectory())),
+            OnboardingStep::ApiKey => Column::new()
+                .push(Text::new(
+                    "It's optional, only nsfw wallpapers need one. It's in your wallhaven.cc account settings.",
+                ))
+                .push(
+                    TextInput::new("api key", &self.api_key)
+.style(focus_outline())
+                        .on_input(WallpaperMessage::ApiTokenSet)
+                        .width(Length::Fixed(600.0)),
+                )
+                .push(self.api_key_status_view()),
+            OnboardingStep::Resolution => Column::new()
+                .push(Text::new(
+                    "Searches start with wallpapers at least this big and in the same shape.",
+                ))
+                .push(
+                    PickList::new(
+                        &wallapi::types::RESOLUTION_POSSIBILITIES[..],
+                        self.settings.monitor_resolution,
+                        WallpaperMessage::SetMonitorResolution,
+                    )
+                    .placeholder("pick a resolution")
+                    .style(iced::theme::PickList::Custom(
+                        Rc::new(crate::style::pick_style::PickList),
+                        Rc::new(crate::style::pick_style::PickList),
+                    )),
+                )
+                .push(Text::new(match self.monitors.is_empty() {
+                    true => String::new(),
+                    false => format!(
+                        "Detected {}",
+                        self.monitors
+                            .iter()
+                            .map(|monitor| format!("{} {}", monitor.name, monitor.resolution))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                })),
+            OnboardingStep::Content => {
+                let categories = self.settings.default_categories.clone().unwrap_or_default();
+                let purity = self.settings.default_purity.clone().unwrap_or_default();
+                let mut nsfw_button = make_button("nsfw").style(inactive_style(purity.nsfw));
+                if !self.api_key.is_empty() {
+                    nsfw_button = nsfw_button
+                        .on_press(WallpaperMessage::ToggleDefaultPurity(PurityOptions::Nsfw));
+                }
+                Column::new()
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .push(
+                                make_button("general")
+                                    .on_press(WallpaperMessage::ToggleDefaultContent(
+                                        ContentTypes::General,
+                                    ))
+                                    .style(inactive_style(categories.general)),
+                            )
+                            .push(
+                                make_button("anime")
+                                    .on_press(WallpaperMessage::ToggleDefaultContent(
+                                        ContentTypes::Anime,
+                                    ))
+                                    .style(inactive_style(categories.anime)),
+                            )
+                            .push(
+                                make_button("people")
+                                    .on_press(WallpaperMessage::ToggleDefaultContent(
+                                        ContentTypes::People,
+                                    ))
+                                    .style(inactive_style(categories.people)),
+                            ),
+                    )
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .push(
+                                make_button("clean")
+                                    .on_press(WallpaperMessage::ToggleDefaultPurity(
+                                        PurityOptions::Sfw,
+                                    ))
+                                    .style(inactive_style(purity.clean)),
+                            )
+                            .push(
+                                make_button("sketchy")
+                                    .on_press(WallpaperMessage::ToggleDefaultPurity(
+                                        PurityOptions::Sketchy,
+                                    ))
+                                    .style(inactive_style(purity.sketchy)),
+                            )
+                            .push(nsfw_button),
+                    )
+            }
+        };
+        let back = make_button_fa("back", "arrow-left");
+        let back = match step.previous() {
+            Some(previous) => back.on_press(WallpaperMessage::OnboardingStepChanged(previous)),
+            None => back,
+        };
+        let forward = match step.next() {
+            Some(next) => make_button_fa("next", "arrow-right")
+                .on_press(WallpaperMessage::OnboardingStepChanged(next)),
+            None => make_button_fa("start searching", "search")
+                .on_press(WallpaperMessage::FinishOnboarding()),
+        };
+        Container::new(
+            Column::new()
+                .spacing(20)
+                .push(Text::new("Welcome to wall-a-bunga").size(32))
+                .push(
+                    Text::new(format!(
+                        "{}/{}  {}",
+                        step.number(),
+                        OnboardingStep::LIST.len(),
+                        step.title()
+                    ))
+                    .size(26),
+                )
+                .push(content.spacing(10))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(back)
+                        .push(forward)
+                        .push(make_button("skip").on_press(WallpaperMessage::FinishOnboarding())),
+                ),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+    }
+
+    /// Sets the active tab's filters to wallpapers that fit a screen of `resolution`
+    fn suggest_filters(&mut self, resolution: XYCombo) {
+        info!("Suggesting filters for {}", resolution);
+        let search_options = &mut self.tab_mut().search_options;
+        search_options.resolutions = None;
+        search_options.minimum_resolution = Some(resolution);
+        if let Some(ratio) = closest_aspect_ratio(resolution) {
+            search_options.ratios = Some(HashSet::from([ratio]));
+        }
+        self.resolution_menu.is_minimum_set = true;
+    }
+
+    fn custom_combos_mut(&mut self, kind: ComboKind) -> &mut CustomCombos {
+        match kind {
+            ComboKind::Resolution => &mut self.resolution_menu.custom,
+            ComboKind::AspectRatio => &mut self.aspect_menu.custom,
+        }
+    }
+
+    fn refresh_custom_combos(&mut self) {
+        self.resolution_menu
+            .custom
+            .set_entries(&self.settings.custom_resolutions);
+        self.aspect_menu
+            .custom
+            .set_entries(&self.settings.custom_ratios);
+    }
+
+    fn save_blacklist(&self) -> Command<WallpaperMessage> {
+        let blacklist = self.settings.blacklist.clone();
+        Command::perform(
+            async move {
+                SavedSettings::save_blacklist(blacklist)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            WallpaperMessage::SettingsSaved,
+        )
+    }
+
+    fn save_custom_combos(&self) -> Command<WallpaperMessage> {
+        let resolutions = self.settings.custom_resolutions.clone();
+        let ratios = self.settings.custom_ratios.clone();
+        Command::perform(
+            async move {
+                SavedSettings::save_custom_combos(resolutions, ratios)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            WallpaperMessage::SettingsSaved,
+        )
+    }
+
+    fn save_source_folders(&self) -> Command<WallpaperMessage> {
+        let folders = self.settings.source_folders.clone();
+        Command::perform(
+            async move {
+                SavedSettings::save_source_folders(folders)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            WallpaperMessage::SettingsSaved,
+        )
+    }
+
+    fn save_settings(&self) -> Command<WallpaperMessage> {
+        let settings = self.settings.clone();
+        Command::perform(
+            async move {
+                // the keyring can block while it asks to be unlocked
+                let settings = spawn_blocking(move || credentials::store_keys(settings))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                SavedSettings::save_settings(settings)
+                    .await
+                    .map_err(|e| e.to_string())
+            },
+            WallpaperMessage::SettingsSaved,
+        )
+    }
+
+    /// Runs a search for the active tab using its current search options
+    fn search_active_tab(&self) -> Command<WallpaperMessage> {
+        self.search_tab(self.tab())
+    }
+
+    fn search_tab(&self, tab: &SearchTab) -> Command<WallpaperMessage> {
+        let search = tab.search_id();
+        let options = SearchOptions {
+            purity: self
+                .purity_lock()
+                .allowed(tab.search_options.purity.clone()),
+            ..tab.search_options.clone()
+        };
+        if let Some(collection) = &tab.collection {
+            return Command::perform(
+                WallpaperUi::collection_command(
+                    self.client.clone(),
+                    collection.clone(),
+                    options,
+                    self.library.clone(),
+                ),
+                move |response| WallpaperMessage::SearchReceived(search, response),
+            );
+        }
+        if let (Source::Folder(folder), false) = (&tab.source, tab.local) {
+            return Command::perform(
+                WallpaperUi::folder_search_command(
+                    self.folder_index.clone(),
+                    folder.clone(),
+                    options,
+                ),
+                move |response| WallpaperMessage::SearchReceived(search, response),
+            );
+        }
+        if let (Source::Unsplash, false) = (&tab.source, tab.local) {
+            return Command::perform(
+                WallpaperUi::unsplash_command(
+                    self.client.clone(),
+                    self.settings.unsplash_access_key.clone(),
+                    tab.search_value.clone(),
+                    tab.search_options.page.unwrap_or(1) as i64,
+                    self.library.clone(),
+                ),
+                move |response| WallpaperMessage::SearchReceived(search, response),
+            );
+        }
+        if let (Source::Reddit(subreddit), false) = (&tab.source, tab.local) {
+            let page = tab.search_options.page.unwrap_or(1);
+            // the first page starts from the top, later ones where the last one ended
+            let after = tab.cursor.clone().filter(|_| page > 1);
+            return Command::perform(
+                WallpaperUi::reddit_command(
+                    self.client.clone(),
+                    subreddit.clone(),
+                    tab.search_value.clone(),
+                    after,
+                    page as i64,
+                    self.library.clone(),
+                ),
+                move |(cursor, response)| {
+                    WallpaperMessage::RedditPageReceived(search, cursor, response)
+                },
+            );
+        }
+        match tab.local {
+            true => Command::perform(
+                WallpaperUi::library_search_command(self.library.clone(), options),
+                move |response| WallpaperMessage::SearchReceived(search, response),
+            ),
+            false => Command::perform(
+                WallpaperUi::search_command(
+                    self.client.clone(),
+                    self.settings.blacklist.exclude_tags(&options),
+                    self.library.clone(),
+                ),
+                move |response| match response {
+                    Some(response) => WallpaperMessage::SearchReceived(search, response),
+                    None => WallpaperMessage::SearchOffline(search),
+                },
+            ),
+        }
+    }
+
+    /// Opens a listed collection in a new tab, which is searched like any other
+    fn open_collection(&mut self, index: usize) -> Command<WallpaperMessage> {
+        let (username, collection) = match &self.collections {
+            // wallhaven only serves a collection's wallpapers by the owner's name
+            CollectionsState::Loaded(Some(username), collections) => match collections.get(index) {
+                Some(collection) => (username.clone(), collection.clone()),
+                None => return Command::none(),
+            },
+            _ => return Command::none(),
+        };
+        let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+        let mut tab = SearchTab::new(self.next_tab_id, api_key, self.default_purity(), None);
+        tab.collection = Some(OpenCollection {
+            username,
+            id: collection.id,
+            label: collection.label,
+        });
+        self.tabs.push(tab);
+        self.next_tab_id += 1;
+        self.active_tab = self.tabs.len() - 1;
+        self.preview.mode = PreviewMode::Disable;
+        self.controls.submenu = Submenu::None;
+        self.search_active_tab()
+    }
+
+    fn wallpaper_failed(&mut self, error: String) {
+        self.banners.show(
+            BannerSource::Wallpaper,
+            Severity::Error,
+            format!("Couldn't set the wallpaper: {}", error),
+        );
+    }
+
+    /// Counts the failed downloads in one banner, they're retried from the downloads list
+    fn update_download_banner(&mut self) {
+        match self.download_manager.failed_count() {
+            0 => self.banners.dismiss(BannerSource::Downloads),
+            1 => self.banners.show(
+                BannerSource::Downloads,
+                Severity::Warning,
+                "A download failed, it can be retried from the downloads list".to_string(),
+            ),
+            failed => self.banners.show(
+                BannerSource::Downloads,
+                Severity::Warning,
+                format!(
+                    "{} downloads failed, they can be retried from the downloads list",
+                    failed
+                ),
+            ),
+        }
+    }
+
+    fn previewed(&self) -> Option<&(ListingData, ImageView)> {
+        self.tab().search_results.get(self.preview.index?)
+    }
+
+    /// Measures the save directory against the disk quota, pruning downloads if the settings
+    /// allow it
+    fn check_quota(&mut self) -> Command<WallpaperMessage> {
+        let megabytes = match self.settings.max_disk_usage_megabytes {
+            Some(megabytes) => megabytes,
+            None => {
+                self.quota = None;
+                return Command::none();
             }
-            WallpaperMessage::DownloadImages() => {
-                let image_urls = self
+        };
+        // a download finishing mid check is counted by the next one
+        if self.checking_quota {
+            return Command::none();
+        }
+        self.checking_quota = true;
+        Command::perform(
+            enforce_quota(
+                self.library.clone(),
+                self.save_directory(),
+                megabytes as u64 * MEGABYTE,
+                self.settings.quota_action,
+            ),
+            WallpaperMessage::QuotaChecked,
+        )
+    }
+
+    /// Deletes copies from a duplicate group, the group is gone once a single copy is left
+    fn delete_duplicates(
+        &mut self,
+        group: usize,
+        paths: Vec<PathBuf>,
+    ) -> Command<WallpaperMessage> {
+        let duplicates = match self.duplicates.get_mut(group) {
+            Some(duplicates) => duplicates,
+            None => return Command::none(),
+        };
+        // taken out right away so the last copy can't be deleted by clicking too fast
+        duplicates.files.retain(|file| !paths.contains(&file.path));
+        let kept = match duplicates.files.first() {
+            Some(file) => file.path.clone(),
+            None => return Command::none(),
+        };
+        if duplicates.files.len() < 2 {
+            self.duplicates.remove(group);
+        }
+        let directory = self.save_directory();
+        Command::batch(paths.into_iter().map(|path| {
+            Command::perform(
+                delete_duplicate(self.library.clone(), path, kept.clone(), directory.clone()),
+                WallpaperMessage::DuplicateDeleted,
+            )
+        }))
+    }
+
+    /// Selects or deselects results, adding or removing them from the cart
+    fn update_selection(&mut self, option: SelectionUpdateType) {
+        match option {
+            SelectionUpdateType::Single(id) => {
+                let image = self.tabs[self.active_tab]
                     .search_results
                     .iter_mut()
-                    .rev() // reverse the order so that when we queue these, the first are inserted last
-                    .filter(|(_, image)| {
-                        image.state == ImageState::Selected || image.state == ImageState::Failed
-                    })
-                    .map(|(listing, image)| {
-                        image.state = ImageState::Queued;
-                        (&listing.path, &listing.id)
-                    });
-
-                for (url, id) in image_urls {
-                    let file_name = match url.split('/').last() {
-                        Some(name) => name,
-                        None => {
-                            error!("Error getting filename of url: {}", url);
-                            continue;
-                        }
+                    .find(|(l, _)| l.id == id);
+                if let Some((listing, result_data)) = image {
+                    // toggle checked
+                    result_data.state = match result_data.state {
+                        ImageState::Unselected => ImageState::Selected,
+                        ImageState::Selected => ImageState::Unselected,
+                        ImageState::Failed => ImageState::Selected,
+                        // default return same state
+                        _ => result_data.state,
                     };
-                    let save_path = PathBuf::from(
-                        &self
-                            .settings
-                            .save_directory
-                            .clone()
-                            .unwrap_or_else(|| "./".to_string()),
-                    )
-                    .join(file_name);
-                    self.download_manager.queue_download(url, id, save_path);
-                }
-            }
-            WallpaperMessage::SortingTypeChanged(sort) => {
-                self.search_options.sorting = Some(sort);
-            }
-            WallpaperMessage::TogglePurity(purity_toggle) => {
-                let purity = self.search_options.purity.get_or_insert(Purity::default());
-                match purity_toggle {
-                    PurityOptions::Sfw => {
-                        purity.clean = !purity.clean;
-                    }
-                    PurityOptions::Sketchy => {
-                        purity.sketchy = !purity.sketchy;
+                    match result_data.state {
+                        ImageState::Selected => {
+                            self.selection.cart.add(listing, &result_data.image_handle)
+                        }
+                        _ => self.selection.cart.remove(&id),
                     }
-                    PurityOptions::Nsfw => {
-                        purity.nsfw = !purity.nsfw;
+                    let state = result_data.state;
+                    // keep the same wallpaper in other tabs in sync
+                    for image in self.results_with_id_mut(&id) {
+                        if matches!(image.state, ImageState::Unselected | ImageState::Selected) {
+                            image.state = state;
+                        }
                     }
                 }
             }
-            WallpaperMessage::ToggleContentType(content_toggle) => {
-                let content = self
-                    .search_options
-                    .categories
-                    .get_or_insert(Categories::default());
-                match content_toggle {
-                    ContentTypes::Anime => {
-                        content.anime = !content.anime;
-                    }
-                    ContentTypes::General => {
-                        content.general = !content.general;
+            SelectionUpdateType::SelectAll => {
+                for (l, r) in &mut self.tabs[self.active_tab].search_results {
+                    if r.state == ImageState::Unselected {
+                        r.state = ImageState::Selected;
+                        self.selection.cart.add(l, &r.image_handle);
                     }
-                    ContentTypes::People => {
-                        content.people = !content.people;
-                    }
-                }
-            }
-            WallpaperMessage::ApiTokenSet(token) => {
-                self.api_key = token;
-                if !self.api_key.is_empty() {
-                    self.search_options.api_key = Some(self.api_key.clone());
-                } else {
-                    self.search_options.api_key = None;
                 }
             }
-            WallpaperMessage::ChangeSubmenu(menu) => {
-                // Toggle the submenu to none if already set, otherwise set value
-                if self.controls.submenu == menu {
-                    self.controls.submenu = Submenu::None;
-                } else {
-                    self.controls.submenu = menu;
+            SelectionUpdateType::Matching(criteria) => {
+                let matching: Vec<_> = self
+                    .visible_results()
+                    .filter(|(_, (listing, image))| {
+                        image.state == ImageState::Unselected && criteria.matches(listing)
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+                info!("Selecting {} results matching {}", matching.len(), criteria);
+                let tab = &mut self.tabs[self.active_tab];
+                for index in matching {
+                    let (listing, image) = &mut tab.search_results[index];
+                    image.state = ImageState::Selected;
+                    self.selection.cart.add(listing, &image.image_handle);
                 }
             }
-            WallpaperMessage::ChooseDirectory() => {
-                return Command::perform(
-                    WallpaperUi::choose_directory(),
-                    WallpaperMessage::DirectoryChosen,
-                );
-            }
-            WallpaperMessage::DirectoryChosen(path) => {
-                if let Some(p) = path {
-                    if let Some(s) = p.to_str() {
-                        self.settings.save_directory = Some(s.to_string());
-                        return Command::none();
-                    }
-                }
-                self.settings.save_directory = None;
-            }
-            WallpaperMessage::ResolutionSelected(resolution) => {
-                // Clear out the minimum resolution option
-                self.search_options.minimum_resolution = None;
-                debug!("Resolution selected {}", resolution);
-                let res_map = self
-                    .search_options
-                    .resolutions
-                    .get_or_insert(HashSet::new());
-                if res_map.contains(&resolution) {
-                    res_map.remove(&resolution);
-                    if res_map.is_empty() {
-                        self.search_options.resolutions = None;
+            SelectionUpdateType::DeselectAll => {
+                for (l, r) in &mut self.tabs[self.active_tab].search_results {
+                    if r.state == ImageState::Selected {
+                        r.state = ImageState::Unselected;
+                        self.selection.cart.remove(&l.id);
                     }
-                } else {
-                    res_map.insert(resolution);
                 }
             }
-            WallpaperMessage::AspectRatioSelected(aspect_ratio) => {
-                info!("Selected aspect ratio {}", aspect_ratio);
-                let ratio_map = self.search_options.ratios.get_or_insert(HashSet::new());
-                if ratio_map.contains(&aspect_ratio) {
-                    ratio_map.remove(&aspect_ratio);
-                } else {
-                    ratio_map.insert(aspect_ratio);
+        }
+    }
+
+    /// Remembers a selection change to undo, unless nothing ended up changing
+    fn record_selection(&mut self, before: DownloadCart<image::Handle>) {
+        let unchanged = before.items().map(|item| &item.id).eq(self
+            .selection
+            .cart
+            .items()
+            .map(|item| &item.id));
+        if !unchanged {
+            self.selection.undo.record(UndoableEdit::Selection {
+                before,
+                after: self.selection.cart.clone(),
+            });
+        }
+    }
+
+    /// Puts back the cart of an undone or redone selection change, along with the results' states
+    fn restore_cart(&mut self, cart: DownloadCart<image::Handle>) {
+        for tab in &mut self.tabs {
+            for (listing, image) in &mut tab.search_results {
+                image.state = match (image.state, cart.contains(&listing.id)) {
+                    (ImageState::Unselected | ImageState::Failed, true) => ImageState::Selected,
+                    (ImageState::Selected, false) => ImageState::Unselected,
+                    (state, _) => state,
+                };
+            }
+        }
+        self.selection.cart = cart;
+    }
+
+    /// Takes a blacklisted wallpaper out of every tab, returning what was removed so it can be
+    /// put back
+    fn remove_blacklisted(&mut self, id: &str) -> Vec<(TabId, usize, (ListingData, ImageView))> {
+        let mut removed = Vec::new();
+        for tab in &mut self.tabs {
+            // backwards, so removing doesn't shift the results left to check
+            for index in (0..tab.search_results.len()).rev() {
+                if tab.search_results[index].0.id == id {
+                    removed.push((tab.id, index, tab.search_results.remove(index)));
                 }
             }
-            WallpaperMessage::SaveSettings() => {
-                self.settings.api_key = self.search_options.api_key.clone();
-                return Command::perform(
-                    SavedSettings::save_settings(self.settings.clone()),
-                    WallpaperMessage::SaveCompleted,
-                );
+            tab.focus = None;
+        }
+        // the preview points into the results that just shifted
+        self.preview.mode = PreviewMode::Disable;
+        removed
+    }
+
+    /// Queues a wallpaper for download into the save directory
+    fn queue_download(&mut self, id: &str, path: &str, created_at: &str) {
+        for image in self.results_with_id_mut(id) {
+            image.state = ImageState::Queued;
+        }
+        let file_name = match (unsplash::photo_id(id), path.rsplit('/').next()) {
+            // unsplash's links end in query parameters instead of a file name
+            (Some(_), _) => format!("{}.jpg", id),
+            (None, Some(name)) => name.to_string(),
+            (None, None) => {
+                error!("Error getting filename of url: {}", path);
+                return;
             }
-            WallpaperMessage::SaveCompleted(()) => {
-                info!("Save complete!");
+        };
+        let save_path = self.save_directory().join(file_name);
+        let uploaded = match self.settings.use_upload_date {
+            true => parse_upload_date(created_at),
+            false => None,
+        };
+        let conflict = FileConflict {
+            id: id.to_string(),
+            url: path.to_string(),
+            save_path,
+            uploaded,
+        };
+        match conflict.save_path.exists() {
+            true => self.resolve_file_conflict(conflict, self.settings.existing_file_policy),
+            false => self.download_manager.queue_download(
+                conflict.url,
+                conflict.id,
+                conflict.save_path,
+                conflict.uploaded,
+            ),
+        }
+    }
+
+    /// Queues the visible results that aren't downloaded yet, then loads the next page until the
+    /// limits are reached or the search runs out of pages
+    fn continue_download_all(&mut self) -> Command<WallpaperMessage> {
+        let tab_id = match &self.downloads.download_all {
+            Some(download_all) => download_all.tab_id,
+            None => return Command::none(),
+        };
+        if self.tab().id != tab_id {
+            info!("Stopped downloading all results, the search tab changed");
+            self.downloads.download_all = None;
+            return Command::none();
+        }
+        let max_count = self.settings.download_all_count.map(|count| count as usize);
+        let max_bytes = self
+            .settings
+            .download_all_megabytes
+            .map(|megabytes| (megabytes as u64 * MEGABYTE) as i64);
+        let results: Vec<_> = self
+            .visible_results()
+            .filter(|(_, (_, image))| {
+                matches!(
+                    image.state,
+                    ImageState::Unselected | ImageState::Selected | ImageState::Failed
+                )
+            })
+            .map(|(_, (listing, _))| listing.clone())
+            .collect();
+        for listing in results {
+            let download_all = self.downloads.download_all.as_mut().expect("checked above");
+            let full = max_count.is_some_and(|max| download_all.queued >= max)
+                || max_bytes.is_some_and(|max| download_all.bytes + listing.file_size > max);
+            if full {
+                info!("Queued {} results, limit reached", download_all.queued);
+                self.downloads.download_all = None;
+                return Command::none();
             }
-            WallpaperMessage::SetIgnoreDownloaded(value) => {
-                self.settings.ignore_downloaded = value;
+            download_all.queued += 1;
+            download_all.bytes += listing.file_size;
+            self.selection.cart.remove(&listing.id);
+            self.queue_download(&listing.id, &listing.path, &listing.created_at);
+        }
+        let tab = self.tab_mut();
+        match tab
+            .paginator
+            .advance(tab.search_meta.as_ref(), Instant::now())
+        {
+            Some(page) => {
+                tab.search_options.set_page(page);
+                self.search_active_tab()
             }
-            WallpaperMessage::DownloadUpdated(u) => match u {
-                DownloadStatus::Progress(id, progress) => {
-                    if let Some((_, i)) = self
-                        .search_results
-                        .iter_mut()
-                        .find(|(val, _)| val.id.eq(&id))
-                    {
-                        i.state = ImageState::Downloading(progress);
-                    }
-                }
-                DownloadStatus::Failed(image) => {
-                    error!("Image {} failed", image);
-                    if let Some((_, l)) = self
-                        .search_results
-                        .iter_mut()
-                        .find(|(l, _)| l.id.eq(&image))
-                    {
-                        l.state = ImageState::Failed
-                    };
-                    self.download_manager.remove_download(&image);
-                }
-                DownloadStatus::Finished(id) => {
-                    info!("Image {} complete", id);
-                    if let Some((_, l)) = self.search_results.iter_mut().find(|(l, _)| l.id.eq(&id))
-                    {
-                        l.state = ImageState::Downloaded
-                    };
-                    self.download_manager.remove_download(&id);
-                }
-            },
-            WallpaperMessage::ResolutionIsSingleTargetChanged(res_mode) => {
-                self.resolution_menu.is_minimum_set = res_mode;
-            }
-            WallpaperMessage::SetMinimumResolution(resolution) => {
-                // clear out other resolutions options in preference of min resolution
-                info!("Minimum resolution set to {}", resolution);
-                self.search_options.resolutions = None;
-                self.search_options.minimum_resolution = Some(resolution);
-            }
-            WallpaperMessage::ChangeConcurrentDownloads(c) => {
-                let value = match c > 0 && c < 10 {
-                    true => c,
-                    false => self.concurrent_download_control.value,
-                };
-                self.concurrent_download_control.value = value;
-                self.download_manager
-                    .set_concurrent_downloads(value as usize)
-            }
-            WallpaperMessage::Scroll(scroll) => {
-                if let PreviewMode::Disable = &self.preview_mode {
-                    // currently we only want to respond to scroll events when the user can see the image list
-                    debug!("scroll {:?}", scroll);
-                    // scroll ranges from 0 to 1. if 1, try to load more wallpapers
-                    let search_meta = if let Some(search_meta) = &self.search_meta {
-                        search_meta
-                    } else {
-                        return Command::none();
-                    };
-                    let page = self.search_options.page.unwrap_or(1);
-                    if scroll.relative_offset().y >= 1.0
-                        && page < search_meta.last_page as i32
-                        && page == search_meta.current_page as i32
-                    {
-                        self.search_options.page = Some(page + 1);
-                        return Command::perform(
-                            WallpaperUi::search_command(
-                                self.search_options.clone(),
-                                self.settings
-                                    .save_directory
-                                    .as_ref()
-                                    .unwrap_or(&"./".to_string())
-                                    .into(),
-                            ),
-                            WallpaperMessage::SearchReceived,
-                        );
-                    }
-                }
+            None => {
+                info!("Queued every result of the search");
+                self.downloads.download_all = None;
+                Command::none()
             }
-            WallpaperMessage::NextPage() => {
-                let mut page = self.search_options.page.unwrap_or(1);
-                if let Some(max_page) = self.search_meta.as_ref().map(|m| m.last_page) {
-                    page += 1;
-                    if page > max_page as i32 {
-                        page = max_page as i32;
-                    }
-                    self.search_options.set_page(page);
-                    return Command::perform(
-                        WallpaperUi::search_command(
-                            self.search_options.clone(),
-                            self.settings
-                                .save_directory
-                                .as_ref()
-                                .unwrap_or(&"./".to_string())
-                                .into(),
-                        ),
-                        WallpaperMessage::SearchReceived,
-                    );
+        }
+    }
+
+    /// Queues or skips a download whose file already exists according to `policy`
+    fn resolve_file_conflict(&mut self, conflict: FileConflict, policy: ExistingFilePolicy) {
+        let FileConflict {
+            id,
+            url,
+            save_path,
+            uploaded,
+        } = conflict;
+        match policy {
+            ExistingFilePolicy::Skip => {
+                info!("Skipping {}, {:?} already exists", id, save_path);
+                for image in self.results_with_id_mut(&id) {
+                    image.state = ImageState::Downloaded;
                 }
             }
-            WallpaperMessage::UpdatePreviewMode(preview) => {
-                self.preview_mode = preview;
-            }
-            WallpaperMessage::DownloadPreview(index) => {
-                if let Some((value, image_view)) = self.search_results.get(index) {
-                    let url = value.path.clone();
-                    let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
-                    let future = async move {
-                        tokio::select! {
-                            img = WallpaperUi::fetch_full_image(url) => Some(img),
-                            _ = receiver.recv() => None,
-                        }
-                    };
-
-                    self.preview_mode = PreviewMode::PreviewRequestDownloading {
-                        preview_handle: image_view.image_handle.clone(),
-                        cancel_mechanism: sender,
-                    };
-                    return Command::perform(future, |selection| match selection {
-                        Some(wall) => {
-                            if let Ok(handle) = wall {
-                                info!("preview loaded!");
-                                WallpaperMessage::UpdatePreviewMode(PreviewMode::PreviewView(
-                                    handle,
-                                ))
-                            } else {
-                                error!("failed to load preview");
-                                WallpaperMessage::UpdatePreviewMode(PreviewMode::PreviewFailed)
-                            }
-                        }
-                        None => {
-                            info!("User cancelled task");
-                            WallpaperMessage::UpdatePreviewMode(PreviewMode::Disable)
-                        }
+            ExistingFilePolicy::Overwrite => self
+                .download_manager
+                .queue_download(url, id, save_path, uploaded),
+            ExistingFilePolicy::Rename => self
+                .download_manager
+                .queue_renamed_download(url, id, save_path, uploaded),
+            ExistingFilePolicy::Ask => {
+                if !self.downloads.file_conflicts.iter().any(|c| c.id == id) {
+                    self.downloads.file_conflicts.push(FileConflict {
+                        id,
+                        url,
+                        save_path,
+                        uploaded,
                     });
                 }
             }
-            WallpaperMessage::CancelPreview() => match &self.preview_mode {
-                PreviewMode::PreviewRequestDownloading {
-                    cancel_mechanism, ..
-                } => {
-                    let cancel_mechanism = cancel_mechanism.clone();
-                    return Command::perform(
-                        async move {
-                            cancel_mechanism.send(()).await.unwrap();
-                        },
-                        |_| {
-                            info!("cancel sent!");
-                            WallpaperMessage::UpdatePreviewMode(PreviewMode::Disable)
-                        },
-                    );
-                }
-                _ => self.preview_mode = PreviewMode::Disable,
-            },
         }
-        Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Self::Message> {
-        Subscription::batch(self.download_manager.get_subscriptions())
-            .map(WallpaperMessage::DownloadUpdated)
+    /// Position within the visible results and result index of the wallpaper next to the one
+    /// being previewed
+    fn adjacent_preview(&self, forward: bool) -> Option<(usize, usize)> {
+        let current = self.preview.index?;
+        let visible: Vec<_> = self.visible_results().map(|(index, _)| index).collect();
+        let position = visible.iter().position(|index| *index == current)?;
+        let position = match forward {
+            true => position + 1,
+            false => position.checked_sub(1)?,
+        };
+        visible.get(position).map(|index| (position, *index))
     }
 
-    fn view(&self) -> Element<'_, Self::Message> {
-        let loading_status = self.get_loading_status();
-        let selected_count = self
-            .search_results
-            .iter()
-            .filter(|(_, l)| l.state == ImageState::Selected)
-            .count();
+    /// Swaps the preview to the previous or next wallpaper, cancelling a preview still downloading
+    fn navigate_preview(&mut self, forward: bool) -> Command<WallpaperMessage> {
+        let (position, index) = match self.adjacent_preview(forward) {
+            Some(adjacent) => adjacent,
+            None => return Command::none(),
+        };
+        // keep the grid focus on the previewed wallpaper for when the preview is closed
+        self.tab_mut().focus = Some(position);
+        self.update(WallpaperMessage::DownloadPreview(index))
+    }
 
-        // Build columns of 5 with our images
-        let ignore_downloaded = self.settings.ignore_downloaded;
+    /// Every result across all the tabs with the given wallpaper id
+    fn results_with_id_mut<'a>(
+        &'a mut self,
+        id: &'a str,
+    ) -> impl Iterator<Item = &'a mut ImageView> {
+        self.tabs
+            .iter_mut()
+            .flat_map(|tab| tab.search_results.iter_mut())
+            .filter(move |(listing, _)| listing.id == id)
+            .map(|(_, image)| image)
+    }
 
-        let results = match self.settings.ignore_downloaded {
-            true => {
-                let num_hidden = self
-                    .search_results
-                    .iter()
-                    .filter(|(_, v)| v.state.eq(&ImageState::Downloaded))
-                    .count();
-                format!(
-                    "{} results ({} hidden)",
-                    self.search_results.len(),
-                    num_hidden
-                )
-            }
-            false => {
-                format!("{} results", self.search_results.len())
+    /// Hands the message to the reducer of the part of the app it's about
+    fn handle_message(&mut self, message: WallpaperMessage) -> Command<WallpaperMessage> {
+        let reducers: [fn(&mut Self, WallpaperMessage) -> Reduced; 5] = [
+            Self::reduce_search,
+            Self::reduce_selection,
+            Self::reduce_downloads,
+            Self::reduce_preview,
+            Self::reduce_settings,
+        ];
+        match reducers
+            .into_iter()
+            .try_fold(message, |message, reduce| reduce(self, message))
+        {
+            Break(command) => command,
+            Continue(message) => {
+                error!("Nothing handles {:?}", message);
+                Command::none()
             }
-        };
+        }
+    }
 
-        // create a next button based on whether or we have another page
-        let next_button = if self
+    /// The button to the next page, left out on the last one
+    fn next_page_button(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        if tab
             .search_meta
             .as_ref()
-            .map(|m| (self.search_options.page.unwrap_or(1) as i64).ne(&m.last_page))
+            .map(|m| (tab.search_options.page.unwrap_or(1) as i64).ne(&m.last_page))
             .unwrap_or(true)
         {
             Column::new().push(
@@ -716,121 +3159,238 @@ impl Application for WallpaperUi {
             )
         } else {
             Column::new()
-        };
-        let is_preview_disabled = matches!(&self.preview_mode, PreviewMode::Disable);
+        }
+    }
 
-        let main_content = match &self.preview_mode {
-            PreviewMode::Disable => {
-                let mut row = Row::new();
-                let mut column = Column::new().spacing(5).push(Text::new("Search results"));
+    /// Thumbnails of the results in rows of [GRID_COLUMNS], only the rows near the viewport are
+    /// built
+    fn results_grid(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let spacing = density().spacing();
+        let mut row = Row::new().spacing(spacing);
+        let mut column = Column::new().spacing(spacing).push(self.layout_toggle());
+        let results: Vec<_> = self.visible_results().collect();
+        let row_count = results.len().div_ceil(GRID_COLUMNS);
+        let row_height = grid_tile_height() + spacing as f32;
+        let rows = visible_rows(
+            tab.scroll_top,
+            self.window_geometry.height as f32,
+            row_height,
+            row_count,
+        );
+        if rows.start > 0 {
+            let height = skipped_height(rows.start, row_height, spacing as f32);
+            column = column.push(Space::with_height(Length::Fixed(height)));
+        }
 
-                for (index, (listing, image)) in self
-                    .search_results
-                    .iter()
-                    .filter(|(_, image)| {
-                        !ignore_downloaded || matches!(image.state, ImageState::Downloaded)
-                    })
-                    .enumerate()
+        // Build rows of GRID_COLUMNS with our images, only those near the viewport
+        for (position, (index, (listing, image))) in results
+            .into_iter()
+            .enumerate()
+            .skip(rows.start * GRID_COLUMNS)
+            .take(rows.len() * GRID_COLUMNS)
+        {
+            // tiles in the rectangle being dragged show how they'll end up
+            let state = match self.selection.drag_selection {
+                Some(drag)
+                    if drag.contains(position, GRID_COLUMNS)
+                        && matches!(
+                            image.state,
+                            ImageState::Unselected | ImageState::Selected | ImageState::Failed
+                        ) =>
                 {
-                    let mut wallpaper_column = Column::new()
-                        // .width(Length::Fixed(250.0))
-                        .push(
-                            Button::new(Image::new(image.image_handle.clone()))
-                                .style(iced::theme::Button::Custom(Box::new(match image.state {
-                                    ImageState::Selected => button_style::Button::Primary,
-                                    ImageState::Unselected => button_style::Button::Inactive,
-                                    ImageState::Queued => button_style::Button::Downloading,
-                                    ImageState::Downloading(_) => button_style::Button::Downloading,
-                                    ImageState::Downloaded => button_style::Button::Downloaded,
-                                    ImageState::Failed => button_style::Button::Failed,
-                                })))
-                                .on_press(WallpaperMessage::SelectionUpdate(
-                                    SelectionUpdateType::Single(listing.id.clone()),
-                                )),
-                        )
-                        .push(
-                            Row::new()
-                                .push(
-                                    Column::new()
-                                        .push(Text::new(format!(
-                                            "w:{}px h:{}px",
-                                            listing.dimension_x, listing.dimension_y
-                                        )))
-                                        .width(Length::Shrink)
-                                        .push(
-                                            Row::new()
-                                                .width(Length::Shrink)
-                                                .push(
-                                                    FAIcon::new(Type::Solid, "heart")
-                                                        .svg()
-                                                        .height(Length::Fixed(20.0)),
-                                                )
-                                                .push(Text::new(trendy_number_format(
-                                                    listing.favorites as f64,
-                                                )))
-                                                .push(Space::new(
-                                                    Length::Fixed(5.0),
-                                                    Length::Shrink,
-                                                ))
-                                                .push(
-                                                    FAIcon::new(Type::Solid, "eye")
-                                                        .svg()
-                                                        .height(Length::Fixed(20.0)),
-                                                )
-                                                .push(Text::new(trendy_number_format(
-                                                    listing.views as f64,
-                                                )))
-                                                .push(Space::new(
-                                                    Length::Fixed(5.0),
-                                                    Length::Shrink,
-                                                ))
-                                                .push(Text::new(match &listing.category {
-                                                    Category::Anime => "Anime",
-                                                    Category::People => "People",
-                                                    Category::General => "General",
-                                                })),
-                                        ),
-                                )
-                                .push(Space::new(Length::Fixed(10.0), Length::Shrink))
-                                .push(
-                                    make_button_fa("preview", "image")
-                                        .on_press(WallpaperMessage::DownloadPreview(index)),
-                                )
-                                .width(Length::Shrink),
-                        );
-                    wallpaper_column = match image.state {
-                        ImageState::Downloading(progress) => wallpaper_column.push(
-                            ProgressBar::new(0.0..=100.0, progress).width(Length::Fixed(256.0)),
-                        ),
-                        _ => wallpaper_column,
-                    };
-                    row = row.push(wallpaper_column);
-                    // grid wrapping
-                    if index % 5 == 4 {
-                        let element: Element<'_, WallpaperMessage> = row.into();
-                        // let element = element.explain(Color::WHITE);
-                        column = column.push(element);
-                        row = Row::new();
+                    match drag.select {
+                        true => ImageState::Selected,
+                        false => ImageState::Unselected,
                     }
                 }
-                column
-                    .push(row)
-                    .push(loading_status)
-                    .push(next_button)
-                    .width(Length::Fill)
-                    .align_items(Alignment::Center)
+                _ => image.state,
+            };
+            // a click is a drag that starts and ends on the same tile
+            let thumbnail = Image::new(image.image_handle.clone())
+                .width(Length::Fixed(THUMBNAIL_WIDTH as f32))
+                .height(Length::Fixed(THUMBNAIL_HEIGHT as f32));
+            let thumbnail =
+                MouseArea::new(thumbnail).on_press(WallpaperMessage::DragSelectStarted(position));
+            let thumbnail = Button::new(thumbnail)
+                .padding(spacing)
+                .style(iced::theme::Button::Custom(Box::new(result_style(state))))
+                .on_press(WallpaperMessage::SelectionUpdate(
+                    SelectionUpdateType::Single(listing.id.clone()),
+                ));
+            let info = Row::new().spacing(5).align_items(Alignment::Center);
+            // unsplash asks for its photographers to be credited wherever a photo is shown
+            let info = match (attribution(listing), &listing.uploader) {
+                (Some(attribution), Some(photographer)) => info.push(photographer_button(
+                    photographer,
+                    &listing.source,
+                    attribution,
+                )),
+                _ => info.push(purity_badge(listing.purity)),
+            };
+            let info = info
+                .push(
+                    make_button_fa("preview", "image")
+                        .on_press(WallpaperMessage::DownloadPreview(index)),
+                )
+                .push(labeled(
+                    make_button_fa("", "ellipsis-h")
+                        .on_press(WallpaperMessage::OpenContextMenu(listing.id.clone())),
+                    "More actions",
+                ));
+            // kept on the same line, every tile is as tall
+            let info = match upscale_badge(listing, self.settings.monitor_resolution) {
+                Some(badge) => info.push(badge),
+                None => info,
+            };
+            let mut wallpaper_column = Column::new()
+                .push(
+                    Tooltip::new(
+                        thumbnail,
+                        listing_metadata(listing),
+                        tooltip::Position::FollowCursor,
+                    )
+                    .gap(10)
+                    .padding(8)
+                    .style(iced::theme::Container::Box),
+                )
+                .push(info);
+            wallpaper_column = match image.state {
+                ImageState::Downloading(Some(progress)) => wallpaper_column
+                    .push(ProgressBar::new(0.0..=100.0, progress).width(Length::Fixed(256.0))),
+                ImageState::Downloading(None) => wallpaper_column.push(Text::new("downloading...")),
+                ImageState::Failed => match self.download_manager.failure(&listing.id) {
+                    Some(reason) => wallpaper_column
+                        .push(Text::new(format!("failed: {}", reason)).width(Length::Fixed(256.0))),
+                    None => wallpaper_column,
+                },
+                _ => wallpaper_column,
+            };
+            // the open menu makes its tile taller than the others
+            let height = match self
+                .selection
+                .context_menu
+                .as_ref()
+                .filter(|m| m.id == listing.id)
+            {
+                Some(menu) => {
+                    wallpaper_column =
+                        wallpaper_column.push(self.context_menu_view(menu, index, listing, image));
+                    Length::Shrink
+                }
+                None => Length::Fixed(grid_tile_height()),
+            };
+            let tile = MouseArea::new(wallpaper_column)
+                .on_right_press(WallpaperMessage::OpenContextMenu(listing.id.clone()))
+                .on_enter(WallpaperMessage::DragSelectMoved(position));
+            let tile = Container::new(tile).padding(3).height(height);
+            row = match tab.focus == Some(position) {
+                true => row.push(tile.style(iced::theme::Container::Custom(Box::new(
+                    container_style::Focused,
+                )))),
+                false => row.push(tile),
+            };
+            // grid wrapping
+            if position % GRID_COLUMNS == GRID_COLUMNS - 1 {
+                let element: Element<'_, WallpaperMessage> = row.into();
+                // let element = element.explain(Color::WHITE);
+                column = column.push(element);
+                row = Row::new().spacing(spacing);
             }
-            PreviewMode::PreviewRequestDownloading { preview_handle, .. } => Column::new()
+        }
+        column = column.push(row);
+        if rows.end < row_count {
+            let height = skipped_height(row_count - rows.end, row_height, spacing as f32);
+            column = column.push(Space::with_height(Length::Fixed(height)));
+        }
+        column
+    }
+
+    /// The results of the active tab, or the wallpaper previewed instead of them
+    fn results_view(&self) -> Column<'_, WallpaperMessage> {
+        match &self.preview.mode {
+            PreviewMode::Disable if self.settings.result_layout == ResultLayout::List => self
+                .results_list()
+                .push(self.get_loading_status())
+                .push(self.next_page_button())
+                .width(Length::Fill)
+                .align_items(Alignment::Center),
+            PreviewMode::Disable => self
+                .results_grid()
+                .push(self.get_loading_status())
+                .push(self.next_page_button())
+                .width(Length::Fill)
+                .align_items(Alignment::Center),
+            PreviewMode::PreviewRequestDownloading {
+                preview_handle,
+                downloaded,
+                total,
+                ..
+            } => Column::new()
                 .push(Text::new("Downloading full-size image preview").size(26))
-                .push(make_button_fa("cancel", "ban").on_press(WallpaperMessage::CancelPreview()))
-                .push(Image::new(preview_handle.clone())),
-            PreviewMode::PreviewView(image) => Column::new()
                 .push(
-                    make_button_fa("back", "arrow-left")
-                        .on_press(WallpaperMessage::UpdatePreviewMode(PreviewMode::Disable)),
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(
+                            ProgressBar::new(
+                                0.0..=total.unwrap_or_default() as f32,
+                                *downloaded as f32,
+                            )
+                            .width(Length::Fixed(400.0)),
+                        )
+                        .push(Text::new(match total {
+                            Some(total) => format!(
+                                "{} / {}",
+                                file_size_format(*downloaded as i64),
+                                file_size_format(*total as i64)
+                            ),
+                            None => file_size_format(*downloaded as i64),
+                        })),
                 )
-                .push(Viewer::new(image.clone()).width(Length::Fill))
-                .align_items(Alignment::Center),
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .push(
+                            make_button_fa("cancel", "ban")
+                                .on_press(WallpaperMessage::CancelPreview()),
+                        )
+                        .push(self.preview_navigation()),
+                )
+                .push(Image::new(preview_handle.clone()).width(Length::Fill)),
+            PreviewMode::PreviewThumbnail { preview_handle, .. } => {
+                Column::new()
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .push(make_button_fa("back", "arrow-left").on_press(
+                                WallpaperMessage::UpdatePreviewMode(PreviewMode::Disable),
+                            ))
+                            .push(self.preview_navigation())
+                            .push(
+                                make_button_fa("load full size", "download")
+                                    .on_press(WallpaperMessage::LoadFullPreview()),
+                            ),
+                    )
+                    .push(Text::new("Data saver is on, showing the thumbnail"))
+                    .push(Image::new(preview_handle.clone()).width(Length::Fill))
+                    .align_items(Alignment::Center)
+            }
+            PreviewMode::PreviewView(image) => {
+                Column::new()
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .push(make_button_fa("back", "arrow-left").on_press(
+                                WallpaperMessage::UpdatePreviewMode(PreviewMode::Disable),
+                            ))
+                            .push(self.preview_navigation())
+                            .push(self.preview_zoom_controls())
+                            .push(self.preview_actions()),
+                    )
+                    .push(self.preview_image(image))
+                    .align_items(Alignment::Center)
+            }
             PreviewMode::PreviewFailed => Column::new()
                 .push(
                     make_button_fa("back", "arrow-left")
@@ -838,40 +3398,198 @@ impl Application for WallpaperUi {
                 )
                 .push(Text::new("Failed to load preview").size(26))
                 .align_items(Alignment::Center),
-        };
+        }
+    }
 
+    /// Where searches are typed in and started, with what can be done with the search
+    fn search_bar(&self) -> Row<'_, WallpaperMessage> {
+        let tab = self.tab();
         let text_input = Row::new()
             .height(Length::Shrink)
             .width(Length::Fill)
+            .align_items(Alignment::Center);
+        // the library only has what was downloaded
+        let text_input = match tab.local {
+            true => text_input,
+            false => text_input.push(
+                PickList::new(
+                    Source::choices(
+                        &self.settings.source_folders,
+                        &self.settings.disabled_sources,
+                    ),
+                    Some(tab.source.clone()),
+                    WallpaperMessage::SetSource,
+                )
+                .padding(14)
+                .style(iced::theme::PickList::Custom(
+                    Rc::new(crate::style::pick_style::PickList),
+                    Rc::new(crate::style::pick_style::PickList),
+                )),
+            ),
+        };
+        let text_input = match tab.local
+            || self.settings.disabled_sources.contains(&SourceKind::Folder)
+        {
+            true => text_input,
+            false => text_input.push(labeled(
+                make_button_fa("", "folder-plus").on_press(WallpaperMessage::ChooseSourceFolder()),
+                "Search a folder",
+            )),
+        };
+        text_input
             .push(
-                TextInput::new("Search", &self.search_value)
+                TextInput::new("Search", &tab.search_value)
+                    .style(focus_outline())
                     .size(16)
                     .padding(15)
                     .on_input(WallpaperMessage::SearchUpdated)
                     .on_submit(WallpaperMessage::Search()),
             )
-            .push(
-                make_button_fa("search", "search")
-                    .width(Length::Shrink)
-                    .height(Length::Shrink)
-                    .on_press(WallpaperMessage::Search()),
-            );
+            .push(
+                make_button_fa("search", "search")
+                    .width(Length::Shrink)
+                    .height(Length::Shrink)
+                    .on_press(WallpaperMessage::Search()),
+            )
+            .push(
+                make_button_fa("history", "history")
+                    .style(inactive_style(self.search.show_history))
+                    .on_press(WallpaperMessage::ToggleHistory()),
+            )
+            .push(match tab.is_wallhaven() {
+                true => {
+                    make_button_fa("copy link", "link").on_press(WallpaperMessage::CopySearchLink())
+                }
+                false => make_button_fa("copy link", "link"),
+            })
+            .push(make_button_fa("surprise me", "random").on_press(WallpaperMessage::SurpriseMe()))
+            .push(match tab.is_wallhaven() {
+                true => make_button_fa("watch", "eye").on_press(WallpaperMessage::WatchSearch()),
+                false => make_button_fa("watch", "eye"),
+            })
+    }
+
+    /// Tags left out of the search, and the seed of random orders
+    fn exclude_row(&self) -> Row<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let exclude_row = tab.excluded_tags.iter().enumerate().fold(
+            Row::new().spacing(5).align_items(Alignment::Center).push(
+                TextInput::new("exclude tags", &tab.exclude_value)
+                    .style(focus_outline())
+                    .size(16)
+                    .padding(8)
+                    .on_input(WallpaperMessage::ExcludeUpdated)
+                    .on_submit(WallpaperMessage::AddExcludedTags())
+                    .width(Length::Fixed(250.0)),
+            ),
+            |row, (index, tag)| {
+                row.push(
+                    make_button_fa(tag, "times")
+                        .style(inactive_style(false))
+                        .padding([5, 10])
+                        .on_press(WallpaperMessage::RemoveExcludedTag(index)),
+                )
+            },
+        );
+        match (tab.is_wallhaven(), tab.search_options.sorting) {
+            (true, Some(Sorting::Random)) => {
+                let copy = make_button_fa("", "copy").padding([5, 10]);
+                exclude_row
+                    .push(Space::new(Length::Fill, Length::Shrink))
+                    .push(Text::new("seed"))
+                    .push(
+                        TextInput::new("seed", &tab.seed_value)
+                            .style(focus_outline())
+                            .size(16)
+                            .padding(8)
+                            .on_input(WallpaperMessage::SeedUpdated)
+                            .on_submit(WallpaperMessage::ApplySeed())
+                            .width(Length::Fixed(100.0)),
+                    )
+                    .push(labeled(
+                        match is_valid_seed(&tab.seed_value) {
+                            true => copy.on_press(WallpaperMessage::CopyToClipboard(
+                                tab.seed_value.clone(),
+                            )),
+                            false => copy.style(inactive_style(false)),
+                        },
+                        "Copy the seed",
+                    ))
+            }
+            _ => exclude_row,
+        }
+    }
+
+    /// Past searches to pick from, empty unless the history is open
+    fn history_dropdown(&self) -> Column<'_, WallpaperMessage> {
+        if self.search.show_history {
+            let now = unix_now();
+            self.search.history.entries.iter().enumerate().fold(
+                Column::new().spacing(2).width(Length::Fill),
+                |column, (index, entry)| {
+                    let query = match entry.query() {
+                        "" => "(no query)",
+                        query => query,
+                    };
+                    column.push(
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Alignment::Center)
+                            .push(
+                                make_button(query).on_press(WallpaperMessage::RecallSearch(index)),
+                            )
+                            .push(Text::new(entry.describe_filters()).width(Length::Fill))
+                            .push(Text::new(time_ago_format(
+                                now.saturating_sub(entry.searched_at),
+                            )))
+                            .push(labeled(
+                                make_button_fa("", "times")
+                                    .style(inactive_style(false))
+                                    .on_press(WallpaperMessage::DeleteHistoryEntry(index)),
+                                "Remove from history",
+                            )),
+                    )
+                },
+            )
+        } else {
+            Column::new()
+        }
+    }
+
+    /// Known tags matching the word being typed
+    fn tag_suggestion_list(&self) -> Column<'_, WallpaperMessage> {
+        self.tag_suggestions().into_iter().fold(
+            Column::new().spacing(2).width(Length::Fill),
+            |column, tag| {
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(
+                            make_button(&tag.name)
+                                .on_press(WallpaperMessage::PickTagSuggestion(tag.id)),
+                        )
+                        .push(Text::new(&tag.category).size(14)),
+                )
+            },
+        )
+    }
 
+    /// Categories, purity and sorting of the search, and the buttons opening the submenus
+    fn filter_row(&self) -> Row<'_, WallpaperMessage> {
+        let tab = self.tab();
         let default_t = Categories::default();
         let default_p = Purity::default();
-        let search_type = self
-            .search_options
-            .categories
-            .as_ref()
-            .unwrap_or(&default_t);
-        let purity = self.search_options.purity.as_ref().unwrap_or(&default_p);
+        let search_type = tab.search_options.categories.as_ref().unwrap_or(&default_t);
+        let purity = tab.search_options.purity.as_ref().unwrap_or(&default_p);
 
         let mut nsfw_button = make_button("nsfw").style(inactive_style(purity.nsfw));
-        if !self.api_key.is_empty() {
+        // wallpapers on disk were already downloaded, no key is needed to see them
+        if !self.api_key.is_empty() || tab.is_on_disk() {
             nsfw_button = nsfw_button.on_press(WallpaperMessage::TogglePurity(PurityOptions::Nsfw));
         }
 
-        let filter_row = Row::new()
+        Row::new()
             .height(Length::Shrink)
             .width(Length::Shrink)
             //.align_items(Align::Center)
@@ -905,7 +3623,7 @@ impl Application for WallpaperUi {
             .push(
                 PickList::new(
                     &Sorting::LIST[..],
-                    self.search_options.sorting,
+                    tab.search_options.sorting,
                     WallpaperMessage::SortingTypeChanged,
                 )
                 .style(iced::theme::PickList::Custom(
@@ -921,138 +3639,1798 @@ impl Application for WallpaperUi {
                     .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Resolution)),
             )
             .push(
-                make_button("aspect ratio")
-                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::AspectRatio)),
+                make_button("aspect ratio")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::AspectRatio)),
+            )
+            .push(
+                make_button_fa("filter", "filter")
+                    .style(inactive_style(tab.filter.is_active()))
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Filters)),
+            )
+            .push(Space::new(Length::FillPortion(5), Length::Shrink))
+            .push(
+                make_button("select all").on_press(WallpaperMessage::SelectionUpdate(
+                    SelectionUpdateType::SelectAll,
+                )),
+            )
+            .push(
+                make_button("select by")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::SelectBy)),
+            )
+            .push(
+                make_button("deselect all").on_press(WallpaperMessage::SelectionUpdate(
+                    SelectionUpdateType::DeselectAll,
+                )),
+            )
+            .push(labeled(
+                make_button_fa("", "undo")
+                    .style(inactive_style(self.selection.undo.can_undo()))
+                    .on_press(WallpaperMessage::Undo()),
+                "Undo",
+            ))
+            .push(labeled(
+                make_button_fa("", "redo")
+                    .style(inactive_style(self.selection.undo.can_redo()))
+                    .on_press(WallpaperMessage::Redo()),
+                "Redo",
+            ))
+            .push(
+                make_button("settings")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Settings)),
+            )
+            .push(
+                make_button_fa("cart", "shopping-cart")
+                    .style(inactive_style(!self.selection.cart.is_empty()))
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Cart)),
+            )
+            .push(
+                make_button_fa("download", "download").on_press(WallpaperMessage::DownloadImages()),
+            )
+            .push(
+                make_button("import list")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Import)),
+            )
+            .push(make_button("library").on_press(WallpaperMessage::OpenLibrary()))
+            .push(
+                make_button("duplicates")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Duplicates)),
+            )
+            .push(
+                make_button_fa("monitors", "desktop")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Monitors)),
+            )
+            .push(
+                make_button_fa("watched", "eye")
+                    .style(inactive_style(!self.watches.watches.is_empty()))
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Watches)),
+            )
+            .push(
+                make_button_fa("daily", "calendar")
+                    .style(inactive_style(self.settings.daily_wallpaper))
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Daily)),
+            )
+            .push(
+                make_button("collections")
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Collections)),
+            )
+            .push(
+                make_button("download all results")
+                    .style(inactive_style(self.downloads.download_all.is_none()))
+                    .on_press(WallpaperMessage::DownloadAllResults()),
+            )
+    }
+
+    /// Counts of the selection, the cart and the results under them, and the page input
+    fn selection_info(&self) -> Row<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let selected_count = tab
+            .search_results
+            .iter()
+            .filter(|(_, l)| l.state == ImageState::Selected)
+            .count();
+
+        let mut results = format!("{} results", tab.search_results.len());
+        if self.settings.downloaded_filter != DownloadedFilter::ShowAll && !tab.is_on_disk() {
+            let num_hidden = tab
+                .search_results
+                .iter()
+                .filter(|(_, v)| {
+                    !self
+                        .settings
+                        .downloaded_filter
+                        .shows(v.state == ImageState::Downloaded)
+                })
+                .count();
+            results.push_str(&format!(" ({} hidden)", num_hidden));
+        }
+        if tab.filter.is_active() {
+            let num_filtered = tab
+                .search_results
+                .iter()
+                .filter(|(l, _)| !tab.filter.matches(l))
+                .count();
+            results.push_str(&format!(" ({} filtered)", num_filtered));
+        }
+
+        let (current_page, last_page) = tab
+            .search_meta
+            .as_ref()
+            .map_or((0, 0), |f| (f.current_page, f.last_page));
+
+        // the cart is what the download button downloads
+        let cart_size = match (
+            self.selection.cart.total_size(),
+            self.selection.cart.is_empty(),
+        ) {
+            (_, true) => String::new(),
+            (bytes, false) => match self.download_manager.estimated_time(bytes as u64) {
+                Some(seconds) => format!(
+                    " ({}, about {})",
+                    file_size_format(bytes),
+                    remaining_time_format(seconds)
+                ),
+                None => format!(" ({})", file_size_format(bytes)),
+            },
+        };
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(
+                Text::new(format!(
+                    "selected: {}  cart: {}{}  page: {}/{} {}",
+                    selected_count,
+                    self.selection.cart.len(),
+                    cart_size,
+                    current_page,
+                    last_page,
+                    results
+                ))
+                // .color(Color::WHITE)
+                .size(26),
+            )
+            .push(
+                TextInput::new("page", &tab.page_value)
+                    .style(focus_outline())
+                    .size(16)
+                    .padding(8)
+                    .on_input(WallpaperMessage::PageInputChanged)
+                    .on_submit(WallpaperMessage::GoToPage())
+                    .width(Length::Fixed(70.0)),
+            )
+            .push(match tab.source {
+                // reddit only says where the page after the last one starts
+                Source::Reddit(_) => make_button("go to page"),
+                Source::Wallhaven | Source::Unsplash | Source::Folder(_) => {
+                    make_button("go to page").on_press(WallpaperMessage::GoToPage())
+                }
+            })
+    }
+
+    /// The tabs, data saver and the download summary
+    fn status_row(&self) -> Row<'_, WallpaperMessage> {
+        let tab_row = self.tabs.iter().enumerate().fold(
+            Row::new().spacing(5).align_items(Alignment::Center),
+            |row, (index, search_tab)| {
+                let row = row.push(
+                    make_button(search_tab.title())
+                        .style(inactive_style(index == self.active_tab))
+                        .on_press(WallpaperMessage::SelectTab(index)),
+                );
+                match self.tabs.len() > 1 {
+                    true => row.push(labeled(
+                        make_button_fa("", "times")
+                            .style(inactive_style(false))
+                            .on_press(WallpaperMessage::CloseTab(index)),
+                        "Close tab",
+                    )),
+                    false => row,
+                }
+            },
+        );
+
+        Row::new()
+            .align_items(Alignment::Center)
+            .push(tab_row.push(labeled(
+                make_button_fa("", "plus").on_press(WallpaperMessage::NewTab()),
+                "New tab",
+            )))
+            .push(Space::new(Length::Fill, Length::Fixed(10.0)))
+            .push(labeled(
+                make_button_fa("data saver", "leaf")
+                    .style(inactive_style(self.saving_data()))
+                    .on_press(WallpaperMessage::ToggleDataSaver()),
+                "Small previews and no loading ahead",
+            ))
+            .push(labeled(
+                Button::new(download_summary(&self.download_manager))
+                    .style(inactive_style(self.controls.submenu == Submenu::Downloads))
+                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Downloads)),
+                "Downloads",
+            ))
+            .spacing(5)
+    }
+
+    /// The open submenu, empty when none is
+    fn submenu_view(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        match self.controls.submenu {
+            Submenu::Settings => self.settings_menu(),
+            Submenu::Resolution => Column::new()
+                .spacing(5)
+                .push(self.resolution_menu.build_resolution_row(
+                    &tab.search_options.resolutions,
+                    &tab.search_options.minimum_resolution,
+                ))
+                .push(
+                    self.resolution_menu
+                        .custom
+                        .input_row(ComboKind::Resolution, "5120x1440"),
+                ),
+            Submenu::AspectRatio => Column::new()
+                .spacing(5)
+                .push(self.aspect_menu.build_ratio_row(&tab.search_options.ratios))
+                .push(
+                    self.aspect_menu
+                        .custom
+                        .input_row(ComboKind::AspectRatio, "32:10"),
+                ),
+            Submenu::SelectBy => Column::new().push(self.selection_menu.build_selection_row()),
+            Submenu::Filters => self.filters_menu(),
+            Submenu::Cart => self.cart_menu(),
+            Submenu::Import => Column::new()
+                .spacing(5)
+                .push(Text::new("Import").size(26))
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new(
+                                "wallhaven ids or links, separated by spaces or commas",
+                                &self.downloads.import_value,
+                            )
+                            .style(focus_outline())
+                            .on_input(WallpaperMessage::ImportInputChanged)
+                            .on_submit(WallpaperMessage::ImportList())
+                            .width(Length::Fixed(600.0)),
+                        )
+                        .push(make_button("load file").on_press(WallpaperMessage::LoadImportFile()))
+                        .push(
+                            make_button_fa("import", "download")
+                                .on_press(WallpaperMessage::ImportList()),
+                        ),
+                )
+                .push(Text::new(&self.downloads.import_status)),
+            Submenu::Duplicates => self.duplicates_menu(),
+            Submenu::Watches => self.watches_menu(),
+            Submenu::PurityLock => Column::new()
+                .spacing(5)
+                .push(Text::new("Purity lock").size(26))
+                .push(Text::new(
+                    "Sketchy and nsfw wallpapers are locked, enter the PIN to show them until wall-a-bunga closes",
+                ))
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new("PIN", &self.settings_page.pin_value)
+                                .secure(true)
+                                .style(focus_outline())
+                                .on_input(WallpaperMessage::PinUpdated)
+                                .on_submit(WallpaperMessage::UnlockPurity())
+                                .width(Length::Fixed(200.0)),
+                        )
+                        .push(make_button("unlock").on_press(WallpaperMessage::UnlockPurity()))
+                        .push(Text::new(self.settings_page.pin_error.as_deref().unwrap_or_default())),
+                ),
+            Submenu::Update => self.update_menu(),
+            Submenu::Collections => self.collections_menu(),
+            Submenu::Daily => self.daily_menu(),
+            Submenu::Monitors => self.monitors_menu(),
+            Submenu::Downloads => Column::new()
+                .spacing(5)
+                .push(Text::new("Downloads").size(26))
+                .push(download_list(&self.download_manager)),
+            Submenu::None => Column::new(),
+        }
+    }
+
+    /// Every setting, saved with the button at the bottom
+    fn settings_menu(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .align_items(Alignment::Start)
+            .push(Text::new("Settings").size(26))
+            .push(self.profile_settings())
+            .push(self.connection_settings())
+            .push(self.search_defaults_settings())
+            .push(self.file_settings())
+            .push(self.system_settings())
+            .push(self.purity_lock_settings())
+            .push(self.sources_settings())
+            .push(self.startup_settings())
+            .push(self.appearance_settings())
+            .push(
+                Row::new()
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(
+                        make_button("save settings")
+                            .on_press(WallpaperMessage::SaveSettings())
+                            .width(Length::Shrink),
+                    )
+                    .push(
+                        make_button_fa("export settings", "upload")
+                            .on_press(WallpaperMessage::ExportSettings()),
+                    )
+                    .push(
+                        make_button_fa("import settings", "download")
+                            .on_press(WallpaperMessage::ImportSettings()),
+                    )
+                    .push(Text::new(&self.settings_page.bundle_status)),
+            )
+    }
+
+    /// Switching between and adding settings profiles
+    fn profile_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new().push(
+            Row::new()
+                .padding([10, 5])
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .push(Text::new("Profile:"))
+                .push(
+                    PickList::new(
+                        self.settings.profile_names(),
+                        self.settings.profile.clone(),
+                        WallpaperMessage::SwitchProfile,
+                    )
+                    .placeholder("none")
+                    .style(iced::theme::PickList::Custom(
+                        Rc::new(crate::style::pick_style::PickList),
+                        Rc::new(crate::style::pick_style::PickList),
+                    )),
+                )
+                .push(
+                    TextInput::new("new profile", &self.settings_page.profile_value)
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::ProfileNameChanged)
+                        .on_submit(WallpaperMessage::AddProfile())
+                        .width(Length::Fixed(200.0)),
+                )
+                .push(make_button_fa("add", "plus").on_press(WallpaperMessage::AddProfile()))
+                .push({
+                    let remove = make_button_fa("remove", "times");
+                    match self.settings.profile {
+                        Some(_) => remove.on_press(WallpaperMessage::RemoveProfile()),
+                        None => remove,
+                    }
+                }),
+        )
+    }
+
+    /// How downloads use the connection and the wallhaven api key
+    fn connection_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .push(
+                Column::new()
+                    .padding([10, 5])
+                    .push(Text::new("Concurrent Downloads"))
+                    .push(self.downloads.concurrent_download_control.view()),
+            )
+            .push(
+                Column::new()
+                    .padding([10, 5])
+                    .push(Text::new("Download speed limit (KB/s):"))
+                    .push(
+                        TextInput::new("unlimited", &self.downloads.speed_limit_value)
+                            .style(focus_outline())
+                            .on_input(WallpaperMessage::SpeedLimitChanged)
+                            .width(Length::Fixed(200.0)),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Data saver:"))
+                    .push(
+                        PickList::new(
+                            &DataSaver::LIST[..],
+                            Some(self.settings.data_saver),
+                            WallpaperMessage::SetDataSaver,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    )
+                    .push(
+                        Text::new(match self.metered {
+                            true => "the connection is metered",
+                            false => "",
+                        })
+                        .size(14),
+                    ),
+            )
+            .push(
+                Column::new()
+                    .padding([10, 5])
+                    .width(Length::Fill)
+                    .push(Text::new("wallhaven.cc api token (required for nsfw):"))
+                    .push(
+                        TextInput::new("api key", &self.api_key)
+                            .style(focus_outline())
+                            .on_input(WallpaperMessage::ApiTokenSet)
+                            .width(Length::Fixed(600.0)),
+                    )
+                    .push(self.api_key_status_view()),
+            )
+    }
+
+    /// What new tabs search for and what's never shown
+    fn search_defaults_settings(&self) -> Column<'_, WallpaperMessage> {
+        let default_purity = self.settings.default_purity.clone().unwrap_or_default();
+        let default_categories = self.settings.default_categories.clone().unwrap_or_default();
+        Column::new()
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("New tabs search:"))
+                    .push(
+                        make_button("general")
+                            .on_press(WallpaperMessage::ToggleDefaultContent(
+                                ContentTypes::General,
+                            ))
+                            .style(inactive_style(default_categories.general)),
+                    )
+                    .push(
+                        make_button("anime")
+                            .on_press(WallpaperMessage::ToggleDefaultContent(ContentTypes::Anime))
+                            .style(inactive_style(default_categories.anime)),
+                    )
+                    .push(
+                        make_button("people")
+                            .on_press(WallpaperMessage::ToggleDefaultContent(ContentTypes::People))
+                            .style(inactive_style(default_categories.people)),
+                    )
+                    .push(
+                        make_button("clean")
+                            .on_press(WallpaperMessage::ToggleDefaultPurity(PurityOptions::Sfw))
+                            .style(inactive_style(default_purity.clean)),
+                    )
+                    .push(
+                        make_button("sketchy")
+                            .on_press(WallpaperMessage::ToggleDefaultPurity(
+                                PurityOptions::Sketchy,
+                            ))
+                            .style(inactive_style(default_purity.sketchy)),
+                    )
+                    .push(
+                        make_button("nsfw")
+                            .on_press(WallpaperMessage::ToggleDefaultPurity(PurityOptions::Nsfw))
+                            .style(inactive_style(default_purity.nsfw)),
+                    ),
+            )
+            .push(
+                self.settings.blacklist.tags.iter().enumerate().fold(
+                    Row::new()
+                        .padding([10, 5])
+                        .spacing(5)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(format!(
+                            "Never show {} blacklisted wallpapers",
+                            self.settings.blacklist.ids.len()
+                        )))
+                        .push(
+                            make_button("clear")
+                                .on_press(WallpaperMessage::ClearBlacklistedWallpapers()),
+                        )
+                        .push(Text::new("or tags:"))
+                        .push(
+                            TextInput::new("blacklist tags", &self.settings_page.blacklist_value)
+                                .style(focus_outline())
+                                .on_input(WallpaperMessage::BlacklistTagsUpdated)
+                                .on_submit(WallpaperMessage::AddBlacklistedTags())
+                                .width(Length::Fixed(200.0)),
+                        ),
+                    |row, (index, tag)| {
+                        row.push(
+                            make_button_fa(tag, "times")
+                                .style(inactive_style(false))
+                                .on_press(WallpaperMessage::RemoveBlacklistedTag(index)),
+                        )
+                    },
+                ),
+            )
+    }
+
+    /// Where downloads go, what happens to existing files and how much may be kept
+    fn file_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .push(
+                Row::new()
+                    .width(Length::FillPortion(4))
+                    .push(
+                        Column::new()
+                            .padding([10, 5])
+                            .push(Text::new("save directory:"))
+                            .push(Text::new(
+                                self.settings
+                                    .save_directory
+                                    .clone()
+                                    .map(|s| s.into())
+                                    .unwrap_or(Cow::Borrowed("./")),
+                            )),
+                    )
+                    .push(
+                        make_button("Choose Directory")
+                            .on_press(WallpaperMessage::ChooseDirectory())
+                            .padding([10, 5]),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Downloaded wallpapers:"))
+                    .push(
+                        PickList::new(
+                            &DownloadedFilter::LIST[..],
+                            Some(self.settings.downloaded_filter),
+                            WallpaperMessage::SetDownloadedFilter,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("When a file already exists:"))
+                    .push(
+                        PickList::new(
+                            &ExistingFilePolicy::LIST[..],
+                            Some(self.settings.existing_file_policy),
+                            WallpaperMessage::SetExistingFilePolicy,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    ),
+            )
+            .push(Row::new().padding([10, 5]).push(Checkbox::new(
+                "date files by when the wallpaper was uploaded",
+                self.settings.use_upload_date,
+                WallpaperMessage::SetUseUploadDate,
+            )))
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Download all results stops at"))
+                    .push(
+                        TextInput::new(
+                            "any number of",
+                            &self
+                                .settings
+                                .download_all_count
+                                .map(|count| count.to_string())
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetDownloadAllCount)
+                        .width(Length::Fixed(120.0)),
+                    )
+                    .push(Text::new("wallpapers or"))
+                    .push(
+                        TextInput::new(
+                            "any",
+                            &self
+                                .settings
+                                .download_all_megabytes
+                                .map(|megabytes| megabytes.to_string())
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetDownloadAllMegabytes)
+                        .width(Length::Fixed(120.0)),
+                    )
+                    .push(Text::new("MB")),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Save directory may use up to"))
+                    .push(
+                        TextInput::new(
+                            "unlimited",
+                            &self
+                                .settings
+                                .max_disk_usage_megabytes
+                                .map(|megabytes| megabytes.to_string())
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetMaxDiskUsage)
+                        .width(Length::Fixed(120.0)),
+                    )
+                    .push(Text::new("MB, then"))
+                    .push(
+                        PickList::new(
+                            &QuotaAction::LIST[..],
+                            Some(self.settings.quota_action),
+                            WallpaperMessage::SetQuotaAction,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    ),
+            )
+    }
+
+    /// Logging, drawing, setting wallpapers and the proxy
+    fn system_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Log to file:"))
+                    .push(
+                        PickList::new(
+                            &LogLevel::LIST[..],
+                            Some(self.settings.log_level),
+                            WallpaperMessage::SetLogLevel,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    )
+                    .push(Text::new(
+                        log_path()
+                            .map(|path| path.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                    )),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Draw with:"))
+                    .push(
+                        PickList::new(
+                            &Renderer::LIST[..],
+                            Some(self.settings.renderer),
+                            WallpaperMessage::SetRenderer,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    )
+                    .push(Text::new("after restarting")),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Set wallpapers with"))
+                    .push(
+                        TextInput::new(
+                            "the desktop's default, or a command using {path}",
+                            self.settings
+                                .wallpaper_command
+                                .as_deref()
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetWallpaperCommand)
+                        .width(Length::Fixed(400.0)),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Connect through proxy"))
+                    .push(
+                        TextInput::new(
+                            "none, or like http://host:port",
+                            self.settings.proxy.as_deref().unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetProxy)
+                        .width(Length::Fixed(400.0)),
+                    ),
+            )
+    }
+
+    /// Rotating wallpapers, updates, the tray and starting at login
+    fn startup_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Switch to another downloaded wallpaper every"))
+                    .push(
+                        TextInput::new(
+                            "never",
+                            &self
+                                .settings
+                                .rotation_minutes
+                                .map(|minutes| minutes.to_string())
+                                .unwrap_or_default(),
+                        )
+                        .style(focus_outline())
+                        .on_input(WallpaperMessage::SetRotationMinutes)
+                        .width(Length::Fixed(120.0)),
+                    )
+                    .push(Text::new("minutes")),
+            )
+            .push(Row::new().padding([10, 5]).push(Checkbox::new(
+                "check github for new versions at startup",
+                self.settings.check_for_updates,
+                WallpaperMessage::SetCheckForUpdates,
+            )))
+            .push(match tray::SUPPORTED {
+                true => Row::new().padding([10, 5]).push(Checkbox::new(
+                    "closing the window keeps running in the tray",
+                    self.settings.minimize_to_tray,
+                    WallpaperMessage::SetMinimizeToTray,
+                )),
+                false => Row::new(),
+            })
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Checkbox::new(
+                        "start at login",
+                        self.settings.start_at_login,
+                        WallpaperMessage::SetStartAtLogin,
+                    ))
+                    .push(
+                        PickList::new(
+                            match tray::SUPPORTED {
+                                true => &StartMode::LIST[..],
+                                // nothing to bring back a hidden window
+                                false => &[StartMode::Window, StartMode::Headless][..],
+                            },
+                            Some(self.settings.start_mode),
+                            WallpaperMessage::SetStartMode,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    )
+                    .push(Text::new(
+                        self.settings_page
+                            .autostart_error
+                            .as_deref()
+                            .unwrap_or_default(),
+                    )),
+            )
+    }
+
+    /// Theme, accent color, density and the monitor resolution
+    fn appearance_settings(&self) -> Column<'_, WallpaperMessage> {
+        Column::new()
+            .push(
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Theme:"))
+                    .push(
+                        PickList::new(
+                            &ThemeMode::LIST[..],
+                            Some(self.settings.theme_mode),
+                            WallpaperMessage::SetThemeMode,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    )
+                    .push(Text::new("accent color"))
+                    .push(
+                        TextInput::new("#de6b1c", &self.settings_page.accent_value)
+                            .style(focus_outline())
+                            .on_input(WallpaperMessage::SetAccentColor)
+                            .width(Length::Fixed(120.0)),
+                    )
+                    .push(
+                        PickList::new(
+                            &Density::LIST[..],
+                            Some(self.settings.density),
+                            WallpaperMessage::SetDensity,
+                        )
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    ),
             )
-            .push(Space::new(Length::FillPortion(5), Length::Shrink))
             .push(
-                make_button("select all").on_press(WallpaperMessage::SelectionUpdate(
-                    SelectionUpdateType::SelectAll,
-                )),
+                Row::new()
+                    .padding([10, 5])
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(Text::new("Monitor resolution:"))
+                    .push(
+                        PickList::new(
+                            &wallapi::types::RESOLUTION_POSSIBILITIES[..],
+                            self.settings.monitor_resolution,
+                            WallpaperMessage::SetMonitorResolution,
+                        )
+                        .placeholder("used to preview cropping")
+                        .style(iced::theme::PickList::Custom(
+                            Rc::new(crate::style::pick_style::PickList),
+                            Rc::new(crate::style::pick_style::PickList),
+                        )),
+                    ),
+            )
+    }
+
+    /// Local filters and the order of the results already fetched
+    fn filters_menu(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let filter = &tab.filter;
+        let pick_style = || {
+            iced::theme::PickList::Custom(
+                Rc::new(crate::style::pick_style::PickList),
+                Rc::new(crate::style::pick_style::PickList),
             )
+        };
+        let arrange = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("arrange fetched results by"))
             .push(
-                make_button("deselect all").on_press(WallpaperMessage::SelectionUpdate(
-                    SelectionUpdateType::DeselectAll,
-                )),
+                PickList::new(&LocalSort::LIST[..], tab.local_sort, |sort| {
+                    WallpaperMessage::SetLocalSort(Some(sort))
+                })
+                .placeholder("as fetched")
+                .style(pick_style()),
             )
+            .push(match tab.local_sort {
+                Some(_) => make_button("as fetched").on_press(WallpaperMessage::SetLocalSort(None)),
+                None => make_button("as fetched").style(inactive_style(false)),
+            });
+        Column::new().spacing(5).push(arrange).push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(Text::new("min favorites"))
+                .push(
+                    TextInput::new(
+                        "any",
+                        &filter
+                            .min_favorites
+                            .map(|f| f.to_string())
+                            .unwrap_or_default(),
+                    )
+                    .style(focus_outline())
+                    .on_input(|text| {
+                        WallpaperMessage::ResultFilterChanged(ResultFilter {
+                            min_favorites: parse_minimum(&text),
+                            ..filter.clone()
+                        })
+                    })
+                    .width(Length::Fixed(100.0)),
+                )
+                .push(Text::new("min views"))
+                .push(
+                    TextInput::new(
+                        "any",
+                        &filter.min_views.map(|v| v.to_string()).unwrap_or_default(),
+                    )
+                    .style(focus_outline())
+                    .on_input(|text| {
+                        WallpaperMessage::ResultFilterChanged(ResultFilter {
+                            min_views: parse_minimum(&text),
+                            ..filter.clone()
+                        })
+                    })
+                    .width(Length::Fixed(100.0)),
+                )
+                .push(Text::new("min resolution"))
+                .push(
+                    PickList::new(
+                        &wallapi::types::RESOLUTION_POSSIBILITIES[..],
+                        filter.min_resolution,
+                        |resolution| {
+                            WallpaperMessage::ResultFilterChanged(ResultFilter {
+                                min_resolution: Some(resolution),
+                                ..filter.clone()
+                            })
+                        },
+                    )
+                    .placeholder("any")
+                    .style(pick_style()),
+                )
+                .push(Text::new("file type"))
+                .push(
+                    PickList::new(&FileType::LIST[..], filter.file_type, |file_type| {
+                        WallpaperMessage::ResultFilterChanged(ResultFilter {
+                            file_type: Some(file_type),
+                            ..filter.clone()
+                        })
+                    })
+                    .placeholder("any")
+                    .style(pick_style()),
+                )
+                .push(Checkbox::new(
+                    "exclude AI",
+                    filter.exclude_ai,
+                    |exclude_ai| {
+                        WallpaperMessage::ResultFilterChanged(ResultFilter {
+                            exclude_ai,
+                            ..filter.clone()
+                        })
+                    },
+                ))
+                .push(make_button("clear filters").on_press(
+                    WallpaperMessage::ResultFilterChanged(ResultFilter::default()),
+                )),
+        )
+    }
+
+    /// Wallpapers picked for download, across searches
+    fn cart_menu(&self) -> Column<'_, WallpaperMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
             .push(
-                make_button("settings")
-                    .on_press(WallpaperMessage::ChangeSubmenu(Submenu::Settings)),
+                Text::new(format!(
+                    "Cart: {} wallpapers ({})",
+                    self.selection.cart.len(),
+                    file_size_format(self.selection.cart.total_size())
+                ))
+                .size(26),
             )
             .push(
-                make_button_fa("download", "download").on_press(WallpaperMessage::DownloadImages()),
-            );
-
-        let (current_page, last_page) = self
-            .search_meta
-            .as_ref()
-            .map_or((0, 0), |f| (f.current_page, f.last_page));
-
-        let selection_info = Column::new().push(
-            Text::new(format!(
-                "selected: {}  page: {}/{} {}",
-                selected_count, current_page, last_page, results
-            ))
-            // .color(Color::WHITE)
-            .size(26),
+                make_button_fa("download cart", "download")
+                    .on_press(WallpaperMessage::DownloadImages()),
+            )
+            .push(make_button("clear cart").on_press(WallpaperMessage::ClearCart()));
+        let items = self.selection.cart.items().fold(
+            Row::new().spacing(5).align_items(Alignment::Center),
+            |row, item| {
+                row.push(
+                    Column::new()
+                        .align_items(Alignment::Center)
+                        .push(Image::new(item.thumb.clone()).width(Length::Fixed(120.0)))
+                        .push(
+                            make_button_fa("remove", "times")
+                                .style(inactive_style(false))
+                                .on_press(WallpaperMessage::RemoveFromCart(item.id.clone())),
+                        ),
+                )
+            },
         );
+        Column::new().spacing(5).push(header).push(
+            Scrollable::new(items)
+                .direction(scrollable::Direction::Horizontal(Default::default()))
+                .width(Length::Fill),
+        )
+    }
 
-        let status_row = Row::new()
+    /// Groups of downloaded wallpapers that look the same
+    fn duplicates_menu(&self) -> Column<'_, WallpaperMessage> {
+        let header = Row::new()
+            .spacing(10)
             .align_items(Alignment::Center)
-            .push(Space::new(Length::Fill, Length::Fixed(10.0)))
-            .push(self.download_manager.view())
-            .spacing(5);
-
-        let submenu = match self.controls.submenu {
-            Submenu::Settings => Column::new()
-                .align_items(Alignment::Start)
-                .push(Text::new("Settings").size(26))
+            .push(Text::new("Duplicates").size(26))
+            .push(make_button("scan library").on_press(WallpaperMessage::ScanDuplicates()))
+            .push(Text::new(&self.duplicate_status));
+        let header = match &self.deleting_duplicates {
+            Some((_, paths)) => header
+                .push(Text::new(match paths.len() {
+                    1 => "Delete this copy?".to_string(),
+                    count => format!("Delete {} copies?", count),
+                }))
                 .push(
-                    Column::new()
-                        .padding([10, 5])
-                        .push(Text::new("Concurrent Downloads"))
-                        .push(self.concurrent_download_control.view()),
+                    make_button_fa("delete", "trash")
+                        .style(inactive_style(false))
+                        .on_press(WallpaperMessage::ConfirmDeleteDuplicates()),
                 )
-                .push(
+                .push(make_button("cancel").on_press(WallpaperMessage::CancelDeleteDuplicates())),
+            None => header,
+        };
+        let groups = self.duplicates.iter().enumerate().fold(
+            Column::new().spacing(10),
+            |column, (index, group)| {
+                let copies = group.files.iter().fold(
+                    Row::new().spacing(5).align_items(Alignment::End),
+                    |row, file| {
+                        let file_name = file
+                            .path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let resolution = file
+                            .dimensions
+                            .map(|(width, height)| format!("{}x{}", width, height))
+                            .unwrap_or_else(|| "unknown size".to_string());
+                        row.push(
+                            Column::new()
+                                .spacing(2)
+                                .align_items(Alignment::Center)
+                                .push(
+                                    Image::new(image::Handle::from_path(
+                                        file.thumbnail.as_ref().unwrap_or(&file.path),
+                                    ))
+                                    .width(Length::Fixed(160.0)),
+                                )
+                                .push(Text::new(file_name).size(14))
+                                .push(
+                                    Text::new(format!(
+                                        "{} {}",
+                                        resolution,
+                                        file_size_format(file.size as i64)
+                                    ))
+                                    .size(14),
+                                )
+                                .push(
+                                    make_button_fa("delete", "trash")
+                                        .style(inactive_style(false))
+                                        .on_press(WallpaperMessage::DeleteDuplicate(
+                                            index,
+                                            file.path.clone(),
+                                        )),
+                                ),
+                        )
+                    },
+                );
+                let kind = match group.exact {
+                    true => "identical files",
+                    false => "similar images",
+                };
+                column.push(
                     Column::new()
-                        .padding([10, 5])
-                        .width(Length::Fill)
-                        .push(Text::new("wallhaven.cc api token (required for nsfw):"))
+                        .spacing(5)
                         .push(
-                            TextInput::new("api key", &self.api_key)
-                                .on_input(WallpaperMessage::ApiTokenSet)
-                                .width(Length::Fixed(600.0)),
-                        ),
+                            Row::new()
+                                .spacing(10)
+                                .align_items(Alignment::Center)
+                                .push(Text::new(format!("{} copies, {}", group.files.len(), kind)))
+                                .push(
+                                    make_button("keep best")
+                                        .on_press(WallpaperMessage::KeepBestDuplicate(index)),
+                                )
+                                .push(
+                                    make_button("ignore")
+                                        .on_press(WallpaperMessage::IgnoreDuplicates(index)),
+                                ),
+                        )
+                        .push(copies),
                 )
-                .push(
+            },
+        );
+        Column::new()
+            .spacing(5)
+            .push(header)
+            .push(Scrollable::new(groups).height(Length::Fixed(400.0)))
+    }
+
+    /// Searches checked for new wallpapers in the background
+    fn watches_menu(&self) -> Column<'_, WallpaperMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Watched searches").size(26))
+            .push(match self.checking_watches {
+                true => make_button("checking..."),
+                false => make_button("check now").on_press(WallpaperMessage::CheckWatches()),
+            })
+            .push(Text::new("every"))
+            .push(
+                TextInput::new("60", &self.settings.watch_interval_minutes.to_string())
+                    .style(focus_outline())
+                    .on_input(WallpaperMessage::SetWatchInterval)
+                    .width(Length::Fixed(80.0)),
+            )
+            .push(Text::new("minutes"));
+        let now = unix_now();
+        let watches = self.watches.watches.iter().enumerate().fold(
+            Column::new().spacing(5),
+            |column, (index, watch)| {
+                let checked = match watch.last_checked {
+                    Some(checked) => {
+                        format!("checked {}", time_ago_format(now.saturating_sub(checked)))
+                    }
+                    None => "not checked yet".to_string(),
+                };
+                column.push(
                     Row::new()
-                        .width(Length::FillPortion(4))
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(watch.query()).width(Length::Fixed(250.0)))
+                        .push(Text::new(checked).width(Length::Fixed(200.0)))
+                        .push(Checkbox::new("notify", watch.notify, move |_| {
+                            WallpaperMessage::ToggleWatchNotify(index)
+                        }))
+                        .push(labeled(
+                            make_button_fa("", "trash")
+                                .style(inactive_style(false))
+                                .on_press(WallpaperMessage::RemoveWatch(index)),
+                            "Stop watching",
+                        )),
+                )
+            },
+        );
+        Column::new()
+            .spacing(5)
+            .push(header)
+            .push(match self.watches.watches.is_empty() {
+                true => Column::new().push(Text::new(
+                    "Press watch next to the search box to download what's new for a search",
+                )),
+                false => watches,
+            })
+            .push(Text::new(
+                "Run wall-a-bunga --watch to keep checking without the window open",
+            ))
+    }
+
+    /// A newer release and getting it
+    fn update_menu(&self) -> Column<'_, WallpaperMessage> {
+        match &self.update {
+            Some(update) => {
+                let header = Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::new(format!("wall-a-bunga {}", update.version())).size(26))
+                    .push(Text::new(format!("you have {}", env!("CARGO_PKG_VERSION"))))
+                    .push(
+                        make_button_fa("release page", "external-link-alt")
+                            .on_press(WallpaperMessage::OpenInBrowser(update.html_url.clone())),
+                    );
+                // only windows is installed from a single file
+                let header = match (cfg!(windows), update.installer()) {
+                    (true, Some(installer)) => header
+                        .push(match self.downloading_update {
+                            true => make_button_fa("downloading...", "download"),
+                            false => make_button_fa("install", "download")
+                                .on_press(WallpaperMessage::DownloadUpdate()),
+                        })
+                        .push(Text::new(file_size_format(installer.size as i64))),
+                    _ => header,
+                };
+                Column::new()
+                    .spacing(5)
+                    .push(header.push(Text::new(self.update_error.as_deref().unwrap_or_default())))
+                    .push(
+                        Scrollable::new(Text::new(
+                            update.body.as_deref().unwrap_or("No changelog"),
+                        ))
+                        .height(Length::Fixed(300.0)),
+                    )
+            }
+            None => Column::new().push(Text::new("You have the latest version")),
+        }
+    }
+
+    /// Collections of a wallhaven user to browse
+    fn collections_menu(&self) -> Column<'_, WallpaperMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Collections").size(26))
+            .push(
+                TextInput::new("username, or empty for yours", &self.collections_username)
+                    .style(focus_outline())
+                    .on_input(WallpaperMessage::CollectionsUsernameUpdated)
+                    .on_submit(WallpaperMessage::LoadCollections())
+                    .width(Length::Fixed(300.0)),
+            )
+            .push(match self.collections {
+                CollectionsState::Loading => make_button("loading..."),
+                _ => make_button("load").on_press(WallpaperMessage::LoadCollections()),
+            });
+        let content = match &self.collections {
+            CollectionsState::NotLoaded | CollectionsState::Loading => Column::new(),
+            CollectionsState::Failed(e) => Column::new().push(Text::new(e)),
+            CollectionsState::Loaded(_, collections) if collections.is_empty() => {
+                Column::new().push(Text::new("There are no public collections"))
+            }
+            CollectionsState::Loaded(username, collections) => {
+                let can_open = username.is_some();
+                let list = collections.iter().enumerate().fold(
+                    Column::new().spacing(5),
+                    |column, (index, collection)| {
+                        let open = make_button("open");
+                        let download = make_button_fa("download all", "download");
+                        let (open, download) = match can_open {
+                            true => (
+                                open.on_press(WallpaperMessage::OpenCollection(index)),
+                                download.on_press(WallpaperMessage::DownloadCollection(index)),
+                            ),
+                            false => (open, download),
+                        };
+                        let visibility = match collection.public {
+                            0 => "private",
+                            _ => "public",
+                        };
+                        column.push(
+                            Row::new()
+                                .spacing(10)
+                                .align_items(Alignment::Center)
+                                .push(Text::new(&collection.label).width(Length::Fixed(250.0)))
+                                .push(
+                                    Text::new(format!(
+                                        "{} wallpapers, {}",
+                                        collection.count, visibility
+                                    ))
+                                    .width(Length::Fixed(200.0)),
+                                )
+                                .push(open)
+                                .push(download),
+                        )
+                    },
+                );
+                match can_open {
+                    true => list,
+                    false => list.push(Text::new(
+                        "wallhaven only opens collections by their owner's name, enter your username to open these",
+                    )),
+                }
+            }
+        };
+        Column::new().spacing(5).push(header).push(content)
+    }
+
+    /// The wallpaper picked for today and where it's picked from
+    fn daily_menu(&self) -> Column<'_, WallpaperMessage> {
+        let tab = self.tab();
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Wallpaper of the day").size(26))
+            .push(Checkbox::new(
+                "set the day's top wallpaper every day",
+                self.settings.daily_wallpaper,
+                WallpaperMessage::SetDailyWallpaper,
+            ));
+        let filters = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new(format!(
+                "Picks from the toplist of {}",
+                self.daily.query()
+            )))
+            .push(match tab.is_wallhaven() {
+                true => {
+                    make_button("use this search").on_press(WallpaperMessage::UseSearchForDaily())
+                }
+                false => make_button("use this search"),
+            })
+            .push(match self.picking_daily {
+                true => make_button("picking..."),
+                false => make_button("pick now").on_press(WallpaperMessage::PickDailyWallpaper()),
+            })
+            .push(match self.daily.picks.is_empty() || self.picking_daily {
+                true => make_button_fa("skip", "ban"),
+                false => make_button_fa("skip", "ban")
+                    .style(inactive_style(false))
+                    .on_press(WallpaperMessage::SkipDailyWallpaper()),
+            })
+            .push(Text::new(self.daily_error.as_deref().unwrap_or_default()));
+        let now = unix_now();
+        let picks = self.daily.picks.iter().fold(
+            Row::new().spacing(10).align_items(Alignment::End),
+            |row, pick| {
+                row.push(
+                    Column::new()
+                        .spacing(2)
+                        .align_items(Alignment::Center)
                         .push(
-                            Column::new()
-                                .padding([10, 5])
-                                .push(Text::new("save directory:"))
-                                .push(Text::new(
-                                    self.settings
-                                        .save_directory
-                                        .clone()
-                                        .map(|s| s.into())
-                                        .unwrap_or(Cow::Borrowed("./")),
-                                )),
+                            Image::new(image::Handle::from_path(&pick.path))
+                                .width(Length::Fixed(160.0)),
                         )
+                        .push(Text::new(&pick.listing.id).size(14))
                         .push(
-                            make_button("Choose Directory")
-                                .on_press(WallpaperMessage::ChooseDirectory())
-                                .padding([10, 5]),
+                            Text::new(time_ago_format(now.saturating_sub(pick.picked_at))).size(14),
+                        )
+                        .push(
+                            make_button_fa("set", "desktop").on_press(
+                                WallpaperMessage::SetAsWallpaper(pick.listing.id.clone()),
+                            ),
                         ),
                 )
-                .push(Checkbox::new(
-                    "Ignore downloaded",
-                    self.settings.ignore_downloaded,
-                    WallpaperMessage::SetIgnoreDownloaded,
-                ))
-                .push(
-                    make_button("save settings")
-                        .on_press(WallpaperMessage::SaveSettings())
-                        .width(Length::Shrink),
+            },
+        );
+        Column::new()
+            .spacing(5)
+            .push(header)
+            .push(filters)
+            .push(match self.daily.picks.is_empty() {
+                true => Row::new().push(Text::new("Nothing has been picked yet")),
+                false => Row::new().push(
+                    Scrollable::new(picks)
+                        .direction(scrollable::Direction::Horizontal(Default::default())),
                 ),
-            Submenu::Resolution => Column::new().push(self.resolution_menu.build_resolution_row(
-                &self.search_options.resolutions,
-                &self.search_options.minimum_resolution,
+            })
+            .push(Text::new(
+                "Skipped wallpapers are never picked again, wall-a-bunga --watch picks them too",
+            ))
+    }
+
+    /// The detected monitors and the wallpaper of each
+    fn monitors_menu(&self) -> Column<'_, WallpaperMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Monitors").size(26))
+            .push(make_button("detect").on_press(WallpaperMessage::DetectMonitors()))
+            .push(
+                make_button_fa("apply", "desktop")
+                    .style(inactive_style(!self.monitor_assignments.is_empty()))
+                    .on_press(WallpaperMessage::ApplyMonitorWallpapers()),
+            );
+        let monitors = match self.monitors.is_empty() {
+            true => Column::new().push(Text::new(
+                "No monitors detected, the platform didn't report any",
             )),
-            Submenu::AspectRatio => Column::new().push(
-                self.aspect_menu
-                    .build_ratio_row(&self.search_options.ratios),
-            ), // todo implement
-            Submenu::None => Column::new(),
+            false => {
+                self.monitors
+                    .iter()
+                    .fold(Column::new().spacing(10), |column, monitor| {
+                        let assigned = match self.monitor_assignments.get(&monitor.name) {
+                            Some((id, handle)) => Column::new()
+                                .spacing(2)
+                                .align_items(Alignment::Center)
+                                .push(Image::new(handle.clone()).width(Length::Fixed(160.0)))
+                                .push(Text::new(id).size(14)),
+                            None => Column::new().push(Text::new("nothing assigned")),
+                        };
+                        // results that fill the monitor without cropping or scaling
+                        let suggestions = self
+                            .tab()
+                            .search_results
+                            .iter()
+                            .filter(|(listing, _)| {
+                                listing.resolution == monitor.resolution.to_string()
+                            })
+                            .take(6)
+                            .fold(Row::new().spacing(5), |row, (listing, image)| {
+                                let picked = self
+                                    .monitor_assignments
+                                    .get(&monitor.name)
+                                    .is_some_and(|(id, _)| *id == listing.id);
+                                row.push(
+                                    Button::new(
+                                        Image::new(image.image_handle.clone())
+                                            .width(Length::Fixed(120.0)),
+                                    )
+                                    .style(inactive_style(picked))
+                                    .on_press(
+                                        WallpaperMessage::AssignToMonitor(
+                                            monitor.name.clone(),
+                                            listing.id.clone(),
+                                        ),
+                                    ),
+                                )
+                            });
+                        column.push(
+                            Row::new()
+                                .spacing(10)
+                                .align_items(Alignment::Center)
+                                .push(
+                                    Column::new()
+                                        .spacing(5)
+                                        .width(Length::Fixed(200.0))
+                                        .push(Text::new(&monitor.name).size(20))
+                                        .push(Text::new(monitor.resolution.to_string()))
+                                        .push(make_button("search this resolution").on_press(
+                                            WallpaperMessage::SearchMonitorResolution(
+                                                monitor.resolution,
+                                            ),
+                                        )),
+                                )
+                                .push(assigned)
+                                .push(suggestions),
+                        )
+                    })
+            }
+        };
+        Column::new()
+            .spacing(5)
+            .push(header)
+            .push(Scrollable::new(monitors).height(Length::Fixed(400.0)))
+    }
+
+    /// Banners, the state of the downloads and files waiting on what to do with them
+    fn notices_view(&self) -> Column<'_, WallpaperMessage> {
+        let notices = Column::new().push(self.banners.view());
+        let notices = match self.download_manager.is_offline() {
+            true => notices.push(Text::new(
+                "Offline, downloads continue once the connection is back",
+            )),
+            false => notices,
+        };
+        let notices = match self.download_manager.throttled_for() {
+            Some(remaining) => notices.push(Text::new(format!(
+                "wallhaven is throttling downloads, resuming in {}s",
+                remaining.as_secs() + 1
+            ))),
+            None => notices,
+        };
+        let notices = match &self.quota {
+            Some(quota) if quota.exceeded() => notices.push(Text::new(format!(
+                "The save directory uses {}, over the {} limit",
+                file_size_format(quota.used as i64),
+                file_size_format(quota.limit as i64)
+            ))),
+            _ => notices,
+        };
+        let notices = match &self.downloads.download_all {
+            Some(download_all) => notices.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::new(format!(
+                        "Downloading all results: {} queued ({})",
+                        download_all.queued,
+                        file_size_format(download_all.bytes)
+                    )))
+                    .push(make_button("stop").on_press(WallpaperMessage::StopDownloadAll())),
+            ),
+            None => notices,
+        };
+        self.downloads
+            .file_conflicts
+            .iter()
+            .fold(notices.spacing(5), |column, conflict| {
+                let file_name = conflict
+                    .save_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let resolve = |label: &'static str, policy: ExistingFilePolicy| {
+                    make_button(label).on_press(WallpaperMessage::ResolveFileConflict(
+                        conflict.id.clone(),
+                        policy,
+                    ))
+                };
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(format!("{} already exists", file_name)))
+                        .push(resolve("overwrite", ExistingFilePolicy::Overwrite))
+                        .push(resolve("keep both", ExistingFilePolicy::Rename))
+                        .push(resolve("skip", ExistingFilePolicy::Skip)),
+                )
+            })
+    }
+}
+
+impl Application for WallpaperUi {
+    type Executor = executor::Default;
+    type Message = WallpaperMessage;
+    type Flags = StartupFlags;
+
+    fn new(flags: Self::Flags) -> (Self, Command<WallpaperMessage>) {
+        let first_run = matches!(flags.settings, Ok(None));
+        let mut banners = Banners::default();
+        let mut settings = match flags.settings {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(e) => {
+                banners.show(
+                    BannerSource::Settings,
+                    Severity::Error,
+                    format!(
+                        "Couldn't load the settings, the defaults are used until they're saved. {}",
+                        e
+                    ),
+                );
+                SavedSettings::default()
+            }
+        };
+        // nothing's unlocked yet
+        let lock = PurityLock {
+            locked: settings.purity_pin.is_some(),
+        };
+        settings.default_purity = lock.allowed(settings.default_purity.take());
+        let key = settings.api_key.clone();
+        let http = build_client(settings.proxy.as_deref()).unwrap_or_else(|e| {
+            error!("Connecting without the proxy, {}", e);
+            banners.show(
+                BannerSource::Settings,
+                Severity::Error,
+                format!(
+                    "Couldn't use the proxy, {}. Requests go out without one until it's fixed in the settings.",
+                    e
+                ),
+            );
+            build_client(None).unwrap_or_default()
+        });
+        let mut download_manager = DownloadManager::default();
+        download_manager.set_client(http.clone());
+        download_manager.set_speed_limit(settings.download_speed_limit);
+        let client = WallhavenClient::with_client(http);
+        let startup_client = client.clone();
+        set_appearance(settings.accent_color.as_deref(), settings.density);
+        let window_geometry = settings.window.unwrap_or_default();
+        let library = match Library::open() {
+            Ok(library) => library,
+            Err(e) => {
+                // downloads still work, they just won't be remembered after a restart
+                error!("Failed to open the library index {:?}", e);
+                banners.show(
+                    BannerSource::Downloads,
+                    Severity::Error,
+                    format!(
+                        "Couldn't open the library, downloads won't be remembered after a restart. {}",
+                        e
+                    ),
+                );
+                Library::default()
+            }
+        };
+        let directory: PathBuf = settings
+            .save_directory
+            .clone()
+            .unwrap_or_else(|| "./".to_string())
+            .into();
+        let startup_library = library.clone();
+        let protected = match library.protected_ids() {
+            Ok(ids) => ids.into_iter().collect(),
+            Err(e) => {
+                error!("Failed to read protected wallpapers {:?}", e);
+                HashSet::new()
+            }
+        };
+        let mut tab = SearchTab::new(
+            0,
+            key.clone(),
+            settings.default_purity.clone(),
+            settings.default_categories.clone(),
+        );
+        if let Some(options) = settings.last_search.clone() {
+            tab.restore(options);
+        }
+        // searching for what the app was started with is the startup search, so there's no
+        // second search racing it for the first tab
+        let command = match flags.command {
+            Some(IpcCommand::Search { query }) => {
+                tab.restore(SearchOptions {
+                    query: Some(query),
+                    ..tab.search_options.clone()
+                });
+                None
+            }
+            command => command,
+        };
+        // neither can the last search or a link it was started with
+        tab.search_options.purity = lock.allowed(tab.search_options.purity.take());
+        let startup_search = settings.blacklist.exclude_tags(&tab.search_options);
+        let startup_id = tab.search_id();
+        let mut ui = Self {
+            settings_page: SettingsPage {
+                accent_value: settings.accent_color.clone().unwrap_or_default(),
+                ..Default::default()
+            },
+            downloads: DownloadsState {
+                concurrent_download_control: IncrementControl { value: 5 },
+                speed_limit_value: settings
+                    .download_speed_limit
+                    .map(|limit| limit.to_string())
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            search: SearchState {
+                history: SearchHistory::load_history().unwrap_or_default(),
+                tags: TagCache::load_tags().unwrap_or_default(),
+                ..Default::default()
+            },
+            settings,
+            tabs: vec![tab],
+            window_geometry,
+            next_tab_id: 1,
+            api_key: key.unwrap_or_default(),
+            download_manager,
+            watches: WatchList::load_watches().unwrap_or_default(),
+            daily: DailyWallpaper::load_daily().unwrap_or_default(),
+            library,
+            protected,
+            banners,
+            client,
+            // nothing's configured yet, asked for before the first search
+            onboarding: first_run.then(OnboardingStep::default),
+            ..Self::default()
+        };
+        if ui.banners.get(BannerSource::Settings).is_some() {
+            ui.controls.submenu = Submenu::Settings;
+        }
+        ui.refresh_custom_combos();
+        ui.taskbar.set_jump_list(&ui.save_directory());
+        let quota = ui.check_quota();
+        // files already in the save directory are indexed first so the results show them
+        let startup = match first_run {
+            // the onboarding asks for the screen's resolution, it's filled in with the detected
+            // one without waiting for a large save directory to be indexed
+            true => Command::batch(vec![
+                Command::perform(detect_monitors(), WallpaperMessage::MonitorsDetected),
+                Command::perform(
+                    WallpaperUi::index_directory(startup_library, directory),
+                    |_| WallpaperMessage::LibraryUpdated(Ok(())),
+                ),
+            ]),
+            false => Command::perform(
+                async move {
+                    WallpaperUi::index_directory(startup_library.clone(), directory).await;
+                    WallpaperUi::search_command(startup_client, startup_search, startup_library)
+                        .await
+                },
+                move |response| match response {
+                    Some(response) => WallpaperMessage::SearchReceived(startup_id, response),
+                    None => WallpaperMessage::SearchOffline(startup_id),
+                },
+            ),
         };
+        let command = match command {
+            Some(command) => Command::perform(async {}, |_| WallpaperMessage::Ipc(command)),
+            None => Command::none(),
+        };
+        (
+            ui,
+            Command::batch(vec![
+                startup,
+                command,
+                quota,
+                Command::perform(async {}, |_| WallpaperMessage::CheckDailyWallpaper()),
+                Command::perform(async {}, |_| WallpaperMessage::CheckForUpdates()),
+                Command::perform(async {}, |_| WallpaperMessage::CheckSystemTheme()),
+                Command::perform(async {}, |_| WallpaperMessage::CheckMetered()),
+            ]),
+        )
+    }
+
+    fn title(&self) -> String {
+        "wall-a-bunga".to_string()
+    }
+
+    fn update(&mut self, message: WallpaperMessage) -> Command<WallpaperMessage> {
+        let command = self.handle_message(message);
+        // downloads move along from many messages, it's compared after every one of them
+        self.taskbar
+            .set_progress(self.download_manager.batch_progress());
+        self.update_crash_state();
+        command
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            Subscription::batch(download_subscriptions(&self.download_manager))
+                .map(WallpaperMessage::DownloadUpdated),
+            match self.download_manager.throttled_for() {
+                Some(_) => iced::time::every(Duration::from_secs(1))
+                    .map(|_| WallpaperMessage::ThrottleTick()),
+                None => Subscription::none(),
+            },
+            match self.download_manager.is_offline() {
+                true => iced::time::every(Duration::from_secs(15))
+                    .map(|_| WallpaperMessage::CheckConnection()),
+                false => Subscription::none(),
+            },
+            self.preview_subscription(),
+            keyboard_shortcuts(),
+            match (self.settings.rotation_minutes, self.rotation_paused) {
+                (Some(minutes), false) => {
+                    iced::time::every(Duration::from_secs(minutes.max(1) as u64 * 60))
+                        .map(|_| WallpaperMessage::RotateWallpaper())
+                }
+                _ => Subscription::none(),
+            },
+            Subscription::from_recipe(TrayIcon).map(WallpaperMessage::Tray),
+            ipc::listen().map(WallpaperMessage::Ipc),
+            // there's no portable way to be told when the desktop switches
+            match self.settings.theme_mode {
+                ThemeMode::System => iced::time::every(Duration::from_secs(60))
+                    .map(|_| WallpaperMessage::CheckSystemTheme()),
+                _ => Subscription::none(),
+            },
+            // connections switch, like joining a phone's hotspot
+            match self.settings.data_saver {
+                DataSaver::WhenMetered => iced::time::every(Duration::from_secs(60))
+                    .map(|_| WallpaperMessage::CheckMetered()),
+                _ => Subscription::none(),
+            },
+            match self.watches.watches.is_empty() {
+                true => Subscription::none(),
+                false => iced::time::every(Duration::from_secs(
+                    self.settings.watch_interval_minutes.max(1) as u64 * 60,
+                ))
+                .map(|_| WallpaperMessage::CheckWatches()),
+            },
+            // hourly, so a new day is noticed soon after it starts
+            match self.settings.daily_wallpaper {
+                true => iced::time::every(Duration::from_secs(60 * 60))
+                    .map(|_| WallpaperMessage::CheckDailyWallpaper()),
+                false => Subscription::none(),
+            },
+            iced::event::listen_with(|event, _| match event {
+                iced::Event::Window(_, window::Event::CloseRequested) => {
+                    Some(WallpaperMessage::CloseRequested())
+                }
+                iced::Event::Window(_, window::Event::Resized { width, height }) => {
+                    Some(WallpaperMessage::WindowResized(width, height))
+                }
+                iced::Event::Window(_, window::Event::Moved { x, y }) => {
+                    Some(WallpaperMessage::WindowMoved(x, y))
+                }
+                // anywhere in the window, the drag can end outside of the results
+                iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    Some(WallpaperMessage::DragSelectEnded())
+                }
+                _ => None,
+            }),
+        ])
+    }
 
+    fn view(&self) -> Element<'_, Self::Message> {
+        if let Some(step) = self.onboarding {
+            return self.onboarding_view(step);
+        }
         let mut column = Column::new()
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(20)
             .align_items(Alignment::Center)
             .spacing(10)
-            .push(status_row)
-            .push(filter_row)
-            .push(submenu)
-            .push(text_input);
+            .push(self.status_row())
+            .push(self.filter_row())
+            .push(self.submenu_view())
+            .push(self.notices_view())
+            .push(self.search_bar())
+            .push(self.exclude_row())
+            .push(self.tag_suggestion_list())
+            .push(self.history_dropdown());
         // this horrible hack lets me disable the scroll for preview mode.
         // is there a better way to do this?
         // yes.
         // am i going to do it right now?
         // no.
         // maybe one day.
-        if is_preview_disabled {
+        if matches!(&self.preview.mode, PreviewMode::Disable) {
             column = column
+                .push(self.uploader_header())
                 .push(
-                    Scrollable::new(main_content)
+                    Scrollable::new(self.results_view())
+                        .id(results_scrollable_id())
                         .on_scroll(WallpaperMessage::Scroll)
                         .width(Length::Fill)
                         .height(Length::Fill), // .align_items(Alignment::Center),
                 )
-                .push(selection_info);
+                .push(self.selection_info());
         } else {
-            column = column.push(main_content);
+            column = column.push(self.results_view());
         }
         Container::new(column)
             .padding(15)
@@ -1064,39 +5442,9 @@ impl Application for WallpaperUi {
     type Theme = iced::Theme;
 
     fn theme(&self) -> Self::Theme {
-        iced::Theme::Dark
+        match (self.settings.theme_mode, self.system_dark) {
+            (ThemeMode::Light, _) | (ThemeMode::System, Some(false)) => iced::Theme::Light,
+            _ => iced::Theme::Dark,
+        }
     }
-
-    // type Theme = WallabungaTheme;
 }
-
-// #[derive(Default)]
-// pub struct WallabungaTheme;
-
-// pub enum StyleMode {
-//     Dark,
-//     Light,
-// }
-
-// impl Default for StyleMode {
-//     fn default() -> Self {
-//         Self::Dark
-//     }
-// }
-
-// impl StyleSheet for WallabungaTheme {
-//     type Style = StyleMode;
-
-//     fn appearance(&self, style: &Self::Style) -> iced::application::Appearance {
-//         match style {
-//             StyleMode::Dark => iced::application::Appearance {
-//                 background_color: Color::from_rgb(0.1, 0.1, 0.11),
-//                 text_color: Color::from_rgb(0.98, 0.97, 0.95),
-//             },
-//             StyleMode::Light => iced::application::Appearance {
-//                 background_color: Color::from_rgb(0.98, 0.97, 0.95),
-//                 text_color: Color::from_rgb(0.1, 0.1, 0.11),
-//             },
-//         }
-//     }
-// }
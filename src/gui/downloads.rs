@@ -0,0 +1,631 @@
+use crate::banner::BannerSource;
+use crate::clipboard::copy_image;
+use crate::daily::{pick_daily_wallpaper, DailyWallpaper};
+use crate::duplicates::scan_duplicates;
+use crate::file_manager::reveal_in_file_manager;
+use crate::gui::{ImageState, IncrementControl, Reduced, TabId, WallpaperMessage, WallpaperUi};
+use crate::import::{load_list_file, parse_wallpaper_ids, resolve_wallpapers};
+use crate::library::LibraryEntry;
+use crate::wallpaper::{next_rotation, set_monitor_wallpapers, set_wallpaper};
+use iced::widget::image;
+use iced::Command;
+use log::{error, info};
+use std::collections::HashMap;
+use std::ops::ControlFlow::{Break, Continue};
+use std::path::PathBuf;
+use std::time::SystemTime;
+use wallapi::types::ListingData;
+use wallcore::connectivity::probe_connection;
+use wallcore::download_manager::DownloadStatus;
+use wallcore::history::unix_now;
+use wallcore::unsplash;
+
+#[derive(Debug, Clone)]
+pub(super) struct FileConflict {
+    pub(super) id: String,
+    /// Full size image url
+    pub(super) url: String,
+    pub(super) save_path: PathBuf,
+    pub(super) uploaded: Option<SystemTime>,
+}
+
+/// Progress of queueing every result of a search
+#[derive(Debug, Clone)]
+pub(super) struct DownloadAll {
+    pub(super) tab_id: TabId,
+    pub(super) queued: usize,
+    /// Size of everything queued so far
+    pub(super) bytes: i64,
+}
+
+/// Inputs of the downloads page and the downloads waiting on something
+#[derive(Debug, Default)]
+pub(super) struct DownloadsState {
+    pub(super) concurrent_download_control: IncrementControl,
+    /// Contents of the speed limit input, may not be a valid number while being typed
+    pub(super) speed_limit_value: String,
+    /// Downloads waiting on the user to decide what happens to the file they would replace
+    pub(super) file_conflicts: Vec<FileConflict>,
+    /// Set while "download all results" is walking through the pages of a search
+    pub(super) download_all: Option<DownloadAll>,
+    /// Wallpaper ids or links to import
+    pub(super) import_value: String,
+    /// Result of the last import, or what's being imported right now
+    pub(super) import_status: String,
+    /// Id of a wallpaper that becomes the desktop wallpaper once its download finishes
+    pub(super) set_when_downloaded: Option<String>,
+    /// Wallpapers found by a watch that are downloading, by id, for their library entries
+    pub(super) watched_downloads: HashMap<String, ListingData>,
+}
+
+impl WallpaperUi {
+    /// The download queue, downloading everything found and importing lists of wallpapers, and
+    /// what's done with downloaded files: setting them as the wallpaper, the daily wallpaper and
+    /// cleaning up duplicates
+    pub(super) fn reduce_downloads(&mut self, message: WallpaperMessage) -> Reduced {
+        match message {
+            WallpaperMessage::DownloadImages() => {
+                // failed downloads in view get retried along with the cart
+                for (listing, image) in &self.tabs[self.active_tab].search_results {
+                    if image.state == ImageState::Failed {
+                        self.selection.cart.add(listing, &image.image_handle);
+                    }
+                }
+                let items = self.selection.cart.take_all();
+                // reverse the order so that when we queue these, the first are inserted last
+                for item in items.iter().rev() {
+                    self.queue_download(&item.id, &item.path, &item.created_at);
+                }
+            }
+            WallpaperMessage::DownloadedStateRefreshed(states) => {
+                for (id, downloaded) in states {
+                    for image in self.results_with_id_mut(&id) {
+                        image.state = match (image.state, downloaded) {
+                            (ImageState::Unselected | ImageState::Failed, true) => {
+                                ImageState::Downloaded
+                            }
+                            (ImageState::Downloaded, false) => ImageState::Unselected,
+                            (state, _) => state,
+                        };
+                    }
+                }
+            }
+            WallpaperMessage::CheckConnection() => {
+                return Break(Command::perform(
+                    probe_connection(self.client.http().clone()),
+                    WallpaperMessage::ConnectionChecked,
+                ));
+            }
+            WallpaperMessage::ConnectionChecked(online) => {
+                if online && self.download_manager.is_offline() {
+                    self.download_manager.set_offline(false);
+                }
+            }
+            WallpaperMessage::QuotaChecked(report) => {
+                self.checking_quota = false;
+                for id in &report.pruned {
+                    self.protected.remove(id);
+                    for l in self.results_with_id_mut(id) {
+                        l.state = ImageState::Unselected
+                    }
+                }
+                if report.exceeded() {
+                    info!(
+                        "Save directory uses {} bytes, over the {} byte quota",
+                        report.used, report.limit
+                    );
+                }
+                self.quota = Some(report);
+            }
+            WallpaperMessage::DownloadCollection(index) => {
+                let command = self.open_collection(index);
+                if self.tab().collection.is_some() {
+                    info!("Downloading all of collection {}", self.tab().title());
+                    self.downloads.download_all = Some(DownloadAll {
+                        tab_id: self.tab().id,
+                        queued: 0,
+                        bytes: 0,
+                    });
+                }
+                return Break(command);
+            }
+            WallpaperMessage::DownloadAllResults() => {
+                if self.tab().is_on_disk() {
+                    return Break(Command::none());
+                }
+                info!("Downloading all results of the current search");
+                self.downloads.download_all = Some(DownloadAll {
+                    tab_id: self.tab().id,
+                    queued: 0,
+                    bytes: 0,
+                });
+                return Break(self.continue_download_all());
+            }
+            WallpaperMessage::StopDownloadAll() => self.downloads.download_all = None,
+            WallpaperMessage::ImportInputChanged(value) => self.downloads.import_value = value,
+            WallpaperMessage::LoadImportFile() => {
+                return Break(Command::perform(
+                    load_list_file(),
+                    WallpaperMessage::ImportFileLoaded,
+                ));
+            }
+            WallpaperMessage::ImportFileLoaded(text) => {
+                if let Some(text) = text {
+                    // the input is a single line
+                    self.downloads.import_value =
+                        text.split_whitespace().collect::<Vec<_>>().join(" ");
+                }
+            }
+            WallpaperMessage::ImportList() => {
+                let ids = parse_wallpaper_ids(&self.downloads.import_value);
+                if ids.is_empty() {
+                    self.downloads.import_status = "No wallpaper ids or links found".to_string();
+                    return Break(Command::none());
+                }
+                info!("Importing {} wallpapers", ids.len());
+                self.downloads.import_status = format!("Looking up {} wallpapers...", ids.len());
+                return Break(Command::perform(
+                    resolve_wallpapers(
+                        self.client.clone(),
+                        ids,
+                        self.tab().search_options.api_key.clone(),
+                    ),
+                    WallpaperMessage::ImportResolved,
+                ));
+            }
+            WallpaperMessage::ImportResolved((found, missing)) => {
+                self.downloads.import_status = match missing.is_empty() {
+                    true => format!("Queued {} wallpapers", found.len()),
+                    false => format!(
+                        "Queued {} wallpapers, couldn't find {}",
+                        found.len(),
+                        missing.join(", ")
+                    ),
+                };
+                for listing in found {
+                    self.selection.cart.remove(&listing.id);
+                    self.queue_download(&listing.id, &listing.path, &listing.created_at);
+                }
+                self.downloads.import_value.clear();
+            }
+            WallpaperMessage::ResolveFileConflict(id, policy) => {
+                if let Some(index) = self
+                    .downloads
+                    .file_conflicts
+                    .iter()
+                    .position(|c| c.id == id)
+                {
+                    let conflict = self.downloads.file_conflicts.remove(index);
+                    self.resolve_file_conflict(conflict, policy);
+                }
+            }
+            WallpaperMessage::DownloadUpdated(u) => match u {
+                DownloadStatus::Progress {
+                    id,
+                    percentage,
+                    downloaded,
+                    total,
+                } => {
+                    for i in self.results_with_id_mut(&id) {
+                        i.state = ImageState::Downloading(percentage);
+                    }
+                    self.download_manager
+                        .set_progress(&id, percentage, downloaded, total);
+                }
+                DownloadStatus::Renamed { id, save_path } => {
+                    self.download_manager.set_save_path(&id, save_path);
+                }
+                DownloadStatus::Throttled(id) => {
+                    // the item stays queued, the stream retries it after the backoff
+                    info!("Image {} throttled", id);
+                }
+                DownloadStatus::Cancelled(id) => {
+                    info!("Image {} cancelled", id);
+                    for l in self.results_with_id_mut(&id) {
+                        l.state = ImageState::Unselected
+                    }
+                    self.download_manager.remove_cancelled(&id);
+                }
+                DownloadStatus::Offline(id) => {
+                    info!("Image {} waiting for the connection", id);
+                    for l in self.results_with_id_mut(&id) {
+                        l.state = ImageState::Queued
+                    }
+                    self.download_manager.mark_offline(&id);
+                }
+                DownloadStatus::Failed { id, reason } => {
+                    error!("Image {} failed, {}", id, reason);
+                    for l in self.results_with_id_mut(&id) {
+                        l.state = ImageState::Failed
+                    }
+                    self.download_manager.mark_failed(&id, reason);
+                    self.update_download_banner();
+                }
+                DownloadStatus::Finished(id) => {
+                    info!("Image {} complete", id);
+                    for l in self.results_with_id_mut(&id) {
+                        l.state = ImageState::Downloaded
+                    }
+                    if let Some(path) = self.download_manager.remove_download(&id) {
+                        let set_wallpaper_command =
+                            match self.downloads.set_when_downloaded.as_ref() {
+                                Some(pending) if *pending == id => {
+                                    self.downloads.set_when_downloaded = None;
+                                    self.current_wallpaper = Some(id.clone());
+                                    Command::perform(
+                                        set_wallpaper(
+                                            path.clone(),
+                                            self.settings.wallpaper_command.clone(),
+                                        ),
+                                        WallpaperMessage::WallpaperSet,
+                                    )
+                                }
+                                _ => Command::none(),
+                            };
+                        let listing = self
+                            .tabs
+                            .iter()
+                            .flat_map(|tab| tab.search_results.iter())
+                            .find(|(listing, _)| listing.id == id)
+                            .map(|(listing, _)| listing)
+                            .or_else(|| self.downloads.watched_downloads.get(&id));
+                        let entry = LibraryEntry {
+                            tags: listing
+                                .map(|l| l.tags.iter().map(|tag| tag.name.clone()).collect())
+                                .unwrap_or_default(),
+                            resolution: listing.map(|l| l.resolution.clone()),
+                            listing: listing.cloned(),
+                            protected: self.protected.contains(&id),
+                            id,
+                            path,
+                            hash: None,
+                            image_hash: None,
+                        };
+                        // unsplash's guidelines ask for every download of a photo to be reported
+                        let track_command = match (
+                            unsplash::photo_id(&entry.id),
+                            &self.settings.unsplash_access_key,
+                        ) {
+                            (Some(photo_id), Some(access_key)) => {
+                                let client = self.client.http().clone();
+                                let (photo_id, access_key) =
+                                    (photo_id.to_string(), access_key.clone());
+                                Command::perform(
+                                    async move {
+                                        unsplash::track_download(&client, &access_key, &photo_id)
+                                            .await
+                                            .map_err(|e| e.to_string())
+                                    },
+                                    WallpaperMessage::UnsplashDownloadTracked,
+                                )
+                            }
+                            _ => Command::none(),
+                        };
+                        self.downloads.watched_downloads.remove(&entry.id);
+                        return Break(Command::batch(vec![
+                            Command::perform(
+                                WallpaperUi::record_download(self.library.clone(), entry),
+                                WallpaperMessage::LibraryUpdated,
+                            ),
+                            set_wallpaper_command,
+                            track_command,
+                        ]));
+                    }
+                }
+            },
+            WallpaperMessage::PauseDownload(id) => self.download_manager.pause_download(&id),
+            WallpaperMessage::ResumeDownload(id) => self.download_manager.resume_download(&id),
+            WallpaperMessage::RetryDownload(id) => {
+                self.download_manager.retry_download(&id);
+                for l in self.results_with_id_mut(&id) {
+                    l.state = ImageState::Queued
+                }
+                self.update_download_banner();
+            }
+            WallpaperMessage::CancelDownload(id) => {
+                // downloads that already started report back once they've cleaned up
+                if self.download_manager.cancel_download(&id) {
+                    for l in self.results_with_id_mut(&id) {
+                        l.state = ImageState::Unselected
+                    }
+                }
+                self.update_download_banner();
+            }
+            WallpaperMessage::SpeedLimitChanged(value) => {
+                // an empty box means no limit, anything else has to be a number
+                let limit = match value.trim() {
+                    "" => None,
+                    trimmed => match trimmed.parse::<u32>() {
+                        Ok(limit) => Some(limit),
+                        Err(_) => return Break(Command::none()),
+                    },
+                };
+                self.downloads.speed_limit_value = value;
+                self.settings.download_speed_limit = limit;
+                self.download_manager.set_speed_limit(limit);
+            }
+            WallpaperMessage::ChangeConcurrentDownloads(c) => {
+                let value = self
+                    .download_manager
+                    .set_concurrent_downloads(c.max(0) as usize);
+                self.downloads.concurrent_download_control.value = value as i32;
+            }
+            WallpaperMessage::DownloadNow(id) => {
+                let listing = self
+                    .tab()
+                    .search_results
+                    .iter()
+                    .find(|(listing, _)| listing.id == id)
+                    .map(|(listing, _)| listing.clone());
+                if let Some(listing) = listing {
+                    self.selection.cart.remove(&id);
+                    self.queue_download(&listing.id, &listing.path, &listing.created_at);
+                }
+            }
+            WallpaperMessage::ThrottleTick() => {}
+            WallpaperMessage::SetAsWallpaper(id) => {
+                let path = match self.downloaded_file(&id) {
+                    Ok(Some(path)) => path,
+                    Ok(None) => {
+                        self.wallpaper_failed(format!("{} hasn't been downloaded", id));
+                        return Break(Command::none());
+                    }
+                    Err(e) => {
+                        self.wallpaper_failed(e.to_string());
+                        return Break(Command::none());
+                    }
+                };
+                self.current_wallpaper = Some(id);
+                return Break(Command::perform(
+                    set_wallpaper(path, self.settings.wallpaper_command.clone()),
+                    WallpaperMessage::WallpaperSet,
+                ));
+            }
+            WallpaperMessage::SetWhenDownloaded(id) => {
+                let listing = self
+                    .tab()
+                    .search_results
+                    .iter()
+                    .find(|(listing, _)| listing.id == id)
+                    .map(|(listing, image)| (listing.clone(), image.state));
+                let (listing, state) = match listing {
+                    Some(listing) => listing,
+                    None => return Break(Command::none()),
+                };
+                if state == ImageState::Downloaded {
+                    return Break(self.handle_message(WallpaperMessage::SetAsWallpaper(id)));
+                }
+                if !matches!(state, ImageState::Queued | ImageState::Downloading(_)) {
+                    self.selection.cart.remove(&id);
+                    self.queue_download(&id, &listing.path, &listing.created_at);
+                }
+                self.downloads.set_when_downloaded = Some(id);
+            }
+            WallpaperMessage::WallpaperSet(result) => match result {
+                Ok(()) => self.banners.dismiss(BannerSource::Wallpaper),
+                Err(e) => {
+                    error!("Failed to set the wallpaper {}", e);
+                    self.wallpaper_failed(e);
+                }
+            },
+            WallpaperMessage::AssignToMonitor(monitor, id) => {
+                let (listing, image) = match self
+                    .tab()
+                    .search_results
+                    .iter()
+                    .find(|(listing, _)| listing.id == id)
+                {
+                    Some(result) => result.clone(),
+                    None => return Break(Command::none()),
+                };
+                if matches!(
+                    image.state,
+                    ImageState::Unselected | ImageState::Selected | ImageState::Failed
+                ) {
+                    self.selection.cart.remove(&id);
+                    self.queue_download(&listing.id, &listing.path, &listing.created_at);
+                }
+                self.monitor_assignments
+                    .insert(monitor, (id, image.image_handle));
+            }
+            WallpaperMessage::ApplyMonitorWallpapers() => {
+                let mut wallpapers = vec![];
+                for monitor in &self.monitors {
+                    let id = match self.monitor_assignments.get(&monitor.name) {
+                        Some((id, _)) => id,
+                        None => continue,
+                    };
+                    match self.downloaded_file(id) {
+                        Ok(Some(path)) => wallpapers.push((monitor.clone(), path)),
+                        Ok(None) => {
+                            let e = format!("{} hasn't finished downloading", id);
+                            self.wallpaper_failed(e);
+                            return Break(Command::none());
+                        }
+                        Err(e) => {
+                            self.wallpaper_failed(e.to_string());
+                            return Break(Command::none());
+                        }
+                    }
+                }
+                if wallpapers.is_empty() {
+                    return Break(Command::none());
+                }
+                return Break(Command::perform(
+                    set_monitor_wallpapers(
+                        self.monitors.clone(),
+                        wallpapers,
+                        self.settings.wallpaper_command.clone(),
+                    ),
+                    WallpaperMessage::WallpaperSet,
+                ));
+            }
+            WallpaperMessage::RotateWallpaper() => {
+                let entries = match self.library.entries() {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        error!("Failed to read the library {:?}", e);
+                        return Break(Command::none());
+                    }
+                };
+                let entry = match next_rotation(entries, self.current_wallpaper.as_deref()) {
+                    Some(entry) => entry,
+                    None => {
+                        info!("No other downloaded wallpaper to rotate to");
+                        return Break(Command::none());
+                    }
+                };
+                self.current_wallpaper = Some(entry.id);
+                return Break(Command::perform(
+                    set_wallpaper(entry.path, self.settings.wallpaper_command.clone()),
+                    WallpaperMessage::WallpaperSet,
+                ));
+            }
+            WallpaperMessage::CheckDailyWallpaper() => {
+                if self.settings.daily_wallpaper && self.daily.is_due(unix_now()) {
+                    return Break(self.handle_message(WallpaperMessage::PickDailyWallpaper()));
+                }
+            }
+            WallpaperMessage::PickDailyWallpaper() => {
+                if self.picking_daily {
+                    return Break(Command::none());
+                }
+                self.picking_daily = true;
+                let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+                return Break(Command::perform(
+                    pick_daily_wallpaper(
+                        self.client.clone(),
+                        self.daily.clone(),
+                        api_key,
+                        self.save_directory(),
+                        self.library.clone(),
+                        self.settings.wallpaper_command.clone(),
+                    ),
+                    |result| WallpaperMessage::DailyWallpaperPicked(result.map(Box::new)),
+                ));
+            }
+            WallpaperMessage::DailyWallpaperPicked(result) => {
+                self.picking_daily = false;
+                match result {
+                    Ok(pick) => {
+                        self.current_wallpaper = Some(pick.listing.id.clone());
+                        self.daily.picks.insert(0, *pick);
+                        self.daily_error = None;
+                        return Break(Command::perform(
+                            DailyWallpaper::save_daily(self.daily.clone()),
+                            |result| {
+                                WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string()))
+                            },
+                        ));
+                    }
+                    Err(e) => {
+                        error!("Failed to pick the wallpaper of the day {}", e);
+                        self.daily_error = Some(e);
+                    }
+                }
+            }
+            WallpaperMessage::SkipDailyWallpaper() => {
+                if self.picking_daily || self.daily.skip().is_none() {
+                    return Break(Command::none());
+                }
+                return Break(Command::batch(vec![
+                    Command::perform(DailyWallpaper::save_daily(self.daily.clone()), |result| {
+                        WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string()))
+                    }),
+                    self.handle_message(WallpaperMessage::PickDailyWallpaper()),
+                ]));
+            }
+            WallpaperMessage::ScanDuplicates() => {
+                self.duplicate_status = "Scanning for duplicates...".to_string();
+                return Break(Command::perform(
+                    scan_duplicates(self.library.clone(), self.save_directory()),
+                    WallpaperMessage::DuplicatesScanned,
+                ));
+            }
+            WallpaperMessage::DuplicatesScanned(groups) => {
+                self.duplicate_status = match groups.len() {
+                    0 => "No duplicates found".to_string(),
+                    count => format!("Found {} wallpapers with more than one copy", count),
+                };
+                self.duplicates = groups;
+                self.deleting_duplicates = None;
+            }
+            WallpaperMessage::DeleteDuplicate(group, path) => {
+                self.deleting_duplicates = Some((group, vec![path]));
+            }
+            WallpaperMessage::KeepBestDuplicate(group) => {
+                let paths = match self.duplicates.get(group) {
+                    Some(duplicates) => duplicates
+                        .files
+                        .iter()
+                        .skip(1)
+                        .map(|file| file.path.clone())
+                        .collect(),
+                    None => return Break(Command::none()),
+                };
+                self.deleting_duplicates = Some((group, paths));
+            }
+            WallpaperMessage::ConfirmDeleteDuplicates() => {
+                if let Some((group, paths)) = self.deleting_duplicates.take() {
+                    return Break(self.delete_duplicates(group, paths));
+                }
+            }
+            WallpaperMessage::CancelDeleteDuplicates() => self.deleting_duplicates = None,
+            WallpaperMessage::IgnoreDuplicates(group) => {
+                if group < self.duplicates.len() {
+                    self.duplicates.remove(group);
+                }
+                self.deleting_duplicates = None;
+            }
+            WallpaperMessage::DuplicateDeleted(result) => {
+                if let Err(e) = result {
+                    error!("{}", e);
+                    self.duplicate_status = e;
+                }
+            }
+            WallpaperMessage::LibraryUpdated(result) => match result {
+                Ok(()) => return Break(self.check_quota()),
+                Err(e) => error!("Failed to add download to the library {}", e),
+            },
+            WallpaperMessage::UnsplashDownloadTracked(result) => {
+                if let Err(e) = result {
+                    error!("Failed to tell unsplash about a download {}", e);
+                }
+            }
+            WallpaperMessage::RevealFile(path) => {
+                return Break(Command::perform(
+                    reveal_in_file_manager(path),
+                    WallpaperMessage::FileRevealed,
+                ));
+            }
+            WallpaperMessage::RevealDownloaded(id) => match self.downloaded_file(&id) {
+                Ok(Some(path)) => {
+                    return Break(self.handle_message(WallpaperMessage::RevealFile(path)))
+                }
+                Ok(None) => error!("{} hasn't been downloaded", id),
+                Err(e) => error!("Failed to look up {} in the library {}", id, e),
+            },
+            WallpaperMessage::CopyDownloadedImage(id) => match self.downloaded_file(&id) {
+                Ok(Some(path)) => {
+                    let handle = image::Handle::from_path(path);
+                    return Break(Command::perform(copy_image(handle), |result| {
+                        WallpaperMessage::ImageCopied(result.map_err(|e| e.to_string()))
+                    }));
+                }
+                Ok(None) => error!("{} hasn't been downloaded", id),
+                Err(e) => error!("Failed to look up {} in the library {}", id, e),
+            },
+            WallpaperMessage::FileRevealed(result) => {
+                if let Err(e) = result {
+                    error!("Failed to open the file manager {}", e);
+                }
+            }
+            WallpaperMessage::ImageCopied(result) => match result {
+                Ok(()) => info!("Copied image to the clipboard"),
+                Err(e) => error!("Failed to copy image to the clipboard {}", e),
+            },
+            message => return Continue(message),
+        }
+        Break(Command::none())
+    }
+}
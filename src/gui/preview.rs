@@ -0,0 +1,329 @@
+use crate::clipboard::copy_image;
+use crate::gui::{
+    ContextMenu, PreviewMode, PreviewZoom, Reduced, SearchTab, SelectionUpdateType,
+    UploaderProfile, WallpaperMessage, WallpaperUi,
+};
+use crate::preview_download::{fetch_large_thumbnail, PreviewDownload, PreviewStatus};
+use iced::widget::image;
+use iced::Command;
+use log::{debug, error, info};
+use std::ops::ControlFlow::{Break, Continue};
+use wallapi::types::Sorting;
+use wallcore::tags::TagCache;
+
+/// The full size preview and how it's shown
+#[derive(Debug, Default)]
+pub(super) struct PreviewState {
+    pub(super) mode: PreviewMode,
+    /// Index into the active tab's results of the wallpaper being previewed
+    pub(super) index: Option<usize>,
+    /// Incremented for every preview download, so results of abandoned downloads are ignored
+    pub(super) request: u64,
+    /// Keeps converting the full resolution of the previewed wallpaper while a scaled down one is
+    /// shown
+    pub(super) scaled: Option<PreviewDownload>,
+    pub(super) zoom: PreviewZoom,
+    /// Shows the preview cropped the way it would be on the user's monitor
+    pub(super) show_monitor_fit: bool,
+}
+
+impl WallpaperUi {
+    /// The full size preview of a wallpaper, the context menu of results and sharing them
+    pub(super) fn reduce_preview(&mut self, message: WallpaperMessage) -> Reduced {
+        match message {
+            WallpaperMessage::UpdatePreviewMode(preview) => {
+                self.preview.mode = preview;
+            }
+            WallpaperMessage::DownloadPreview(index) => {
+                let preview_request = self.preview.request + 1;
+                let local = self.tab().is_on_disk();
+                let saving_data = self.saving_data();
+                let wallhaven = self.tab().is_wallhaven();
+                let api_key = self.tab().search_options.api_key.clone();
+                let lock = self.purity_lock();
+                // links and `wall-a-bunga open` preview what they find, whatever its purity
+                if let Some((value, image_view)) = self
+                    .tab()
+                    .search_results
+                    .get(index)
+                    .filter(|(value, _)| lock.shows(value.purity))
+                {
+                    // search results leave out the uploader, so the details are fetched alongside
+                    let details = match wallhaven && value.uploader.is_none() {
+                        true => Some(value.id.clone()),
+                        false => None,
+                    };
+                    // the small thumbnail is shown until the large one arrives, data saver keeps
+                    // showing it
+                    let large_thumbnail = match local || saving_data {
+                        true => Command::none(),
+                        false => Command::perform(
+                            fetch_large_thumbnail(
+                                self.client.http().clone(),
+                                value.thumbs.large.clone(),
+                            ),
+                            move |handle| {
+                                WallpaperMessage::PreviewThumbnailReceived(preview_request, handle)
+                            },
+                        ),
+                    };
+                    self.preview.mode = match local {
+                        // library results already have the full size file
+                        true => PreviewMode::PreviewView(match &image_view.file {
+                            Some(file) => image::Handle::from_path(file),
+                            None => image_view.image_handle.clone(),
+                        }),
+                        false => {
+                            let preview_handle = image_view.image_handle.clone();
+                            let download = PreviewDownload {
+                                client: self.client.http().clone(),
+                                url: value.path.clone(),
+                                request: preview_request,
+                                fit: (self.window_geometry.width, self.window_geometry.height),
+                            };
+                            match saving_data {
+                                true => PreviewMode::PreviewThumbnail {
+                                    preview_handle,
+                                    download,
+                                },
+                                // the subscription picks this up and starts downloading
+                                false => PreviewMode::PreviewRequestDownloading {
+                                    preview_handle,
+                                    download,
+                                    downloaded: 0,
+                                    total: None,
+                                },
+                            }
+                        }
+                    };
+                    self.preview.index = Some(index);
+                    self.preview.request = preview_request;
+                    self.preview.zoom = PreviewZoom::Fit;
+                    if let Some(id) = details {
+                        return Break(Command::batch(vec![
+                            large_thumbnail,
+                            Command::perform(
+                                WallpaperUi::wallpaper_details(
+                                    self.client.clone(),
+                                    id.clone(),
+                                    api_key,
+                                ),
+                                move |details| {
+                                    WallpaperMessage::WallpaperDetailsReceived(id, details)
+                                },
+                            ),
+                        ]));
+                    }
+                    return Break(large_thumbnail);
+                }
+            }
+            WallpaperMessage::LoadFullPreview() => {
+                if let PreviewMode::PreviewThumbnail {
+                    preview_handle,
+                    download,
+                } = &self.preview.mode
+                {
+                    self.preview.mode = PreviewMode::PreviewRequestDownloading {
+                        preview_handle: preview_handle.clone(),
+                        download: download.clone(),
+                        downloaded: 0,
+                        total: None,
+                    };
+                }
+            }
+            WallpaperMessage::WallpaperDetailsReceived(id, details) => {
+                let details = match details {
+                    Some(details) => details,
+                    None => return Break(Command::none()),
+                };
+                for (listing, _) in self
+                    .tabs
+                    .iter_mut()
+                    .flat_map(|tab| tab.search_results.iter_mut())
+                    .filter(|(listing, _)| listing.id == id)
+                {
+                    listing.uploader = details.uploader.clone();
+                    listing.tags = details.tags.clone();
+                }
+                if !details.tags.is_empty() {
+                    self.search.tags.record(&details.tags);
+                    return Break(Command::perform(
+                        TagCache::save_tags(self.search.tags.clone()),
+                        |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                    ));
+                }
+            }
+            WallpaperMessage::OpenUploader(uploader) => {
+                let api_key = self.tab().search_options.api_key.clone();
+                let mut tab = SearchTab::new(
+                    self.next_tab_id,
+                    api_key,
+                    self.default_purity(),
+                    self.settings.default_categories.clone(),
+                );
+                tab.search_value = format!("@{}", uploader.username);
+                tab.search_options.query = Some(tab.search_value.clone());
+                tab.search_options.sorting = Some(Sorting::DateAdded);
+                let tab_id = tab.id;
+                let avatar = uploader.avatar.medium.clone();
+                tab.uploader = Some(UploaderProfile {
+                    uploader,
+                    avatar: None,
+                });
+                self.tabs.push(tab);
+                self.next_tab_id += 1;
+                self.active_tab = self.tabs.len() - 1;
+                self.preview.mode = PreviewMode::Disable;
+                return Break(Command::batch(vec![
+                    self.search_active_tab(),
+                    Command::perform(
+                        WallpaperUi::fetch_avatar(self.client.clone(), avatar),
+                        move |avatar| WallpaperMessage::UploaderAvatarReceived(tab_id, avatar),
+                    ),
+                ]));
+            }
+            WallpaperMessage::UploaderAvatarReceived(tab_id, avatar) => {
+                if let Some(profile) = self
+                    .tabs
+                    .iter_mut()
+                    .find(|tab| tab.id == tab_id)
+                    .and_then(|tab| tab.uploader.as_mut())
+                {
+                    profile.avatar = avatar;
+                }
+            }
+            WallpaperMessage::OpenContextMenu(id) => {
+                self.selection.context_menu = match &self.selection.context_menu {
+                    Some(menu) if menu.id == id => None,
+                    _ => Some(ContextMenu { id, details: false }),
+                };
+            }
+            WallpaperMessage::CloseContextMenu() => self.selection.context_menu = None,
+            WallpaperMessage::ContextMenuAction(message) => {
+                self.selection.context_menu = None;
+                return Break(self.handle_message(*message));
+            }
+            WallpaperMessage::ShowDetails(id) => {
+                self.selection.context_menu = Some(ContextMenu {
+                    id: id.clone(),
+                    details: true,
+                });
+                let tab = self.tab();
+                let missing = tab.is_wallhaven()
+                    && tab
+                        .search_results
+                        .iter()
+                        .any(|(listing, _)| listing.id == id && listing.uploader.is_none());
+                if missing {
+                    let api_key = tab.search_options.api_key.clone();
+                    return Break(Command::perform(
+                        WallpaperUi::wallpaper_details(self.client.clone(), id.clone(), api_key),
+                        move |details| WallpaperMessage::WallpaperDetailsReceived(id, details),
+                    ));
+                }
+            }
+            WallpaperMessage::PreviewUpdated(status) => match (status, &mut self.preview.mode) {
+                (
+                    PreviewStatus::Progress {
+                        request,
+                        downloaded,
+                        total,
+                    },
+                    PreviewMode::PreviewRequestDownloading {
+                        downloaded: current_downloaded,
+                        total: current_total,
+                        ..
+                    },
+                ) if request == self.preview.request => {
+                    *current_downloaded = downloaded;
+                    *current_total = total;
+                }
+                (PreviewStatus::Scaled(request, handle), _) if request == self.preview.request => {
+                    let mode =
+                        std::mem::replace(&mut self.preview.mode, PreviewMode::PreviewView(handle));
+                    if let PreviewMode::PreviewRequestDownloading { download, .. } = mode {
+                        self.preview.scaled = Some(download);
+                    }
+                }
+                (PreviewStatus::Finished(request, handle), _)
+                    if request == self.preview.request =>
+                {
+                    self.preview.mode = PreviewMode::PreviewView(handle);
+                    self.preview.scaled = None;
+                }
+                (PreviewStatus::Failed(request), _) if request == self.preview.request => {
+                    self.preview.mode = PreviewMode::PreviewFailed;
+                }
+                (status, _) => debug!("Ignoring outdated preview status {:?}", status),
+            },
+            WallpaperMessage::PreviewThumbnailReceived(request, Some(handle)) => {
+                if let PreviewMode::PreviewRequestDownloading { preview_handle, .. } =
+                    &mut self.preview.mode
+                {
+                    if request == self.preview.request {
+                        *preview_handle = handle;
+                    }
+                }
+            }
+            WallpaperMessage::PreviewThumbnailReceived(_, None) => {}
+            WallpaperMessage::SetPreviewZoom(zoom) => self.preview.zoom = zoom,
+            WallpaperMessage::ToggleMonitorFit() => {
+                self.preview.show_monitor_fit = !self.preview.show_monitor_fit
+            }
+            WallpaperMessage::ZoomIn() => self.preview.zoom = self.preview.zoom.zoom_in(),
+            WallpaperMessage::ZoomOut() => self.preview.zoom = self.preview.zoom.zoom_out(),
+            WallpaperMessage::PreviousPreview() => return Break(self.navigate_preview(false)),
+            WallpaperMessage::NextPreview() => return Break(self.navigate_preview(true)),
+            WallpaperMessage::SelectPreviewed() => {
+                if let Some((listing, _)) = self.previewed() {
+                    let id = listing.id.clone();
+                    return Break(self.handle_message(WallpaperMessage::SelectionUpdate(
+                        SelectionUpdateType::Single(id),
+                    )));
+                }
+            }
+            WallpaperMessage::DownloadPreviewed() => {
+                if let Some((listing, _)) = self.previewed() {
+                    let (id, path, created_at) = (
+                        listing.id.clone(),
+                        listing.path.clone(),
+                        listing.created_at.clone(),
+                    );
+                    self.selection.cart.remove(&id);
+                    self.queue_download(&id, &path, &created_at);
+                }
+            }
+            WallpaperMessage::CopyPreviewImage() => {
+                if let PreviewMode::PreviewView(handle) = &self.preview.mode {
+                    return Break(Command::perform(copy_image(handle.clone()), |result| {
+                        WallpaperMessage::ImageCopied(result.map_err(|e| e.to_string()))
+                    }));
+                }
+            }
+            WallpaperMessage::CancelPreview() => {
+                // dropping the download subscription cancels it
+                info!("Preview closed");
+                self.preview.mode = PreviewMode::Disable;
+            }
+            WallpaperMessage::PreviewFocused() => {
+                if let (PreviewMode::Disable, Some((index, _))) =
+                    (&self.preview.mode, self.focused_result())
+                {
+                    return Break(self.handle_message(WallpaperMessage::DownloadPreview(index)));
+                }
+            }
+            WallpaperMessage::CopyToClipboard(text) => {
+                info!("Copying {} to the clipboard", text);
+                return Break(iced::clipboard::write(text));
+            }
+            WallpaperMessage::OpenInBrowser(url) => {
+                info!("Opening {} in the browser", url);
+                if let Err(e) = open::that_detached(&url) {
+                    error!("Failed to open {}: {:?}", url, e);
+                }
+            }
+            message => return Continue(message),
+        }
+        Break(Command::none())
+    }
+}
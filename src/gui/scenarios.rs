@@ -0,0 +1,485 @@
+//! Plays sequences of messages through [WallpaperUi] the way clicking around would, and compares
+//! what the results, the cart and the downloads end up as. The commands the messages return are
+//! dropped, so nothing reaches wallhaven or the disk.
+
+use crate::duplicates::{DuplicateFile, DuplicateGroup};
+use crate::gui::{
+    ImageState, ImageView, PreviewMode, PurityOptions, SearchId, SelectionUpdateType, Submenu,
+    TabId, WallpaperMessage, WallpaperUi,
+};
+use crate::monitors::Monitor;
+use crate::onboarding::OnboardingStep;
+use crate::purity_lock::new_pin;
+use iced::widget::image;
+use std::path::PathBuf;
+use wallapi::types::{
+    GenericResponse, ListingData, Purity, PurityLevel, SearchMetaData, Tag, XYCombo,
+};
+use wallcore::download_manager::DownloadStatus;
+use wallcore::result_filter::ResultFilter;
+use wallcore::settings::DataSaver;
+
+/// What a scenario checks, the parts of the state that show up in the window
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    /// Results of the active tab in the order they're shown
+    results: Vec<(&'static str, ImageState)>,
+    cart: Vec<&'static str>,
+    /// Last page of the active tab that arrived
+    page: Option<i64>,
+    /// Ids in the download queue, failed downloads included
+    downloads: Vec<&'static str>,
+}
+
+/// Ids in scenarios are short literals, snapshots hand them back as the same literals so they
+/// can be compared with `assert_eq!`
+const IDS: [&str; 4] = ["a", "b", "c", "d"];
+
+fn id(id: &str) -> &'static str {
+    IDS.into_iter()
+        .find(|known| *known == id)
+        .expect("scenarios only use the ids in IDS")
+}
+
+/// A window with a single empty search tab, downloads are saved where nothing exists yet
+fn new_ui() -> WallpaperUi {
+    let mut ui = WallpaperUi::default();
+    ui.tabs.push(Default::default());
+    ui.settings.save_directory = Some(
+        std::env::temp_dir()
+            .join("wall-a-bunga-scenarios")
+            .to_string_lossy()
+            .to_string(),
+    );
+    ui
+}
+
+/// A page of search results for the first tab
+fn page(tab_id: TabId, current_page: i64, last_page: i64, ids: &[&str]) -> WallpaperMessage {
+    let results = ids
+        .iter()
+        .map(|id| {
+            let listing = ListingData {
+                id: id.to_string(),
+                path: format!("https://w.wallhaven.cc/full/{0}/wallhaven-{0}.jpg", id),
+                ..Default::default()
+            };
+            let image = ImageView {
+                state: ImageState::Unselected,
+                image_handle: image::Handle::from_pixels(1, 1, vec![0; 4]),
+                file: None,
+            };
+            (listing, image)
+        })
+        .collect();
+    WallpaperMessage::SearchReceived(
+        SearchId {
+            tab: tab_id,
+            generation: 0,
+        },
+        GenericResponse {
+            data: Some(results),
+            error: None,
+            meta: Some(SearchMetaData {
+                current_page,
+                last_page,
+                ..Default::default()
+            }),
+        },
+    )
+}
+
+fn select(id: &str) -> WallpaperMessage {
+    WallpaperMessage::SelectionUpdate(SelectionUpdateType::Single(id.to_string()))
+}
+
+/// Handles every message in order and takes a snapshot of where that left the window
+fn play(ui: &mut WallpaperUi, messages: impl IntoIterator<Item = WallpaperMessage>) -> Snapshot {
+    for message in messages {
+        let _ = ui.handle_message(message);
+    }
+    Snapshot {
+        results: ui
+            .tab()
+            .search_results
+            .iter()
+            .map(|(listing, image)| (id(&listing.id), image.state))
+            .collect(),
+        cart: ui.selection.cart.items().map(|item| id(&item.id)).collect(),
+        page: ui.tab().search_meta.as_ref().map(|meta| meta.current_page),
+        downloads: ui
+            .download_manager
+            .downloads()
+            .map(|(download, _)| id(download))
+            .collect(),
+    }
+}
+
+#[test]
+fn selection_survives_paging() {
+    let mut ui = new_ui();
+    let snapshot = play(
+        &mut ui,
+        [
+            page(0, 1, 3, &["a", "b"]),
+            select("a"),
+            WallpaperMessage::NextPage(),
+            // random orders can repeat a wallpaper on a later page
+            page(0, 2, 3, &["c", "a"]),
+        ],
+    );
+    assert_eq!(
+        snapshot,
+        Snapshot {
+            results: vec![
+                ("a", ImageState::Selected),
+                ("b", ImageState::Unselected),
+                ("c", ImageState::Unselected),
+                ("a", ImageState::Selected),
+            ],
+            cart: vec!["a"],
+            page: Some(2),
+            downloads: vec![],
+        }
+    );
+    // deselecting one of them deselects both
+    let snapshot = play(&mut ui, [select("a")]);
+    assert_eq!(snapshot.results[0], ("a", ImageState::Unselected));
+    assert_eq!(snapshot.results[3], ("a", ImageState::Unselected));
+    assert!(snapshot.cart.is_empty());
+}
+
+#[test]
+fn downloads_move_results_along() {
+    let mut ui = new_ui();
+    let snapshot = play(
+        &mut ui,
+        [
+            page(0, 1, 1, &["a", "b", "c"]),
+            select("a"),
+            select("b"),
+            WallpaperMessage::DownloadImages(),
+        ],
+    );
+    assert_eq!(
+        snapshot,
+        Snapshot {
+            results: vec![
+                ("a", ImageState::Queued),
+                ("b", ImageState::Queued),
+                ("c", ImageState::Unselected),
+            ],
+            cart: vec![],
+            page: Some(1),
+            // queued last to first
+            downloads: vec!["b", "a"],
+        }
+    );
+    let snapshot = play(
+        &mut ui,
+        [
+            WallpaperMessage::DownloadUpdated(DownloadStatus::Progress {
+                id: "a".to_string(),
+                percentage: Some(50.0),
+                downloaded: 500,
+                total: Some(1000),
+            }),
+            WallpaperMessage::DownloadUpdated(DownloadStatus::Failed {
+                id: "b".to_string(),
+                reason: "Not found".to_string(),
+            }),
+        ],
+    );
+    assert_eq!(
+        snapshot.results[..2],
+        [
+            ("a", ImageState::Downloading(Some(50.0))),
+            ("b", ImageState::Failed),
+        ]
+    );
+    assert_eq!(snapshot.downloads, ["b", "a"]);
+    // downloading again retries what failed
+    let snapshot = play(
+        &mut ui,
+        [
+            WallpaperMessage::DownloadUpdated(DownloadStatus::Finished("a".to_string())),
+            WallpaperMessage::DownloadImages(),
+        ],
+    );
+    assert_eq!(
+        snapshot.results[..2],
+        [("a", ImageState::Downloaded), ("b", ImageState::Queued)]
+    );
+    assert_eq!(snapshot.downloads, ["b"]);
+    assert_eq!(ui.download_manager.failure("b"), None);
+}
+
+#[test]
+fn purity_lock_asks_for_the_pin() {
+    let mut ui = new_ui();
+    ui.settings.purity_pin = Some(new_pin("1234").unwrap());
+    let mut results = page(0, 1, 1, &["a", "b"]);
+    // reddit marks its posts nsfw whatever the filters say
+    if let WallpaperMessage::SearchReceived(_, response) = &mut results {
+        response.data.as_mut().unwrap()[1].0.purity = PurityLevel::Nsfw;
+    }
+    let nsfw = || WallpaperMessage::TogglePurity(PurityOptions::Nsfw);
+    let snapshot = play(&mut ui, [results, nsfw()]);
+    assert_eq!(snapshot.results.len(), 2);
+    assert_eq!(ui.visible_results().count(), 1);
+    assert!(
+        !ui.tab()
+            .search_options
+            .purity
+            .clone()
+            .unwrap_or_default()
+            .nsfw
+    );
+    assert_eq!(ui.controls.submenu, Submenu::PurityLock);
+    // links preview what they find, but not past the lock
+    play(&mut ui, [WallpaperMessage::DownloadPreview(1)]);
+    assert!(matches!(ui.preview.mode, PreviewMode::Disable));
+    // neither do new tabs searching with the defaults
+    ui.settings.default_purity = Some(Purity {
+        clean: true,
+        sketchy: true,
+        nsfw: true,
+    });
+    play(&mut ui, [WallpaperMessage::NewTab()]);
+    assert_eq!(ui.tab().search_options.purity, Some(Purity::default()));
+    play(&mut ui, [WallpaperMessage::SelectTab(0)]);
+
+    play(
+        &mut ui,
+        [
+            WallpaperMessage::PinUpdated("4321".to_string()),
+            WallpaperMessage::UnlockPurity(),
+        ],
+    );
+    assert!(ui.settings_page.pin_error.is_some());
+    play(
+        &mut ui,
+        [
+            WallpaperMessage::PinUpdated("1234".to_string()),
+            WallpaperMessage::UnlockPurity(),
+            nsfw(),
+        ],
+    );
+    assert!(ui.tab().search_options.purity.clone().unwrap().nsfw);
+    assert_eq!(ui.visible_results().count(), 2);
+    assert_eq!(ui.controls.submenu, Submenu::None);
+}
+
+#[test]
+fn data_saver_holds_back_full_previews() {
+    let mut ui = new_ui();
+    play(
+        &mut ui,
+        [
+            page(0, 1, 1, &["a", "b"]),
+            WallpaperMessage::ToggleDataSaver(),
+            WallpaperMessage::DownloadPreview(0),
+        ],
+    );
+    assert!(matches!(
+        ui.preview.mode,
+        PreviewMode::PreviewThumbnail { .. }
+    ));
+    play(&mut ui, [WallpaperMessage::LoadFullPreview()]);
+    assert!(matches!(
+        ui.preview.mode,
+        PreviewMode::PreviewRequestDownloading { .. }
+    ));
+
+    play(
+        &mut ui,
+        [
+            WallpaperMessage::ToggleDataSaver(),
+            WallpaperMessage::DownloadPreview(1),
+        ],
+    );
+    assert!(matches!(
+        ui.preview.mode,
+        PreviewMode::PreviewRequestDownloading { .. }
+    ));
+
+    // turning it off for a while keeps it on for metered connections
+    ui.settings.data_saver = DataSaver::WhenMetered;
+    ui.metered = true;
+    play(&mut ui, [WallpaperMessage::ToggleDataSaver()]);
+    assert!(!ui.saving_data());
+    play(&mut ui, [WallpaperMessage::ToggleDataSaver()]);
+    assert_eq!(ui.settings.data_saver, DataSaver::WhenMetered);
+}
+
+#[test]
+fn undo_brings_back_the_selection() {
+    let mut ui = new_ui();
+    let selected = play(
+        &mut ui,
+        [
+            page(0, 1, 1, &["a", "b", "c"]),
+            WallpaperMessage::SelectionUpdate(SelectionUpdateType::SelectAll),
+        ],
+    );
+    assert_eq!(selected.cart, ["a", "b", "c"]);
+    let cleared = play(&mut ui, [WallpaperMessage::ClearCart()]);
+    assert!(cleared.cart.is_empty());
+    assert!(cleared
+        .results
+        .iter()
+        .all(|(_, state)| *state == ImageState::Unselected));
+    assert_eq!(play(&mut ui, [WallpaperMessage::Undo()]), selected);
+    assert_eq!(play(&mut ui, [WallpaperMessage::Redo()]), cleared);
+}
+
+#[test]
+fn deleting_duplicates_asks_first() {
+    let mut ui = new_ui();
+    let file = |name: &str| DuplicateFile {
+        path: PathBuf::from(name),
+        size: 1000,
+        dimensions: None,
+        thumbnail: None,
+    };
+    ui.duplicates = vec![DuplicateGroup {
+        files: vec![file("a.jpg"), file("b.jpg"), file("c.jpg")],
+        exact: true,
+    }];
+    play(
+        &mut ui,
+        [
+            WallpaperMessage::KeepBestDuplicate(0),
+            WallpaperMessage::CancelDeleteDuplicates(),
+        ],
+    );
+    assert_eq!(ui.duplicates[0].files.len(), 3);
+
+    play(&mut ui, [WallpaperMessage::KeepBestDuplicate(0)]);
+    assert_eq!(ui.duplicates[0].files.len(), 3);
+    play(&mut ui, [WallpaperMessage::ConfirmDeleteDuplicates()]);
+    assert!(ui.duplicates.is_empty());
+}
+
+#[test]
+fn onboarding_fills_in_the_detected_resolution() {
+    let mut ui = new_ui();
+    ui.onboarding = Some(OnboardingStep::Resolution);
+    let detected = || {
+        WallpaperMessage::MonitorsDetected(vec![
+            Monitor {
+                name: "DP-1".to_string(),
+                resolution: XYCombo { x: 2560, y: 1440 },
+            },
+            Monitor {
+                name: "HDMI-1".to_string(),
+                resolution: XYCombo { x: 1920, y: 1080 },
+            },
+        ])
+    };
+    play(&mut ui, [detected()]);
+    assert_eq!(
+        ui.settings.monitor_resolution,
+        Some(XYCombo { x: 2560, y: 1440 })
+    );
+    // what was picked instead isn't overwritten by detecting again
+    play(
+        &mut ui,
+        [
+            WallpaperMessage::SetMonitorResolution(XYCombo { x: 1920, y: 1080 }),
+            detected(),
+        ],
+    );
+    assert_eq!(
+        ui.settings.monitor_resolution,
+        Some(XYCombo { x: 1920, y: 1080 })
+    );
+}
+
+#[test]
+fn failed_search_without_a_connection_goes_offline() {
+    let mut ui = new_ui();
+    let snapshot = play(
+        &mut ui,
+        [
+            page(0, 1, 3, &["a"]),
+            WallpaperMessage::SearchOffline(SearchId {
+                tab: 0,
+                generation: 0,
+            }),
+        ],
+    );
+    assert!(ui.download_manager.is_offline());
+    // the page that couldn't be fetched is asked for again
+    assert_eq!(snapshot.page, Some(1));
+    assert_eq!(snapshot.results, [("a", ImageState::Unselected)]);
+}
+
+#[test]
+fn late_pages_of_a_replaced_search_are_dropped() {
+    let mut ui = new_ui();
+    let snapshot = play(
+        &mut ui,
+        [
+            page(0, 1, 3, &["a"]),
+            WallpaperMessage::NextPage(),
+            WallpaperMessage::Search(),
+            // page 2 of the search before arrives after the new one started
+            page(0, 2, 3, &["b"]),
+        ],
+    );
+    assert!(snapshot.results.is_empty());
+    assert_eq!(snapshot.page, None);
+}
+
+#[test]
+fn excluding_ai_art_loads_the_tags_it_needs() {
+    let mut ui = new_ui();
+    let ai_art = ListingData {
+        id: "a".to_string(),
+        tags: vec![Tag {
+            name: "AI Art".to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    play(
+        &mut ui,
+        [
+            page(0, 1, 3, &["a", "b"]),
+            WallpaperMessage::ResultFilterChanged(ResultFilter {
+                exclude_ai: true,
+                ..Default::default()
+            }),
+            // the details turning it on asked for
+            WallpaperMessage::WallpaperDetailsReceived("a".to_string(), Some(Box::new(ai_art))),
+        ],
+    );
+    let tab = ui.tab();
+    let shown: Vec<&str> = tab
+        .search_results
+        .iter()
+        .filter(|(listing, _)| tab.filter.matches(listing))
+        .map(|(listing, _)| id(&listing.id))
+        .collect();
+    assert_eq!(shown, ["b"]);
+    // the next pages come without it
+    assert_eq!(tab.search_options.hide_ai_art, Some(true));
+}
+
+#[test]
+fn going_to_a_page_before_any_arrived() {
+    let mut ui = new_ui();
+    ui.tab_mut().page_value = "4".to_string();
+    play(&mut ui, [WallpaperMessage::GoToPage()]);
+    assert_eq!(ui.tab().search_options.page, Some(4));
+}
+
+#[test]
+fn new_tabs_use_the_api_key_of_the_settings() {
+    let mut ui = new_ui();
+    ui.api_key = "secret".to_string();
+    // the first tab was opened before the key was set
+    play(&mut ui, [WallpaperMessage::NewTab()]);
+    assert_eq!(ui.tab().search_options.api_key.as_deref(), Some("secret"));
+}
@@ -0,0 +1,754 @@
+use crate::banner::{BannerSource, Severity};
+use crate::gui::{
+    near_end, results_scrollable_id, CollectionsState, ImageState, PreviewMode, Reduced, SearchTab,
+    Submenu, WallpaperMessage, WallpaperUi,
+};
+use crate::submenus::{parse_combo, ComboKind};
+use crate::watch::{check_watches, WatchList};
+use iced::widget::scrollable::{self, RelativeOffset};
+use iced::Command;
+use log::{debug, info};
+use std::collections::HashSet;
+use std::ops::ControlFlow::{Break, Continue};
+use std::time::Instant;
+use wallapi::query::QueryBuilder;
+use wallapi::types::{Categories, GenericResponse, Purity, SearchOptions, Sorting};
+use wallapi::url::WallhavenUrl;
+use wallcore::connectivity::CONNECTION_ERROR;
+use wallcore::history::SearchHistory;
+use wallcore::settings::SavedSettings;
+use wallcore::source::Source;
+use wallcore::tags::TagCache;
+use wallcore::utils::{is_valid_seed, random_seed};
+
+/// Past searches and the tags suggested while typing one
+#[derive(Debug, Default)]
+pub(super) struct SearchState {
+    pub(super) history: SearchHistory,
+    pub(super) show_history: bool,
+    /// Tags of previewed wallpapers, suggested while typing a search
+    pub(super) tags: TagCache,
+}
+
+impl WallpaperUi {
+    /// Searching, paging through the results, the tabs, the search history and watched searches
+    pub(super) fn reduce_search(&mut self, message: WallpaperMessage) -> Reduced {
+        match message {
+            WallpaperMessage::Search() => {
+                // pasted wallhaven links are opened or reproduced instead of searched for
+                // a seed from a pasted link brings back the same random order
+                let mut linked_seed = None;
+                let link = match self.tab().is_wallhaven() {
+                    true => self.tab().search_value.parse::<WallhavenUrl>().ok(),
+                    false => None,
+                };
+                match link {
+                    Some(WallhavenUrl::Wallpaper(id)) => {
+                        info!("Opening wallpaper {} from link", id);
+                        let library = self.library.clone();
+                        let client = self.client.clone();
+                        let tab = self.tab_mut();
+                        tab.clear_results();
+                        let search = tab.search_id();
+                        let api_key = tab.search_options.api_key.clone();
+                        self.preview.mode = PreviewMode::Disable;
+                        self.search.show_history = false;
+                        return Break(Command::perform(
+                            WallpaperUi::wallpaper_command(client, id, api_key, library),
+                            move |response| {
+                                WallpaperMessage::WallpaperLinkReceived(search, response)
+                            },
+                        ));
+                    }
+                    Some(WallhavenUrl::Search(options)) => {
+                        info!("Reproducing search from link {:?}", options);
+                        linked_seed = options.seed.clone();
+                        let tab = self.tab_mut();
+                        tab.search_value = options.query.clone().unwrap_or_default();
+                        tab.excluded_tags.clear();
+                        tab.search_options = SearchOptions {
+                            api_key: tab.search_options.api_key.clone(),
+                            ..options
+                        };
+                    }
+                    None => {}
+                }
+                let tab = self.tab_mut();
+                // searching from a collection tab turns it into a regular search
+                tab.collection = None;
+                if tab.uploader.as_ref().is_some_and(|profile| {
+                    tab.search_value.trim() != format!("@{}", profile.uploader.username)
+                }) {
+                    tab.uploader = None;
+                }
+                // pull any `-tag` terms out of the search box into chips
+                let mut query = QueryBuilder::parse(&tab.search_value);
+                for tag in &tab.excluded_tags {
+                    query.exclude_tag(tag);
+                }
+                tab.excluded_tags = query.excluded_tags().to_vec();
+                tab.search_value = query.terms().join(" ");
+                tab.search_options.set_query(query.build());
+                tab.search_options.page = None;
+                // links and profiles can bring back a purity the lock keeps away
+                let lock = self.purity_lock();
+                let tab = self.tab_mut();
+                tab.search_options.purity = lock.allowed(tab.search_options.purity.take());
+                let seed = linked_seed.unwrap_or_else(random_seed);
+                tab.seed_value = seed.clone();
+                tab.search_options.seed = Some(seed);
+                tab.clear_results();
+                self.preview.mode = PreviewMode::Disable;
+                self.search.show_history = false;
+                // only wallhaven searches can be searched again from the history
+                if !self.tab().is_wallhaven() {
+                    return Break(self.search_active_tab());
+                }
+                self.search
+                    .history
+                    .record(&self.tabs[self.active_tab].search_options);
+                let last_search = SavedSettings::remembered_search(&self.tab().search_options);
+                self.settings.last_search = Some(last_search.clone());
+                return Break(Command::batch(vec![
+                    self.search_active_tab(),
+                    Command::perform(
+                        SearchHistory::save_history(self.search.history.clone()),
+                        |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                    ),
+                    Command::perform(
+                        async move {
+                            SavedSettings::save_last_search(last_search)
+                                .await
+                                .map_err(|e| e.to_string())
+                        },
+                        WallpaperMessage::SettingsSaved,
+                    ),
+                ]));
+            }
+            WallpaperMessage::SearchUpdated(msg) => {
+                self.tab_mut().search_value = msg;
+            }
+            WallpaperMessage::PickTagSuggestion(id) => {
+                // wallhaven can't combine an exact tag with other terms, it replaces the query
+                self.tab_mut().search_value = format!("id:{}", id);
+                return Break(self.handle_message(WallpaperMessage::Search()));
+            }
+            WallpaperMessage::ExcludeUpdated(msg) => {
+                self.tab_mut().exclude_value = msg;
+            }
+            WallpaperMessage::AddExcludedTags() => {
+                let tab = self.tab_mut();
+                for tag in tab
+                    .exclude_value
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                {
+                    let tag = tag.trim_start_matches('-');
+                    if !tag.is_empty() && !tab.excluded_tags.iter().any(|t| t == tag) {
+                        tab.excluded_tags.push(tag.to_string());
+                    }
+                }
+                tab.exclude_value.clear();
+            }
+            WallpaperMessage::RemoveExcludedTag(index) => {
+                let tab = self.tab_mut();
+                if index < tab.excluded_tags.len() {
+                    tab.excluded_tags.remove(index);
+                }
+            }
+            WallpaperMessage::SearchReceived(search, mut values) => {
+                let tab_id = search.tab;
+                let tab = match self.tabs.iter_mut().find(|tab| tab.id == tab_id) {
+                    Some(tab) => tab,
+                    None => {
+                        debug!("Search received for closed tab {}", tab_id);
+                        return Break(Command::none());
+                    }
+                };
+                if tab.paginator.generation() != search.generation {
+                    debug!("Dropping a page of a replaced search in tab {}", tab_id);
+                    return Break(Command::none());
+                }
+                let failed = values.data.is_none();
+                if let Some(data) = &mut values.data {
+                    info!("Updated search results");
+                    data.retain(|(listing, _)| !self.settings.blacklist.hides(listing));
+                    // anything already in the cart shows up as selected
+                    for (listing, image) in data.iter_mut() {
+                        if image.state == ImageState::Unselected
+                            && self.selection.cart.contains(&listing.id)
+                        {
+                            image.state = ImageState::Selected;
+                        }
+                    }
+                    tab.search_results.append(data);
+                    self.banners.dismiss(BannerSource::Search(tab_id));
+                } else if let Some(error) = values.error {
+                    self.banners.show(
+                        BannerSource::Search(tab_id),
+                        Severity::Error,
+                        format!("Searching \"{}\" failed: {}", tab.title(), error),
+                    );
+                }
+                // a failed page leaves the pages before it as they were, so it's asked for again
+                if values.meta.is_some() || !failed {
+                    debug!("Updating search meta: {:?}", values.meta);
+                    tab.search_meta = values.meta;
+                }
+                tab.paginator.received();
+                if self
+                    .downloads
+                    .download_all
+                    .as_ref()
+                    .is_some_and(|download_all| download_all.tab_id == tab_id)
+                {
+                    return Break(self.continue_download_all());
+                }
+            }
+            WallpaperMessage::SearchOffline(search) => {
+                self.download_manager.set_offline(true);
+                let response = GenericResponse {
+                    error: Some(CONNECTION_ERROR.to_string()),
+                    ..Default::default()
+                };
+                return Break(
+                    self.handle_message(WallpaperMessage::SearchReceived(search, response)),
+                );
+            }
+            WallpaperMessage::WallpaperLinkReceived(search, response) => {
+                let found = response.data.as_ref().is_some_and(|data| !data.is_empty());
+                let command =
+                    self.handle_message(WallpaperMessage::SearchReceived(search, response));
+                if found && self.tab().search_id() == search {
+                    return Break(Command::batch(vec![
+                        command,
+                        self.handle_message(WallpaperMessage::DownloadPreview(0)),
+                    ]));
+                }
+                return Break(command);
+            }
+            WallpaperMessage::SetSource(source) => {
+                let tab = self.tab_mut();
+                if tab.source == source {
+                    return Break(Command::none());
+                }
+                info!("Searching {} instead", source);
+                tab.source = source;
+                tab.cursor = None;
+                return Break(self.handle_message(WallpaperMessage::Search()));
+            }
+            WallpaperMessage::ChooseSourceFolder() => {
+                return Break(Command::perform(
+                    WallpaperUi::choose_directory(),
+                    WallpaperMessage::SourceFolderChosen,
+                ));
+            }
+            WallpaperMessage::SourceFolderChosen(Some(folder)) => {
+                self.settings.add_source_folder(folder.clone());
+                return Break(Command::batch(vec![
+                    self.save_source_folders(),
+                    self.handle_message(WallpaperMessage::SetSource(Source::Folder(folder))),
+                ]));
+            }
+            WallpaperMessage::SourceFolderChosen(None) => {}
+            WallpaperMessage::RedditPageReceived(search, cursor, response) => {
+                // a page that failed is asked for again from where it would have started
+                if response.data.is_some() {
+                    if let Some(tab) = self.tabs.iter_mut().find(|tab| tab.search_id() == search) {
+                        tab.cursor = cursor;
+                    }
+                }
+                return Break(
+                    self.handle_message(WallpaperMessage::SearchReceived(search, response)),
+                );
+            }
+            WallpaperMessage::SortingTypeChanged(sort) => {
+                self.tab_mut().search_options.sorting = Some(sort);
+            }
+            WallpaperMessage::SeedUpdated(seed) => self.tab_mut().seed_value = seed,
+            WallpaperMessage::ApplySeed() => {
+                let tab = self.tab_mut();
+                let seed = tab.seed_value.trim().to_string();
+                if !is_valid_seed(&seed) {
+                    return Break(Command::none());
+                }
+                info!("Searching again with seed {}", seed);
+                tab.seed_value = seed.clone();
+                tab.search_options.seed = Some(seed);
+                tab.search_options.sorting = Some(Sorting::Random);
+                tab.search_options.page = None;
+                tab.clear_results();
+                self.preview.mode = PreviewMode::Disable;
+                return Break(self.search_active_tab());
+            }
+            WallpaperMessage::TogglePurity(purity_toggle) => {
+                if self.needs_unlock(purity_toggle, self.tab().search_options.purity.as_ref()) {
+                    self.controls.submenu = Submenu::PurityLock;
+                    return Break(Command::none());
+                }
+                let purity = self
+                    .tab_mut()
+                    .search_options
+                    .purity
+                    .get_or_insert(Purity::default());
+                purity_toggle.toggle(purity);
+            }
+            WallpaperMessage::ToggleContentType(content_toggle) => {
+                let content = self
+                    .tab_mut()
+                    .search_options
+                    .categories
+                    .get_or_insert(Categories::default());
+                content_toggle.toggle(content);
+            }
+            WallpaperMessage::ResolutionSelected(resolution) => {
+                // Clear out the minimum resolution option
+                let search_options = &mut self.tab_mut().search_options;
+                search_options.minimum_resolution = None;
+                debug!("Resolution selected {}", resolution);
+                let res_map = search_options.resolutions.get_or_insert(HashSet::new());
+                if res_map.contains(&resolution) {
+                    res_map.remove(&resolution);
+                    if res_map.is_empty() {
+                        search_options.resolutions = None;
+                    }
+                } else {
+                    res_map.insert(resolution);
+                }
+            }
+            WallpaperMessage::AspectRatioSelected(aspect_ratio) => {
+                info!("Selected aspect ratio {}", aspect_ratio);
+                let ratio_map = self
+                    .tab_mut()
+                    .search_options
+                    .ratios
+                    .get_or_insert(HashSet::new());
+                if ratio_map.contains(&aspect_ratio) {
+                    ratio_map.remove(&aspect_ratio);
+                } else {
+                    ratio_map.insert(aspect_ratio);
+                }
+            }
+            WallpaperMessage::RetryBanner(source) => {
+                self.banners.dismiss(source);
+                if let BannerSource::Search(tab_id) = source {
+                    if let Some(tab) = self.tabs.iter().find(|tab| tab.id == tab_id) {
+                        return Break(self.search_tab(tab));
+                    }
+                }
+            }
+            WallpaperMessage::CustomComboInput(kind, value) => {
+                let custom = self.custom_combos_mut(kind);
+                custom.input = value;
+                custom.invalid = false;
+            }
+            WallpaperMessage::AddCustomCombo(kind) => {
+                let custom = self.custom_combos_mut(kind);
+                let combo = match parse_combo(&custom.input) {
+                    Some(combo) => combo,
+                    None => {
+                        custom.invalid = true;
+                        return Break(Command::none());
+                    }
+                };
+                custom.input.clear();
+                let (entries, fixed) = match kind {
+                    ComboKind::Resolution => (
+                        &mut self.settings.custom_resolutions,
+                        &wallapi::types::RESOLUTION_POSSIBILITIES[..],
+                    ),
+                    ComboKind::AspectRatio => (
+                        &mut self.settings.custom_ratios,
+                        &wallapi::types::ASPECT_RATIOS[..],
+                    ),
+                };
+                if !fixed.contains(&combo) && !entries.contains(&combo) {
+                    entries.push(combo);
+                }
+                self.refresh_custom_combos();
+                let is_minimum_set = self.resolution_menu.is_minimum_set;
+                let search_options = &mut self.tab_mut().search_options;
+                match kind {
+                    ComboKind::Resolution if is_minimum_set => {
+                        search_options.resolutions = None;
+                        search_options.minimum_resolution = Some(combo);
+                    }
+                    ComboKind::Resolution => {
+                        search_options.minimum_resolution = None;
+                        search_options
+                            .resolutions
+                            .get_or_insert_with(HashSet::new)
+                            .insert(combo);
+                    }
+                    ComboKind::AspectRatio => {
+                        search_options
+                            .ratios
+                            .get_or_insert_with(HashSet::new)
+                            .insert(combo);
+                    }
+                }
+                return Break(self.save_custom_combos());
+            }
+            WallpaperMessage::RemoveCustomCombo(kind, combo) => {
+                match kind {
+                    ComboKind::Resolution => {
+                        self.settings.custom_resolutions.retain(|c| *c != combo)
+                    }
+                    ComboKind::AspectRatio => self.settings.custom_ratios.retain(|c| *c != combo),
+                }
+                self.refresh_custom_combos();
+                return Break(self.save_custom_combos());
+            }
+            WallpaperMessage::SearchMonitorResolution(resolution) => {
+                let search_options = &mut self.tab_mut().search_options;
+                search_options.minimum_resolution = None;
+                search_options.resolutions = Some(HashSet::from([resolution]));
+                return Break(self.handle_message(WallpaperMessage::Search()));
+            }
+            WallpaperMessage::CollectionsUsernameUpdated(username) => {
+                self.collections_username = username
+            }
+            WallpaperMessage::LoadCollections() => {
+                let username = self.collections_username.trim().to_string();
+                let username = (!username.is_empty()).then_some(username);
+                let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+                if username.is_none() && api_key.is_none() {
+                    self.collections = CollectionsState::Failed(
+                        "Enter a username, or an api key in the settings to list your own"
+                            .to_string(),
+                    );
+                    return Break(Command::none());
+                }
+                self.collections = CollectionsState::Loading;
+                return Break(Command::perform(
+                    WallpaperUi::collections_command(self.client.clone(), username, api_key),
+                    WallpaperMessage::CollectionsLoaded,
+                ));
+            }
+            WallpaperMessage::CollectionsLoaded(result) => {
+                self.collections = match result {
+                    Ok((username, collections)) => {
+                        info!("Listed {} collections", collections.len());
+                        CollectionsState::Loaded(username, collections)
+                    }
+                    Err(e) => CollectionsState::Failed(e),
+                };
+            }
+            WallpaperMessage::OpenCollection(index) => {
+                return Break(self.open_collection(index));
+            }
+            WallpaperMessage::ResolutionIsSingleTargetChanged(res_mode) => {
+                self.resolution_menu.is_minimum_set = res_mode;
+            }
+            WallpaperMessage::SetMinimumResolution(resolution) => {
+                // clear out other resolutions options in preference of min resolution
+                info!("Minimum resolution set to {}", resolution);
+                let search_options = &mut self.tab_mut().search_options;
+                search_options.resolutions = None;
+                search_options.minimum_resolution = Some(resolution);
+            }
+            WallpaperMessage::Scroll(scroll) => {
+                if let PreviewMode::Disable = &self.preview.mode {
+                    // currently we only want to respond to scroll events when the user can see the image list
+                    debug!("scroll {:?}", scroll);
+                    let prefetch = !self.saving_data();
+                    let tab = self.tab_mut();
+                    tab.scroll_offset = scroll.relative_offset();
+                    tab.scroll_top = scroll.absolute_offset().y;
+                    // start loading the next page before the user reaches the end
+                    if near_end(&scroll, prefetch) {
+                        let page = tab
+                            .paginator
+                            .next_page(tab.search_meta.as_ref(), Instant::now());
+                        if let Some(page) = page {
+                            tab.search_options.page = Some(page);
+                            return Break(self.search_active_tab());
+                        }
+                    }
+                }
+            }
+            WallpaperMessage::NextPage() => {
+                let tab = self.tab_mut();
+                match tab
+                    .paginator
+                    .next_page(tab.search_meta.as_ref(), Instant::now())
+                {
+                    Some(page) => {
+                        tab.search_options.set_page(page);
+                        return Break(self.search_active_tab());
+                    }
+                    None => debug!("No next page to load yet"),
+                }
+            }
+            WallpaperMessage::PageInputChanged(value) => {
+                // only allow numbers to be typed
+                if value.chars().all(|c| c.is_ascii_digit()) {
+                    self.tab_mut().page_value = value;
+                }
+            }
+            WallpaperMessage::GoToPage() => {
+                let tab = self.tab_mut();
+                if matches!(tab.source, Source::Reddit(_)) {
+                    return Break(Command::none());
+                }
+                let page = match tab.page_value.parse::<i32>() {
+                    Ok(page) => page,
+                    Err(_) => return Break(Command::none()),
+                };
+                // without a page to go by yet there's no telling how many pages there are
+                let page = match &tab.search_meta {
+                    Some(meta) => page.clamp(1, (meta.last_page as i32).max(1)),
+                    None => page.max(1),
+                };
+                info!("Jumping to page {}", page);
+                tab.page_value.clear();
+                tab.search_options.set_page(page);
+                tab.clear_results();
+                return Break(Command::batch(vec![
+                    self.search_active_tab(),
+                    scrollable::snap_to(results_scrollable_id(), RelativeOffset::START),
+                ]));
+            }
+            WallpaperMessage::FindSimilar(id) => {
+                let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+                let mut tab = SearchTab::new(
+                    self.next_tab_id,
+                    api_key,
+                    self.default_purity(),
+                    self.settings.default_categories.clone(),
+                );
+                tab.search_value = format!("like:{}", id);
+                tab.search_options.query = Some(tab.search_value.clone());
+                self.tabs.push(tab);
+                self.next_tab_id += 1;
+                self.active_tab = self.tabs.len() - 1;
+                self.preview.mode = PreviewMode::Disable;
+                return Break(self.search_active_tab());
+            }
+            WallpaperMessage::CopySearchLink() => {
+                if let Some(link) = self.tab().share_link() {
+                    return Break(self.handle_message(WallpaperMessage::CopyToClipboard(link)));
+                }
+            }
+            WallpaperMessage::ToggleHistory() => {
+                self.search.show_history = !self.search.show_history;
+            }
+            WallpaperMessage::RecallSearch(index) => {
+                if let Some(entry) = self.search.history.entries.get(index) {
+                    info!("Recalling search {:?}", entry);
+                    let tab = &mut self.tabs[self.active_tab];
+                    tab.search_value = entry.query().to_string();
+                    tab.excluded_tags.clear();
+                    tab.search_options = SearchOptions {
+                        api_key: tab.search_options.api_key.clone(),
+                        ..entry.options.clone()
+                    };
+                    return Break(self.handle_message(WallpaperMessage::Search()));
+                }
+            }
+            WallpaperMessage::DeleteHistoryEntry(index) => {
+                self.search.history.remove(index);
+                return Break(Command::perform(
+                    SearchHistory::save_history(self.search.history.clone()),
+                    |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                ));
+            }
+            WallpaperMessage::ResultFilterChanged(filter) => {
+                let tab = self.tab_mut();
+                let exclude_ai = filter.exclude_ai && !tab.filter.exclude_ai;
+                // wallhaven leaves AI art out of the next pages itself
+                tab.search_options.hide_ai_art = filter.exclude_ai.then_some(true);
+                tab.filter = filter;
+                tab.focus = None;
+                if exclude_ai && tab.is_wallhaven() {
+                    // search results come without tags, they're needed to tell AI art apart
+                    let api_key = tab.search_options.api_key.clone();
+                    let missing: Vec<String> = tab
+                        .search_results
+                        .iter()
+                        .filter(|(listing, _)| listing.tags.is_empty())
+                        .map(|(listing, _)| listing.id.clone())
+                        .collect();
+                    return Break(Command::batch(missing.into_iter().map(|id| {
+                        Command::perform(
+                            WallpaperUi::wallpaper_details(
+                                self.client.clone(),
+                                id.clone(),
+                                api_key.clone(),
+                            ),
+                            move |details| WallpaperMessage::WallpaperDetailsReceived(id, details),
+                        )
+                    })));
+                }
+            }
+            WallpaperMessage::SetLocalSort(sort) => {
+                let tab = self.tab_mut();
+                tab.local_sort = sort;
+                tab.focus = None;
+            }
+            WallpaperMessage::NewTab() => {
+                let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+                self.tabs.push(SearchTab::new(
+                    self.next_tab_id,
+                    api_key,
+                    self.default_purity(),
+                    self.settings.default_categories.clone(),
+                ));
+                self.next_tab_id += 1;
+                self.active_tab = self.tabs.len() - 1;
+                self.preview.mode = PreviewMode::Disable;
+                return Break(self.search_active_tab());
+            }
+            WallpaperMessage::OpenLibrary() => {
+                if let Some(index) = self.tabs.iter().position(|tab| tab.local) {
+                    return Break(self.handle_message(WallpaperMessage::SelectTab(index)));
+                }
+                let mut tab = SearchTab::new(self.next_tab_id, None, None, None);
+                tab.local = true;
+                // everything that was downloaded is shown until the purity buttons say otherwise
+                tab.search_options.purity = self.purity_lock().allowed(Some(Purity {
+                    clean: true,
+                    sketchy: true,
+                    nsfw: true,
+                }));
+                self.tabs.push(tab);
+                self.next_tab_id += 1;
+                self.active_tab = self.tabs.len() - 1;
+                self.preview.mode = PreviewMode::Disable;
+                return Break(self.search_active_tab());
+            }
+            WallpaperMessage::SelectTab(index) => {
+                if index < self.tabs.len() {
+                    self.active_tab = index;
+                    self.preview.mode = PreviewMode::Disable;
+                    return Break(scrollable::snap_to(
+                        results_scrollable_id(),
+                        self.tab().scroll_offset,
+                    ));
+                }
+            }
+            WallpaperMessage::CloseTab(index) => {
+                // the last tab can't be closed, there's always something to search in
+                if index < self.tabs.len() && self.tabs.len() > 1 {
+                    let tab = self.tabs.remove(index);
+                    self.banners.dismiss(BannerSource::Search(tab.id));
+                    if self.active_tab > index || self.active_tab == self.tabs.len() {
+                        self.active_tab -= 1;
+                    }
+                    self.preview.mode = PreviewMode::Disable;
+                    return Break(scrollable::snap_to(
+                        results_scrollable_id(),
+                        self.tab().scroll_offset,
+                    ));
+                }
+            }
+            WallpaperMessage::SurpriseMe() => {
+                let options = SearchOptions {
+                    sorting: Some(Sorting::Random),
+                    seed: Some(random_seed()),
+                    page: None,
+                    // the library tab searches without one
+                    api_key: Some(self.api_key.clone()).filter(|key| !key.is_empty()),
+                    ..self.tab().search_options.clone()
+                };
+                // every surprise lands in the same tab
+                let index = match self.tabs.iter().position(|tab| tab.roulette) {
+                    Some(index) => index,
+                    None => {
+                        let mut tab = SearchTab::new(self.next_tab_id, None, None, None);
+                        tab.roulette = true;
+                        self.tabs.push(tab);
+                        self.next_tab_id += 1;
+                        self.tabs.len() - 1
+                    }
+                };
+                let tab = &mut self.tabs[index];
+                tab.seed_value = options.seed.clone().unwrap_or_default();
+                tab.search_options = options;
+                tab.clear_results();
+                let search = tab.search_id();
+                self.active_tab = index;
+                self.preview.mode = PreviewMode::Disable;
+                info!("Picking a random wallpaper");
+                return Break(Command::perform(
+                    WallpaperUi::search_command(
+                        self.client.clone(),
+                        self.settings
+                            .blacklist
+                            .exclude_tags(&self.tab().search_options),
+                        self.library.clone(),
+                    ),
+                    move |response| match response {
+                        Some(response) => WallpaperMessage::WallpaperLinkReceived(search, response),
+                        None => WallpaperMessage::SearchOffline(search),
+                    },
+                ));
+            }
+            WallpaperMessage::Reroll() => {
+                // the page of random results is used up before asking for another one
+                if self.adjacent_preview(true).is_some() {
+                    return Break(self.navigate_preview(true));
+                }
+                return Break(self.handle_message(WallpaperMessage::SurpriseMe()));
+            }
+            WallpaperMessage::WatchSearch() => {
+                let options = self.tab().search_options.clone();
+                if !self.tab().is_wallhaven() || !self.watches.add(&options) {
+                    return Break(Command::none());
+                }
+                // the first check notes where the search is at, later ones download what's new
+                return Break(Command::batch(vec![
+                    Command::perform(WatchList::save_watches(self.watches.clone()), |result| {
+                        WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string()))
+                    }),
+                    self.handle_message(WallpaperMessage::CheckWatches()),
+                ]));
+            }
+            WallpaperMessage::RemoveWatch(index) => {
+                self.watches.remove(index);
+                return Break(Command::perform(
+                    WatchList::save_watches(self.watches.clone()),
+                    |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                ));
+            }
+            WallpaperMessage::ToggleWatchNotify(index) => {
+                if let Some(watch) = self.watches.watches.get_mut(index) {
+                    watch.notify = !watch.notify;
+                }
+                return Break(Command::perform(
+                    WatchList::save_watches(self.watches.clone()),
+                    |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                ));
+            }
+            WallpaperMessage::CheckWatches() => {
+                if self.checking_watches || self.watches.watches.is_empty() {
+                    return Break(Command::none());
+                }
+                self.checking_watches = true;
+                let api_key = Some(self.api_key.clone()).filter(|key| !key.is_empty());
+                return Break(Command::perform(
+                    check_watches(self.client.clone(), self.watches.watches.clone(), api_key),
+                    WallpaperMessage::WatchesChecked,
+                ));
+            }
+            WallpaperMessage::WatchesChecked((checked, found)) => {
+                self.checking_watches = false;
+                self.watches.update(checked);
+                for listing in found {
+                    if self.library.is_downloaded(&listing.id) {
+                        continue;
+                    }
+                    self.queue_download(&listing.id, &listing.path, &listing.created_at);
+                    self.downloads
+                        .watched_downloads
+                        .insert(listing.id.clone(), listing);
+                }
+                return Break(Command::perform(
+                    WatchList::save_watches(self.watches.clone()),
+                    |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                ));
+            }
+            message => return Continue(message),
+        }
+        Break(Command::none())
+    }
+}
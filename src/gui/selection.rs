@@ -0,0 +1,247 @@
+use crate::gui::{
+    results_scrollable_id, ContextMenu, FocusDirection, ImageState, PreviewMode, Reduced,
+    SelectionUpdateType, UndoableEdit, WallpaperMessage, WallpaperUi, GRID_COLUMNS,
+};
+use iced::widget::image;
+use iced::widget::scrollable::{self, RelativeOffset};
+use iced::Command;
+use log::{error, info};
+use std::ops::ControlFlow::{Break, Continue};
+use wallcore::cart::DownloadCart;
+use wallcore::drag_select::DragSelection;
+use wallcore::settings::ResultLayout;
+use wallcore::undo::UndoStack;
+
+/// Wallpapers picked for download and the menus and drags that pick them
+#[derive(Debug, Default)]
+pub(super) struct SelectionState {
+    pub(super) cart: DownloadCart<image::Handle>,
+    /// Result whose right click menu is open
+    pub(super) context_menu: Option<ContextMenu>,
+    /// Rectangle being dragged over the results grid
+    pub(super) drag_selection: Option<DragSelection>,
+    /// Selection and blacklist changes that can be undone
+    pub(super) undo: UndoStack<UndoableEdit>,
+}
+
+impl WallpaperUi {
+    /// Picking results to download, the cart and undoing what happened to them
+    pub(super) fn reduce_selection(&mut self, message: WallpaperMessage) -> Reduced {
+        match message {
+            WallpaperMessage::SelectionUpdate(option) => {
+                let before = self.selection.cart.clone();
+                self.update_selection(option);
+                self.record_selection(before);
+            }
+            WallpaperMessage::RemoveFromCart(id) => {
+                let before = self.selection.cart.clone();
+                self.selection.cart.remove(&id);
+                for image in self.results_with_id_mut(&id) {
+                    if image.state == ImageState::Selected {
+                        image.state = ImageState::Unselected;
+                    }
+                }
+                self.record_selection(before);
+            }
+            WallpaperMessage::ClearCart() => {
+                let before = self.selection.cart.clone();
+                for item in self.selection.cart.take_all() {
+                    for image in self.results_with_id_mut(&item.id) {
+                        if image.state == ImageState::Selected {
+                            image.state = ImageState::Unselected;
+                        }
+                    }
+                }
+                self.record_selection(before);
+            }
+            WallpaperMessage::BlacklistWallpaper(id) => {
+                if !self.settings.blacklist.add_id(&id) {
+                    return Break(Command::none());
+                }
+                info!("Blacklisted wallpaper {}", id);
+                let removed = self.remove_blacklisted(&id);
+                self.selection
+                    .undo
+                    .record(UndoableEdit::Blacklist { id, removed });
+                return Break(self.save_blacklist());
+            }
+            WallpaperMessage::Undo() => match self.selection.undo.undo() {
+                Some(UndoableEdit::Selection { before, after }) => {
+                    self.restore_cart(before.clone());
+                    self.selection
+                        .undo
+                        .undone(UndoableEdit::Selection { before, after });
+                }
+                Some(UndoableEdit::Blacklist { id, removed }) => {
+                    self.settings
+                        .blacklist
+                        .ids
+                        .retain(|blacklisted| *blacklisted != id);
+                    // removed last to first, so putting them back in reverse keeps the indices
+                    for (tab_id, index, result) in removed.iter().rev().cloned() {
+                        if let Some(tab) = self.tabs.iter_mut().find(|tab| tab.id == tab_id) {
+                            let index = index.min(tab.search_results.len());
+                            tab.search_results.insert(index, result);
+                            tab.focus = None;
+                        }
+                    }
+                    self.preview.mode = PreviewMode::Disable;
+                    self.selection
+                        .undo
+                        .undone(UndoableEdit::Blacklist { id, removed });
+                    return Break(self.save_blacklist());
+                }
+                None => {}
+            },
+            WallpaperMessage::Redo() => match self.selection.undo.redo() {
+                Some(UndoableEdit::Selection { before, after }) => {
+                    self.restore_cart(after.clone());
+                    self.selection
+                        .undo
+                        .redone(UndoableEdit::Selection { before, after });
+                }
+                Some(UndoableEdit::Blacklist { id, .. }) => {
+                    self.settings.blacklist.add_id(&id);
+                    let removed = self.remove_blacklisted(&id);
+                    self.selection
+                        .undo
+                        .redone(UndoableEdit::Blacklist { id, removed });
+                    return Break(self.save_blacklist());
+                }
+                None => {}
+            },
+            WallpaperMessage::ToggleProtected(id) => {
+                let protected = !self.protected.contains(&id);
+                if let Err(e) = self.library.set_protected(&id, protected) {
+                    error!("Failed to protect {} {:?}", id, e);
+                    return Break(Command::none());
+                }
+                match protected {
+                    true => self.protected.insert(id),
+                    false => self.protected.remove(&id),
+                };
+            }
+            WallpaperMessage::DragSelectStarted(position) => {
+                let select = self
+                    .visible_results()
+                    .nth(position)
+                    .is_some_and(|(_, (_, image))| image.state != ImageState::Selected);
+                self.selection.drag_selection = Some(DragSelection::new(position, select));
+            }
+            WallpaperMessage::DragSelectMoved(position) => {
+                if let Some(drag) = &mut self.selection.drag_selection {
+                    drag.end = position;
+                }
+            }
+            WallpaperMessage::DragSelectEnded() => {
+                let drag = match self.selection.drag_selection.take() {
+                    Some(drag) => drag,
+                    None => return Break(Command::none()),
+                };
+                // toggling keeps the cart and the other tabs in sync
+                let toggled: Vec<String> = self
+                    .visible_results()
+                    .enumerate()
+                    .filter(|(position, (_, (_, image)))| {
+                        drag.contains(*position, GRID_COLUMNS)
+                            && match drag.select {
+                                true => {
+                                    matches!(
+                                        image.state,
+                                        ImageState::Unselected | ImageState::Failed
+                                    )
+                                }
+                                false => image.state == ImageState::Selected,
+                            }
+                    })
+                    .map(|(_, (_, (listing, _)))| listing.id.clone())
+                    .collect();
+                // a single change to undo
+                let before = self.selection.cart.clone();
+                for id in toggled {
+                    self.update_selection(SelectionUpdateType::Single(id));
+                }
+                self.record_selection(before);
+            }
+            WallpaperMessage::MoveFocus(direction) => {
+                if !matches!(self.preview.mode, PreviewMode::Disable) {
+                    // left and right flip through the results while previewing
+                    return Break(match direction {
+                        FocusDirection::Left => self.navigate_preview(false),
+                        FocusDirection::Right => self.navigate_preview(true),
+                        _ => Command::none(),
+                    });
+                }
+                let count = self.visible_results().count();
+                if count == 0 {
+                    return Break(Command::none());
+                }
+                // the list has a single column
+                let columns = match self.settings.result_layout {
+                    ResultLayout::Grid => GRID_COLUMNS,
+                    ResultLayout::List => 1,
+                };
+                let tab = self.tab_mut();
+                let focus = match (tab.focus, direction) {
+                    (None, _) => 0,
+                    (Some(f), FocusDirection::Left) => f.saturating_sub(1),
+                    (Some(f), FocusDirection::Right) => f + 1,
+                    (Some(f), FocusDirection::Up) => f.checked_sub(columns).unwrap_or(f),
+                    (Some(f), FocusDirection::Down) => f + columns,
+                }
+                .min(count - 1);
+                tab.focus = Some(focus);
+                // keep the focused row on screen
+                let rows = count.div_ceil(columns);
+                let y = match rows {
+                    0 | 1 => 0.0,
+                    _ => (focus / columns) as f32 / (rows - 1) as f32,
+                };
+                return Break(scrollable::snap_to(
+                    results_scrollable_id(),
+                    RelativeOffset { x: 0.0, y },
+                ));
+            }
+            WallpaperMessage::ToggleFocusedSelection() => {
+                if let Some((_, (listing, _))) = self.focused_result() {
+                    let id = listing.id.clone();
+                    return Break(self.handle_message(WallpaperMessage::SelectionUpdate(
+                        SelectionUpdateType::Single(id),
+                    )));
+                }
+            }
+            message => return Continue(message),
+        }
+        Break(Command::none())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gui::{WallpaperMessage, WallpaperUi};
+    use iced::widget::image;
+    use wallapi::types::ListingData;
+    use wallcore::settings::Renderer;
+
+    #[test]
+    fn clearing_the_cart_can_be_undone() {
+        let mut ui = WallpaperUi::default();
+        let listing = ListingData {
+            id: "abc123".to_string(),
+            ..Default::default()
+        };
+        ui.selection
+            .cart
+            .add(&listing, &image::Handle::from_pixels(1, 1, vec![0; 4]));
+        assert!(ui
+            .reduce_selection(WallpaperMessage::ClearCart())
+            .is_break());
+        assert!(ui.selection.cart.is_empty());
+        assert!(ui.reduce_selection(WallpaperMessage::Undo()).is_break());
+        assert!(ui.selection.cart.contains("abc123"));
+        // the settings reducer's message is handed on
+        assert!(ui
+            .reduce_selection(WallpaperMessage::SetRenderer(Renderer::Software))
+            .is_continue());
+    }
+}
@@ -0,0 +1,789 @@
+use crate::autostart::set_start_at_login;
+use crate::banner::{BannerSource, Severity};
+use crate::bundle::{export_settings, import_settings, keep_keys, SettingsBundle};
+use crate::daily::DailyWallpaper;
+use crate::file_log::set_file_level;
+use crate::gui::{
+    ApiKeyStatus, PreviewMode, Reduced, SearchTab, Submenu, WallpaperMessage, WallpaperUi,
+};
+use crate::http::build_client;
+use crate::ipc::IpcCommand;
+use crate::metered::connection_metered;
+use crate::monitors::detect_monitors;
+use crate::purity_lock::{lock_purity, new_pin, pin_matches};
+use crate::style::{parse_hex_color, set_appearance};
+use crate::system_theme::system_prefers_dark;
+use crate::tray::{self, TrayAction};
+use crate::watch::WatchList;
+use iced::{window, Command};
+use log::{error, info};
+use std::ops::ControlFlow::{Break, Continue};
+use std::path::PathBuf;
+use std::time::Duration;
+use wallapi::types::{Categories, Purity, SearchOptions};
+use wallapi::url::WallhavenUrl;
+use wallapi::WallhavenClient;
+use wallcore::result_filter::parse_minimum;
+use wallcore::settings::{DataSaver, SavedSettings, ThemeMode};
+use wallcore::source::Source;
+use wallcore::updates::{download_installer, latest_release};
+
+/// Inputs of the settings page and what came of them
+#[derive(Debug, Default)]
+pub(super) struct SettingsPage {
+    pub(super) api_key_status: ApiKeyStatus,
+    /// Accent color as typed, applied once it's a valid color
+    pub(super) accent_value: String,
+    /// Name of the profile to add
+    pub(super) profile_value: String,
+    /// Contents of the blacklisted tags input
+    pub(super) blacklist_value: String,
+    /// Result of the last settings export or import
+    pub(super) bundle_status: String,
+    pub(super) pin_value: String,
+    /// Why the PIN wasn't taken
+    pub(super) pin_error: Option<String>,
+    /// Why registering to start at login failed last time
+    pub(super) autostart_error: Option<String>,
+}
+
+impl WallpaperUi {
+    /// Changing, saving and moving the settings between machines, and the window around them:
+    /// menus, monitors, onboarding, the tray, updates and closing
+    pub(super) fn reduce_settings(&mut self, message: WallpaperMessage) -> Reduced {
+        match message {
+            WallpaperMessage::ApiTokenSet(token) => {
+                self.api_key = token;
+                let api_key = match self.api_key.is_empty() {
+                    false => Some(self.api_key.clone()),
+                    true => None,
+                };
+                for tab in &mut self.tabs {
+                    tab.search_options.api_key = api_key.clone();
+                }
+                if self.api_key.is_empty() {
+                    self.settings_page.api_key_status = ApiKeyStatus::Unchecked;
+                    return Break(Command::none());
+                }
+                self.settings_page.api_key_status = ApiKeyStatus::Checking;
+                // waits for the typing to stop before asking wallhaven
+                let key = self.api_key.clone();
+                return Break(Command::perform(
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(600)).await;
+                        key
+                    },
+                    WallpaperMessage::ValidateApiKey,
+                ));
+            }
+            WallpaperMessage::ValidateApiKey(key) => {
+                if key == self.api_key {
+                    return Break(Command::perform(
+                        WallpaperUi::validate_api_key(self.client.clone(), key.clone()),
+                        move |status| WallpaperMessage::ApiKeyValidated(key.clone(), status),
+                    ));
+                }
+            }
+            WallpaperMessage::ApiKeyValidated(key, status) => {
+                // a newer key is being checked already
+                if key == self.api_key {
+                    self.settings_page.api_key_status = status;
+                }
+            }
+            WallpaperMessage::ChooseDirectory() => {
+                return Break(Command::perform(
+                    WallpaperUi::choose_directory(),
+                    WallpaperMessage::DirectoryChosen,
+                ));
+            }
+            WallpaperMessage::DirectoryChosen(path) => {
+                self.settings.save_directory = path.and_then(|p| p.to_str().map(String::from));
+                self.taskbar.set_jump_list(&self.save_directory());
+                // wallpapers in the new directory may not be in the library yet
+                let ids = self
+                    .tabs
+                    .iter()
+                    .flat_map(|tab| tab.search_results.iter())
+                    .map(|(listing, _)| listing.id.clone())
+                    .collect();
+                return Break(Command::perform(
+                    WallpaperUi::find_downloaded(self.library.clone(), self.save_directory(), ids),
+                    WallpaperMessage::DownloadedStateRefreshed,
+                ));
+            }
+            WallpaperMessage::SaveSettings() => {
+                self.settings.api_key = self.tab().search_options.api_key.clone();
+                self.settings_page.autostart_error =
+                    set_start_at_login(self.settings.start_at_login, self.settings.start_mode)
+                        .err();
+                if let Some(e) = &self.settings_page.autostart_error {
+                    error!("Failed to change starting at login {}", e);
+                }
+                // the proxy may have changed, requests already running finish on the old client
+                let http = match build_client(self.settings.proxy.as_deref()) {
+                    Ok(http) => http,
+                    Err(e) => {
+                        error!("Not saving the settings, {}", e);
+                        self.banners.show(
+                            BannerSource::Settings,
+                            Severity::Error,
+                            format!("Couldn't save the settings, {}.", e),
+                        );
+                        return Break(Command::none());
+                    }
+                };
+                self.download_manager.set_client(http.clone());
+                self.client = WallhavenClient::with_client(http);
+                return Break(Command::batch(vec![
+                    self.save_settings(),
+                    self.check_quota(),
+                ]));
+            }
+            WallpaperMessage::SaveCompleted(result) => match result {
+                Ok(()) => info!("Save complete!"),
+                Err(e) => error!("Failed to save {}", e),
+            },
+            WallpaperMessage::SettingsSaved(result) => match result {
+                Ok(()) => self.banners.dismiss(BannerSource::Settings),
+                Err(e) => {
+                    error!("Failed to save settings {}", e);
+                    self.banners.show(
+                        BannerSource::Settings,
+                        Severity::Error,
+                        format!("Couldn't save the settings. {}", e),
+                    );
+                }
+            },
+            WallpaperMessage::SetExistingFilePolicy(policy) => {
+                self.settings.existing_file_policy = policy;
+            }
+            WallpaperMessage::SetUseUploadDate(value) => self.settings.use_upload_date = value,
+            WallpaperMessage::SetDownloadAllCount(value) => {
+                // empty means no limit
+                self.settings.download_all_count =
+                    parse_minimum(&value).and_then(|count| u32::try_from(count).ok());
+            }
+            WallpaperMessage::SetDownloadAllMegabytes(value) => {
+                // empty means no limit
+                self.settings.download_all_megabytes =
+                    parse_minimum(&value).and_then(|megabytes| u32::try_from(megabytes).ok());
+            }
+            WallpaperMessage::SetMaxDiskUsage(value) => {
+                // checked once the settings are saved, not while the number is being typed
+                self.settings.max_disk_usage_megabytes =
+                    parse_minimum(&value).and_then(|megabytes| u32::try_from(megabytes).ok());
+            }
+            WallpaperMessage::SetQuotaAction(action) => self.settings.quota_action = action,
+            WallpaperMessage::SetLogLevel(level) => {
+                self.settings.log_level = level;
+                set_file_level(level);
+            }
+            WallpaperMessage::SetRenderer(renderer) => self.settings.renderer = renderer,
+            WallpaperMessage::ClearBlacklistedWallpapers() => {
+                self.settings.blacklist.ids.clear();
+                return Break(self.save_blacklist());
+            }
+            WallpaperMessage::BlacklistTagsUpdated(value) => {
+                self.settings_page.blacklist_value = value
+            }
+            WallpaperMessage::AddBlacklistedTags() => {
+                self.settings
+                    .blacklist
+                    .add_tags(&self.settings_page.blacklist_value);
+                self.settings_page.blacklist_value.clear();
+                return Break(self.save_blacklist());
+            }
+            WallpaperMessage::RemoveBlacklistedTag(index) => {
+                if index < self.settings.blacklist.tags.len() {
+                    self.settings.blacklist.tags.remove(index);
+                    return Break(self.save_blacklist());
+                }
+            }
+            WallpaperMessage::SetWallpaperCommand(command) => {
+                self.settings.wallpaper_command = Some(command).filter(|c| !c.is_empty());
+            }
+            WallpaperMessage::SetProxy(proxy) => {
+                self.settings.proxy = Some(proxy).filter(|p| !p.is_empty());
+            }
+            WallpaperMessage::SetUnsplashAccessKey(key) => {
+                self.settings.unsplash_access_key = Some(key).filter(|k| !k.is_empty());
+            }
+            WallpaperMessage::SetSourceEnabled(kind, enabled) => {
+                self.settings
+                    .disabled_sources
+                    .retain(|disabled| *disabled != kind);
+                if enabled {
+                    return Break(Command::none());
+                }
+                self.settings.disabled_sources.push(kind);
+                // tabs searching what was turned off go back to wallhaven
+                let active = self.tab().id;
+                let mut search_active = false;
+                for tab in self.tabs.iter_mut().filter(|tab| tab.source.kind() == kind) {
+                    info!("Searching wallhaven instead of {}", tab.source);
+                    tab.source = Source::Wallhaven;
+                    tab.cursor = None;
+                    search_active |= tab.id == active;
+                }
+                if search_active {
+                    return Break(self.handle_message(WallpaperMessage::Search()));
+                }
+            }
+            WallpaperMessage::DetectResolution() => {
+                return Break(Command::perform(
+                    detect_monitors(),
+                    WallpaperMessage::ResolutionDetected,
+                ));
+            }
+            WallpaperMessage::ResolutionDetected(monitors) => {
+                if let Some(resolution) = monitors.first().map(|monitor| monitor.resolution) {
+                    self.suggest_filters(resolution);
+                }
+                return Break(self.handle_message(WallpaperMessage::MonitorsDetected(monitors)));
+            }
+            WallpaperMessage::ToggleDefaultContent(content_toggle) => {
+                let content = self
+                    .settings
+                    .default_categories
+                    .get_or_insert(Categories::default());
+                content_toggle.toggle(content);
+            }
+            WallpaperMessage::SetRotationMinutes(value) => {
+                self.settings.rotation_minutes = parse_minimum(&value)
+                    .and_then(|minutes| u32::try_from(minutes).ok())
+                    .filter(|minutes| *minutes > 0);
+            }
+            WallpaperMessage::SetMinimizeToTray(value) => self.settings.minimize_to_tray = value,
+            WallpaperMessage::SwitchProfile(name) => {
+                // a key that was typed in isn't in the settings until they're saved
+                self.settings.api_key = self.tab().search_options.api_key.clone();
+                if !self.settings.switch_profile(&name) {
+                    return Break(Command::none());
+                }
+                info!("Switched to profile {}", name);
+                let key = self.settings.api_key.clone().unwrap_or_default();
+                let directory = self.settings.save_directory.clone().map(PathBuf::from);
+                let options = self
+                    .settings
+                    .last_search
+                    .clone()
+                    .map(|options| SearchOptions {
+                        purity: self.purity_lock().allowed(options.purity.clone()),
+                        ..options
+                    })
+                    .unwrap_or_else(|| SearchOptions {
+                        purity: self.default_purity(),
+                        categories: self.settings.default_categories.clone(),
+                        ..Default::default()
+                    });
+                let mut commands = vec![self.handle_message(WallpaperMessage::ApiTokenSet(key))];
+                if !self.tab().local {
+                    // not through WallpaperMessage::Search, it would save the last search over
+                    // the settings being saved here
+                    let tab = self.tab_mut();
+                    tab.restore(options);
+                    tab.clear_results();
+                    self.preview.mode = PreviewMode::Disable;
+                    commands.push(self.search_active_tab());
+                }
+                commands.push(self.handle_message(WallpaperMessage::DirectoryChosen(directory)));
+                commands.push(self.handle_message(WallpaperMessage::SaveSettings()));
+                return Break(Command::batch(commands));
+            }
+            WallpaperMessage::ProfileNameChanged(value) => self.settings_page.profile_value = value,
+            WallpaperMessage::AddProfile() => {
+                self.settings.api_key = self.tab().search_options.api_key.clone();
+                if self.settings.add_profile(&self.settings_page.profile_value) {
+                    self.settings_page.profile_value.clear();
+                    return Break(self.handle_message(WallpaperMessage::SaveSettings()));
+                }
+            }
+            WallpaperMessage::RemoveProfile() => {
+                self.settings.remove_profile();
+                return Break(self.handle_message(WallpaperMessage::SaveSettings()));
+            }
+            WallpaperMessage::ToggleDefaultPurity(purity_toggle) => {
+                if self.needs_unlock(purity_toggle, self.settings.default_purity.as_ref()) {
+                    self.controls.submenu = Submenu::PurityLock;
+                    return Break(Command::none());
+                }
+                let purity = self
+                    .settings
+                    .default_purity
+                    .get_or_insert(Purity::default());
+                purity_toggle.toggle(purity);
+            }
+            WallpaperMessage::SetStartAtLogin(value) => self.settings.start_at_login = value,
+            WallpaperMessage::PinUpdated(pin) => self.settings_page.pin_value = pin,
+            WallpaperMessage::UnlockPurity() => {
+                let unlocked = match &self.settings.purity_pin {
+                    Some(pin) => pin_matches(pin, &self.settings_page.pin_value),
+                    None => true,
+                };
+                self.settings_page.pin_value.clear();
+                match unlocked {
+                    true => {
+                        info!("Unlocked sketchy and nsfw wallpapers");
+                        self.purity_unlocked = true;
+                        self.settings_page.pin_error = None;
+                        if self.controls.submenu == Submenu::PurityLock {
+                            self.controls.submenu = Submenu::None;
+                        }
+                    }
+                    false => self.settings_page.pin_error = Some("That's not the PIN".to_string()),
+                }
+            }
+            WallpaperMessage::SetPurityPin() => {
+                if self.purity_locked() || self.settings_page.pin_value.trim().is_empty() {
+                    return Break(Command::none());
+                }
+                let pin = new_pin(&self.settings_page.pin_value);
+                self.settings_page.pin_value.clear();
+                let pin = match pin {
+                    Ok(pin) => pin,
+                    Err(e) => {
+                        error!("Failed to hash the PIN {:?}", e);
+                        self.settings_page.pin_error = Some(format!("Couldn't set the PIN. {}", e));
+                        return Break(Command::none());
+                    }
+                };
+                self.settings_page.pin_error = None;
+                self.settings.purity_pin = Some(pin.clone());
+                // locked right away, the PIN opens it again
+                self.purity_unlocked = false;
+                let lock = self.purity_lock();
+                self.settings.default_purity = lock.allowed(self.settings.default_purity.take());
+                if let Some(last_search) = &mut self.settings.last_search {
+                    last_search.purity = lock.allowed(last_search.purity.take());
+                }
+                let mut commands = vec![Command::perform(
+                    SavedSettings::save_purity_pin(Some(pin)),
+                    |result| WallpaperMessage::SettingsSaved(result.map_err(|e| e.to_string())),
+                )];
+                for index in 0..self.tabs.len() {
+                    let locked = match &mut self.tabs[index].search_options.purity {
+                        Some(purity) => lock_purity(purity),
+                        None => false,
+                    };
+                    if locked {
+                        // the locked away results are replaced, from the first page on
+                        let tab = &mut self.tabs[index];
+                        tab.search_options.page = None;
+                        tab.clear_results();
+                        commands.push(self.search_tab(&self.tabs[index]));
+                    }
+                }
+                return Break(Command::batch(commands));
+            }
+            WallpaperMessage::RemovePurityPin() => {
+                if self.purity_locked() {
+                    return Break(Command::none());
+                }
+                self.settings.purity_pin = None;
+                return Break(Command::perform(
+                    SavedSettings::save_purity_pin(None),
+                    |result| WallpaperMessage::SettingsSaved(result.map_err(|e| e.to_string())),
+                ));
+            }
+            WallpaperMessage::SetCheckForUpdates(value) => {
+                self.settings.check_for_updates = value;
+                return Break(self.handle_message(WallpaperMessage::CheckForUpdates()));
+            }
+            WallpaperMessage::SetStartMode(mode) => self.settings.start_mode = mode,
+            WallpaperMessage::SetThemeMode(mode) => {
+                self.settings.theme_mode = mode;
+                return Break(self.handle_message(WallpaperMessage::CheckSystemTheme()));
+            }
+            WallpaperMessage::CheckSystemTheme() => {
+                if self.settings.theme_mode == ThemeMode::System {
+                    return Break(Command::perform(
+                        system_prefers_dark(),
+                        WallpaperMessage::SystemThemeChecked,
+                    ));
+                }
+            }
+            WallpaperMessage::SystemThemeChecked(dark) => self.system_dark = dark,
+            WallpaperMessage::SetAccentColor(value) => {
+                let accent = match value.trim() {
+                    "" => Some(None),
+                    value => parse_hex_color(value).map(|_| Some(value.to_string())),
+                };
+                if let Some(accent) = accent {
+                    self.settings.accent_color = accent;
+                    set_appearance(self.settings.accent_color.as_deref(), self.settings.density);
+                }
+                self.settings_page.accent_value = value;
+            }
+            WallpaperMessage::SetDensity(density) => {
+                self.settings.density = density;
+                set_appearance(self.settings.accent_color.as_deref(), density);
+            }
+            WallpaperMessage::SetResultLayout(layout) => {
+                self.settings.result_layout = layout;
+                self.tab_mut().focus = None;
+                return Break(Command::perform(
+                    async move {
+                        SavedSettings::save_result_layout(layout)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    WallpaperMessage::SettingsSaved,
+                ));
+            }
+            WallpaperMessage::SetDataSaver(data_saver) => {
+                self.settings.data_saver = data_saver;
+                self.data_saver_toggled_from = None;
+                return Break(self.handle_message(WallpaperMessage::CheckMetered()));
+            }
+            WallpaperMessage::ToggleDataSaver() => {
+                // toggling back goes back to what it was, like only on metered connections
+                let data_saver = match self.data_saver_toggled_from.take() {
+                    Some(previous) => previous,
+                    None => {
+                        self.data_saver_toggled_from = Some(self.settings.data_saver);
+                        match self.saving_data() {
+                            true => DataSaver::Off,
+                            false => DataSaver::On,
+                        }
+                    }
+                };
+                self.settings.data_saver = data_saver;
+                return Break(Command::perform(
+                    async move {
+                        SavedSettings::save_data_saver(data_saver)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    WallpaperMessage::SettingsSaved,
+                ));
+            }
+            WallpaperMessage::CheckMetered() => {
+                if self.settings.data_saver == DataSaver::WhenMetered {
+                    return Break(Command::perform(
+                        connection_metered(),
+                        WallpaperMessage::MeteredChecked,
+                    ));
+                }
+            }
+            WallpaperMessage::MeteredChecked(metered) => self.metered = metered.unwrap_or(false),
+            WallpaperMessage::ExportSettings() => {
+                return Break(Command::perform(
+                    export_settings(SettingsBundle::new(&self.settings, &self.watches)),
+                    WallpaperMessage::SettingsExported,
+                ));
+            }
+            WallpaperMessage::SettingsExported(result) => {
+                self.settings_page.bundle_status = match result {
+                    Ok(Some(path)) => format!("Exported to {}", path.display()),
+                    Ok(None) => String::new(),
+                    Err(e) => {
+                        error!("Failed to export settings {}", e);
+                        e
+                    }
+                };
+            }
+            WallpaperMessage::ImportSettings() => {
+                return Break(Command::perform(
+                    async { import_settings().await.map(|bundle| bundle.map(Box::new)) },
+                    WallpaperMessage::SettingsImported,
+                ));
+            }
+            WallpaperMessage::SettingsImported(Err(e)) => {
+                error!("Failed to import settings {}", e);
+                self.settings_page.bundle_status = e;
+            }
+            WallpaperMessage::SettingsImported(Ok(None)) => {}
+            WallpaperMessage::SettingsImported(Ok(Some(bundle))) => {
+                let SettingsBundle {
+                    mut settings,
+                    watches,
+                } = *bundle;
+                keep_keys(&mut settings, &self.settings);
+                let window = self.settings.window;
+                self.settings = settings;
+                self.settings.window = window;
+                self.watches = watches;
+                self.refresh_custom_combos();
+                // applied the same way the settings are when the app starts
+                set_appearance(self.settings.accent_color.as_deref(), self.settings.density);
+                set_file_level(self.settings.log_level);
+                self.settings_page.accent_value =
+                    self.settings.accent_color.clone().unwrap_or_default();
+                self.download_manager
+                    .set_speed_limit(self.settings.download_speed_limit);
+                self.downloads.speed_limit_value = self
+                    .settings
+                    .download_speed_limit
+                    .map(|limit| limit.to_string())
+                    .unwrap_or_default();
+                self.settings_page.bundle_status = "Imported settings".to_string();
+                return Break(Command::batch(vec![
+                    self.handle_message(WallpaperMessage::ApiTokenSet(
+                        self.settings.api_key.clone().unwrap_or_default(),
+                    )),
+                    self.handle_message(WallpaperMessage::SaveSettings()),
+                    self.handle_message(WallpaperMessage::CheckSystemTheme()),
+                    Command::perform(WatchList::save_watches(self.watches.clone()), |result| {
+                        WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string()))
+                    }),
+                ]));
+            }
+            WallpaperMessage::SetWatchInterval(value) => {
+                if let Some(minutes) = parse_minimum(&value)
+                    .and_then(|minutes| u32::try_from(minutes).ok())
+                    .filter(|minutes| *minutes > 0)
+                {
+                    self.settings.watch_interval_minutes = minutes;
+                }
+            }
+            WallpaperMessage::SetDownloadedFilter(value) => {
+                self.settings.downloaded_filter = value;
+                self.tab_mut().focus = None;
+            }
+            WallpaperMessage::SetMonitorResolution(resolution) => {
+                self.settings.monitor_resolution = Some(resolution);
+            }
+            WallpaperMessage::ChangeSubmenu(menu) => {
+                // Toggle the submenu to none if already set, otherwise set value
+                if self.controls.submenu == menu {
+                    self.controls.submenu = Submenu::None;
+                } else {
+                    self.controls.submenu = menu;
+                    if self.controls.submenu == Submenu::Monitors {
+                        return Break(self.handle_message(WallpaperMessage::DetectMonitors()));
+                    }
+                }
+            }
+            WallpaperMessage::DismissBanner(source) => self.banners.dismiss(source),
+            WallpaperMessage::DetectMonitors() => {
+                return Break(Command::perform(
+                    detect_monitors(),
+                    WallpaperMessage::MonitorsDetected,
+                ));
+            }
+            WallpaperMessage::MonitorsDetected(monitors) => {
+                if self.settings.monitor_resolution.is_none() {
+                    self.settings.monitor_resolution =
+                        monitors.first().map(|monitor| monitor.resolution);
+                }
+                self.monitors = monitors;
+            }
+            WallpaperMessage::OnboardingStepChanged(step) => self.onboarding = Some(step),
+            WallpaperMessage::FinishOnboarding() => {
+                self.onboarding = None;
+                let purity = self.default_purity();
+                let categories = self.settings.default_categories.clone();
+                let search_options = &mut self.tab_mut().search_options;
+                search_options.purity = purity;
+                search_options.categories = categories;
+                if let Some(resolution) = self.settings.monitor_resolution {
+                    self.suggest_filters(resolution);
+                }
+                return Break(Command::batch(vec![
+                    self.search_active_tab(),
+                    self.handle_message(WallpaperMessage::SaveSettings()),
+                ]));
+            }
+            WallpaperMessage::Tray(action) => match action {
+                TrayAction::Open => {
+                    return Break(Command::batch([
+                        window::change_mode(window::Id::MAIN, window::Mode::Windowed),
+                        window::gain_focus(window::Id::MAIN),
+                    ]));
+                }
+                TrayAction::PauseRotation(paused) => self.rotation_paused = paused,
+                TrayAction::NextWallpaper => {
+                    return Break(self.handle_message(WallpaperMessage::RotateWallpaper()));
+                }
+                TrayAction::PauseDownloads(paused) => self.download_manager.set_paused(paused),
+                TrayAction::Quit => return Break(self.handle_message(WallpaperMessage::Quit())),
+                TrayAction::Failed => {
+                    self.tray_failed = true;
+                    // there'd be nothing to bring back a window that started minimized
+                    return Break(window::change_mode(
+                        window::Id::MAIN,
+                        window::Mode::Windowed,
+                    ));
+                }
+            },
+            WallpaperMessage::Ipc(command) => match command {
+                IpcCommand::NextWallpaper => {
+                    return Break(self.handle_message(WallpaperMessage::RotateWallpaper()));
+                }
+                IpcCommand::PauseRotation => self.rotation_paused = true,
+                IpcCommand::ResumeRotation => self.rotation_paused = false,
+                IpcCommand::PauseDownloads => self.download_manager.set_paused(true),
+                IpcCommand::ResumeDownloads => self.download_manager.set_paused(false),
+                IpcCommand::Search { query } => {
+                    self.tab_mut().search_value = query;
+                    return Break(self.handle_message(WallpaperMessage::Search()));
+                }
+                IpcCommand::Open => {
+                    return Break(self.handle_message(WallpaperMessage::Tray(TrayAction::Open)))
+                }
+                IpcCommand::ShowWallpaper { id } => {
+                    info!("Showing wallpaper {} that was asked for", id);
+                    // a tab of its own, so it isn't replaced by a search that's still running
+                    let mut tab = SearchTab::new(
+                        self.next_tab_id,
+                        self.tab().search_options.api_key.clone(),
+                        self.default_purity(),
+                        self.settings.default_categories.clone(),
+                    );
+                    tab.search_value = WallhavenUrl::Wallpaper(id).to_string();
+                    self.tabs.push(tab);
+                    self.next_tab_id += 1;
+                    self.active_tab = self.tabs.len() - 1;
+                    return Break(Command::batch(vec![
+                        self.handle_message(WallpaperMessage::Tray(TrayAction::Open)),
+                        self.handle_message(WallpaperMessage::Search()),
+                    ]));
+                }
+                IpcCommand::Quit => return Break(self.handle_message(WallpaperMessage::Quit())),
+            },
+            WallpaperMessage::CloseRequested() => {
+                if self.settings.minimize_to_tray && tray::SUPPORTED && !self.tray_failed {
+                    // rotation and downloads carry on in the background
+                    return Break(window::change_mode(window::Id::MAIN, window::Mode::Hidden));
+                }
+                return Break(self.handle_message(WallpaperMessage::Quit()));
+            }
+            WallpaperMessage::Quit() => {
+                return Break(Command::perform(
+                    SavedSettings::save_window(self.window_geometry),
+                    |result| {
+                        // nothing left to show the error in
+                        if let Err(e) = result {
+                            error!("Failed to save the window size {}", e);
+                        }
+                        WallpaperMessage::Closed()
+                    },
+                ));
+            }
+            WallpaperMessage::Closed() => return Break(window::close(window::Id::MAIN)),
+            // minimizing on Windows resizes the window to nothing and moves it to -32000
+            WallpaperMessage::WindowResized(width, height) => {
+                if width > 0 && height > 0 {
+                    self.window_geometry.width = width;
+                    self.window_geometry.height = height;
+                }
+            }
+            WallpaperMessage::WindowMoved(x, y) => {
+                if x > -32000 && y > -32000 {
+                    self.window_geometry.position = Some((x, y));
+                }
+            }
+            WallpaperMessage::SetDailyWallpaper(value) => {
+                self.settings.daily_wallpaper = value;
+                return Break(Command::batch(vec![
+                    self.save_settings(),
+                    self.handle_message(WallpaperMessage::CheckDailyWallpaper()),
+                ]));
+            }
+            WallpaperMessage::UseSearchForDaily() => {
+                if !self.tab().is_wallhaven() {
+                    return Break(Command::none());
+                }
+                let options = self.tab().search_options.clone();
+                self.daily.set_filters(&options);
+                return Break(Command::perform(
+                    DailyWallpaper::save_daily(self.daily.clone()),
+                    |result| WallpaperMessage::SaveCompleted(result.map_err(|e| e.to_string())),
+                ));
+            }
+            WallpaperMessage::CheckForUpdates() => {
+                if !self.settings.check_for_updates {
+                    return Break(Command::none());
+                }
+                let client = self.client.http().clone();
+                return Break(Command::perform(
+                    async move { latest_release(&client).await.map_err(|e| e.to_string()) },
+                    WallpaperMessage::UpdateChecked,
+                ));
+            }
+            WallpaperMessage::UpdateChecked(Ok(release)) => {
+                if release.is_newer_than(env!("CARGO_PKG_VERSION")) {
+                    info!("wall-a-bunga {} is available", release.version());
+                    self.banners.show(
+                        BannerSource::Update,
+                        Severity::Info,
+                        format!("wall-a-bunga {} is available", release.version()),
+                    );
+                    self.update = Some(release);
+                }
+            }
+            // nothing's wrong with the app, so it isn't worth a banner
+            WallpaperMessage::UpdateChecked(Err(e)) => error!("Failed to check for updates {}", e),
+            WallpaperMessage::DownloadUpdate() => {
+                let update = self.update.clone();
+                if let (Some(update), false) = (update, self.downloading_update) {
+                    self.downloading_update = true;
+                    self.update_error = None;
+                    let client = self.client.http().clone();
+                    return Break(Command::perform(
+                        async move {
+                            download_installer(&client, &update, &std::env::temp_dir())
+                                .await
+                                .map_err(|e| e.to_string())
+                        },
+                        WallpaperMessage::UpdateDownloaded,
+                    ));
+                }
+            }
+            WallpaperMessage::UpdateDownloaded(result) => {
+                self.downloading_update = false;
+                match result.map(|installer| (open::that_detached(&installer), installer)) {
+                    Ok((Ok(()), installer)) => {
+                        info!("Started the installer {:?}", installer);
+                        // the installer can't replace the app while it's running
+                        return Break(self.handle_message(WallpaperMessage::Quit()));
+                    }
+                    Ok((Err(e), installer)) => {
+                        self.update_error = Some(format!("Failed to start {:?} {}", installer, e))
+                    }
+                    Err(e) => self.update_error = Some(e),
+                }
+            }
+            WallpaperMessage::FocusInput(forward) => {
+                return Break(match forward {
+                    true => iced::widget::focus_next(),
+                    false => iced::widget::focus_previous(),
+                });
+            }
+            WallpaperMessage::Escape() => match &self.preview.mode {
+                PreviewMode::Disable => {
+                    self.controls.submenu = Submenu::None;
+                    self.search.show_history = false;
+                    self.selection.context_menu = None;
+                }
+                _ => return Break(self.handle_message(WallpaperMessage::CancelPreview())),
+            },
+            message => return Continue(message),
+        }
+        Break(Command::none())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gui::{WallpaperMessage, WallpaperUi};
+    use wallcore::settings::Renderer;
+
+    #[test]
+    fn settings_change_without_the_window() {
+        let mut ui = WallpaperUi::default();
+        assert!(ui
+            .reduce_settings(WallpaperMessage::SetRenderer(Renderer::Software))
+            .is_break());
+        assert_eq!(ui.settings.renderer, Renderer::Software);
+        assert!(ui
+            .reduce_settings(WallpaperMessage::BlacklistTagsUpdated("anime".to_string()))
+            .is_break());
+        assert_eq!(ui.settings_page.blacklist_value, "anime");
+        // out of range, nothing to remove or save
+        assert!(ui
+            .reduce_settings(WallpaperMessage::RemoveBlacklistedTag(5))
+            .is_break());
+        assert!(ui.reduce_settings(WallpaperMessage::Undo()).is_continue());
+    }
+}
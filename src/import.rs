@@ -0,0 +1,87 @@
+use log::{error, info};
+use native_dialog::FileDialog;
+use std::time::Duration;
+use wallapi::types::ListingData;
+use wallapi::url::WallhavenUrl;
+use wallapi::WallhavenClient;
+
+/// wallhaven allows 45 api calls a minute
+const REQUEST_DELAY: Duration = Duration::from_millis(1400);
+
+/// Pulls wallpaper ids out of a list of ids and links separated by whitespace or commas,
+/// dropping duplicates and anything that isn't recognised
+pub(crate) fn parse_wallpaper_ids(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for token in text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+    {
+        let id = match token.parse::<WallhavenUrl>() {
+            Ok(WallhavenUrl::Wallpaper(id)) => id,
+            _ if token.len() >= 6 && token.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                token.to_lowercase()
+            }
+            _ => {
+                info!("Skipping {} in import list", token);
+                continue;
+            }
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// Looks up every wallpaper, returning the ones that were found along with the ids that weren't
+pub(crate) async fn resolve_wallpapers(
+    client: WallhavenClient,
+    ids: Vec<String>,
+    api_key: Option<String>,
+) -> (Vec<ListingData>, Vec<String>) {
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for (index, id) in ids.into_iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(REQUEST_DELAY).await;
+        }
+        match client.wallpaper(&id, api_key.as_deref()).await {
+            Ok(response) => match response.data {
+                Some(listing) => found.push(listing),
+                None => missing.push(id),
+            },
+            Err(e) => {
+                error!("Failed to look up {} {:?}", id, e);
+                missing.push(id);
+            }
+        }
+    }
+    (found, missing)
+}
+
+/// Asks for a text file with the list to import
+pub(crate) async fn load_list_file() -> Option<String> {
+    let path = FileDialog::new()
+        .add_filter("Text", &["txt", "csv"])
+        .show_open_single_file()
+        .ok()
+        .flatten()?;
+    match tokio::fs::read_to_string(&path).await {
+        Ok(text) => Some(text),
+        Err(e) => {
+            error!("Failed to read {:?} {:?}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::parse_wallpaper_ids;
+
+    #[test]
+    fn parse_import_list() {
+        let list = "j3m8y5, https://wallhaven.cc/w/9dw9kx\nhttps://whvn.cc/j3m8y5 not-an-id 28vjgm";
+        assert_eq!(parse_wallpaper_ids(list), ["j3m8y5", "9dw9kx", "28vjgm"]);
+    }
+}
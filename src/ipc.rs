@@ -0,0 +1,516 @@
+use iced::futures::stream::BoxStream;
+use iced::Subscription;
+use iced_futures::subscription::{EventStream, Recipe};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use wallapi::url::WallhavenUrl;
+
+/// Sent to the running app over its control socket, one JSON object per line like
+/// `{"command": "search", "query": "cats"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub(crate) enum IpcCommand {
+    /// Switches the desktop to another downloaded wallpaper
+    NextWallpaper,
+    PauseRotation,
+    ResumeRotation,
+    PauseDownloads,
+    ResumeDownloads,
+    /// Searches the active tab
+    Search {
+        query: String,
+    },
+    /// Shows the window again after it was minimized to the tray
+    Open,
+    /// Brings up the window and previews the wallpaper in a tab of its own
+    ShowWallpaper {
+        id: String,
+    },
+    Quit,
+}
+
+impl IpcCommand {
+    /// Commands that can be the app's arguments, they're forwarded when it's already running and
+    /// handled once it has started when it wasn't
+    pub(crate) fn can_start_app(&self) -> bool {
+        matches!(
+            self,
+            IpcCommand::Open | IpcCommand::Search { .. } | IpcCommand::ShowWallpaper { .. }
+        )
+    }
+}
+
+impl FromStr for IpcCommand {
+    type Err = String;
+
+    /// Parses the command line form, like `next-wallpaper`, `search cats` or `open j3m8y5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, rest) = s.split_once(' ').unwrap_or((s, ""));
+        match (command, rest.trim()) {
+            ("search", query) => Ok(IpcCommand::Search {
+                query: query.to_string(),
+            }),
+            ("open", wallpaper) if !wallpaper.is_empty() => Ok(IpcCommand::ShowWallpaper {
+                id: wallpaper_id(wallpaper)?,
+            }),
+            (command, "") => serde_json::from_value(serde_json::json!({ "command": command }))
+                .map_err(|_| format!("Unknown command {}", command)),
+            (command, _) => Err(format!("{} doesn't take arguments", command)),
+        }
+    }
+}
+
+/// The id of a wallpaper given as its id or any link to it, like `https://wallhaven.cc/w/j3m8y5`
+fn wallpaper_id(wallpaper: &str) -> Result<String, String> {
+    match wallpaper.parse::<WallhavenUrl>() {
+        Ok(WallhavenUrl::Wallpaper(id)) => Ok(id),
+        Ok(WallhavenUrl::Search(_)) => Err(format!("{} is a search, not a wallpaper", wallpaper)),
+        Err(_) if wallpaper.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(wallpaper.to_string()),
+        Err(_) => Err(format!("{} isn't a wallpaper id or link", wallpaper)),
+    }
+}
+
+/// Answer to every line sent over the socket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IpcReply {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl IpcReply {
+    fn to_line(result: Result<(), String>) -> String {
+        let reply = IpcReply {
+            ok: result.is_ok(),
+            error: result.err(),
+        };
+        serde_json::to_string(&reply).expect("Failed to serialize reply") + "\n"
+    }
+}
+
+/// Answers the commands sent over one connection, one reply line per command line
+async fn handle_connection<S>(stream: S, commands: UnboundedSender<IpcCommand>)
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<IpcCommand>(&line) {
+            Ok(command) => {
+                info!("Received {:?} over the control socket", command);
+                commands
+                    .send(command)
+                    .map_err(|_| "wall-a-bunga is shutting down".to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        if writer
+            .write_all(IpcReply::to_line(result).as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use crate::ipc::{handle_connection, IpcCommand};
+    use log::error;
+    use std::fs::{DirBuilder, Permissions};
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+    use std::path::{Path, PathBuf};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::mpsc::UnboundedSender;
+
+    fn user_id() -> u32 {
+        // https://man7.org/linux/man-pages/man2/getuid.2.html, it can't fail
+        unsafe { libc::getuid() }
+    }
+
+    /// `$XDG_RUNTIME_DIR`, which only the user can get into. The temp directory is shared with
+    /// everyone, so the socket goes in a directory of the user's own in there.
+    fn socket_dir() -> PathBuf {
+        match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::temp_dir().join(format!("wall-a-bunga-{}", user_id())),
+        }
+    }
+
+    pub(crate) fn socket_path() -> PathBuf {
+        socket_dir().join("wall-a-bunga.sock")
+    }
+
+    /// Fails when the path belongs to another user, they could otherwise stand in for the app
+    fn check_owner(path: &Path) -> Result<std::fs::Metadata, String> {
+        let metadata = std::fs::symlink_metadata(path).map_err(|e| format!("{:?} {}", path, e))?;
+        match metadata.uid() == user_id() {
+            true => Ok(metadata),
+            false => Err(format!("{:?} belongs to another user", path)),
+        }
+    }
+
+    /// Creates the socket's directory so only the user can get into it
+    fn create_socket_dir() -> Result<(), String> {
+        let dir = socket_dir();
+        if let Err(e) = DirBuilder::new().mode(0o700).create(&dir) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(format!("Failed to create {:?} {}", dir, e));
+            }
+        }
+        let metadata = check_owner(&dir)?;
+        if !metadata.is_dir() {
+            return Err(format!("{:?} isn't a directory", dir));
+        }
+        if metadata.mode() & 0o077 != 0 {
+            std::fs::set_permissions(&dir, Permissions::from_mode(0o700))
+                .map_err(|e| format!("Failed to restrict {:?} {}", dir, e))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn address() -> String {
+        socket_path().to_string_lossy().to_string()
+    }
+
+    /// Binds the socket so only the user can connect to it, taking it over when it was left
+    /// behind by an app that's gone
+    async fn bind() -> Result<UnixListener, String> {
+        create_socket_dir()?;
+        let path = socket_path();
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) if e.kind() != std::io::ErrorKind::AddrInUse => {
+                return Err(format!("Failed to bind {:?} {}", path, e))
+            }
+            Err(_) => {
+                if UnixStream::connect(&path).await.is_ok() {
+                    return Err(format!("Another wall-a-bunga is listening on {:?}", path));
+                }
+                // only a socket of the user's own is taken over, never some other file
+                if !check_owner(&path)?.file_type().is_socket() {
+                    return Err(format!("{:?} is in the way of the socket", path));
+                }
+                tokio::fs::remove_file(&path)
+                    .await
+                    .map_err(|e| format!("Failed to remove stale socket {:?} {}", path, e))?;
+                UnixListener::bind(&path).map_err(|e| format!("Failed to bind {:?} {}", path, e))?
+            }
+        };
+        std::fs::set_permissions(&path, Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict {:?} {}", path, e))?;
+        Ok(listener)
+    }
+
+    /// Accepts connections until the app stops listening
+    async fn serve(listener: UnixListener, commands: UnboundedSender<IpcCommand>) {
+        while !commands.is_closed() {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, commands.clone()));
+                }
+                Err(e) => {
+                    error!("Failed to accept a control connection {:?}", e);
+                    return;
+                }
+            }
+        }
+        let _ = tokio::fs::remove_file(socket_path()).await;
+    }
+
+    pub(crate) async fn start(commands: UnboundedSender<IpcCommand>) -> Result<(), String> {
+        tokio::spawn(serve(bind().await?, commands));
+        Ok(())
+    }
+
+    pub(crate) async fn connect() -> Result<UnixStream, String> {
+        let path = socket_path();
+        // commands and their replies only go to the user's own app
+        check_owner(&path).map_err(|e| format!("wall-a-bunga isn't running, {}", e))?;
+        UnixStream::connect(&path)
+            .await
+            .map_err(|e| format!("wall-a-bunga isn't running, {:?} {}", path, e))
+    }
+}
+
+/// Windows has no unix sockets in its async runtime, a named pipe stands in for the socket
+#[cfg(windows)]
+mod pipe {
+    use crate::ipc::{handle_connection, IpcCommand};
+    use log::error;
+    use std::ffi::c_void;
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+    use tokio::sync::mpsc::UnboundedSender;
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::Foundation::{CloseHandle, LocalFree, BOOL, HANDLE, HLOCAL};
+    use windows::Win32::Security::Authorization::{
+        ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+        SDDL_REVISION_1,
+    };
+    use windows::Win32::Security::{
+        GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY,
+        TOKEN_USER,
+    };
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    /// The SID of the user running the app, like `S-1-5-21-...`
+    fn user_sid() -> windows::core::Result<String> {
+        unsafe {
+            let mut token = HANDLE::default();
+            OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+            // the first call only says how big the answer is
+            let mut length = 0;
+            let _ = GetTokenInformation(token, TokenUser, None, 0, &mut length);
+            // u64s so the TOKEN_USER in there is aligned
+            let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+            let result = GetTokenInformation(
+                token,
+                TokenUser,
+                Some(buffer.as_mut_ptr() as *mut c_void),
+                length,
+                &mut length,
+            );
+            let _ = CloseHandle(token);
+            result?;
+            let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+            let mut sid = PWSTR::null();
+            ConvertSidToStringSidW(user.User.Sid, &mut sid)?;
+            let text = String::from_utf16_lossy(sid.as_wide());
+            let _ = LocalFree(HLOCAL(sid.0 as *mut c_void));
+            Ok(text)
+        }
+    }
+
+    /// Pipes are seen by every user on the machine, so each user gets one of their own
+    fn pipe_name() -> Result<String, String> {
+        let sid = user_sid().map_err(|e| format!("Couldn't look up the user {}", e))?;
+        Ok(format!(r"\\.\pipe\wall-a-bunga-{}", sid))
+    }
+
+    pub(crate) fn address() -> String {
+        pipe_name().unwrap_or_else(|e| e)
+    }
+
+    /// Lets only the user into the pipe, other users can't send commands or stand in for it
+    struct PipeSecurity(PSECURITY_DESCRIPTOR);
+
+    // the descriptor is only read once it's made, it's freed when this is dropped
+    unsafe impl Send for PipeSecurity {}
+
+    impl PipeSecurity {
+        fn new() -> Result<Self, String> {
+            let sid = user_sid().map_err(|e| format!("Couldn't look up the user {}", e))?;
+            // a protected DACL with all access for the user alone, nothing is inherited
+            // https://learn.microsoft.com/en-us/windows/win32/secauthz/security-descriptor-string-format
+            let sddl = HSTRING::from(format!("D:P(A;;GA;;;{})", sid));
+            let mut descriptor = PSECURITY_DESCRIPTOR::default();
+            unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                    &sddl,
+                    SDDL_REVISION_1,
+                    &mut descriptor,
+                    None,
+                )
+            }
+            .map_err(|e| format!("Couldn't secure the control pipe {}", e))?;
+            Ok(PipeSecurity(descriptor))
+        }
+
+        fn create(&self, options: &ServerOptions, name: &str) -> std::io::Result<NamedPipeServer> {
+            let mut attributes = SECURITY_ATTRIBUTES {
+                nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: self.0 .0,
+                bInheritHandle: BOOL(0),
+            };
+            unsafe {
+                options.create_with_security_attributes_raw(
+                    name,
+                    &mut attributes as *mut SECURITY_ATTRIBUTES as *mut c_void,
+                )
+            }
+        }
+    }
+
+    impl Drop for PipeSecurity {
+        fn drop(&mut self) {
+            let _ = unsafe { LocalFree(HLOCAL(self.0 .0)) };
+        }
+    }
+
+    /// Waits for a client on the pipe, then opens the next instance of it for the one after.
+    /// The pipe goes away with the last instance, so there's nothing left behind to take over.
+    async fn serve(
+        mut server: NamedPipeServer,
+        name: String,
+        security: PipeSecurity,
+        commands: UnboundedSender<IpcCommand>,
+    ) {
+        while !commands.is_closed() {
+            if let Err(e) = server.connect().await {
+                error!("Failed to accept a control connection {:?}", e);
+                return;
+            }
+            let next = match security.create(&ServerOptions::new(), &name) {
+                Ok(next) => next,
+                Err(e) => {
+                    error!("Failed to open the control pipe {:?}", e);
+                    return;
+                }
+            };
+            tokio::spawn(handle_connection(
+                std::mem::replace(&mut server, next),
+                commands.clone(),
+            ));
+        }
+    }
+
+    pub(crate) async fn start(commands: UnboundedSender<IpcCommand>) -> Result<(), String> {
+        let name = pipe_name()?;
+        let security = PipeSecurity::new()?;
+        // only the first instance can be created with this, it fails while another app has it
+        let server = security
+            .create(
+                ServerOptions::new()
+                    .first_pipe_instance(true)
+                    .reject_remote_clients(true),
+                &name,
+            )
+            .map_err(|e| format!("Another wall-a-bunga is listening on {} {}", name, e))?;
+        tokio::spawn(serve(server, name, security, commands));
+        Ok(())
+    }
+
+    pub(crate) async fn connect() -> Result<NamedPipeClient, String> {
+        let name = pipe_name()?;
+        ClientOptions::new()
+            .open(&name)
+            .map_err(|e| format!("wall-a-bunga isn't running, {} {}", name, e))
+    }
+}
+
+#[cfg(windows)]
+use pipe as platform;
+#[cfg(unix)]
+use unix as platform;
+
+/// Listens on the control socket for as long as the subscription is kept
+#[derive(Debug, Clone)]
+struct IpcServer;
+
+impl Recipe for IpcServer {
+    type Output = IpcCommand;
+
+    fn hash(&self, state: &mut iced_futures::core::Hasher) {
+        use std::hash::Hash;
+
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: EventStream) -> BoxStream<'static, Self::Output> {
+        let (sender, receiver) = unbounded_channel();
+        Box::pin(futures::stream::unfold(
+            (Some(sender), receiver),
+            |(sender, mut receiver)| async move {
+                // the sender moves into the server, the stream ends once it's gone
+                if let Some(sender) = sender {
+                    match platform::start(sender).await {
+                        Ok(()) => info!("Listening for commands on {}", platform::address()),
+                        Err(e) => {
+                            warn!("No control socket, {}", e);
+                            return None;
+                        }
+                    }
+                }
+                let command = receiver.recv().await?;
+                Some((command, (None, receiver)))
+            },
+        ))
+    }
+}
+
+/// Sends a command to the running app, used by `--send`
+pub(crate) async fn send(command: &IpcCommand) -> Result<(), String> {
+    let (reader, mut writer) = tokio::io::split(platform::connect().await?);
+    let line = serde_json::to_string(command).map_err(|e| e.to_string())? + "\n";
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    let reply = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No reply from wall-a-bunga")?;
+    let reply: IpcReply = serde_json::from_str(&reply).map_err(|e| e.to_string())?;
+    match reply.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Whether another app is listening on the control socket
+pub(crate) async fn is_running() -> bool {
+    platform::connect().await.is_ok()
+}
+
+/// Commands sent to the control socket, window manager keybindings and scripts use it to drive
+/// the app
+pub(crate) fn listen() -> Subscription<IpcCommand> {
+    Subscription::from_recipe(IpcServer)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ipc::{IpcCommand, IpcReply};
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "next-wallpaper".parse::<IpcCommand>(),
+            Ok(IpcCommand::NextWallpaper)
+        );
+        assert_eq!(
+            "search  zero two ".parse::<IpcCommand>(),
+            Ok(IpcCommand::Search {
+                query: "zero two".to_string()
+            })
+        );
+        assert!("pause-downloads now".parse::<IpcCommand>().is_err());
+        assert!("dance".parse::<IpcCommand>().is_err());
+        let show = IpcCommand::ShowWallpaper {
+            id: "j3m8y5".to_string(),
+        };
+        assert_eq!("open j3m8y5".parse::<IpcCommand>(), Ok(show.clone()));
+        assert_eq!(
+            "open https://whvn.cc/j3m8y5".parse::<IpcCommand>(),
+            Ok(show)
+        );
+        assert_eq!("open".parse::<IpcCommand>(), Ok(IpcCommand::Open));
+        assert!("open https://wallhaven.cc/search?q=cats"
+            .parse::<IpcCommand>()
+            .is_err());
+        assert!("open ../cats.png".parse::<IpcCommand>().is_err());
+        assert!("search cats".parse::<IpcCommand>().unwrap().can_start_app());
+        assert!(!IpcCommand::Quit.can_start_app());
+        assert_eq!(
+            serde_json::from_str::<IpcCommand>(r#"{"command": "search", "query": "cats"}"#)
+                .unwrap(),
+            IpcCommand::Search {
+                query: "cats".to_string()
+            }
+        );
+        assert_eq!(IpcReply::to_line(Ok(())), "{\"ok\":true}\n");
+    }
+}
@@ -0,0 +1,518 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use platform_dirs::AppDirs;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::spawn_blocking;
+use wallapi::types::{ListingData, PurityLevel, SearchOptions, XYCombo};
+
+/// Schema changes, applied in order to bring older databases up to date. Only add to the end.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS downloads (
+        id TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        hash TEXT,
+        tags TEXT NOT NULL DEFAULT '',
+        resolution TEXT,
+        downloaded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    )",
+    "ALTER TABLE downloads ADD COLUMN image_hash INTEGER",
+    "ALTER TABLE downloads ADD COLUMN listing TEXT",
+    "ALTER TABLE downloads ADD COLUMN protected INTEGER NOT NULL DEFAULT 0",
+];
+
+/// Wallhaven ratios are rounded, 2560x1080 counts as 21x9
+const RATIO_TOLERANCE: f64 = 0.02;
+
+/// A wallpaper that was downloaded
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LibraryEntry {
+    pub(crate) id: String,
+    /// Where the file was saved, which may have been renamed to avoid overwriting another file
+    pub(crate) path: PathBuf,
+    /// [file_hash] of the contents, None when the file was indexed without being read
+    pub(crate) hash: Option<String>,
+    /// Perceptual hash of the image, filled in by the duplicate scan
+    pub(crate) image_hash: Option<u64>,
+    /// Empty unless the tags were known when it was downloaded
+    pub(crate) tags: Vec<String>,
+    pub(crate) resolution: Option<String>,
+    /// Search result it was downloaded from, None for files that were indexed from disk
+    pub(crate) listing: Option<ListingData>,
+    /// Never deleted to keep the save directory under the disk quota
+    pub(crate) protected: bool,
+}
+
+impl LibraryEntry {
+    /// Width and height, read from the file when the library doesn't know them
+    fn dimensions(&self) -> Option<(u32, u32)> {
+        self.resolution
+            .as_deref()
+            .and_then(|resolution| resolution.parse::<XYCombo>().ok())
+            .map(|resolution| (resolution.x as u32, resolution.y as u32))
+            .or_else(|| image_rs::image_dimensions(&self.path).ok())
+    }
+
+    /// The search result it was downloaded from, or as much of one as the file tells us
+    pub(crate) fn to_listing(&self) -> ListingData {
+        if let Some(listing) = &self.listing {
+            return listing.clone();
+        }
+        let (width, height) = self.dimensions().unwrap_or_default();
+        ListingData {
+            id: self.id.clone(),
+            url: format!("https://wallhaven.cc/w/{}", self.id),
+            short_url: format!("https://whvn.cc/{}", self.id),
+            dimension_x: width as i64,
+            dimension_y: height as i64,
+            resolution: format!("{}x{}", width, height),
+            ratio: match height {
+                0 => String::new(),
+                _ => format!("{:.2}", width as f64 / height as f64),
+            },
+            file_size: std::fs::metadata(&self.path)
+                .map(|metadata| metadata.len() as i64)
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the wallpaper fits the query, resolutions, ratios and purity of the search. A
+    /// purity that isn't known never hides the wallpaper.
+    pub(crate) fn matches(&self, options: &SearchOptions) -> bool {
+        let query = options.query.as_deref().unwrap_or_default().to_lowercase();
+        let has_tag = |term: &str| {
+            self.id == term
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(term))
+                || self
+                    .listing
+                    .as_ref()
+                    .is_some_and(|listing| format!("{:?}", listing.category).to_lowercase() == term)
+        };
+        let query_matches = query
+            .split_whitespace()
+            .all(|term| match term.strip_prefix('-') {
+                Some(excluded) => !has_tag(excluded),
+                None => has_tag(term.trim_start_matches('+')),
+            });
+        if !query_matches {
+            return false;
+        }
+        if let (Some(purity), Some(listing)) = (&options.purity, &self.listing) {
+            let allowed = match listing.purity {
+                PurityLevel::Sfw => purity.clean,
+                PurityLevel::Sketchy => purity.sketchy,
+                PurityLevel::Nsfw => purity.nsfw,
+            };
+            if !allowed {
+                return false;
+            }
+        }
+        let size_filtered = options.resolutions.as_ref().is_some_and(|r| !r.is_empty())
+            || options.minimum_resolution.is_some()
+            || options.ratios.as_ref().is_some_and(|r| !r.is_empty());
+        if !size_filtered {
+            return true;
+        }
+        let (width, height) = match self.dimensions() {
+            Some(dimensions) => dimensions,
+            None => return false,
+        };
+        let resolution = XYCombo {
+            x: width as i32,
+            y: height as i32,
+        };
+        if let Some(resolutions) = options.resolutions.as_ref().filter(|r| !r.is_empty()) {
+            if !resolutions.contains(&resolution) {
+                return false;
+            }
+        }
+        if let Some(minimum) = &options.minimum_resolution {
+            if resolution.x < minimum.x || resolution.y < minimum.y {
+                return false;
+            }
+        }
+        if let Some(ratios) = options.ratios.as_ref().filter(|r| !r.is_empty()) {
+            let ratio = width as f64 / height as f64;
+            return ratios.iter().any(|wanted| {
+                let wanted = wanted.x as f64 / wanted.y as f64;
+                ((ratio - wanted) / wanted).abs() <= RATIO_TOLERANCE
+            });
+        }
+        true
+    }
+}
+
+/// Index of downloaded wallpapers kept in sqlite, so finding out whether a result was already
+/// downloaded doesn't mean probing the save directory for every thumbnail
+#[derive(Debug, Clone)]
+pub(crate) struct Library {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl Library {
+    // Function left sync intentionally, same as the settings
+    pub(crate) fn open() -> Result<Self> {
+        let app_dirs = AppDirs::new(Some("wall-a-bunga"), true)
+            .ok_or_else(|| anyhow!("No config directory for the library index"))?;
+        let database = app_dirs.config_dir.join("library.sqlite");
+        std::fs::create_dir_all(&app_dirs.config_dir)?;
+        let library = Library::from_connection(Connection::open(&database)?)?;
+        info!("Opened library index at {:?}", database);
+        Ok(library)
+    }
+
+    fn in_memory() -> Self {
+        Connection::open_in_memory()
+            .map_err(anyhow::Error::from)
+            .and_then(Library::from_connection)
+            .expect("Failed to create an in memory library index")
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for migration in MIGRATIONS.iter().skip(version) {
+            connection.execute_batch(migration)?;
+        }
+        connection.execute_batch(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    pub(crate) fn record(&self, entry: &LibraryEntry) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO downloads
+             (id, path, hash, tags, resolution, image_hash, listing, protected)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.id,
+                entry.path.to_string_lossy(),
+                entry.hash,
+                entry.tags.join(","),
+                entry.resolution,
+                // sqlite integers are signed, the bits are kept as they are
+                entry.image_hash.map(|hash| hash as i64),
+                entry
+                    .listing
+                    .as_ref()
+                    .and_then(|listing| serde_json::to_string(listing).ok()),
+                entry.protected
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn find(&self, id: &str) -> Result<Option<LibraryEntry>> {
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("SELECT {} FROM downloads WHERE id = ?1", ENTRY_COLUMNS),
+                params![id],
+                entry_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Every wallpaper in the library, whether or not the file still exists
+    pub(crate) fn entries(&self) -> Result<Vec<LibraryEntry>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM downloads ORDER BY downloaded_at",
+            ENTRY_COLUMNS
+        ))?;
+        let entries = statement
+            .query_map([], entry_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    /// Downloaded wallpapers that match the search, newest first
+    pub(crate) fn search(&self, options: &SearchOptions) -> Result<Vec<LibraryEntry>> {
+        let mut entries = self.entries()?;
+        entries.reverse();
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.path.exists() && entry.matches(options))
+            .collect())
+    }
+
+    /// The wallpaper saved at `path`
+    pub(crate) fn find_path(&self, path: &Path) -> Result<Option<LibraryEntry>> {
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("SELECT {} FROM downloads WHERE path = ?1", ENTRY_COLUMNS),
+                params![path.to_string_lossy()],
+                entry_from_row,
+            )
+            .optional()?)
+    }
+
+    pub(crate) fn set_protected(&self, id: &str, protected: bool) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE downloads SET protected = ?2 WHERE id = ?1",
+            params![id, protected],
+        )?;
+        Ok(())
+    }
+
+    /// Ids of every protected wallpaper
+    pub(crate) fn protected_ids(&self) -> Result<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT id FROM downloads WHERE protected = 1")?;
+        let ids = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    pub(crate) fn remove(&self, id: &str) -> Result<()> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM downloads WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Whether the wallpaper was downloaded, [Library::prune_missing] forgets the ones that were
+    /// deleted since
+    pub(crate) fn is_downloaded(&self, id: &str) -> bool {
+        match self.find(id) {
+            Ok(entry) => entry.is_some(),
+            Err(e) => {
+                error!("Failed to look up {} in the library {:?}", id, e);
+                false
+            }
+        }
+    }
+
+    /// [Library::is_downloaded] on a blocking thread, the lookup can wait on the connection
+    pub(crate) async fn check_downloaded(&self, id: String) -> bool {
+        let library = self.clone();
+        match spawn_blocking(move || library.is_downloaded(&id)).await {
+            Ok(downloaded) => downloaded,
+            Err(e) => {
+                error!("Library lookup failed {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Forgets wallpapers whose files were deleted since they were downloaded, the number
+    /// forgotten. The files are checked without holding the connection.
+    pub(crate) fn prune_missing(&self) -> Result<usize> {
+        let missing: Vec<LibraryEntry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| !entry.path.exists())
+            .collect();
+        for entry in &missing {
+            info!("{:?} is gone, removing it from the library", entry.path);
+            self.remove(&entry.id)?;
+        }
+        Ok(missing.len())
+    }
+
+    /// Adds wallpapers in the directory that aren't indexed yet, like ones downloaded before the
+    /// index existed. Files aren't read, so they're added without a hash.
+    pub(crate) fn import_directory(&self, directory: &Path) -> Result<usize> {
+        let mut imported = 0;
+        for entry in std::fs::read_dir(directory)?.flatten() {
+            let path = entry.path();
+            let id = match wallpaper_id(&path) {
+                Some(id) => id,
+                None => continue,
+            };
+            if self.find(&id)?.is_none() {
+                self.record(&LibraryEntry {
+                    id,
+                    path,
+                    hash: None,
+                    image_hash: None,
+                    tags: vec![],
+                    resolution: None,
+                    listing: None,
+                    protected: false,
+                })?;
+                imported += 1;
+            }
+        }
+        info!("Imported {} wallpapers from {:?}", imported, directory);
+        Ok(imported)
+    }
+}
+
+const ENTRY_COLUMNS: &str = "id, path, hash, tags, resolution, image_hash, listing, protected";
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<LibraryEntry> {
+    Ok(LibraryEntry {
+        id: row.get(0)?,
+        path: PathBuf::from(row.get::<_, String>(1)?),
+        hash: row.get(2)?,
+        tags: split_tags(&row.get::<_, String>(3)?),
+        resolution: row.get(4)?,
+        image_hash: row.get::<_, Option<i64>>(5)?.map(|hash| hash as u64),
+        listing: row
+            .get::<_, Option<String>>(6)?
+            .and_then(|listing| serde_json::from_str(&listing).ok()),
+        protected: row.get(7)?,
+    })
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Id of a wallpaper saved with its original name, `wallhaven-j3m8y5.jpg` or the numbered
+/// `wallhaven-j3m8y5-2.jpg`
+fn wallpaper_id(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?;
+    if !matches!(extension, "jpg" | "jpeg" | "png") {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?.strip_prefix("wallhaven-")?;
+    let id = stem.split('-').next()?;
+    Some(id.to_string())
+}
+
+/// 64 bit FNV-1a hash of a file, good enough to spot identical wallpapers
+pub(crate) fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Fnv1a::default();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::library::{wallpaper_id, Library, LibraryEntry};
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use wallapi::types::{ListingData, Purity, PurityLevel, SearchOptions, XYCombo};
+
+    fn entry() -> LibraryEntry {
+        LibraryEntry {
+            id: "j3m8y5".to_string(),
+            path: PathBuf::from("walls/wallhaven-j3m8y5.jpg"),
+            hash: Some("00ff".to_string()),
+            image_hash: Some(u64::MAX),
+            tags: vec!["landscape".to_string(), "night".to_string()],
+            resolution: Some("2560x1080".to_string()),
+            listing: Some(ListingData {
+                id: "j3m8y5".to_string(),
+                purity: PurityLevel::Sketchy,
+                ..Default::default()
+            }),
+            protected: true,
+        }
+    }
+
+    #[test]
+    fn record_and_find() {
+        let library = Library::default();
+        let entry = entry();
+        library.record(&entry).unwrap();
+        assert_eq!(library.find("j3m8y5").unwrap(), Some(entry.clone()));
+        assert_eq!(library.find("9dw9kx").unwrap(), None);
+        assert_eq!(library.entries().unwrap(), vec![entry.clone()]);
+        assert_eq!(library.protected_ids().unwrap(), ["j3m8y5"]);
+        library.set_protected("j3m8y5", false).unwrap();
+        assert!(library.protected_ids().unwrap().is_empty());
+        library.set_protected("j3m8y5", true).unwrap();
+        assert_eq!(library.find_path(&entry.path).unwrap(), Some(entry.clone()));
+        // looking it up leaves it alone, only pruning forgets that the file doesn't exist
+        assert!(library.is_downloaded("j3m8y5"));
+        assert_eq!(library.prune_missing().unwrap(), 1);
+        assert!(!library.is_downloaded("j3m8y5"));
+    }
+
+    #[test]
+    fn wallpaper_ids_from_file_names() {
+        assert_eq!(
+            wallpaper_id(Path::new("wallhaven-j3m8y5.jpg")),
+            Some("j3m8y5".to_string())
+        );
+        assert_eq!(
+            wallpaper_id(Path::new("wallhaven-j3m8y5-2.png")),
+            Some("j3m8y5".to_string())
+        );
+        assert_eq!(wallpaper_id(Path::new("wallhaven-j3m8y5.jpg.part")), None);
+        assert_eq!(wallpaper_id(Path::new("holiday.jpg")), None);
+    }
+
+    #[test]
+    fn search_matches() {
+        let entry = entry();
+        let search = |options: SearchOptions| entry.matches(&options);
+        assert!(search(SearchOptions::default()));
+        assert!(search(SearchOptions {
+            query: Some("Land -city".to_string()),
+            ..Default::default()
+        }));
+        assert!(!search(SearchOptions {
+            query: Some("landscape -night".to_string()),
+            ..Default::default()
+        }));
+        assert!(!search(SearchOptions {
+            purity: Some(Purity::default()),
+            ..Default::default()
+        }));
+        assert!(search(SearchOptions {
+            ratios: Some(HashSet::from([XYCombo { x: 21, y: 9 }])),
+            minimum_resolution: Some(XYCombo { x: 1920, y: 1080 }),
+            ..Default::default()
+        }));
+        assert!(!search(SearchOptions {
+            resolutions: Some(HashSet::from([XYCombo { x: 1920, y: 1080 }])),
+            ..Default::default()
+        }));
+    }
+}
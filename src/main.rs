@@ -1,14 +1,47 @@
-mod download_manager;
+mod autostart;
+mod banner;
+mod bundle;
+mod clipboard;
+mod crash;
+mod credentials;
+mod daily;
+mod download_view;
+mod duplicates;
+mod file_log;
+mod file_manager;
+mod folder;
 mod font_awesome;
 mod gui;
-mod settings;
+mod http;
+mod import;
+mod ipc;
+mod library;
+mod metered;
+mod monitors;
+mod onboarding;
+mod preview_download;
+mod purity_lock;
+mod quota;
+mod renderer;
+mod shortcuts;
 mod style;
 mod submenus;
-mod utils;
+mod system_theme;
+mod taskbar;
+mod thumbnails;
+mod tray;
+mod virtual_rows;
+mod wallpaper;
+mod watch;
 
-use crate::settings::SavedSettings;
-use gui::WallpaperUi;
-use iced::{window, Application, Settings, Size};
+use crate::renderer::{relaunch_in_software, select_renderer};
+use crate::thumbnails::prune_thumbnail_cache;
+use gui::{StartupFlags, WallpaperUi};
+use iced::{window, Application, Point, Settings, Size};
+use log::error;
+use std::path::Path;
+use wallcore::download_manager::clean_partial_downloads;
+use wallcore::settings::{LogLevel, Renderer, SavedSettings, WindowGeometry};
 
 /// Hides the console that pops up when the Iced gui is started on Windows.
 #[cfg(windows)]
@@ -25,19 +58,138 @@ fn hide_console_window() {
     }
 }
 
+/// Passes the command to the app when it's already running and brings up its window, so
+/// there's never a second download queue competing with the first. False when it isn't running.
+fn hand_to_running_app(command: Option<&ipc::IpcCommand>, minimized: bool) -> bool {
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the runtime");
+    runtime.block_on(async {
+        match (command, minimized) {
+            (Some(command), _) => ipc::send(command).await.is_ok(),
+            // started again at login, the window stays where it is
+            (None, true) => ipc::is_running().await,
+            (None, false) => ipc::send(&ipc::IpcCommand::Open).await.is_ok(),
+        }
+    })
+}
+
 fn main() {
-    pretty_env_logger::init();
+    file_log::init();
+    crash::install_panic_hook();
     #[cfg(windows)]
     hide_console_window();
-    WallpaperUi::run(Settings {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--send") {
+        // `--send search cats` drives the running app, for keybindings and scripts
+        let result = args[index + 1..]
+            .join(" ")
+            .parse::<ipc::IpcCommand>()
+            .and_then(|command| {
+                tokio::runtime::Runtime::new()
+                    .expect("Failed to start the runtime")
+                    .block_on(ipc::send(&command))
+            });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let minimized = args.iter().any(|arg| arg == "--minimized");
+    // the arguments before any flags are a command, like `open j3m8y5` or `search cats`
+    let positional: Vec<&str> = args[1..]
+        .iter()
+        .map(String::as_str)
+        .take_while(|arg| !arg.starts_with("--"))
+        .collect();
+    let command = match positional.is_empty() {
+        true => None,
+        false => match positional.join(" ").parse::<ipc::IpcCommand>() {
+            Ok(command) if command.can_start_app() => Some(command),
+            Ok(_) => {
+                eprintln!(
+                    "{} needs wall-a-bunga to be running, use --send",
+                    positional[0]
+                );
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    // the watch daemon runs next to the window, everything else runs once
+    if !args.iter().any(|arg| arg == "--watch") && hand_to_running_app(command.as_ref(), minimized)
+    {
+        return;
+    }
+    let settings = SavedSettings::load_settings();
+    file_log::set_file_level(match &settings {
+        Ok(Some(settings)) => settings.log_level,
+        _ => LogLevel::default(),
+    });
+    let settings = settings
+        .map(|settings| settings.map(credentials::load_keys))
+        .map_err(|e| {
+            error!("Failed to load settings {}", e);
+            e.to_string()
+        });
+    if args.iter().any(|arg| arg == "--watch") {
+        // headless, downloads what's new for the watched searches until it's killed
+        let settings = match settings {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(e) => {
+                // nobody would see the defaults being used instead
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        tokio::runtime::Runtime::new()
+            .expect("Failed to start the runtime")
+            .block_on(watch::run_daemon(settings));
+        return;
+    }
+    let loaded = settings.as_ref().ok().and_then(Option::as_ref);
+    let geometry = loaded.and_then(|s| s.window).unwrap_or_default();
+    let save_directory = loaded
+        .and_then(|s| s.save_directory.clone())
+        .unwrap_or_else(|| "./".to_string());
+    crash::offer_last_report();
+    clean_partial_downloads(Path::new(&save_directory));
+    prune_thumbnail_cache();
+    let renderer = select_renderer(&args, loaded.map(|s| s.renderer).unwrap_or_default());
+    let result = WallpaperUi::run(Settings {
         window: window::Settings {
-            size: Size::new(1800.0, 800.0),
-            min_size: None,
+            size: Size::new(
+                geometry.width.max(WindowGeometry::MIN_WIDTH) as f32,
+                geometry.height.max(WindowGeometry::MIN_HEIGHT) as f32,
+            ),
+            position: geometry
+                .position
+                .map(|(x, y)| window::Position::Specific(Point::new(x as f32, y as f32)))
+                .unwrap_or_default(),
+            min_size: Some(Size::new(
+                WindowGeometry::MIN_WIDTH as f32,
+                WindowGeometry::MIN_HEIGHT as f32,
+            )),
             max_size: None,
+            // closing may only minimize to the tray, see WallpaperMessage::CloseRequested
+            exit_on_close_request: false,
+            // started at login, the tray brings the window back
+            visible: !(minimized && tray::SUPPORTED),
             ..Default::default()
         },
-        flags: SavedSettings::load_settings(),
-        ..Default::default()
-    })
-    .expect("Failed to launch UI");
+        ..Settings::with_flags(StartupFlags { settings, command })
+    });
+    match result {
+        Err(iced::Error::GraphicsCreationFailed(e)) if renderer != Renderer::Software => {
+            error!(
+                "Failed to set up the graphics card, starting again in software {:?}",
+                e
+            );
+            let status = relaunch_in_software(&args).expect("Failed to launch UI");
+            std::process::exit(status.code().unwrap_or(1));
+        }
+        result => result.expect("Failed to launch UI"),
+    }
 }
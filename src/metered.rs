@@ -0,0 +1,59 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::system_theme::command_output;
+use log::debug;
+
+/// Reads `busctl get-property` of NetworkManager's `Metered`, like `u 4`. The values are 0 for
+/// unknown, 1 for yes, 2 for no, 3 for guessed yes and 4 for guessed no.
+/// https://networkmanager.dev/docs/api/latest/nm-dbus-types.html#NMMetered
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_metered(output: &str) -> Option<bool> {
+    match output.split_whitespace().nth(1)? {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn platform_metered() -> Option<bool> {
+    let output = command_output(
+        "busctl",
+        &[
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ],
+    )
+    .await;
+    output.as_deref().and_then(parse_metered)
+}
+
+/// Windows and macOS only tell through their network APIs, which the app doesn't use yet
+#[cfg(any(windows, target_os = "macos"))]
+async fn platform_metered() -> Option<bool> {
+    None
+}
+
+/// Whether the network connection is metered, None when it can't be told
+pub(crate) async fn connection_metered() -> Option<bool> {
+    let metered = platform_metered().await;
+    debug!("Connection metered {:?}", metered);
+    metered
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn parse_network_manager_metered() {
+        use crate::metered::parse_metered;
+
+        assert_eq!(parse_metered("u 1\n"), Some(true));
+        assert_eq!(parse_metered("u 3\n"), Some(true));
+        assert_eq!(parse_metered("u 4\n"), Some(false));
+        assert_eq!(parse_metered("u 0\n"), None);
+        assert_eq!(parse_metered(""), None);
+    }
+}
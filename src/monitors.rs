@@ -0,0 +1,263 @@
+use log::info;
+use wallapi::types::{XYCombo, ASPECT_RATIOS};
+
+/// A connected monitor
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Monitor {
+    /// Name the platform knows the monitor by, like `HDMI-1`, used to set its wallpaper
+    pub(crate) name: String,
+    pub(crate) resolution: XYCombo,
+}
+
+/// Reads the monitors out of `xrandr --query`, which lists connected outputs like
+/// `HDMI-1 connected primary 1920x1080+0+0 (normal left inverted right) 527mm x 296mm`, the
+/// primary one comes first
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_xrandr(output: &str) -> Vec<Monitor> {
+    let mut monitors: Vec<(bool, Monitor)> = output
+        .lines()
+        .filter(|line| line.contains(" connected"))
+        .filter_map(|line| {
+            let name = line.split_whitespace().next()?;
+            // outputs that are connected but turned off have no geometry
+            let geometry = line
+                .split_whitespace()
+                .find(|word| word.contains('x') && word.contains('+'))?;
+            let resolution = geometry.split('+').next()?.parse().ok()?;
+            let primary = line.split_whitespace().any(|word| word == "primary");
+            Some((
+                primary,
+                Monitor {
+                    name: name.to_string(),
+                    resolution,
+                },
+            ))
+        })
+        .collect();
+    monitors.sort_by_key(|(primary, _)| !primary);
+    monitors.into_iter().map(|(_, monitor)| monitor).collect()
+}
+
+/// Reads the active outputs out of `swaymsg -t get_outputs --raw`
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_sway_outputs(output: &str) -> Vec<Monitor> {
+    let outputs: Vec<serde_json::Value> = serde_json::from_str(output).unwrap_or_default();
+    outputs
+        .iter()
+        .filter(|output| output["active"].as_bool().unwrap_or(false))
+        .filter_map(|output| {
+            let mode = &output["current_mode"];
+            Some(Monitor {
+                name: output["name"].as_str()?.to_string(),
+                resolution: XYCombo {
+                    x: mode["width"].as_i64()? as i32,
+                    y: mode["height"].as_i64()? as i32,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Reads the displays out of `system_profiler SPDisplaysDataType`, which has a line like
+/// `Resolution: 2880 x 1800 Retina` for each of them
+#[cfg(target_os = "macos")]
+fn parse_system_profiler(output: &str) -> Vec<Monitor> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Resolution:"))
+        .filter_map(|resolution| {
+            let mut numbers = resolution
+                .split_whitespace()
+                .filter_map(|word| word.parse::<i32>().ok());
+            Some(XYCombo {
+                x: numbers.next()?,
+                y: numbers.next()?,
+            })
+        })
+        .enumerate()
+        .map(|(index, resolution)| Monitor {
+            // osascript numbers desktops from 1
+            name: (index + 1).to_string(),
+            resolution,
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    match tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Ok(output) => {
+            log::error!(
+                "{} failed {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            log::error!("Failed to run {} {:?}", program, e);
+            None
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn platform_monitors() -> Vec<Monitor> {
+    if std::env::var_os("SWAYSOCK").is_some() {
+        if let Some(output) = command_output("swaymsg", &["-t", "get_outputs", "--raw"]).await {
+            return parse_sway_outputs(&output);
+        }
+    }
+    command_output("xrandr", &["--query"])
+        .await
+        .map(|output| parse_xrandr(&output))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+async fn platform_monitors() -> Vec<Monitor> {
+    command_output("system_profiler", &["SPDisplaysDataType"])
+        .await
+        .map(|output| parse_system_profiler(&output))
+        .unwrap_or_default()
+}
+
+#[cfg(windows)]
+async fn platform_monitors() -> Vec<Monitor> {
+    use std::mem;
+    use std::ptr;
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::{HDC, HMONITOR, LPRECT};
+    use winapi::um::winuser::{
+        EnumDisplayMonitors, GetMonitorInfoW, MONITORINFOEXW, MONITORINFOF_PRIMARY,
+    };
+
+    unsafe extern "system" fn collect(
+        monitor: HMONITOR,
+        _: HDC,
+        _: LPRECT,
+        monitors: LPARAM,
+    ) -> BOOL {
+        let monitors = &mut *(monitors as *mut Vec<Monitor>);
+        let mut info: MONITORINFOEXW = mem::zeroed();
+        info.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut _) != 0 {
+            let rect = info.rcMonitor;
+            let name_length = info
+                .szDevice
+                .iter()
+                .position(|c| *c == 0)
+                .unwrap_or(info.szDevice.len());
+            let monitor = Monitor {
+                name: String::from_utf16_lossy(&info.szDevice[..name_length]),
+                resolution: XYCombo {
+                    x: rect.right - rect.left,
+                    y: rect.bottom - rect.top,
+                },
+            };
+            match info.dwFlags & MONITORINFOF_PRIMARY {
+                0 => monitors.push(monitor),
+                _ => monitors.insert(0, monitor),
+            }
+        }
+        TRUE
+    }
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+    // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumdisplaymonitors
+    unsafe {
+        EnumDisplayMonitors(
+            ptr::null_mut(),
+            ptr::null(),
+            Some(collect),
+            &mut monitors as *mut Vec<Monitor> as LPARAM,
+        );
+    }
+    monitors
+}
+
+/// Finds the connected monitors and their resolutions, empty when the platform won't say. The
+/// primary monitor is first when the platform says which one it is.
+pub(crate) async fn detect_monitors() -> Vec<Monitor> {
+    let monitors = platform_monitors().await;
+    info!("Detected monitors {:?}", monitors);
+    monitors
+}
+
+/// The common aspect ratio closest to a screen's, None when none is within 5% of it
+pub(crate) fn closest_aspect_ratio(resolution: XYCombo) -> Option<XYCombo> {
+    if resolution.x <= 0 || resolution.y <= 0 {
+        return None;
+    }
+    let ratio = |combo: &XYCombo| combo.x as f32 / combo.y as f32;
+    let screen = ratio(&resolution);
+    let difference = |combo: &XYCombo| (ratio(combo) - screen).abs() / screen;
+    ASPECT_RATIOS
+        .iter()
+        .min_by(|a, b| difference(a).total_cmp(&difference(b)))
+        .filter(|closest| difference(closest) < 0.05)
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn parse_linux_monitors() {
+        use crate::monitors::{parse_sway_outputs, parse_xrandr, Monitor};
+        use wallapi::types::XYCombo;
+
+        let xrandr = "Screen 0: minimum 320 x 200, current 4480 x 1440, maximum 16384 x 16384
+DP-1 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+   2560x1440     59.95*+
+HDMI-1 connected 1920x1080+2560+0 (normal left inverted right x axis y axis) 527mm x 296mm
+HDMI-2 disconnected (normal left inverted right x axis y axis)
+DP-2 connected (normal left inverted right x axis y axis)";
+        assert_eq!(
+            parse_xrandr(xrandr),
+            [
+                Monitor {
+                    name: "DP-1".to_string(),
+                    resolution: XYCombo { x: 2560, y: 1440 },
+                },
+                Monitor {
+                    name: "HDMI-1".to_string(),
+                    resolution: XYCombo { x: 1920, y: 1080 },
+                },
+            ]
+        );
+        let sway = r#"[{"name": "eDP-1", "active": true, "current_mode": {"width": 2256, "height": 1504}},
+            {"name": "DP-3", "active": false, "current_mode": {"width": 0, "height": 0}}]"#;
+        assert_eq!(
+            parse_sway_outputs(sway),
+            [Monitor {
+                name: "eDP-1".to_string(),
+                resolution: XYCombo { x: 2256, y: 1504 },
+            }]
+        );
+        let primary_second = "HDMI-1 connected 1920x1080+2560+0 (normal) 527mm x 296mm
+DP-1 connected primary 2560x1440+0+0 (normal) 597mm x 336mm";
+        assert_eq!(parse_xrandr(primary_second)[0].name, "DP-1");
+    }
+
+    #[test]
+    fn suggests_aspect_ratios() {
+        use crate::monitors::closest_aspect_ratio;
+        use wallapi::types::XYCombo;
+
+        let ratio = |x, y| closest_aspect_ratio(XYCombo { x, y });
+        assert_eq!(ratio(1920, 1080), Some(XYCombo { x: 16, y: 9 }));
+        assert_eq!(ratio(1366, 768), Some(XYCombo { x: 16, y: 9 }));
+        assert_eq!(ratio(3440, 1440), Some(XYCombo { x: 21, y: 9 }));
+        assert_eq!(ratio(2256, 1504), Some(XYCombo { x: 3, y: 2 }));
+        assert_eq!(ratio(2000, 700), None);
+        assert_eq!(ratio(0, 0), None);
+    }
+}
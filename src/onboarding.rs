@@ -0,0 +1,64 @@
+/// Steps of the wizard shown on the first launch, before anything is searched for
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum OnboardingStep {
+    #[default]
+    SaveDirectory,
+    ApiKey,
+    Resolution,
+    Content,
+}
+
+impl OnboardingStep {
+    pub(crate) const LIST: [OnboardingStep; 4] = [
+        OnboardingStep::SaveDirectory,
+        OnboardingStep::ApiKey,
+        OnboardingStep::Resolution,
+        OnboardingStep::Content,
+    ];
+
+    /// Counted from 1
+    pub(crate) fn number(self) -> usize {
+        OnboardingStep::LIST
+            .iter()
+            .position(|step| *step == self)
+            .unwrap_or_default()
+            + 1
+    }
+
+    pub(crate) fn next(self) -> Option<Self> {
+        OnboardingStep::LIST.get(self.number()).copied()
+    }
+
+    pub(crate) fn previous(self) -> Option<Self> {
+        OnboardingStep::LIST
+            .get(self.number().checked_sub(2)?)
+            .copied()
+    }
+
+    pub(crate) fn title(self) -> &'static str {
+        match self {
+            OnboardingStep::SaveDirectory => "Where should wallpapers be saved?",
+            OnboardingStep::ApiKey => "Do you have a wallhaven.cc api key?",
+            OnboardingStep::Resolution => "What resolution is your screen?",
+            OnboardingStep::Content => "What would you like to see?",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::onboarding::OnboardingStep;
+
+    #[test]
+    fn walks_through_steps() {
+        let first = OnboardingStep::default();
+        assert_eq!(first.number(), 1);
+        assert_eq!(first.previous(), None);
+        assert_eq!(first.next(), Some(OnboardingStep::ApiKey));
+        assert_eq!(
+            OnboardingStep::Content.previous(),
+            Some(OnboardingStep::Resolution)
+        );
+        assert_eq!(OnboardingStep::Content.next(), None);
+    }
+}
@@ -0,0 +1,227 @@
+use iced::futures::stream::BoxStream;
+use iced::widget::image;
+use iced_futures::subscription::{EventStream, Recipe};
+use image_rs::imageops::FilterType;
+use image_rs::DynamicImage;
+use log::{debug, error, info, warn};
+use reqwest::Response;
+use tokio::task::spawn_blocking;
+
+/// Streams a full size image for the preview, reporting progress along the way.
+/// Dropping the subscription cancels the download.
+#[derive(Debug, Clone)]
+pub(crate) struct PreviewDownload {
+    pub(crate) client: reqwest::Client,
+    /// URL of the full size image
+    pub(crate) url: String,
+    /// Preview request this download belongs to, so late messages from a previous preview
+    /// can be told apart
+    pub(crate) request: u64,
+    /// Width and height of the window, larger images are shown scaled down to it first
+    pub(crate) fit: (u32, u32),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum PreviewStatus {
+    Progress {
+        request: u64,
+        downloaded: u64,
+        /// Not every server tells us the size up front
+        total: Option<u64>,
+    },
+    Finished(u64, image::Handle),
+    /// Scaled down to the window, the full resolution follows with [PreviewStatus::Finished]
+    /// once it's converted
+    Scaled(u64, image::Handle),
+    Failed(u64),
+}
+
+enum PreviewState {
+    Started,
+    Downloading {
+        response: Box<Response>,
+        bytes: Vec<u8>,
+        total: Option<u64>,
+    },
+    /// The scaled down preview is shown, the full resolution is converted for zooming in
+    Scaled(DynamicImage),
+    Completed,
+}
+
+fn to_handle(image: &DynamicImage) -> image::Handle {
+    let pixels = image.to_rgba8();
+    image::Handle::from_pixels(pixels.width(), pixels.height(), pixels.into_raw())
+}
+
+impl PreviewDownload {
+    /// Converts the image ourselves, falling back to letting Iced decode it. Images larger than
+    /// `fit` come back scaled down to it along with the decoded image, so the full resolution
+    /// can be converted after the scaled one is shown.
+    async fn decode(
+        bytes: Vec<u8>,
+        fit: (u32, u32),
+    ) -> Option<(image::Handle, Option<DynamicImage>)> {
+        spawn_blocking(move || match image_rs::load_from_memory(&bytes) {
+            Ok(image) if image.width() > fit.0 || image.height() > fit.1 => {
+                let scaled = image.resize(fit.0, fit.1, FilterType::Triangle);
+                (to_handle(&scaled), Some(image))
+            }
+            Ok(image) => (to_handle(&image), None),
+            Err(_) => {
+                warn!("Failed to convert image ourselves, letting Iced try.");
+                (image::Handle::from_memory(bytes), None)
+            }
+        })
+        .await
+        .ok()
+    }
+}
+
+/// Fetches wallhaven's large thumbnail, shown in place of the preview while it downloads
+pub(crate) async fn fetch_large_thumbnail(
+    client: reqwest::Client,
+    url: String,
+) -> Option<image::Handle> {
+    let bytes = async {
+        client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    };
+    let bytes = match bytes.await {
+        Ok(bytes) => bytes.to_vec(),
+        Err(e) => {
+            debug!("Failed to fetch the large thumbnail {} {:?}", url, e);
+            return None;
+        }
+    };
+    spawn_blocking(move || image_rs::load_from_memory(&bytes).ok())
+        .await
+        .ok()
+        .flatten()
+        .map(|image| to_handle(&image))
+}
+
+impl Recipe for PreviewDownload {
+    type Output = PreviewStatus;
+
+    fn hash(&self, state: &mut iced_futures::core::Hasher) {
+        use std::hash::Hash;
+
+        std::any::TypeId::of::<Self>().hash(state);
+        self.url.hash(state);
+        self.request.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: EventStream) -> BoxStream<'static, Self::Output> {
+        let PreviewDownload {
+            client,
+            url,
+            request,
+            fit,
+        } = *self;
+        Box::pin(futures::stream::unfold(
+            PreviewState::Started,
+            move |state| {
+                let url = url.clone();
+                let client = client.clone();
+                async move {
+                    match state {
+                        PreviewState::Started => {
+                            info!("Downloading preview: {}", &url);
+                            match client.get(&url).send().await {
+                                Ok(response) => {
+                                    let total = response.content_length();
+                                    Some((
+                                        PreviewStatus::Progress {
+                                            request,
+                                            downloaded: 0,
+                                            total,
+                                        },
+                                        PreviewState::Downloading {
+                                            response: Box::new(response),
+                                            bytes: Vec::with_capacity(
+                                                total.unwrap_or_default() as usize
+                                            ),
+                                            total,
+                                        },
+                                    ))
+                                }
+                                Err(e) => {
+                                    error!("Failed to request preview {:?}", e);
+                                    Some((PreviewStatus::Failed(request), PreviewState::Completed))
+                                }
+                            }
+                        }
+                        PreviewState::Downloading {
+                            mut response,
+                            mut bytes,
+                            total,
+                        } => match response.chunk().await {
+                            Ok(Some(chunk)) => {
+                                debug!("Downloaded preview chunk {} bytes", chunk.len());
+                                bytes.extend_from_slice(&chunk);
+                                Some((
+                                    PreviewStatus::Progress {
+                                        request,
+                                        downloaded: bytes.len() as u64,
+                                        total,
+                                    },
+                                    PreviewState::Downloading {
+                                        response,
+                                        bytes,
+                                        total,
+                                    },
+                                ))
+                            }
+                            Ok(None) => match PreviewDownload::decode(bytes, fit).await {
+                                Some((handle, Some(image))) => {
+                                    info!("Scaled preview loaded");
+                                    Some((
+                                        PreviewStatus::Scaled(request, handle),
+                                        PreviewState::Scaled(image),
+                                    ))
+                                }
+                                Some((handle, None)) => {
+                                    info!("preview loaded!");
+                                    Some((
+                                        PreviewStatus::Finished(request, handle),
+                                        PreviewState::Completed,
+                                    ))
+                                }
+                                None => {
+                                    error!("failed to load preview");
+                                    Some((PreviewStatus::Failed(request), PreviewState::Completed))
+                                }
+                            },
+                            Err(e) => {
+                                error!("Failed to download preview {:?}", e);
+                                Some((PreviewStatus::Failed(request), PreviewState::Completed))
+                            }
+                        },
+                        PreviewState::Scaled(image) => {
+                            match spawn_blocking(move || to_handle(&image)).await {
+                                Ok(handle) => Some((
+                                    PreviewStatus::Finished(request, handle),
+                                    PreviewState::Completed,
+                                )),
+                                // the scaled preview stays
+                                Err(e) => {
+                                    error!("Failed to convert the full resolution {:?}", e);
+                                    None
+                                }
+                            }
+                        }
+                        PreviewState::Completed => {
+                            debug!("Closing preview download");
+                            None
+                        }
+                    }
+                }
+            },
+        ))
+    }
+}
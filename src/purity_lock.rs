@@ -0,0 +1,106 @@
+use argon2::Argon2;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use wallapi::types::{Purity, PurityLevel};
+use wallcore::settings::PurityPin;
+
+/// A PIN is only a few digits, so it's hashed with argon2, which is slow and memory hungry enough
+/// that trying every PIN against the settings file isn't instant
+fn hash(salt: &str, pin: &str) -> Result<String, argon2::Error> {
+    let mut hash = [0u8; 32];
+    Argon2::default().hash_password_into(pin.as_bytes(), salt.as_bytes(), &mut hash)?;
+    Ok(hash.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Hashes a new PIN with a salt of its own, surrounding spaces aren't part of it
+pub(crate) fn new_pin(pin: &str) -> Result<PurityPin, argon2::Error> {
+    let salt: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    Ok(PurityPin {
+        hash: hash(&salt, pin.trim())?,
+        salt,
+    })
+}
+
+pub(crate) fn pin_matches(pin: &PurityPin, entered: &str) -> bool {
+    hash(&pin.salt, entered.trim()).is_ok_and(|hash| hash == pin.hash)
+}
+
+/// Whether sketchy and nsfw are kept away. Every search and preview goes through it, so links,
+/// profiles and new tabs can't bring them back while the PIN isn't entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PurityLock {
+    pub(crate) locked: bool,
+}
+
+impl PurityLock {
+    /// The purity a search may use, sketchy and nsfw are left out while locked
+    pub(crate) fn allowed(self, purity: Option<Purity>) -> Option<Purity> {
+        match (self.locked, purity) {
+            (true, Some(mut purity)) => {
+                lock_purity(&mut purity);
+                Some(purity)
+            }
+            // wallhaven only sends clean wallpapers without a purity
+            (_, purity) => purity,
+        }
+    }
+
+    /// Whether a wallpaper of that purity may be shown
+    pub(crate) fn shows(self, purity: PurityLevel) -> bool {
+        !self.locked || purity == PurityLevel::Sfw
+    }
+}
+
+/// Turns sketchy and nsfw off, true when either was on
+pub(crate) fn lock_purity(purity: &mut Purity) -> bool {
+    let changed = purity.sketchy || purity.nsfw;
+    purity.sketchy = false;
+    purity.nsfw = false;
+    // wallhaven finds nothing without a purity, clean is all that's left
+    purity.clean |= changed;
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use crate::purity_lock::{lock_purity, new_pin, pin_matches, PurityLock};
+    use wallapi::types::{Purity, PurityLevel};
+
+    #[test]
+    fn checks_pins() {
+        let pin = new_pin(" 1234 ").unwrap();
+        assert!(pin_matches(&pin, "1234"));
+        assert!(!pin_matches(&pin, "4321"));
+        assert!(!pin_matches(&pin, ""));
+        // the same PIN hashes differently every time it's set
+        assert_ne!(new_pin("1234").unwrap().hash, pin.hash);
+
+        let mut purity = Purity {
+            clean: false,
+            sketchy: true,
+            nsfw: true,
+        };
+        assert!(lock_purity(&mut purity));
+        assert!(purity.clean && !purity.sketchy && !purity.nsfw);
+        assert!(!lock_purity(&mut purity));
+
+        let lock = PurityLock { locked: true };
+        let everything = Purity {
+            clean: true,
+            sketchy: true,
+            nsfw: true,
+        };
+        assert_eq!(
+            lock.allowed(Some(everything.clone())),
+            Some(Purity::default())
+        );
+        assert!(lock.shows(PurityLevel::Sfw) && !lock.shows(PurityLevel::Nsfw));
+        let unlocked = PurityLock { locked: false };
+        assert_eq!(unlocked.allowed(Some(everything.clone())), Some(everything));
+        assert!(unlocked.shows(PurityLevel::Sketchy));
+    }
+}
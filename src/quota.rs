@@ -0,0 +1,172 @@
+use crate::library::{Library, LibraryEntry};
+use log::{error, info, warn};
+use std::path::{Path, PathBuf};
+use tokio::task::spawn_blocking;
+use wallcore::settings::QuotaAction;
+
+/// How much of the disk quota the save directory uses, after any pruning
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct QuotaReport {
+    pub(crate) used: u64,
+    pub(crate) limit: u64,
+    /// Ids of the wallpapers that were deleted to get back under the limit
+    pub(crate) pruned: Vec<String>,
+}
+
+impl QuotaReport {
+    pub(crate) fn exceeded(&self) -> bool {
+        self.used > self.limit
+    }
+}
+
+/// Total size of the files directly inside the directory
+fn directory_size(directory: &Path) -> std::io::Result<u64> {
+    Ok(std::fs::read_dir(directory)?
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum())
+}
+
+/// Picks the wallpapers to delete to free up `excess` bytes, in the order the action prunes
+/// them. Protected wallpapers are never picked. `entries` are the oldest download first, along
+/// with their file size.
+fn prune_candidates(
+    mut entries: Vec<(LibraryEntry, u64)>,
+    excess: u64,
+    action: QuotaAction,
+) -> Vec<(LibraryEntry, u64)> {
+    entries.retain(|(entry, _)| !entry.protected);
+    if action == QuotaAction::PruneLeastFavorite {
+        // stable, so wallpapers with as many favorites stay oldest first. Imported ones have no
+        // listing to tell how popular they are, those go last rather than counting as unpopular.
+        entries.sort_by_key(|(entry, _)| match &entry.listing {
+            Some(listing) => (false, listing.favorites),
+            None => (true, 0),
+        });
+    }
+    let mut freed = 0;
+    entries
+        .into_iter()
+        .take_while(|(_, size)| {
+            let needed = freed < excess;
+            freed += size;
+            needed
+        })
+        .collect()
+}
+
+/// Measures the save directory and, when it's over the limit and the action allows it, deletes
+/// downloads until it fits again
+pub(crate) async fn enforce_quota(
+    library: Library,
+    directory: PathBuf,
+    limit: u64,
+    action: QuotaAction,
+) -> QuotaReport {
+    let result = spawn_blocking(move || {
+        let used = match directory_size(&directory) {
+            Ok(used) => used,
+            Err(e) => {
+                error!("Failed to measure {:?} {:?}", directory, e);
+                return QuotaReport {
+                    limit,
+                    ..Default::default()
+                };
+            }
+        };
+        let mut report = QuotaReport {
+            used,
+            limit,
+            pruned: vec![],
+        };
+        if !report.exceeded() || action == QuotaAction::Warn {
+            return report;
+        }
+        let entries = match library.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Failed to read the library {:?}", e);
+                return report;
+            }
+        };
+        // only files in the save directory count towards its usage
+        let entries = entries
+            .into_iter()
+            .filter(|entry| entry.path.starts_with(&directory))
+            .filter_map(|entry| {
+                let size = std::fs::metadata(&entry.path).ok()?.len();
+                Some((entry, size))
+            })
+            .collect();
+        for (entry, size) in prune_candidates(entries, used - limit, action) {
+            if let Err(e) = std::fs::remove_file(&entry.path) {
+                warn!("Failed to prune {:?} {:?}", entry.path, e);
+                continue;
+            }
+            info!("Pruned {:?} to stay under the disk quota", entry.path);
+            if let Err(e) = library.remove(&entry.id) {
+                error!("Failed to remove {} from the library {:?}", entry.id, e);
+            }
+            report.used = report.used.saturating_sub(size);
+            report.pruned.push(entry.id);
+        }
+        report
+    })
+    .await;
+    match result {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Quota task failed {:?}", e);
+            QuotaReport {
+                limit,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::library::LibraryEntry;
+    use crate::quota::prune_candidates;
+    use wallapi::types::ListingData;
+    use wallcore::settings::QuotaAction;
+
+    fn entry(id: &str, favorites: Option<i64>, protected: bool) -> (LibraryEntry, u64) {
+        let entry = LibraryEntry {
+            id: id.to_string(),
+            path: format!("wallhaven-{}.jpg", id).into(),
+            hash: None,
+            image_hash: None,
+            tags: vec![],
+            resolution: None,
+            listing: favorites.map(|favorites| ListingData {
+                favorites,
+                ..Default::default()
+            }),
+            protected,
+        };
+        (entry, 100)
+    }
+
+    #[test]
+    fn prunes_until_under_the_limit() {
+        let ids = |action| {
+            let entries = vec![
+                entry("imported", None, false),
+                entry("oldest", Some(50), false),
+                entry("kept", Some(0), true),
+                entry("popular", Some(90), false),
+                entry("newest", Some(10), false),
+            ];
+            prune_candidates(entries, 150, action)
+                .into_iter()
+                .map(|(entry, _)| entry.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(QuotaAction::PruneOldest), ["imported", "oldest"]);
+        assert_eq!(ids(QuotaAction::PruneLeastFavorite), ["newest", "oldest"]);
+    }
+}
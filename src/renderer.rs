@@ -0,0 +1,72 @@
+use log::info;
+use std::process::{Command, ExitStatus};
+use wallcore::settings::Renderer;
+
+/// Iced tries the backends named in this variable, in order
+const BACKEND_VARIABLE: &str = "ICED_BACKEND";
+
+/// Iced's name for the backend, None leaves iced to try the graphics card and then software
+fn backend(renderer: Renderer) -> Option<&'static str> {
+    match renderer {
+        Renderer::Auto => None,
+        Renderer::Gpu => Some("wgpu"),
+        Renderer::Software => Some("tiny-skia"),
+    }
+}
+
+/// Value of `--renderer`, like `--renderer software`
+fn renderer_flag(args: &[String]) -> Option<Renderer> {
+    let index = args.iter().position(|arg| arg == "--renderer")?;
+    Renderer::from_flag(args.get(index + 1)?)
+}
+
+/// Picks the renderer from `--renderer`, or from the settings without it. A backend that's
+/// already set in the environment is left alone.
+pub(crate) fn select_renderer(args: &[String], saved: Renderer) -> Renderer {
+    let renderer = renderer_flag(args).unwrap_or(saved);
+    if std::env::var_os(BACKEND_VARIABLE).is_none() {
+        if let Some(backend) = backend(renderer) {
+            info!("Rendering with {}", backend);
+            std::env::set_var(BACKEND_VARIABLE, backend);
+        }
+    }
+    renderer
+}
+
+/// Arguments to start again with, the program and any `--renderer` replaced by software
+fn software_args(args: &[String]) -> Vec<String> {
+    let mut args: Vec<_> = args.iter().skip(1).cloned().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--renderer") {
+        args.drain(index..(index + 2).min(args.len()));
+    }
+    args.extend(["--renderer".to_string(), "software".to_string()]);
+    args
+}
+
+/// Starts the app again drawing in software, for when the graphics card couldn't be set up.
+/// A window can only be opened once per process, so it's a new process.
+pub(crate) fn relaunch_in_software(args: &[String]) -> std::io::Result<ExitStatus> {
+    Command::new(std::env::current_exe()?)
+        .args(software_args(args))
+        .env(BACKEND_VARIABLE, "tiny-skia")
+        .status()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::renderer::{renderer_flag, software_args};
+    use wallcore::settings::Renderer;
+
+    #[test]
+    fn relaunches_with_software_renderer() {
+        let args: Vec<String> = ["wall-a-bunga", "--renderer", "gpu", "--minimized"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(renderer_flag(&args), Some(Renderer::Gpu));
+        assert_eq!(renderer_flag(&args[..2]), None);
+        assert_eq!(
+            software_args(&args),
+            ["--minimized", "--renderer", "software"]
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::gui::{FocusDirection, PreviewZoom, SelectionUpdateType, WallpaperMessage};
+use iced::keyboard::{self, KeyCode};
+use iced::Subscription;
+
+/// Application wide keyboard shortcuts.
+/// Keys captured by a widget first, such as typing in the search box, never reach this.
+pub(crate) fn keyboard_shortcuts() -> Subscription<WallpaperMessage> {
+    keyboard::on_key_press(|key_code, modifiers| match key_code {
+        KeyCode::Left => Some(WallpaperMessage::MoveFocus(FocusDirection::Left)),
+        KeyCode::Right => Some(WallpaperMessage::MoveFocus(FocusDirection::Right)),
+        KeyCode::Up => Some(WallpaperMessage::MoveFocus(FocusDirection::Up)),
+        KeyCode::Down => Some(WallpaperMessage::MoveFocus(FocusDirection::Down)),
+        KeyCode::Space => Some(WallpaperMessage::ToggleFocusedSelection()),
+        KeyCode::Enter => Some(WallpaperMessage::PreviewFocused()),
+        KeyCode::Escape => Some(WallpaperMessage::Escape()),
+        KeyCode::Tab => Some(WallpaperMessage::FocusInput(!modifiers.shift())),
+        // zoom keys only do something while previewing
+        KeyCode::Plus | KeyCode::Equals | KeyCode::NumpadAdd => Some(WallpaperMessage::ZoomIn()),
+        KeyCode::Minus | KeyCode::NumpadSubtract => Some(WallpaperMessage::ZoomOut()),
+        KeyCode::Key0 | KeyCode::Numpad0 => {
+            Some(WallpaperMessage::SetPreviewZoom(PreviewZoom::Scale(1.0)))
+        }
+        KeyCode::F => Some(WallpaperMessage::SetPreviewZoom(PreviewZoom::Fit)),
+        KeyCode::A if modifiers.command() => Some(WallpaperMessage::SelectionUpdate(
+            SelectionUpdateType::SelectAll,
+        )),
+        KeyCode::D if modifiers.command() => Some(WallpaperMessage::DownloadImages()),
+        KeyCode::Z if modifiers.command() && modifiers.shift() => Some(WallpaperMessage::Redo()),
+        KeyCode::Z if modifiers.command() => Some(WallpaperMessage::Undo()),
+        _ => None,
+    })
+}
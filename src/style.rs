@@ -3,16 +3,69 @@ use crate::gui::WallpaperMessage;
 use font_awesome_as_a_crate::Type;
 use iced::{
     theme,
-    widget::{Button, Row, Space, Text},
-    Length,
+    widget::{tooltip, Button, Container, Row, Space, Text, Tooltip},
+    Color, Element, Length,
 };
+use std::sync::RwLock;
+use wallcore::settings::Density;
+
+/// Orange the app is known for, the main buttons are in it unless another accent is picked
+pub(crate) const DEFAULT_ACCENT: Color = Color::from_rgb(0.87, 0.42, 0.11);
+/// Results that are still downloading
+pub(crate) const PENDING: Color = Color::from_rgb(0.992, 0.992, 0.588);
+
+/// Accent and density picked in the settings
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Appearance {
+    accent: Color,
+    density: Density,
+}
+
+/// The style sheets and button helpers are used all over the view without access to the
+/// settings, so the app keeps this in step with them
+static APPEARANCE: RwLock<Appearance> = RwLock::new(Appearance {
+    accent: DEFAULT_ACCENT,
+    density: Density::Comfortable,
+});
+
+/// Parses colors like `#de6b1c` or `de6b1c`
+pub(crate) fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+    Some(Color::from_rgb8(
+        channel(0..2)?,
+        channel(2..4)?,
+        channel(4..6)?,
+    ))
+}
+
+/// Applies the accent color and density from the settings, an accent that doesn't parse is the
+/// default one
+pub(crate) fn set_appearance(accent: Option<&str>, density: Density) {
+    let mut appearance = APPEARANCE.write().unwrap_or_else(|e| e.into_inner());
+    *appearance = Appearance {
+        accent: accent.and_then(parse_hex_color).unwrap_or(DEFAULT_ACCENT),
+        density,
+    };
+}
+
+pub(crate) fn accent() -> Color {
+    APPEARANCE.read().unwrap_or_else(|e| e.into_inner()).accent
+}
+
+pub(crate) fn density() -> Density {
+    APPEARANCE.read().unwrap_or_else(|e| e.into_inner()).density
+}
 
 pub(crate) fn make_button(
     // state: &'a mut button::State,
     text: &str,
 ) -> Button<'_, WallpaperMessage> {
     Button::new(Text::new(text).size(21))
-        .padding(10)
+        .padding(density().padding())
         .style(theme::Button::custom(button_style::Button::Primary))
         .height(Length::Shrink)
         .width(Length::Shrink)
@@ -35,12 +88,40 @@ pub(crate) fn make_button_fa<'a>(
                     .width(Length::Fixed(21.0)),
             ),
     )
-    .padding(10)
+    .padding(density().padding())
     .style(theme::Button::custom(button_style::Button::Primary))
     .height(Length::Shrink)
     .width(Length::Shrink)
 }
 
+pub(crate) fn make_badge(text: &str, color: Color) -> Container<'_, WallpaperMessage> {
+    Container::new(Text::new(text).size(14))
+        .padding([2, 6])
+        .style(theme::Container::Custom(Box::new(container_style::Badge(
+            color,
+        ))))
+}
+
+/// Names a control that only shows an icon, the name shows up when it's hovered
+pub(crate) fn labeled<'a>(
+    control: impl Into<Element<'a, WallpaperMessage>>,
+    label: &'a str,
+) -> Tooltip<'a, WallpaperMessage> {
+    Tooltip::new(
+        control,
+        Text::new(label).size(14),
+        tooltip::Position::Bottom,
+    )
+    .gap(4)
+    .padding(6)
+    .style(theme::Container::Box)
+}
+
+/// Text inputs get an outline in the accent color while they have the keyboard focus
+pub(crate) fn focus_outline() -> theme::TextInput {
+    theme::TextInput::Custom(Box::new(text_input_style::TextInput))
+}
+
 pub(crate) fn inactive_style(btn: bool) -> theme::Button {
     let custom_style = match btn {
         true => button_style::Button::Primary,
@@ -50,6 +131,7 @@ pub(crate) fn inactive_style(btn: bool) -> theme::Button {
 }
 
 pub mod pick_style {
+    use crate::style::accent;
     use iced::{overlay::menu, widget::pick_list, Background, BorderRadius, Color, Theme};
 
     #[derive(Default, Clone)]
@@ -58,13 +140,14 @@ pub mod pick_style {
     impl menu::StyleSheet for PickList {
         type Style = Theme;
 
-        fn appearance(&self, _style: &Self::Style) -> menu::Appearance {
+        fn appearance(&self, style: &Self::Style) -> menu::Appearance {
+            let palette = style.extended_palette();
             menu::Appearance {
-                text_color: Color::WHITE,
-                background: Background::Color(Color::from_rgb(0.3, 0.3, 0.3)),
+                text_color: palette.background.base.text,
+                background: Background::Color(palette.background.weak.color),
                 border_width: 1.0,
-                border_color: Color::from_rgb(0.3, 0.3, 0.3),
-                selected_background: Color::from_rgb(0.3, 0.3, 0.3).into(),
+                border_color: palette.background.weak.color,
+                selected_background: accent().into(),
                 selected_text_color: Color::WHITE,
                 border_radius: BorderRadius::from([1.0, 1.0, 1.0, 1.0]),
             }
@@ -72,19 +155,20 @@ pub mod pick_style {
     }
 
     impl pick_list::StyleSheet for PickList {
-        fn active(&self, _style: &Self::Style) -> pick_list::Appearance {
+        fn active(&self, style: &Self::Style) -> pick_list::Appearance {
+            let palette = style.extended_palette();
             pick_list::Appearance {
-                text_color: Color::WHITE,
-                placeholder_color: Color::WHITE,
-                background: Color::from_rgb(0.3, 0.3, 0.3).into(),
+                text_color: palette.background.weak.text,
+                placeholder_color: palette.background.weak.text,
+                background: palette.background.weak.color.into(),
                 border_width: 1.0,
                 border_color: Color {
                     a: 0.6,
-                    ..Color::BLACK
+                    ..palette.background.strong.color
                 },
                 border_radius: 10.0.into(),
                 // icon_size: 0.5,
-                handle_color: Color::from_rgb(0.6, 0.2, 0.1),
+                handle_color: accent(),
             }
         }
 
@@ -92,10 +176,7 @@ pub mod pick_style {
             let active = self.active(style);
 
             pick_list::Appearance {
-                border_color: Color {
-                    a: 0.9,
-                    ..Color::BLACK
-                },
+                border_color: style.extended_palette().background.strong.color,
                 ..active
             }
         }
@@ -105,10 +186,12 @@ pub mod pick_style {
 }
 
 pub mod button_style {
+    use crate::style::{accent, PENDING};
     use iced::{widget::button, Background, Color, Theme, Vector};
 
-    #[derive(Copy, Clone)]
+    #[derive(Copy, Clone, Default)]
     pub enum Button {
+        #[default]
         Primary,
         Downloaded,
         Inactive,
@@ -116,21 +199,16 @@ pub mod button_style {
         Failed,
     }
 
-    impl Default for Button {
-        fn default() -> Self {
-            Self::Primary
-        }
-    }
-
     impl button::StyleSheet for Button {
-        fn active(&self, _style: &Self::Style) -> button::Appearance {
+        fn active(&self, style: &Self::Style) -> button::Appearance {
+            let palette = style.extended_palette();
             button::Appearance {
                 background: Some(Background::Color(match self {
-                    Button::Primary => Color::from_rgb(0.87, 0.42, 0.11),
-                    Button::Downloaded => Color::from_rgb(0.467, 0.867, 0.467),
-                    Button::Inactive => Color::from_rgb(0.3, 0.3, 0.3),
-                    Button::Downloading => Color::from_rgb(0.992, 0.992, 0.588),
-                    Button::Failed => Color::from_rgb(1.0, 0.0, 0.0),
+                    Button::Primary => accent(),
+                    Button::Downloaded => palette.success.base.color,
+                    Button::Inactive => palette.background.strong.color,
+                    Button::Downloading => PENDING,
+                    Button::Failed => palette.danger.base.color,
                 })),
                 border_radius: 12.0.into(),
                 shadow_offset: Vector::new(1.0, 1.0),
@@ -142,3 +220,134 @@ pub mod button_style {
         type Style = Theme;
     }
 }
+
+pub mod container_style {
+    use crate::banner::Severity;
+    use crate::style::PENDING;
+    use iced::{widget::container, Background, Color, Theme};
+
+    /// Outline drawn around the result that has keyboard focus
+    pub struct Focused;
+
+    impl container::StyleSheet for Focused {
+        type Style = Theme;
+
+        fn appearance(&self, style: &Self::Style) -> container::Appearance {
+            container::Appearance {
+                border_width: 3.0,
+                border_color: style.palette().text,
+                border_radius: 12.0.into(),
+                ..container::Appearance::default()
+            }
+        }
+    }
+
+    /// Small colored label, such as the purity of a wallpaper
+    pub struct Badge(pub Color);
+
+    impl container::StyleSheet for Badge {
+        type Style = Theme;
+
+        fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+            container::Appearance {
+                text_color: Some(Color::BLACK),
+                background: Some(Background::Color(self.0)),
+                border_radius: 6.0.into(),
+                ..container::Appearance::default()
+            }
+        }
+    }
+
+    /// Background of a banner, colored by how bad it is
+    pub struct Banner(pub Severity);
+
+    impl container::StyleSheet for Banner {
+        type Style = Theme;
+
+        fn appearance(&self, style: &Self::Style) -> container::Appearance {
+            let (background, text_color) = match self.0 {
+                Severity::Info => {
+                    let primary = style.extended_palette().primary.weak;
+                    (primary.color, primary.text)
+                }
+                Severity::Warning => (PENDING, Color::BLACK),
+                Severity::Error => (style.extended_palette().danger.base.color, Color::WHITE),
+            };
+            container::Appearance {
+                text_color: Some(text_color),
+                background: Some(Background::Color(background)),
+                border_radius: 6.0.into(),
+                ..container::Appearance::default()
+            }
+        }
+    }
+}
+
+pub mod text_input_style {
+    use crate::style::accent;
+    use iced::{theme, widget::text_input, Color, Theme};
+
+    /// The theme's look with a thicker outline while focused, so it's easy to find where typing
+    /// goes after moving the focus with the keyboard
+    pub struct TextInput;
+
+    impl text_input::StyleSheet for TextInput {
+        type Style = Theme;
+
+        fn active(&self, style: &Self::Style) -> text_input::Appearance {
+            style.active(&theme::TextInput::Default)
+        }
+
+        fn focused(&self, style: &Self::Style) -> text_input::Appearance {
+            text_input::Appearance {
+                border_width: 2.0,
+                border_color: accent(),
+                ..style.focused(&theme::TextInput::Default)
+            }
+        }
+
+        fn placeholder_color(&self, style: &Self::Style) -> Color {
+            style.placeholder_color(&theme::TextInput::Default)
+        }
+
+        fn value_color(&self, style: &Self::Style) -> Color {
+            style.value_color(&theme::TextInput::Default)
+        }
+
+        fn disabled_color(&self, style: &Self::Style) -> Color {
+            style.disabled_color(&theme::TextInput::Default)
+        }
+
+        fn selection_color(&self, style: &Self::Style) -> Color {
+            style.selection_color(&theme::TextInput::Default)
+        }
+
+        fn hovered(&self, style: &Self::Style) -> text_input::Appearance {
+            style.hovered(&theme::TextInput::Default)
+        }
+
+        fn disabled(&self, style: &Self::Style) -> text_input::Appearance {
+            style.disabled(&theme::TextInput::Default)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::style::parse_hex_color;
+    use iced::Color;
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(
+            parse_hex_color("#ff8000"),
+            Some(Color::from_rgb8(255, 128, 0))
+        );
+        assert_eq!(
+            parse_hex_color(" 00FF00 "),
+            Some(Color::from_rgb8(0, 255, 0))
+        );
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+    }
+}
@@ -1,7 +1,117 @@
+use crate::gui::WallpaperMessage;
+use crate::style::{focus_outline, inactive_style, labeled, make_button, make_button_fa};
+use iced::widget::{Row, Text, TextInput};
+use iced::{Alignment, Length};
+use wallapi::types::XYCombo;
+
 pub(crate) mod ratio_menu;
 pub(crate) mod resolution_menu;
+pub(crate) mod selection_menu;
 
 fn calculate_aspect_ratio(x: i32, y: i32) -> (i32, i32) {
     let gcd = num::integer::gcd(y, x);
     (x / gcd, y / gcd)
 }
+
+/// Which submenu a custom entry belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ComboKind {
+    Resolution,
+    AspectRatio,
+}
+
+/// Parses a resolution or aspect ratio typed in like `5120x1440` or `32:10`
+pub(crate) fn parse_combo(input: &str) -> Option<XYCombo> {
+    let (x, y) = input.trim().split_once(['x', 'X', ':', '×'])?;
+    let combo = XYCombo {
+        x: x.trim().parse().ok()?,
+        y: y.trim().parse().ok()?,
+    };
+    let valid = |value: i32| (1..=65535).contains(&value);
+    (valid(combo.x) && valid(combo.y)).then_some(combo)
+}
+
+/// Resolutions or aspect ratios added on top of the fixed lists, along with the input to add
+/// them with
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CustomCombos {
+    pub(crate) input: String,
+    /// The input couldn't be parsed when it was last added
+    pub(crate) invalid: bool,
+    entries: Vec<(XYCombo, String)>,
+}
+
+impl CustomCombos {
+    pub(crate) fn set_entries(&mut self, combos: &[XYCombo]) {
+        self.entries = combos
+            .iter()
+            .map(|combo| (*combo, combo.to_string()))
+            .collect();
+    }
+
+    pub(crate) fn input_row(
+        &self,
+        kind: ComboKind,
+        placeholder: &str,
+    ) -> Row<'_, WallpaperMessage> {
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(
+                TextInput::new(placeholder, &self.input)
+                    .style(focus_outline())
+                    .on_input(move |value| WallpaperMessage::CustomComboInput(kind, value))
+                    .on_submit(WallpaperMessage::AddCustomCombo(kind))
+                    .width(Length::Fixed(150.0)),
+            )
+            .push(make_button("add").on_press(WallpaperMessage::AddCustomCombo(kind)))
+            .push(Text::new(match self.invalid {
+                true => format!("Type it like {}", placeholder),
+                false => String::new(),
+            }))
+    }
+
+    /// A button selecting each entry next to one removing it
+    fn entry_rows<'a>(
+        &'a self,
+        kind: ComboKind,
+        is_active: impl Fn(&XYCombo) -> bool,
+        on_press: impl Fn(XYCombo) -> WallpaperMessage,
+    ) -> Vec<Row<'a, WallpaperMessage>> {
+        self.entries
+            .iter()
+            .map(|(combo, label)| {
+                Row::new()
+                    .push(
+                        make_button(label)
+                            .style(inactive_style(is_active(combo)))
+                            .on_press(on_press(*combo)),
+                    )
+                    .push(labeled(
+                        make_button_fa("", "times")
+                            .on_press(WallpaperMessage::RemoveCustomCombo(kind, *combo)),
+                        "Remove",
+                    ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::submenus::parse_combo;
+    use wallapi::types::XYCombo;
+
+    #[test]
+    fn parse_custom_combos() {
+        assert_eq!(parse_combo("5120x1440"), Some(XYCombo { x: 5120, y: 1440 }));
+        assert_eq!(parse_combo(" 32 : 10 "), Some(XYCombo { x: 32, y: 10 }));
+        assert_eq!(
+            parse_combo("1920 X 1080"),
+            Some(XYCombo { x: 1920, y: 1080 })
+        );
+        assert_eq!(parse_combo("0x1080"), None);
+        assert_eq!(parse_combo("-16:9"), None);
+        assert_eq!(parse_combo("wide"), None);
+    }
+}
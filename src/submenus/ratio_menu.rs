@@ -1,5 +1,6 @@
 use crate::gui::WallpaperMessage;
 use crate::style::{inactive_style, make_button};
+use crate::submenus::{ComboKind, CustomCombos};
 use iced::widget::Row;
 use std::collections::HashSet;
 use std::sync::OnceLock;
@@ -8,6 +9,7 @@ use wallapi::types::XYCombo;
 #[derive(Debug, Clone)]
 pub(crate) struct RatioMenu {
     options: Vec<(XYCombo, &'static str)>,
+    pub(crate) custom: CustomCombos,
 }
 
 impl Default for RatioMenu {
@@ -24,6 +26,7 @@ impl Default for RatioMenu {
         });
         Self {
             options: options.clone(),
+            custom: CustomCombos::default(),
         }
     }
 }
@@ -39,13 +42,21 @@ impl RatioMenu {
     pub(crate) fn build_ratio_row(
         &self,
         selected_ratios: &Option<HashSet<XYCombo>>,
-    ) -> Row<WallpaperMessage> {
-        self.options.iter().fold(Row::new(), |row, (ratio, label)| {
+    ) -> Row<'_, WallpaperMessage> {
+        let row = self.options.iter().fold(Row::new(), |row, (ratio, label)| {
             row.push(
                 make_button(label)
                     .style(inactive_style(get_is_toggled(ratio, selected_ratios)))
                     .on_press(WallpaperMessage::AspectRatioSelected(*ratio)),
             )
-        })
+        });
+        self.custom
+            .entry_rows(
+                ComboKind::AspectRatio,
+                |ratio| get_is_toggled(ratio, selected_ratios),
+                WallpaperMessage::AspectRatioSelected,
+            )
+            .into_iter()
+            .fold(row, |row, entry| row.push(entry))
     }
 }
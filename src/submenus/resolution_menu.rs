@@ -1,6 +1,6 @@
 use crate::gui::WallpaperMessage;
 use crate::style::{inactive_style, make_button};
-use crate::submenus::calculate_aspect_ratio;
+use crate::submenus::{calculate_aspect_ratio, ComboKind, CustomCombos};
 use iced::widget::{Checkbox, Column, Row, Text};
 use itertools::Itertools;
 use std::collections::HashSet;
@@ -11,6 +11,7 @@ use wallapi::types::XYCombo;
 pub(crate) struct ResolutionOptionsMenu {
     button_states: Vec<(XYCombo, &'static str)>,
     pub(crate) is_minimum_set: bool,
+    pub(crate) custom: CustomCombos,
 }
 
 impl Default for ResolutionOptionsMenu {
@@ -38,6 +39,7 @@ impl Default for ResolutionOptionsMenu {
         Self {
             button_states,
             is_minimum_set: false,
+            custom: CustomCombos::default(),
         }
     }
 }
@@ -47,7 +49,7 @@ impl ResolutionOptionsMenu {
         &self,
         selected_options: &Option<HashSet<XYCombo>>,
         minimum_resolution: &Option<XYCombo>,
-    ) -> Row<WallpaperMessage> {
+    ) -> Row<'_, WallpaperMessage> {
         let check_resolution_active_multi = |option: &XYCombo| -> bool {
             match selected_options {
                 None => false,
@@ -58,12 +60,25 @@ impl ResolutionOptionsMenu {
         let check_minimum_resolution_active = |button_option: &XYCombo| -> bool {
             minimum_resolution
                 .as_ref()
-                .map_or(false, |minimum| button_option.eq(minimum))
+                .is_some_and(|minimum| button_option.eq(minimum))
         };
 
         let is_minimum_resolution = self.is_minimum_set;
 
-        self.button_states
+        let custom = self.custom.entry_rows(
+            ComboKind::Resolution,
+            |res| match is_minimum_resolution {
+                false => check_resolution_active_multi(res),
+                true => check_minimum_resolution_active(res),
+            },
+            |res| match is_minimum_resolution {
+                false => WallpaperMessage::ResolutionSelected(res),
+                true => WallpaperMessage::SetMinimumResolution(res),
+            },
+        );
+
+        let row = self
+            .button_states
             .iter()
             .group_by(|(res, _label)| calculate_aspect_ratio(res.x, res.y))
             .into_iter()
@@ -81,11 +96,26 @@ impl ResolutionOptionsMenu {
                         })
                     },
                 ))
-            })
-            .push(Checkbox::new(
-                "Minimum resolution",
-                self.is_minimum_set,
-                WallpaperMessage::ResolutionIsSingleTargetChanged,
-            ))
+            });
+        let row = match custom.is_empty() {
+            true => row,
+            false => row.push(
+                custom
+                    .into_iter()
+                    .fold(Column::new().push(Text::new("custom")), |column, entry| {
+                        column.push(entry)
+                    }),
+            ),
+        };
+        row.push(
+            Column::new()
+                .spacing(5)
+                .push(Checkbox::new(
+                    "Minimum resolution",
+                    self.is_minimum_set,
+                    WallpaperMessage::ResolutionIsSingleTargetChanged,
+                ))
+                .push(make_button("detect").on_press(WallpaperMessage::DetectResolution())),
+        )
     }
 }
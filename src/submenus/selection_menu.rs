@@ -0,0 +1,108 @@
+use crate::gui::{SelectionUpdateType, WallpaperMessage};
+use crate::style::make_button;
+use iced::widget::{Row, Text};
+use iced::Alignment;
+use std::fmt::{Display, Formatter};
+use wallapi::types::{ListingData, XYCombo};
+use wallcore::utils::trendy_number_format;
+
+/// Rules that can be used to select many results at once
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum SelectionCriteria {
+    AtLeast(XYCombo),
+    Landscape,
+    Portrait,
+    MinimumFavorites(i64),
+}
+
+impl SelectionCriteria {
+    pub(crate) fn matches(&self, listing: &ListingData) -> bool {
+        match self {
+            SelectionCriteria::AtLeast(resolution) => {
+                listing.dimension_x >= resolution.x as i64
+                    && listing.dimension_y >= resolution.y as i64
+            }
+            SelectionCriteria::Landscape => listing.dimension_x > listing.dimension_y,
+            SelectionCriteria::Portrait => listing.dimension_x < listing.dimension_y,
+            SelectionCriteria::MinimumFavorites(favorites) => listing.favorites >= *favorites,
+        }
+    }
+}
+
+impl Display for SelectionCriteria {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionCriteria::AtLeast(resolution) => write!(f, "≥ {}", resolution),
+            SelectionCriteria::Landscape => write!(f, "landscape"),
+            SelectionCriteria::Portrait => write!(f, "portrait"),
+            SelectionCriteria::MinimumFavorites(favorites) => {
+                write!(f, "≥ {} favorites", trendy_number_format(*favorites as f64))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SelectionMenu {
+    options: Vec<(SelectionCriteria, String)>,
+}
+
+impl Default for SelectionMenu {
+    fn default() -> Self {
+        let options = [
+            SelectionCriteria::AtLeast(XYCombo { x: 1920, y: 1080 }),
+            SelectionCriteria::AtLeast(XYCombo { x: 2560, y: 1440 }),
+            SelectionCriteria::AtLeast(XYCombo { x: 3840, y: 2160 }),
+            SelectionCriteria::Landscape,
+            SelectionCriteria::Portrait,
+            SelectionCriteria::MinimumFavorites(100),
+            SelectionCriteria::MinimumFavorites(1000),
+            SelectionCriteria::MinimumFavorites(10000),
+        ];
+        Self {
+            options: options
+                .into_iter()
+                .map(|criteria| (criteria, criteria.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl SelectionMenu {
+    pub(crate) fn build_selection_row(&self) -> Row<'_, WallpaperMessage> {
+        self.options.iter().fold(
+            Row::new()
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .push(Text::new("select all").size(21)),
+            |row, (criteria, label)| {
+                row.push(
+                    make_button(label).on_press(WallpaperMessage::SelectionUpdate(
+                        SelectionUpdateType::Matching(*criteria),
+                    )),
+                )
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn criteria_matches() {
+        let listing = ListingData {
+            dimension_x: 3840,
+            dimension_y: 2160,
+            favorites: 1200,
+            ..Default::default()
+        };
+        assert!(SelectionCriteria::AtLeast(XYCombo { x: 3840, y: 2160 }).matches(&listing));
+        assert!(!SelectionCriteria::AtLeast(XYCombo { x: 3840, y: 2400 }).matches(&listing));
+        assert!(SelectionCriteria::Landscape.matches(&listing));
+        assert!(!SelectionCriteria::Portrait.matches(&listing));
+        assert!(SelectionCriteria::MinimumFavorites(1000).matches(&listing));
+        assert!(!SelectionCriteria::MinimumFavorites(10000).matches(&listing));
+    }
+}
@@ -0,0 +1,110 @@
+use log::debug;
+
+/// Reads `gsettings get org.gnome.desktop.interface color-scheme`, like `'prefer-dark'`, or the
+/// name of the gtk theme on desktops that don't have a color scheme yet, like `'Adwaita-dark'`
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_gsettings(output: &str) -> Option<bool> {
+    let value = output.trim().trim_matches('\'').to_lowercase();
+    match value.as_str() {
+        "" => None,
+        // the color scheme doesn't say, the gtk theme might
+        "default" => None,
+        value => Some(value.contains("dark")),
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    match tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Failed to run {} {:?}", program, e);
+            None
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn platform_prefers_dark() -> Option<bool> {
+    for key in ["color-scheme", "gtk-theme"] {
+        let output =
+            command_output("gsettings", &["get", "org.gnome.desktop.interface", key]).await;
+        if let Some(dark) = output.as_deref().and_then(parse_gsettings) {
+            return Some(dark);
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "macos")]
+async fn platform_prefers_dark() -> Option<bool> {
+    // the key is only there in dark mode
+    let output = command_output("defaults", &["read", "-g", "AppleInterfaceStyle"]).await;
+    Some(output.map_or(false, |style| style.trim() == "Dark"))
+}
+
+#[cfg(windows)]
+async fn platform_prefers_dark() -> Option<bool> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::winerror::ERROR_SUCCESS;
+    use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    let wide = |s: &str| {
+        OsStr::new(s)
+            .encode_wide()
+            .chain(Some(0))
+            .collect::<Vec<u16>>()
+    };
+    let key = wide("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize");
+    let name = wide("AppsUseLightTheme");
+    let mut light: DWORD = 0;
+    let mut size = std::mem::size_of::<DWORD>() as DWORD;
+    // https://learn.microsoft.com/en-us/windows/win32/api/winreg/nf-winreg-reggetvaluew
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_DWORD,
+            ptr::null_mut(),
+            &mut light as *mut DWORD as *mut _,
+            &mut size,
+        )
+    };
+    match status as u32 {
+        ERROR_SUCCESS => Some(light == 0),
+        _ => None,
+    }
+}
+
+/// Whether the desktop is in dark mode, None when it can't be told
+pub(crate) async fn system_prefers_dark() -> Option<bool> {
+    let dark = platform_prefers_dark().await;
+    debug!("System prefers dark {:?}", dark);
+    dark
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn parse_gnome_settings() {
+        use crate::system_theme::parse_gsettings;
+
+        assert_eq!(parse_gsettings("'prefer-dark'\n"), Some(true));
+        assert_eq!(parse_gsettings("'prefer-light'\n"), Some(false));
+        assert_eq!(parse_gsettings("'default'\n"), None);
+        assert_eq!(parse_gsettings("'Adwaita-dark'\n"), Some(true));
+        assert_eq!(parse_gsettings("'Yaru'\n"), Some(false));
+    }
+}
@@ -0,0 +1,201 @@
+use std::path::Path;
+#[cfg(windows)]
+use std::path::PathBuf;
+use wallcore::download_manager::BatchProgress;
+
+/// Download progress on the taskbar button and the tasks in its jump list. Only Windows has
+/// them, elsewhere this does nothing.
+#[derive(Debug, Default)]
+pub(crate) struct Taskbar {
+    /// Last progress shown, so only changes are sent on
+    shown: Option<BatchProgress>,
+    #[cfg(windows)]
+    updates: Option<std::sync::mpsc::Sender<TaskbarUpdate>>,
+}
+
+#[cfg(windows)]
+#[derive(Debug)]
+enum TaskbarUpdate {
+    Progress(BatchProgress),
+    JumpList(PathBuf),
+}
+
+impl Taskbar {
+    pub(crate) fn set_progress(&mut self, progress: BatchProgress) {
+        if self.shown == Some(progress) {
+            return;
+        }
+        self.shown = Some(progress);
+        #[cfg(windows)]
+        self.send(TaskbarUpdate::Progress(progress));
+    }
+
+    /// Sets the jump list's tasks, "Open downloads folder" opens `downloads`
+    #[cfg(windows)]
+    pub(crate) fn set_jump_list(&mut self, downloads: &Path) {
+        // a relative save directory is relative to where the app was started, not explorer
+        let downloads = std::fs::canonicalize(downloads).unwrap_or_else(|_| downloads.into());
+        self.send(TaskbarUpdate::JumpList(downloads));
+    }
+
+    #[cfg(not(windows))]
+    pub(crate) fn set_jump_list(&mut self, _: &Path) {}
+
+    /// The taskbar's COM objects belong to the thread that made them, so they get a thread of
+    /// their own that's started with the first update
+    #[cfg(windows)]
+    fn send(&mut self, update: TaskbarUpdate) {
+        let updates = self.updates.get_or_insert_with(|| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            std::thread::spawn(move || win32::run_taskbar(receiver));
+            sender
+        });
+        if let Err(e) = updates.send(update) {
+            log::error!("The taskbar thread is gone, {:?}", e.0);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use crate::taskbar::TaskbarUpdate;
+    use log::error;
+    use std::path::Path;
+    use std::sync::mpsc::Receiver;
+    use wallcore::download_manager::BatchProgress;
+    use windows::core::{w, ComInterface, Result, HSTRING, PCWSTR};
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PROPVARIANT};
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::System::Variant::VT_LPWSTR;
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW,
+        ITaskbarList3, SHStrDupW, ShellLink, TaskbarList, TBPF_ERROR, TBPF_NOPROGRESS, TBPF_NORMAL,
+        TBPF_PAUSED,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    /// Title of the main window, the same as [crate::gui::WallpaperUi]'s
+    const WINDOW_TITLE: &str = "wall-a-bunga";
+
+    unsafe extern "system" fn find_main_window(window: HWND, found: LPARAM) -> BOOL {
+        let mut process = 0;
+        GetWindowThreadProcessId(window, Some(&mut process as *mut u32));
+        // longer titles are cut off and don't match
+        let mut title = [0u16; 16];
+        let length = GetWindowTextW(window, &mut title).max(0) as usize;
+        if process == std::process::id()
+            && String::from_utf16_lossy(&title[..length]) == WINDOW_TITLE
+        {
+            *(found.0 as *mut HWND) = window;
+            return BOOL(0);
+        }
+        BOOL(1)
+    }
+
+    /// The window iced made, it isn't handed out so it's looked up among the process' windows
+    fn main_window() -> Option<HWND> {
+        let mut found = HWND::default();
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumwindows
+        // stopping early is reported as an error
+        let _ = unsafe {
+            EnumWindows(
+                Some(find_main_window),
+                LPARAM(&mut found as *mut HWND as isize),
+            )
+        };
+        (found.0 != 0).then_some(found)
+    }
+
+    unsafe fn show_progress(taskbar: &ITaskbarList3, progress: BatchProgress) -> Result<()> {
+        // there's no button while the window is hidden in the tray
+        let window = match main_window() {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+        let (state, done) = match progress {
+            BatchProgress::Hidden => return taskbar.SetProgressState(window, TBPF_NOPROGRESS),
+            BatchProgress::Running(done) => (TBPF_NORMAL, done),
+            BatchProgress::Paused(done) => (TBPF_PAUSED, done),
+            BatchProgress::Failed(done) => (TBPF_ERROR, done),
+        };
+        // https://learn.microsoft.com/en-us/windows/win32/api/shobjidl_core/nf-shobjidl_core-itaskbarlist3-setprogressvalue
+        taskbar.SetProgressValue(window, done, BatchProgress::STEPS)?;
+        taskbar.SetProgressState(window, state)
+    }
+
+    /// A jump list task, starts `program` with `arguments` when it's picked
+    unsafe fn task(title: PCWSTR, program: &Path, arguments: &str) -> Result<IShellLinkW> {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(&HSTRING::from(program.as_os_str()))?;
+        link.SetArguments(&HSTRING::from(arguments))?;
+        // tasks are labeled with the link's title, PropVariantClear frees the copy
+        let mut value = PROPVARIANT::default();
+        (*value.Anonymous.Anonymous).vt = VT_LPWSTR;
+        (*value.Anonymous.Anonymous).Anonymous.pwszVal = SHStrDupW(title)?;
+        let store: IPropertyStore = link.cast()?;
+        let result = store
+            .SetValue(&PKEY_Title, &value)
+            .and_then(|_| store.Commit());
+        PropVariantClear(&mut value)?;
+        result.map(|_| link)
+    }
+
+    /// https://learn.microsoft.com/en-us/windows/win32/api/shobjidl_core/nn-shobjidl_core-icustomdestinationlist
+    unsafe fn update_jump_list(downloads: &Path) -> Result<()> {
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut slots = 0;
+        let _removed: IObjectArray = list.BeginList(&mut slots)?;
+        let tasks: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        let program =
+            std::env::current_exe().unwrap_or_else(|_| Path::new("wall-a-bunga.exe").into());
+        // picked while the app runs, it's sent the command like `--send` from a script would
+        tasks.AddObject(&task(
+            w!("Next wallpaper"),
+            &program,
+            "--send next-wallpaper",
+        )?)?;
+        tasks.AddObject(&task(
+            w!("Open downloads folder"),
+            Path::new("explorer.exe"),
+            &format!("\"{}\"", downloads.to_string_lossy()),
+        )?)?;
+        list.AddUserTasks(&tasks)?;
+        list.CommitList()
+    }
+
+    pub(super) fn run_taskbar(updates: Receiver<TaskbarUpdate>) {
+        let taskbar = unsafe {
+            CoInitializeEx(None, COINIT_APARTMENTTHREADED).and_then(|_| {
+                let taskbar: ITaskbarList3 =
+                    CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+                taskbar.HrInit()?;
+                Ok(taskbar)
+            })
+        };
+        let taskbar = taskbar
+            .map_err(|e| error!("Failed to get the taskbar {:?}", e))
+            .ok();
+        for update in updates {
+            let result = match (update, &taskbar) {
+                (TaskbarUpdate::Progress(progress), Some(taskbar)) => unsafe {
+                    show_progress(taskbar, progress)
+                },
+                (TaskbarUpdate::Progress(_), None) => Ok(()),
+                (TaskbarUpdate::JumpList(downloads), _) => unsafe { update_jump_list(&downloads) },
+            };
+            if let Err(e) = result {
+                error!("Failed to update the taskbar {:?}", e);
+            }
+        }
+    }
+}
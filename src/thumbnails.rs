@@ -0,0 +1,163 @@
+use crate::library::Fnv1a;
+use anyhow::{anyhow, Result};
+use iced::widget::image;
+use image_rs::{DynamicImage, ImageFormat};
+use log::{debug, error, info};
+use platform_dirs::AppDirs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Semaphore;
+use tokio::task::spawn_blocking;
+
+/// Width results are shown at, larger images are scaled down to it before they're kept
+pub(crate) const THUMBNAIL_WIDTH: u32 = 300;
+
+/// Height of wallhaven's thumbnails, the downloaded wallpapers' thumbnails are fit into it
+pub(crate) const THUMBNAIL_HEIGHT: u32 = 200;
+
+/// Thumbnails that haven't been written for this long are deleted on start
+const STALE_THUMBNAIL_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Thumbnails decoded at once, so a page of results doesn't take every blocking thread
+const DECODE_THREADS: usize = 4;
+
+static DECODING: Semaphore = Semaphore::const_new(DECODE_THREADS);
+
+/// Where a thumbnail comes from when it isn't cached yet
+#[derive(Debug, Clone)]
+pub(crate) enum ThumbnailSource {
+    /// Fetched from wallhaven with the shared client
+    Url {
+        client: reqwest::Client,
+        url: String,
+    },
+    /// A downloaded wallpaper, scaled down from the full size file
+    File(PathBuf),
+}
+
+/// Where the thumbnails of results are kept, like `~/.cache/wall-a-bunga/thumbnails`
+fn cache_dir() -> Option<PathBuf> {
+    AppDirs::new(Some("wall-a-bunga"), true).map(|dirs| dirs.cache_dir.join("thumbnails"))
+}
+
+fn cached_path(id: &str) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join(format!("{}.jpg", id)))
+}
+
+/// Scales the image down to [THUMBNAIL_WIDTH], smaller images are left alone
+fn scale_down(image: DynamicImage) -> DynamicImage {
+    match image.width() > THUMBNAIL_WIDTH {
+        true => image.thumbnail(THUMBNAIL_WIDTH, u32::MAX),
+        false => image,
+    }
+}
+
+/// Written next to the final path first, so a half written thumbnail is never shown
+fn save_thumbnail(thumbnail: &DynamicImage, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let part_path = path.with_extension("part");
+    // jpeg has no alpha channel
+    thumbnail
+        .to_rgb8()
+        .save_with_format(&part_path, ImageFormat::Jpeg)?;
+    std::fs::rename(&part_path, path)?;
+    Ok(())
+}
+
+/// Loads the thumbnail of a wallpaper, scaled down to the size it's shown at and kept in the disk
+/// cache. Cached thumbnails are handed to iced by path, so they're only decoded while they're
+/// drawn instead of every result's pixels staying in memory. Ones that aren't cached yet are
+/// scaled down on a blocking thread, so a page of results arriving doesn't stall scrolling.
+pub(crate) async fn load_thumbnail(id: &str, source: ThumbnailSource) -> Result<image::Handle> {
+    let path = cached_path(id);
+    if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+        return Ok(image::Handle::from_path(path));
+    }
+    let bytes = match source {
+        ThumbnailSource::Url { client, url } => {
+            client.get(&url).send().await?.bytes().await?.to_vec()
+        }
+        ThumbnailSource::File(file) => tokio::fs::read(&file).await?,
+    };
+    let _permit = DECODING.acquire().await?;
+    spawn_blocking(move || {
+        let thumbnail = scale_down(image_rs::load_from_memory(&bytes)?);
+        if let Some(path) = path {
+            match save_thumbnail(&thumbnail, &path) {
+                Ok(()) => return Ok(image::Handle::from_path(path)),
+                Err(e) => debug!("Failed to cache the thumbnail {:?} {}", path, e),
+            }
+        }
+        // without the cache it's kept in memory, scaled down at least
+        let pixels = thumbnail.to_rgba8();
+        Ok(image::Handle::from_pixels(
+            pixels.width(),
+            pixels.height(),
+            pixels.into_raw(),
+        ))
+    })
+    .await
+    .map_err(|e| anyhow!("Thumbnail task failed {:?}", e))?
+}
+
+/// Scales a file that isn't a result down into the cache, on the thread it's called from. The
+/// file's path and size name it, so a file that was replaced gets a new one.
+pub(crate) fn cache_file_thumbnail(file: &Path) -> Result<PathBuf> {
+    let mut hasher = Fnv1a::default();
+    hasher.write(file.to_string_lossy().as_bytes());
+    hasher.write_u64(std::fs::metadata(file)?.len());
+    let path = cached_path(&format!("file-{:016x}", hasher.finish()))
+        .ok_or_else(|| anyhow!("No cache directory"))?;
+    if !path.exists() {
+        save_thumbnail(&scale_down(image_rs::open(file)?), &path)?;
+    }
+    Ok(path)
+}
+
+/// Deletes cached thumbnails that weren't needed for a while, they're fetched again if they are
+pub(crate) fn prune_thumbnail_cache() {
+    let directory = match cache_dir() {
+        Some(directory) => directory,
+        None => return,
+    };
+    // nothing was cached yet
+    let entries = match std::fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > STALE_THUMBNAIL_AGE);
+        if stale {
+            match std::fs::remove_file(entry.path()) {
+                Ok(()) => removed += 1,
+                Err(e) => error!("Failed to delete file {:?} {:?}", entry.path(), e),
+            }
+        }
+    }
+    if removed > 0 {
+        info!("Removed {} stale thumbnails", removed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::thumbnails::{scale_down, THUMBNAIL_WIDTH};
+    use image_rs::DynamicImage;
+
+    #[test]
+    fn scales_down_to_thumbnail_width() {
+        let large = scale_down(DynamicImage::new_rgb8(1920, 1080));
+        assert_eq!((large.width(), large.height()), (THUMBNAIL_WIDTH, 169));
+        let small = scale_down(DynamicImage::new_rgb8(200, 100));
+        assert_eq!((small.width(), small.height()), (200, 100));
+    }
+}
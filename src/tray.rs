@@ -0,0 +1,275 @@
+use iced::futures::stream::BoxStream;
+use iced_futures::subscription::{EventStream, Recipe};
+use log::info;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Whether this platform gets a tray icon. macOS only allows one on the main thread, which
+/// belongs to the window.
+pub(crate) const SUPPORTED: bool = cfg!(any(windows, all(unix, not(target_os = "macos"))));
+
+/// Picked from the tray menu
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TrayAction {
+    /// Shows the window again after it was minimized to the tray
+    Open,
+    /// Where bool == paused
+    PauseRotation(bool),
+    NextWallpaper,
+    /// Where bool == paused
+    PauseDownloads(bool),
+    Quit,
+    /// The tray couldn't be shown, so there's nothing to minimize to
+    Failed,
+}
+
+/// Shows the tray icon for as long as the subscription is kept, with its menu picks as the output
+#[derive(Debug, Clone)]
+pub(crate) struct TrayIcon;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+struct WallpaperTray {
+    actions: UnboundedSender<TrayAction>,
+    rotation_paused: bool,
+    downloads_paused: bool,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl WallpaperTray {
+    fn send(&self, action: TrayAction) {
+        // the app stopped listening, it's on its way out
+        let _ = self.actions.send(action);
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ksni::Tray for WallpaperTray {
+    fn id(&self) -> String {
+        "wall-a-bunga".to_string()
+    }
+
+    fn title(&self) -> String {
+        "wall-a-bunga".to_string()
+    }
+
+    fn icon_name(&self) -> String {
+        "preferences-desktop-wallpaper".to_string()
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayAction::Open);
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        use ksni::menu::{CheckmarkItem, MenuItem, StandardItem};
+
+        vec![
+            StandardItem {
+                label: "Open wall-a-bunga".to_string(),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::Open)),
+                ..Default::default()
+            }
+            .into(),
+            CheckmarkItem {
+                label: "Pause rotation".to_string(),
+                checked: self.rotation_paused,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.rotation_paused = !tray.rotation_paused;
+                    tray.send(TrayAction::PauseRotation(tray.rotation_paused));
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Next wallpaper".to_string(),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::NextWallpaper)),
+                ..Default::default()
+            }
+            .into(),
+            CheckmarkItem {
+                label: "Pause downloads".to_string(),
+                checked: self.downloads_paused,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.downloads_paused = !tray.downloads_paused;
+                    tray.send(TrayAction::PauseDownloads(tray.downloads_paused));
+                }),
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            StandardItem {
+                label: "Quit".to_string(),
+                icon_name: "application-exit".to_string(),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::Quit)),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+}
+
+/// Registers the tray with the desktop's StatusNotifierItem host, the returned handle keeps it
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn start_tray(actions: UnboundedSender<TrayAction>) -> Option<ksni::Handle<WallpaperTray>> {
+    use ksni::TrayMethods;
+
+    let tray = WallpaperTray {
+        actions,
+        rotation_paused: false,
+        downloads_paused: false,
+    };
+    match tray.spawn().await {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            log::error!("Failed to show the tray icon {:?}", e);
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+const ICON_SIZE: u32 = 32;
+
+/// An orange dot in the color of selected results
+#[cfg(windows)]
+fn icon_pixels() -> Vec<u8> {
+    let center = ICON_SIZE as f32 / 2.0;
+    (0..ICON_SIZE * ICON_SIZE)
+        .flat_map(|index| {
+            let x = (index % ICON_SIZE) as f32 + 0.5 - center;
+            let y = (index / ICON_SIZE) as f32 + 0.5 - center;
+            let alpha = match (x * x + y * y).sqrt() < center - 1.0 {
+                true => 255,
+                false => 0,
+            };
+            [222, 107, 28, alpha]
+        })
+        .collect()
+}
+
+/// Shows the tray and pumps its window messages until the app stops listening. Windows only
+/// delivers the tray's messages to the thread that created it, so it gets a thread of its own.
+#[cfg(windows)]
+fn run_tray(actions: UnboundedSender<TrayAction>) {
+    use std::{mem, ptr};
+    use tray_icon::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use tray_icon::{Icon, MouseButton, TrayIconBuilder, TrayIconEvent};
+    use winapi::um::winuser::{DispatchMessageW, GetMessageW, TranslateMessage, MSG};
+
+    let open = MenuItem::new("Open wall-a-bunga", true, None);
+    let pause_rotation = CheckMenuItem::new("Pause rotation", true, false, None);
+    let next_wallpaper = MenuItem::new("Next wallpaper", true, None);
+    let pause_downloads = CheckMenuItem::new("Pause downloads", true, false, None);
+    let quit = MenuItem::new("Quit", true, None);
+    let menu = Menu::new();
+    if let Err(e) = menu.append_items(&[
+        &open,
+        &pause_rotation,
+        &next_wallpaper,
+        &pause_downloads,
+        &PredefinedMenuItem::separator(),
+        &quit,
+    ]) {
+        log::error!("Failed to build the tray menu {:?}", e);
+        let _ = actions.send(TrayAction::Failed);
+        return;
+    }
+    let icon = match Icon::from_rgba(icon_pixels(), ICON_SIZE, ICON_SIZE) {
+        Ok(icon) => icon,
+        Err(e) => {
+            log::error!("Failed to make the tray icon {:?}", e);
+            let _ = actions.send(TrayAction::Failed);
+            return;
+        }
+    };
+    let _tray = match TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("wall-a-bunga")
+        .with_icon(icon)
+        .build()
+    {
+        Ok(tray) => tray,
+        Err(e) => {
+            log::error!("Failed to show the tray icon {:?}", e);
+            let _ = actions.send(TrayAction::Failed);
+            return;
+        }
+    };
+    let mut message: MSG = unsafe { mem::zeroed() };
+    // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getmessagew
+    while unsafe { GetMessageW(&mut message, ptr::null_mut(), 0, 0) } > 0 {
+        unsafe {
+            TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+        let mut picked = vec![];
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            // check items have already been toggled by the time the event arrives
+            if event.id == *open.id() {
+                picked.push(TrayAction::Open);
+            } else if event.id == *pause_rotation.id() {
+                picked.push(TrayAction::PauseRotation(pause_rotation.is_checked()));
+            } else if event.id == *next_wallpaper.id() {
+                picked.push(TrayAction::NextWallpaper);
+            } else if event.id == *pause_downloads.id() {
+                picked.push(TrayAction::PauseDownloads(pause_downloads.is_checked()));
+            } else if event.id == *quit.id() {
+                picked.push(TrayAction::Quit);
+            }
+        }
+        while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+            if let TrayIconEvent::DoubleClick {
+                button: MouseButton::Left,
+                ..
+            } = event
+            {
+                picked.push(TrayAction::Open);
+            }
+        }
+        for action in picked {
+            if actions.send(action).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+async fn start_tray(actions: UnboundedSender<TrayAction>) -> Option<()> {
+    std::thread::spawn(move || run_tray(actions));
+    Some(())
+}
+
+#[cfg(target_os = "macos")]
+async fn start_tray(_: UnboundedSender<TrayAction>) -> Option<()> {
+    None
+}
+
+impl Recipe for TrayIcon {
+    type Output = TrayAction;
+
+    fn hash(&self, state: &mut iced_futures::core::Hasher) {
+        use std::hash::Hash;
+
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _: EventStream) -> BoxStream<'static, Self::Output> {
+        let (sender, receiver) = unbounded_channel();
+        info!("Starting the tray icon");
+        Box::pin(futures::stream::unfold(
+            (Some(sender), receiver, None),
+            |(sender, mut receiver, tray)| async move {
+                // the sender moves into the tray, the stream ends once the tray is gone
+                let tray = match sender {
+                    Some(sender) => match start_tray(sender).await {
+                        Some(tray) => Some(tray),
+                        None => return Some((TrayAction::Failed, (None, receiver, None))),
+                    },
+                    None => tray,
+                };
+                let action = receiver.recv().await?;
+                Some((action, (None, receiver, tray)))
+            },
+        ))
+    }
+}
@@ -0,0 +1,45 @@
+use std::ops::Range;
+
+/// Rows built past either edge of the viewport, so scrolling doesn't reveal empty space before
+/// the next frame catches up
+const OVERSCAN_ROWS: usize = 2;
+
+/// Rows of results near the viewport, the rest are left out of the view and stood in for by empty
+/// space. `row_height` includes the spacing between rows.
+pub(crate) fn visible_rows(
+    scroll_top: f32,
+    viewport_height: f32,
+    row_height: f32,
+    rows: usize,
+) -> Range<usize> {
+    let first = (scroll_top.max(0.0) / row_height).floor() as usize;
+    let last = ((scroll_top.max(0.0) + viewport_height) / row_height).ceil() as usize;
+    let end = (last + OVERSCAN_ROWS).min(rows);
+    first.saturating_sub(OVERSCAN_ROWS).min(end)..end
+}
+
+/// Height of the space standing in for `rows` rows, the column adds the spacing after it
+pub(crate) fn skipped_height(rows: usize, row_height: f32, spacing: f32) -> f32 {
+    match rows {
+        0 => 0.0,
+        rows => rows as f32 * row_height - spacing,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::virtual_rows::{skipped_height, visible_rows};
+
+    #[test]
+    fn builds_rows_near_viewport() {
+        assert_eq!(visible_rows(0.0, 500.0, 100.0, 1000), 0..7);
+        assert_eq!(visible_rows(1050.0, 500.0, 100.0, 1000), 8..18);
+        // scrolled to the end
+        assert_eq!(visible_rows(99_600.0, 500.0, 100.0, 1000), 994..1000);
+        // fewer results than fit in the viewport
+        assert_eq!(visible_rows(0.0, 500.0, 100.0, 3), 0..3);
+        assert_eq!(visible_rows(0.0, 500.0, 100.0, 0), 0..0);
+        assert_eq!(skipped_height(8, 100.0, 5.0), 795.0);
+        assert_eq!(skipped_height(0, 100.0, 5.0), 0.0);
+    }
+}
@@ -0,0 +1,468 @@
+use crate::library::LibraryEntry;
+use crate::monitors::Monitor;
+use log::info;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::path::{Path, PathBuf};
+
+/// Placeholder in a custom wallpaper command that is replaced with the image path
+const PATH_PLACEHOLDER: &str = "{path}";
+/// Placeholder in a custom wallpaper command that is replaced with the monitor name, the command
+/// is run once for every monitor
+const MONITOR_PLACEHOLDER: &str = "{monitor}";
+
+/// Splits a custom wallpaper command into arguments, putting the image path where `{path}` is or
+/// at the end when it isn't used
+fn custom_command(template: &str, path: &str) -> Vec<String> {
+    let mut args: Vec<String> = template
+        .split_whitespace()
+        .map(|arg| arg.replace(PATH_PLACEHOLDER, path))
+        .collect();
+    if !template.contains(PATH_PLACEHOLDER) {
+        args.push(path.to_string());
+    }
+    args
+}
+
+/// The images feh last set, in monitor order, read out of the `~/.fehbg` script it leaves behind
+/// like `feh --no-fehbg --bg-fill '/a.jpg' '/b.jpg'`
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_fehbg(script: &str) -> Vec<String> {
+    let command = match script
+        .lines()
+        .find(|line| line.trim_start().starts_with("feh "))
+    {
+        Some(command) => command,
+        None => return vec![],
+    };
+    // paths are the quoted arguments, a quote inside one is written as '\''
+    let mut paths = vec![];
+    let mut rest = command.replace("'\\''", "\u{0}");
+    while let Some(start) = rest.find('\'') {
+        let quoted = &rest[start + 1..];
+        let end = match quoted.find('\'') {
+            Some(end) => end,
+            None => break,
+        };
+        paths.push(quoted[..end].replace('\u{0}', "'"));
+        rest = quoted[end + 1..].to_string();
+    }
+    paths
+}
+
+/// Commands that show a different wallpaper on each monitor of the detected desktop, for the
+/// desktops that allow it. `wallpapers` are the names of all the monitors, in order, along with
+/// the image picked for them. `previous` are the images feh last set.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn linux_monitor_commands(
+    desktop: &str,
+    sway: bool,
+    wallpapers: &[(String, Option<String>)],
+    previous: &[String],
+) -> Result<Vec<Vec<String>>, String> {
+    if sway {
+        return Ok(wallpapers
+            .iter()
+            .filter_map(|(monitor, path)| Some((monitor, path.as_ref()?)))
+            .map(|(monitor, path)| {
+                ["swaymsg", "output", monitor, "bg", path, "fill"]
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect()
+            })
+            .collect());
+    }
+    let lowercase = desktop.to_lowercase();
+    if ["gnome", "unity", "budgie", "pantheon", "kde"]
+        .iter()
+        .any(|name| lowercase.contains(name))
+    {
+        return Err(format!(
+            "{} can't show a different wallpaper on each monitor, a custom command using {} can",
+            desktop, MONITOR_PLACEHOLDER
+        ));
+    }
+    // feh hands the images out to the monitors in order, so monitors without a pick keep the
+    // image they had or they'd shift the ones after them over
+    let placeholder = wallpapers
+        .iter()
+        .find_map(|(_, path)| path.clone())
+        .ok_or("No wallpaper was picked for any monitor")?;
+    let mut feh = vec!["feh".to_string(), "--bg-fill".to_string()];
+    feh.extend(wallpapers.iter().enumerate().map(|(index, (_, path))| {
+        path.clone()
+            .or_else(|| previous.get(index).cloned())
+            .unwrap_or_else(|| placeholder.clone())
+    }));
+    Ok(vec![feh])
+}
+
+/// Commands that set the wallpaper on the detected desktop, run in order
+#[cfg(all(unix, not(target_os = "macos")))]
+fn linux_commands(desktop: &str, sway: bool, path: &str) -> Vec<Vec<String>> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+    let desktop = desktop.to_lowercase();
+    if sway {
+        return vec![args(&["swaymsg", "output", "*", "bg", path, "fill"])];
+    }
+    if ["gnome", "unity", "budgie", "pantheon"]
+        .iter()
+        .any(|name| desktop.contains(name))
+    {
+        let uri = format!("file://{}", path);
+        return vec![
+            args(&[
+                "gsettings",
+                "set",
+                "org.gnome.desktop.background",
+                "picture-uri",
+                &uri,
+            ]),
+            // newer versions keep a separate wallpaper for dark mode
+            args(&[
+                "gsettings",
+                "set",
+                "org.gnome.desktop.background",
+                "picture-uri-dark",
+                &uri,
+            ]),
+        ];
+    }
+    if desktop.contains("kde") {
+        return vec![args(&["plasma-apply-wallpaperimage", path])];
+    }
+    vec![args(&["feh", "--bg-fill", path])]
+}
+
+async fn run(args: &[String]) -> Result<(), String> {
+    let (program, args) = args.split_first().ok_or("The wallpaper command is empty")?;
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {} {}", program, e))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+#[cfg(windows)]
+async fn set_platform_wallpaper(path: &Path) -> Result<(), String> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::winuser::{
+        SystemParametersInfoW, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SPI_SETDESKWALLPAPER,
+    };
+
+    let mut wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    tokio::task::spawn_blocking(move || {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-systemparametersinfow
+        let result = unsafe {
+            SystemParametersInfoW(
+                SPI_SETDESKWALLPAPER,
+                0,
+                wide.as_mut_ptr() as *mut _,
+                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+            )
+        };
+        match result {
+            0 => Err(std::io::Error::last_os_error().to_string()),
+            _ => Ok(()),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(target_os = "macos")]
+async fn set_platform_wallpaper(path: &Path) -> Result<(), String> {
+    let path = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    let script = format!(
+        "tell application \"System Events\" to tell every desktop to set picture to \"{}\"",
+        path
+    );
+    run(&["osascript".to_string(), "-e".to_string(), script]).await
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn set_platform_wallpaper(path: &Path) -> Result<(), String> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let sway = std::env::var_os("SWAYSOCK").is_some();
+    let commands = linux_commands(&desktop, sway, &path.to_string_lossy());
+    let (first, rest) = commands.split_first().ok_or("No wallpaper command")?;
+    run(first).await?;
+    for command in rest {
+        // optional extras, like the dark mode wallpaper older desktops don't have
+        if let Err(e) = run(command).await {
+            info!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+/// The device name, like `\\.\DISPLAY1`, of the monitor covering the rectangle
+#[cfg(windows)]
+fn windows_device_name(rect: windows::Win32::Foundation::RECT) -> Option<String> {
+    use std::mem;
+    use winapi::shared::windef::RECT;
+    use winapi::um::winuser::{
+        GetMonitorInfoW, MonitorFromRect, MONITORINFOEXW, MONITOR_DEFAULTTONULL,
+    };
+
+    let rect = RECT {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    };
+    unsafe {
+        let monitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONULL);
+        if monitor.is_null() {
+            return None;
+        }
+        let mut info: MONITORINFOEXW = mem::zeroed();
+        info.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut _) == 0 {
+            return None;
+        }
+        let length = info
+            .szDevice
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(info.szDevice.len());
+        Some(String::from_utf16_lossy(&info.szDevice[..length]))
+    }
+}
+
+/// Sets each monitor's wallpaper through the shell's IDesktopWallpaper, which knows monitors by a
+/// device path of its own. They're matched to the detected monitors by the area they cover.
+#[cfg(windows)]
+unsafe fn set_windows_monitor_wallpapers(
+    wallpapers: &[(Monitor, PathBuf)],
+) -> windows::core::Result<()> {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::{DesktopWallpaper, IDesktopWallpaper};
+
+    // fails when the thread already has COM set up, which is just as good
+    let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+    // https://learn.microsoft.com/en-us/windows/win32/api/shobjidl_core/nn-shobjidl_core-idesktopwallpaper
+    let desktop: IDesktopWallpaper = CoCreateInstance(&DesktopWallpaper, None, CLSCTX_ALL)?;
+    for index in 0..desktop.GetMonitorDevicePathCount()? {
+        let id = desktop.GetMonitorDevicePathAt(index)?;
+        // monitors that aren't attached have no rectangle
+        let name = desktop
+            .GetMonitorRECT(PCWSTR(id.0))
+            .ok()
+            .and_then(windows_device_name);
+        let path = wallpapers
+            .iter()
+            .find(|(monitor, _)| Some(&monitor.name) == name.as_ref())
+            .map(|(_, path)| path);
+        let result = match path {
+            Some(path) => desktop.SetWallpaper(PCWSTR(id.0), &HSTRING::from(path.as_os_str())),
+            None => Ok(()),
+        };
+        CoTaskMemFree(Some(id.0 as *const _));
+        result?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn set_platform_monitor_wallpapers(
+    _: &[Monitor],
+    wallpapers: &[(Monitor, PathBuf)],
+) -> Result<(), String> {
+    let wallpapers = wallpapers.to_vec();
+    tokio::task::spawn_blocking(move || unsafe { set_windows_monitor_wallpapers(&wallpapers) })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+async fn set_platform_monitor_wallpapers(
+    _: &[Monitor],
+    wallpapers: &[(Monitor, PathBuf)],
+) -> Result<(), String> {
+    for (monitor, path) in wallpapers {
+        let path = path
+            .to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        // monitors are named by their desktop number
+        let script = format!(
+            "tell application \"System Events\" to set picture of desktop {} to \"{}\"",
+            monitor.name, path
+        );
+        run(&["osascript".to_string(), "-e".to_string(), script]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn set_platform_monitor_wallpapers(
+    monitors: &[Monitor],
+    wallpapers: &[(Monitor, PathBuf)],
+) -> Result<(), String> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let sway = std::env::var_os("SWAYSOCK").is_some();
+    let wallpapers: Vec<_> = monitors
+        .iter()
+        .map(|monitor| {
+            let path = wallpapers
+                .iter()
+                .find(|(picked, _)| picked.name == monitor.name)
+                .map(|(_, path)| path.to_string_lossy().to_string());
+            (monitor.name.clone(), path)
+        })
+        .collect();
+    let previous = match std::env::var_os("HOME") {
+        Some(home) => tokio::fs::read_to_string(Path::new(&home).join(".fehbg"))
+            .await
+            .map(|script| parse_fehbg(&script))
+            .unwrap_or_default(),
+        None => vec![],
+    };
+    for command in linux_monitor_commands(&desktop, sway, &wallpapers, &previous)? {
+        run(&command).await?;
+    }
+    Ok(())
+}
+
+/// The desktop won't know what a relative path is relative to
+fn absolute(path: PathBuf) -> Result<PathBuf, String> {
+    match path.is_absolute() {
+        true => Ok(path),
+        false => Ok(std::env::current_dir()
+            .map_err(|e| e.to_string())?
+            .join(path)),
+    }
+}
+
+/// Shows a different wallpaper on each monitor, `command` has to use `{monitor}` when it's set.
+/// `monitors` are all the connected ones, the ones missing from `wallpapers` keep what they show.
+pub(crate) async fn set_monitor_wallpapers(
+    monitors: Vec<Monitor>,
+    wallpapers: Vec<(Monitor, PathBuf)>,
+    command: Option<String>,
+) -> Result<(), String> {
+    let wallpapers = wallpapers
+        .into_iter()
+        .map(|(monitor, path)| Ok((monitor, absolute(path)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    info!("Setting monitor wallpapers {:?}", wallpapers);
+    match command.filter(|command| !command.trim().is_empty()) {
+        Some(command) if command.contains(MONITOR_PLACEHOLDER) => {
+            for (monitor, path) in wallpapers {
+                let command = command.replace(MONITOR_PLACEHOLDER, &monitor.name);
+                run(&custom_command(&command, &path.to_string_lossy())).await?;
+            }
+            Ok(())
+        }
+        Some(_) => Err(format!(
+            "The wallpaper command has to use {} to set each monitor",
+            MONITOR_PLACEHOLDER
+        )),
+        None => set_platform_monitor_wallpapers(&monitors, &wallpapers).await,
+    }
+}
+
+/// A random downloaded wallpaper other than `current` to rotate to
+pub(crate) fn next_rotation(
+    entries: Vec<LibraryEntry>,
+    current: Option<&str>,
+) -> Option<LibraryEntry> {
+    let candidates: Vec<LibraryEntry> = entries
+        .into_iter()
+        .filter(|entry| current != Some(entry.id.as_str()))
+        .filter(|entry| entry.path.exists())
+        .collect();
+    candidates.choose(&mut thread_rng()).cloned()
+}
+
+/// Makes the image the desktop wallpaper, using `command` instead of the platform default when
+/// it's set
+pub(crate) async fn set_wallpaper(path: PathBuf, command: Option<String>) -> Result<(), String> {
+    let path = absolute(path)?;
+    info!("Setting wallpaper to {:?}", path);
+    match command.filter(|command| !command.trim().is_empty()) {
+        Some(command) => run(&custom_command(&command, &path.to_string_lossy())).await,
+        None => set_platform_wallpaper(&path).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wallpaper::custom_command;
+
+    #[test]
+    fn custom_commands() {
+        assert_eq!(
+            custom_command("swww img {path} --transition-type grow", "/w/a.jpg"),
+            ["swww", "img", "/w/a.jpg", "--transition-type", "grow"]
+        );
+        assert_eq!(
+            custom_command("xwallpaper --zoom", "/w/a.jpg"),
+            ["xwallpaper", "--zoom", "/w/a.jpg"]
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn linux_desktops() {
+        use crate::wallpaper::linux_commands;
+
+        assert_eq!(linux_commands("ubuntu:GNOME", false, "/a.jpg").len(), 2);
+        assert_eq!(
+            linux_commands("KDE", false, "/a.jpg"),
+            [["plasma-apply-wallpaperimage", "/a.jpg"]]
+        );
+        assert_eq!(linux_commands("", true, "/a.jpg")[0][0], "swaymsg");
+        assert_eq!(
+            linux_commands("i3", false, "/a.jpg"),
+            [["feh", "--bg-fill", "/a.jpg"]]
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn linux_monitor_desktops() {
+        use crate::wallpaper::{linux_monitor_commands, parse_fehbg};
+
+        let wallpapers = [
+            ("DP-1".to_string(), Some("/a.jpg".to_string())),
+            ("DP-2".to_string(), None),
+            ("HDMI-1".to_string(), Some("/b.jpg".to_string())),
+        ];
+        let sway = linux_monitor_commands("", true, &wallpapers, &[]).unwrap();
+        assert_eq!(sway.len(), 2);
+        assert_eq!(
+            sway[1],
+            ["swaymsg", "output", "HDMI-1", "bg", "/b.jpg", "fill"]
+        );
+        // the monitor in between keeps its image so the last one still gets /b.jpg
+        assert_eq!(
+            linux_monitor_commands("i3", false, &wallpapers, &["/x.jpg".to_string()]).unwrap(),
+            [["feh", "--bg-fill", "/a.jpg", "/a.jpg", "/b.jpg"]]
+        );
+        let previous =
+            parse_fehbg("#!/bin/sh\nfeh --no-fehbg --bg-fill '/x.jpg' '/it'\\''s.jpg' \n");
+        assert_eq!(previous, ["/x.jpg", "/it's.jpg"]);
+        assert_eq!(
+            linux_monitor_commands("i3", false, &wallpapers, &previous).unwrap(),
+            [["feh", "--bg-fill", "/a.jpg", "/it's.jpg", "/b.jpg"]]
+        );
+        assert!(linux_monitor_commands("GNOME", false, &wallpapers, &[]).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use crate::daily::{pick_daily_wallpaper, DailyWallpaper};
+use crate::http::build_client;
+use crate::library::{file_hash, Library, LibraryEntry};
+use crate::quota::enforce_quota;
+use crate::wallpaper::{next_rotation, set_wallpaper};
+use log::{error, info, warn};
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::task::spawn_blocking;
+use wallapi::types::{ListingData, SearchOptions, Sorting, SortingOrder};
+use wallapi::WallhavenClient;
+use wallcore::download_manager::{part_path, DOWNLOAD_TIMEOUT};
+use wallcore::files::save_json;
+use wallcore::history::unix_now;
+use wallcore::settings::SavedSettings;
+use wallcore::utils::MEGABYTE;
+
+/// A search that's re-run to download the wallpapers added to it since the last check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Watch {
+    /// Always sorted newest first, never contains the api key
+    pub(crate) options: SearchOptions,
+    /// Newest wallpaper the last check saw, None until the first check
+    pub(crate) last_seen_id: Option<String>,
+    /// Seconds since the unix epoch
+    pub(crate) last_checked: Option<u64>,
+    /// Shows a desktop notification when new wallpapers turn up
+    pub(crate) notify: bool,
+}
+
+impl Watch {
+    pub(crate) fn new(options: &SearchOptions) -> Self {
+        Self {
+            options: SearchOptions {
+                api_key: None,
+                page: None,
+                seed: None,
+                sorting: Some(Sorting::DateAdded),
+                sorting_order: Some(SortingOrder::Descending),
+                ..options.clone()
+            },
+            last_seen_id: None,
+            last_checked: None,
+            notify: true,
+        }
+    }
+
+    pub(crate) fn query(&self) -> &str {
+        match self.options.query.as_deref() {
+            Some(query) if !query.is_empty() => query,
+            _ => "everything",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct WatchList {
+    pub(crate) watches: Vec<Watch>,
+}
+
+impl WatchList {
+    /// Starts watching a search, returns false if it's already watched
+    pub(crate) fn add(&mut self, options: &SearchOptions) -> bool {
+        let watch = Watch::new(options);
+        if self.watches.iter().any(|w| w.options == watch.options) {
+            return false;
+        }
+        self.watches.push(watch);
+        true
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) {
+        if index < self.watches.len() {
+            self.watches.remove(index);
+        }
+    }
+
+    /// Takes on what a check learned, watches that were removed while it ran stay removed
+    pub(crate) fn update(&mut self, checked: Vec<Watch>) {
+        for checked in checked {
+            if let Some(watch) = self
+                .watches
+                .iter_mut()
+                .find(|w| w.options == checked.options)
+            {
+                watch.last_seen_id = checked.last_seen_id;
+                watch.last_checked = checked.last_checked;
+            }
+        }
+    }
+
+    pub(crate) async fn save_watches(watches: WatchList) -> anyhow::Result<()> {
+        let watch_file = save_json("watches.json", &watches).await?;
+        info!("Saved watched searches to {:?}", watch_file);
+        Ok(())
+    }
+
+    // Function left sync intentionally, same as the settings
+    pub(crate) fn load_watches() -> Option<Self> {
+        let app_dirs = AppDirs::new(Some("wall-a-bunga"), true).unwrap();
+        let watch_file = app_dirs.config_dir.join("watches.json");
+        let json = std::fs::read_to_string(watch_file.clone()).ok()?;
+        info!("Loaded watched searches from {:?}", watch_file);
+        serde_json::from_str(&json).ok()
+    }
+}
+
+/// Results that came in after the last seen wallpaper, newest first. When it's no longer among
+/// the results, it was probably deleted and the whole page counts as new.
+fn new_since(listings: &[ListingData], last_seen_id: &str) -> Vec<ListingData> {
+    listings
+        .iter()
+        .take_while(|listing| listing.id != last_seen_id)
+        .cloned()
+        .collect()
+}
+
+async fn notify(summary: String, body: String) {
+    let result = spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .appname("wall-a-bunga")
+            .summary(&summary)
+            .body(&body)
+            .show()
+            .map(|_| ())
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Failed to show a notification {:?}", e),
+        Err(e) => error!("Notification task failed {:?}", e),
+    }
+}
+
+/// Re-runs a watched search, returning the watch moved past the newest result along with the
+/// wallpapers added since the last check. The first check only notes where the search is at.
+async fn check_watch(
+    client: &WallhavenClient,
+    mut watch: Watch,
+    api_key: Option<String>,
+) -> Result<(Watch, Vec<ListingData>), String> {
+    let options = SearchOptions {
+        api_key,
+        ..watch.options.clone()
+    };
+    let response = client.search(&options).await.map_err(|e| e.to_string())?;
+    let listings = response.data.ok_or_else(|| {
+        response
+            .error
+            .unwrap_or_else(|| "No error message".to_string())
+    })?;
+    let new = match &watch.last_seen_id {
+        Some(last_seen_id) => new_since(&listings, last_seen_id),
+        None => vec![],
+    };
+    if let Some(newest) = listings.first() {
+        watch.last_seen_id = Some(newest.id.clone());
+    }
+    watch.last_checked = Some(unix_now());
+    Ok((watch, new))
+}
+
+/// Checks every watch, notifying about new wallpapers for the watches that ask for it. Returns
+/// the checked watches and the new wallpapers, a watch that failed to check is left as it was.
+pub(crate) async fn check_watches(
+    client: WallhavenClient,
+    watches: Vec<Watch>,
+    api_key: Option<String>,
+) -> (Vec<Watch>, Vec<ListingData>) {
+    let mut checked = vec![];
+    let mut found = vec![];
+    for watch in watches {
+        match check_watch(&client, watch.clone(), api_key.clone()).await {
+            Ok((watch, new)) => {
+                info!("{} new wallpapers for {}", new.len(), watch.query());
+                if watch.notify && !new.is_empty() {
+                    notify(
+                        format!("{} new wallpapers", new.len()),
+                        format!("Downloading new wallpapers for {}", watch.query()),
+                    )
+                    .await;
+                }
+                checked.push(watch);
+                // the same wallpaper can turn up in more than one watch
+                for listing in new {
+                    if !found.iter().any(|l: &ListingData| l.id == listing.id) {
+                        found.push(listing);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to check {} {}", watch.query(), e);
+                checked.push(watch);
+            }
+        }
+    }
+    (checked, found)
+}
+
+/// Streams the file at the url to `path` a chunk at a time, so whole wallpapers aren't held in
+/// memory
+async fn write_download(client: &reqwest::Client, url: &str, path: &Path) -> Result<(), String> {
+    let mut response = client
+        .get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())
+}
+
+/// Downloads a wallpaper without the gui's download manager and adds it to the library
+pub(crate) async fn download_wallpaper(
+    client: &reqwest::Client,
+    listing: &ListingData,
+    directory: &Path,
+    library: &Library,
+) -> Result<(), String> {
+    if library.check_downloaded(listing.id.clone()).await {
+        return Ok(());
+    }
+    let file_name = listing
+        .path
+        .rsplit('/')
+        .next()
+        .ok_or_else(|| format!("No file name in {}", listing.path))?;
+    let save_path = directory.join(file_name);
+    if !save_path.exists() {
+        // renamed once it's all there, same as the gui's downloads
+        let part = part_path(&save_path);
+        if let Err(e) = write_download(client, &listing.path, &part).await {
+            if let Err(e) = tokio::fs::remove_file(&part).await {
+                warn!("Failed to remove {:?} {}", part, e);
+            }
+            return Err(e);
+        }
+        tokio::fs::rename(&part, &save_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        info!("Downloaded {} to {:?}", listing.id, save_path);
+    }
+    let entry = LibraryEntry {
+        id: listing.id.clone(),
+        hash: file_hash(&save_path).ok(),
+        path: save_path,
+        image_hash: None,
+        tags: listing.tags.iter().map(|tag| tag.name.clone()).collect(),
+        resolution: Some(listing.resolution.clone()),
+        listing: Some(listing.clone()),
+        protected: false,
+    };
+    library.record(&entry).map_err(|e| e.to_string())
+}
+
+/// Sets the wallpaper of the day when nothing has been picked today
+async fn pick_daily(
+    client: &WallhavenClient,
+    settings: &SavedSettings,
+    directory: &Path,
+    library: &Library,
+) {
+    let mut daily = DailyWallpaper::load_daily().unwrap_or_default();
+    if !daily.is_due(unix_now()) {
+        return;
+    }
+    match pick_daily_wallpaper(
+        client.clone(),
+        daily.clone(),
+        settings.api_key.clone(),
+        directory.to_path_buf(),
+        library.clone(),
+        settings.wallpaper_command.clone(),
+    )
+    .await
+    {
+        Ok(pick) => {
+            daily.picks.insert(0, pick);
+            if let Err(e) = DailyWallpaper::save_daily(daily).await {
+                error!("Failed to save the daily picks {}", e);
+            }
+        }
+        Err(e) => error!("Failed to pick the wallpaper of the day {}", e),
+    }
+}
+
+/// Checks the watched searches once and downloads what's new into the save directory, then picks
+/// the wallpaper of the day and keeps to the disk quota
+async fn check_once(
+    client: &WallhavenClient,
+    settings: &SavedSettings,
+    directory: &Path,
+    library: &Library,
+) {
+    // reloaded every time, so watches added in the gui are picked up
+    let mut watches = WatchList::load_watches().unwrap_or_default();
+    if watches.watches.is_empty() {
+        info!("No searches are watched");
+    }
+    let (checked, found) = check_watches(
+        client.clone(),
+        watches.watches.clone(),
+        settings.api_key.clone(),
+    )
+    .await;
+    watches.update(checked);
+    if let Err(e) = WatchList::save_watches(watches).await {
+        error!("Failed to save the watched searches {}", e);
+    }
+    for listing in &found {
+        if let Err(e) = download_wallpaper(client.http(), listing, directory, library).await {
+            error!("Failed to download {} {}", listing.id, e);
+        }
+    }
+    if settings.daily_wallpaper {
+        pick_daily(client, settings, directory, library).await;
+    }
+    if let Some(megabytes) = settings.max_disk_usage_megabytes {
+        let report = enforce_quota(
+            library.clone(),
+            directory.to_path_buf(),
+            megabytes as u64 * MEGABYTE,
+            settings.quota_action,
+        )
+        .await;
+        if report.exceeded() {
+            warn!(
+                "The save directory uses {} bytes, over the {} byte limit",
+                report.used, report.limit
+            );
+        }
+    }
+}
+
+/// Switches the desktop to another downloaded wallpaper, returns the new one's id
+async fn rotate(
+    settings: &SavedSettings,
+    library: &Library,
+    current: Option<String>,
+) -> Option<String> {
+    let entries = match library.entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to read the library {:?}", e);
+            return current;
+        }
+    };
+    let entry = match next_rotation(entries, current.as_deref()) {
+        Some(entry) => entry,
+        None => {
+            info!("No other downloaded wallpaper to rotate to");
+            return current;
+        }
+    };
+    match set_wallpaper(entry.path, settings.wallpaper_command.clone()).await {
+        Ok(()) => Some(entry.id),
+        Err(e) => {
+            error!("Failed to set the wallpaper {}", e);
+            current
+        }
+    }
+}
+
+/// Runs without a window, checking the watched searches every `watch_interval_minutes` and
+/// downloading what's new into the save directory. Also rotates wallpapers and picks the
+/// wallpaper of the day when they're on. Started with `--watch`.
+pub(crate) async fn run_daemon(settings: SavedSettings) {
+    let library = Library::open().unwrap_or_else(|e| {
+        // downloads still work, they just won't be remembered after a restart
+        error!("Failed to open the library index {:?}", e);
+        Library::default()
+    });
+    let client = match build_client(settings.proxy.as_deref()) {
+        Ok(http) => WallhavenClient::with_client(http),
+        Err(e) => {
+            // nothing is fetched without the proxy it's meant to go through
+            error!("Not watching, {}", e);
+            return;
+        }
+    };
+    let directory = PathBuf::from(settings.save_directory.as_deref().unwrap_or("./"));
+    let interval = Duration::from_secs(settings.watch_interval_minutes.max(1) as u64 * 60);
+    info!("Watching searches every {:?}", interval);
+    let mut watch_timer = tokio::time::interval(interval);
+    // never ticks when rotation is off
+    let rotation = settings
+        .rotation_minutes
+        .map(|minutes| minutes.max(1) as u64 * 60);
+    let mut rotation_timer = tokio::time::interval(Duration::from_secs(rotation.unwrap_or(60)));
+    let mut current = None;
+    loop {
+        tokio::select! {
+            _ = watch_timer.tick() => check_once(&client, &settings, &directory, &library).await,
+            _ = rotation_timer.tick(), if rotation.is_some() => {
+                current = rotate(&settings, &library, current).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::watch::{new_since, Watch, WatchList};
+    use wallapi::types::{ListingData, SearchOptions, Sorting};
+
+    fn listing(id: &str) -> ListingData {
+        ListingData {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_wallpapers_added_since_last_check() {
+        let listings = [
+            listing("new2"),
+            listing("new1"),
+            listing("seen"),
+            listing("old"),
+        ];
+        let ids = |last_seen| {
+            new_since(&listings, last_seen)
+                .into_iter()
+                .map(|l| l.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("seen"), ["new2", "new1"]);
+        assert!(ids("new2").is_empty());
+        // the last seen wallpaper is gone, everything on the page is new
+        assert_eq!(ids("deleted").len(), 4);
+    }
+
+    #[test]
+    fn watches_are_newest_first_and_unique() {
+        let options = SearchOptions {
+            query: Some("cats".to_string()),
+            api_key: Some("secret".to_string()),
+            sorting: Some(Sorting::TopList),
+            ..Default::default()
+        };
+        let mut watches = WatchList::default();
+        assert!(watches.add(&options));
+        assert!(!watches.add(&options));
+        let watch = &watches.watches[0];
+        assert_eq!(watch.options.sorting, Some(Sorting::DateAdded));
+        assert_eq!(watch.options.api_key, None);
+
+        let mut checked = Watch {
+            last_seen_id: Some("abc".to_string()),
+            ..watch.clone()
+        };
+        watches.update(vec![checked.clone()]);
+        assert_eq!(watches.watches[0].last_seen_id.as_deref(), Some("abc"));
+        // removed while the check ran
+        watches.remove(0);
+        checked.last_seen_id = Some("def".to_string());
+        watches.update(vec![checked]);
+        assert!(watches.watches.is_empty());
+    }
+}
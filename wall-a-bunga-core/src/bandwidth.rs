@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket shared by every running download so that together they stay under the
+/// configured speed limit. Also holds back new requests while the server has asked us to slow
+/// down.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<TokenBucket>>,
+    backoff_until: Arc<Mutex<Option<Instant>>>,
+}
+
+#[derive(Debug, Default)]
+struct TokenBucket {
+    /// Bytes per second, None when unlimited
+    rate: Option<f64>,
+    /// Bytes that can be used right away, goes negative while downloads owe time
+    tokens: f64,
+    last_refill: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// Takes `bytes` out of the bucket, returning how long to wait before they may be used
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Option<Duration> {
+        let rate = self.rate?;
+        let elapsed = self
+            .last_refill
+            .map(|last| now.duration_since(last).as_secs_f64())
+            .unwrap_or_default();
+        // allow bursts of up to a second worth of data
+        self.tokens = f64::min(rate, self.tokens + elapsed * rate);
+        self.last_refill = Some(now);
+        self.tokens -= bytes as f64;
+        match self.tokens < 0.0 {
+            true => Some(Duration::from_secs_f64(-self.tokens / rate)),
+            false => None,
+        }
+    }
+}
+
+impl BandwidthLimiter {
+    /// Sets the limit in KB/s, None or 0 removes the limit
+    pub fn set_limit(&self, kilobytes_per_second: Option<u32>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.rate = kilobytes_per_second
+            .filter(|limit| *limit > 0)
+            .map(|limit| limit as f64 * 1000.0);
+        bucket.tokens = bucket.rate.unwrap_or_default();
+        bucket.last_refill = None;
+    }
+
+    /// Stops every download from making new requests for `duration`
+    pub fn back_off(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut backoff_until = self.backoff_until.lock().unwrap();
+        // several downloads can be throttled at once, keep the longest wait
+        if backoff_until.map(|current| current < until).unwrap_or(true) {
+            *backoff_until = Some(until);
+        }
+    }
+
+    /// Time left until requests are allowed again
+    pub fn backoff_remaining(&self) -> Option<Duration> {
+        self.backoff_until
+            .lock()
+            .unwrap()
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Waits out any backoff before a new request is made
+    pub async fn wait_for_backoff(&self) {
+        while let Some(remaining) = self.backoff_remaining() {
+            tokio::time::sleep(remaining).await;
+        }
+    }
+
+    /// Waits until `bytes` fit within the limit
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self.bucket.lock().unwrap().reserve(bytes, Instant::now());
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bandwidth::TokenBucket;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn token_bucket_waits_for_debt() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket {
+            rate: Some(1000.0),
+            tokens: 1000.0,
+            last_refill: Some(now),
+        };
+        assert_eq!(bucket.reserve(500, now), None);
+        assert_eq!(bucket.reserve(1000, now), Some(Duration::from_millis(500)));
+        // half a second later the debt is paid off
+        let later = now + Duration::from_millis(500);
+        assert_eq!(bucket.reserve(0, later), None);
+        let mut unlimited = TokenBucket::default();
+        assert_eq!(unlimited.reserve(1_000_000, now), None);
+    }
+}
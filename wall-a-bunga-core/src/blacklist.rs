@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use wallapi::query::QueryBuilder;
+use wallapi::types::{ListingData, SearchOptions};
+
+/// Wallpapers and tags that are kept out of every search
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blacklist {
+    /// Wallpaper ids picked with "never show again"
+    #[serde(default)]
+    pub ids: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Blacklist {
+    /// Returns false if the wallpaper was already blacklisted
+    pub fn add_id(&mut self, id: &str) -> bool {
+        if self.ids.iter().any(|blacklisted| blacklisted == id) {
+            return false;
+        }
+        self.ids.push(id.to_string());
+        true
+    }
+
+    /// Adds tags separated by spaces or commas, a leading `-` is dropped like in the search box
+    pub fn add_tags(&mut self, input: &str) {
+        for tag in input.split(|c: char| c.is_whitespace() || c == ',') {
+            let tag = tag.trim_start_matches('-');
+            if !tag.is_empty() && !self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                self.tags.push(tag.to_string());
+            }
+        }
+    }
+
+    /// Search results come without their tags, those are only matched by id
+    pub fn hides(&self, listing: &ListingData) -> bool {
+        self.ids.contains(&listing.id)
+            || listing
+                .tags
+                .iter()
+                .any(|tag| self.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag.name)))
+    }
+
+    /// Excludes the blacklisted tags in the query, so wallhaven leaves out the results that
+    /// couldn't be told apart here
+    pub fn exclude_tags(&self, options: &SearchOptions) -> SearchOptions {
+        if self.tags.is_empty() {
+            return options.clone();
+        }
+        let mut query = QueryBuilder::parse(options.query.as_deref().unwrap_or_default());
+        for tag in &self.tags {
+            query.exclude_tag(tag);
+        }
+        SearchOptions {
+            query: Some(query.build()),
+            ..options.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::blacklist::Blacklist;
+    use wallapi::types::{ListingData, SearchOptions, Tag};
+
+    #[test]
+    fn hides_blacklisted_results() {
+        let mut blacklist = Blacklist::default();
+        assert!(blacklist.add_id("j3m8y5"));
+        assert!(!blacklist.add_id("j3m8y5"));
+        blacklist.add_tags("-city, Cars cars");
+        assert_eq!(blacklist.tags, ["city", "Cars"]);
+        let listing = |id: &str, tag: &str| ListingData {
+            id: id.to_string(),
+            tags: vec![Tag {
+                id: 1,
+                name: tag.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(blacklist.hides(&listing("j3m8y5", "nature")));
+        assert!(blacklist.hides(&listing("4x2kqd", "cars")));
+        assert!(!blacklist.hides(&listing("4x2kqd", "nature")));
+        let options = blacklist.exclude_tags(&SearchOptions {
+            query: Some("nature -city".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(options.query.as_deref(), Some("nature -city -Cars"));
+    }
+}
@@ -0,0 +1,71 @@
+use indexmap::IndexMap;
+use wallapi::types::ListingData;
+
+/// A wallpaper the user has selected for download, `T` is the thumbnail it's shown with
+#[derive(Debug, Clone)]
+pub struct CartItem<T> {
+    pub id: String,
+    /// Full size image url
+    pub path: String,
+    pub thumb: T,
+    pub file_size: i64,
+    pub created_at: String,
+}
+
+/// Collects selected wallpapers across searches, pages and tabs until they are downloaded
+#[derive(Debug, Clone)]
+pub struct DownloadCart<T> {
+    items: IndexMap<String, CartItem<T>>,
+}
+
+impl<T> Default for DownloadCart<T> {
+    fn default() -> Self {
+        Self {
+            items: IndexMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> DownloadCart<T> {
+    pub fn add(&mut self, listing: &ListingData, thumb: &T) {
+        self.items
+            .entry(listing.id.clone())
+            .or_insert_with(|| CartItem {
+                id: listing.id.clone(),
+                path: listing.path.clone(),
+                thumb: thumb.clone(),
+                file_size: listing.file_size,
+                created_at: listing.created_at.clone(),
+            });
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.items.shift_remove(id);
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Total size in bytes of everything in the cart
+    pub fn total_size(&self) -> i64 {
+        self.items.values().map(|i| i.file_size).sum()
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &CartItem<T>> {
+        self.items.values()
+    }
+
+    /// Empties the cart, returning everything that was in it in the order it was added
+    pub fn take_all(&mut self) -> Vec<CartItem<T>> {
+        self.items.drain(..).map(|(_, item)| item).collect()
+    }
+}
@@ -0,0 +1,52 @@
+use log::info;
+use std::time::Duration;
+
+/// Shown for searches and checks that couldn't reach wallhaven
+pub const CONNECTION_ERROR: &str = "Couldn't connect to wallhaven.cc";
+
+/// The request never got an answer, as opposed to the server answering with an error
+pub fn is_connection_error(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_body()
+}
+
+/// Same as [is_connection_error] for errors that came out of a search
+pub fn is_search_connection_error(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<wallapi::WallhavenApiClientError>() {
+        Some(wallapi::WallhavenApiClientError::Reqwest(e)) => is_connection_error(e),
+        _ => e
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(is_connection_error),
+    }
+}
+
+/// Asks wallhaven for its headers, any answer at all means the connection is back
+pub async fn probe_connection(client: reqwest::Client) -> bool {
+    let online = client
+        .head("https://wallhaven.cc")
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .is_ok();
+    info!("Connection probe, online: {}", online);
+    online
+}
+
+#[cfg(test)]
+mod test {
+    use crate::connectivity::is_connection_error;
+    use std::net::TcpListener;
+
+    #[tokio::test]
+    async fn refused_connection_is_offline() {
+        // nothing listens on the port once the listener is dropped
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let e = reqwest::get(format!("http://127.0.0.1:{}", port))
+            .await
+            .unwrap_err();
+        assert!(is_connection_error(&e));
+    }
+}
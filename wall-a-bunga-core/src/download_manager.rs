@@ -0,0 +1,1331 @@
+use crate::bandwidth::BandwidthLimiter;
+use crate::connectivity::is_connection_error;
+use crate::utils::file_size_format;
+use futures::stream::BoxStream;
+use indexmap::IndexMap;
+use log::{debug, error, info};
+use reqwest::header::{HeaderMap, CONTENT_RANGE, RANGE, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+
+/// How far along everything queued since the queue was last empty is, like the taskbar button
+/// shows it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BatchProgress {
+    /// Nothing is queued
+    Hidden,
+    /// Where u64 is how much of the batch is done, out of [BatchProgress::STEPS]
+    Running(u64),
+    /// Downloads are paused or waiting for the connection
+    Paused(u64),
+    /// A download failed and waits in the list to be retried
+    Failed(u64),
+}
+
+impl BatchProgress {
+    /// Fine enough for a taskbar button, coarse enough that not every chunk is an update
+    pub const STEPS: u64 = 1000;
+}
+
+#[derive(Debug)]
+pub struct DownloadManager {
+    downloads: IndexMap<String, QueuedDownload>,
+    finished_downloads: usize,
+    /// Downloads finished since the queue was last empty, they count towards the taskbar progress
+    batch_finished: usize,
+    concurrent_downloads: usize,
+    limiter: BandwidthLimiter,
+    /// Every download is held, nothing new starts until it's lifted
+    paused: bool,
+    /// The connection was lost, nothing new starts until it's back
+    offline: bool,
+    /// Smoothed combined speed of past downloads in bytes per second, kept once the queue is
+    /// empty to estimate the next batch
+    recent_speed: Option<f32>,
+    /// Shared with the searches so downloads reuse their connections
+    client: reqwest::Client,
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self {
+            downloads: Default::default(),
+            concurrent_downloads: 5,
+            limiter: Default::default(),
+            finished_downloads: 0,
+            batch_finished: 0,
+            paused: false,
+            offline: false,
+            recent_speed: None,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl DownloadManager {
+    /// Allowed number of downloads running at once, more than this mostly gets throttled by the
+    /// server anyway
+    pub const CONCURRENT_DOWNLOADS: RangeInclusive<usize> = 1..=32;
+
+    /// Queues a wallpaper for download, `uploaded` is applied as the file's modification time
+    pub fn queue_download<T: ToString>(
+        &mut self,
+        url: T,
+        id: T,
+        save_path: PathBuf,
+        uploaded: Option<SystemTime>,
+    ) {
+        self.queue(url.to_string(), id.to_string(), save_path, uploaded, false);
+    }
+
+    /// Queues a wallpaper whose file already exists, it's saved under the first free numbered
+    /// name instead. The name is picked once the download starts, see [DownloadStatus::Renamed].
+    pub fn queue_renamed_download<T: ToString>(
+        &mut self,
+        url: T,
+        id: T,
+        save_path: PathBuf,
+        uploaded: Option<SystemTime>,
+    ) {
+        self.queue(url.to_string(), id.to_string(), save_path, uploaded, true);
+    }
+
+    fn queue(
+        &mut self,
+        url: String,
+        id: String,
+        save_path: PathBuf,
+        uploaded: Option<SystemTime>,
+        rename_existing: bool,
+    ) {
+        if self.downloads.contains_key(&id) {
+            // queueing a failed download again retries it
+            self.retry_download(&id);
+            return;
+        }
+        let (control, receiver) = watch::channel(DownloadControl::Run);
+        self.downloads.insert(
+            id.clone(),
+            QueuedDownload {
+                download: ImageDownload {
+                    url,
+                    id,
+                    save_path,
+                    rename_existing,
+                    control: receiver,
+                    expected_size: None,
+                    limiter: self.limiter.clone(),
+                    uploaded,
+                    client: self.client.clone(),
+                },
+                control,
+                running: false,
+                progress: None,
+                downloaded: 0,
+                failure: None,
+                speed: None,
+                speed_sample: None,
+            },
+        );
+        debug!("Download queue updated {:?}", self.downloads);
+        self.schedule();
+    }
+
+    /// Keeps the numbered name the download picked, it's where the file ends up
+    pub fn set_save_path(&mut self, id: &str, save_path: PathBuf) {
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.download.save_path = save_path;
+            download.download.rename_existing = false;
+        }
+    }
+
+    /// Removes a download that finished, returning where it was saved
+    pub fn remove_download(&mut self, id: &str) -> Option<PathBuf> {
+        let removed = self.downloads.shift_remove(id);
+        self.finished_downloads += 1;
+        self.batch_finished += 1;
+        self.end_batch();
+        self.schedule();
+        removed.map(|queued| queued.download.save_path)
+    }
+
+    /// Removes a download that was cancelled, it doesn't count as finished
+    pub fn remove_cancelled(&mut self, id: &str) {
+        self.downloads.shift_remove(id);
+        self.end_batch();
+        self.schedule();
+    }
+
+    /// The next download queued starts a new batch once everything queued is done
+    fn end_batch(&mut self) {
+        if self.downloads.is_empty() {
+            self.batch_finished = 0;
+        }
+    }
+
+    /// Progress of everything queued since the queue was last empty, for the taskbar button
+    pub fn batch_progress(&self) -> BatchProgress {
+        if self.downloads.is_empty() {
+            return BatchProgress::Hidden;
+        }
+        let downloaded: f32 = self
+            .downloads
+            .values()
+            .filter_map(|d| d.progress)
+            .map(|progress| progress / 100.0)
+            .sum();
+        let total = (self.batch_finished + self.downloads.len()) as f32;
+        let done = ((self.batch_finished as f32 + downloaded) / total * BatchProgress::STEPS as f32)
+            as u64;
+        if self.failed_count() > 0 {
+            BatchProgress::Failed(done)
+        } else if self.paused || self.offline {
+            BatchProgress::Paused(done)
+        } else {
+            BatchProgress::Running(done)
+        }
+    }
+
+    /// Hands free download slots to waiting downloads in the order they were queued. Running
+    /// downloads are never stopped here, so lowering the limit only takes effect as they finish.
+    fn schedule(&mut self) {
+        if self.paused || self.offline {
+            return;
+        }
+        let active = self
+            .downloads
+            .values()
+            .filter(|d| d.running && !d.is_paused())
+            .count();
+        self.downloads
+            .values_mut()
+            .filter(|d| !d.running && !d.is_failed() && !d.is_paused())
+            .take(self.concurrent_downloads.saturating_sub(active))
+            .for_each(|d| {
+                debug!("Starting download {}", d.download.id);
+                d.running = true;
+            });
+    }
+
+    /// Updates the progress of a download, `downloaded` is in bytes and used to measure the speed
+    pub fn set_progress(
+        &mut self,
+        id: &str,
+        progress: Option<f32>,
+        downloaded: u64,
+        total: Option<u64>,
+    ) {
+        if let Some(download) = self.downloads.get_mut(id) {
+            // without a size the progress stays at zero, list_view shows the bytes instead
+            download.progress = Some(progress.unwrap_or_default());
+            download.downloaded = downloaded;
+            download.download.expected_size = total;
+            let now = Instant::now();
+            match download.speed_sample {
+                Some((sampled_at, sampled_bytes)) => {
+                    let elapsed = now.duration_since(sampled_at).as_secs_f32();
+                    // measure over at least a second so the speed doesn't jump around every chunk
+                    if elapsed >= 1.0 {
+                        download.speed =
+                            Some(downloaded.saturating_sub(sampled_bytes) as f32 / elapsed);
+                        download.speed_sample = Some((now, downloaded));
+                        self.update_recent_speed();
+                    }
+                }
+                None => download.speed_sample = Some((now, downloaded)),
+            }
+        }
+    }
+
+    /// Folds the current speed into [DownloadManager::recent_speed], called once per speed sample
+    fn update_recent_speed(&mut self) {
+        let speed = self.total_speed();
+        if speed > 0.0 {
+            self.recent_speed = Some(match self.recent_speed {
+                Some(recent) => recent * 0.9 + speed * 0.1,
+                None => speed,
+            });
+        }
+    }
+
+    /// Estimated seconds to download `bytes` at the speed downloads recently ran at, None until
+    /// something was downloaded
+    pub fn estimated_time(&self, bytes: u64) -> Option<u64> {
+        let speed = self.recent_speed.filter(|speed| *speed > 0.0)?;
+        Some((bytes as f32 / speed).ceil() as u64)
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.downloads.values().filter(|d| d.is_failed()).count()
+    }
+
+    /// Why the download failed, None unless it did
+    pub fn failure(&self, id: &str) -> Option<&str> {
+        self.downloads.get(id)?.failure.as_deref()
+    }
+
+    /// Keeps the failed download around so it can be retried
+    pub fn mark_failed(&mut self, id: &str, reason: String) {
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.failure = Some(reason);
+            download.running = false;
+            download.progress = None;
+            download.reset_speed();
+        }
+    }
+
+    pub fn retry_download(&mut self, id: &str) {
+        if let Some(download) = self.downloads.get_mut(id) {
+            if download.is_failed() {
+                info!("Retrying download {}", id);
+                download.reset_control(DownloadControl::Run);
+                download.failure = None;
+            }
+        }
+        self.schedule();
+    }
+
+    /// Puts a download that lost its connection back in the queue, it continues from its partial
+    /// file once the connection is back
+    pub fn mark_offline(&mut self, id: &str) {
+        self.offline = true;
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.running = false;
+            download.progress = None;
+            download.reset_speed();
+            // keeps a download the user paused paused
+            let control = *download.control.borrow();
+            download.reset_control(control);
+        }
+    }
+
+    /// Holds the queue while there's no connection, downloads waiting on it start once it's back
+    pub fn set_offline(&mut self, offline: bool) {
+        info!("Setting downloads offline to {}", offline);
+        self.offline = offline;
+        self.schedule();
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn pause_download(&mut self, id: &str) {
+        self.send_control(id, DownloadControl::Pause);
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.reset_speed();
+        }
+        // paused downloads give up their slot
+        self.schedule();
+    }
+
+    pub fn resume_download(&mut self, id: &str) {
+        self.send_control(id, DownloadControl::Run);
+        self.schedule();
+    }
+
+    /// Pauses or resumes every download, ones queued while paused wait until it's lifted
+    pub fn set_paused(&mut self, paused: bool) {
+        info!("Setting all downloads paused to {}", paused);
+        self.paused = paused;
+        let control = match paused {
+            true => DownloadControl::Pause,
+            false => DownloadControl::Run,
+        };
+        let ids: Vec<String> = self.downloads.keys().cloned().collect();
+        for id in ids {
+            self.send_control(&id, control);
+            if let Some(download) = self.downloads.get_mut(&id) {
+                download.reset_speed();
+            }
+        }
+        self.schedule();
+    }
+
+    /// Cancels a download. Returns true if it hadn't started yet and was removed right away,
+    /// running downloads clean up their file and report [DownloadStatus::Cancelled] instead.
+    pub fn cancel_download(&mut self, id: &str) -> bool {
+        match self.downloads.get(id) {
+            Some(download) if download.running => {
+                self.send_control(id, DownloadControl::Cancel);
+                false
+            }
+            Some(_) => {
+                self.downloads.shift_remove(id);
+                self.end_batch();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn send_control(&self, id: &str, control: DownloadControl) {
+        if let Some(download) = self.downloads.get(id) {
+            info!("Sending {:?} to download {}", control, id);
+            // the stream may have already finished, in which case there's nothing to control
+            let _ = download.control.send(control);
+        }
+    }
+
+    /// Downloads that should have a stream going, see [ImageDownload::stream]
+    pub fn running(&self) -> impl Iterator<Item = &ImageDownload> {
+        // running downloads keep their stream, even while paused, so they can continue
+        self.downloads
+            .values()
+            .filter(|d| d.running)
+            .map(|d| &d.download)
+    }
+
+    /// Everything queued with its id, in the order it was queued
+    pub fn downloads(&self) -> impl Iterator<Item = (&str, &QueuedDownload)> {
+        self.downloads
+            .iter()
+            .map(|(id, download)| (id.as_str(), download))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.downloads.is_empty()
+    }
+
+    /// Downloads queued, running or failed
+    pub fn len(&self) -> usize {
+        self.downloads.len()
+    }
+
+    /// Downloads holding a slot that aren't paused
+    pub fn running_count(&self) -> usize {
+        self.downloads
+            .values()
+            .filter(|d| d.running && !d.is_paused())
+            .count()
+    }
+
+    pub fn concurrent_downloads(&self) -> usize {
+        self.concurrent_downloads
+    }
+
+    /// Downloads finished since the app started
+    pub fn finished_count(&self) -> usize {
+        self.finished_downloads
+    }
+
+    /// Combined speed of every running download in bytes per second
+    pub fn total_speed(&self) -> f32 {
+        self.downloads
+            .values()
+            .filter(|d| !d.is_failed() && !d.is_paused())
+            .filter_map(|d| d.speed)
+            .sum()
+    }
+
+    /// Estimated seconds until the whole queue is done. Downloads that haven't started yet are
+    /// assumed to be as large as the average of the ones we know the size of.
+    pub fn remaining_time(&self) -> Option<u64> {
+        let speed = self.total_speed();
+        if speed <= 0.0 {
+            return None;
+        }
+        let active: Vec<_> = self.downloads.values().filter(|d| !d.is_failed()).collect();
+        let known: Vec<_> = active
+            .iter()
+            .filter_map(|d| d.download.expected_size)
+            .collect();
+        let average = known.iter().sum::<u64>() / known.len().max(1) as u64;
+        let remaining: u64 = active
+            .iter()
+            .map(|d| {
+                d.download
+                    .expected_size
+                    .unwrap_or(average)
+                    .saturating_sub(d.downloaded)
+            })
+            .sum();
+        Some((remaining as f32 / speed).ceil() as u64)
+    }
+
+    /// Sets how many downloads run at the same time, clamped to
+    /// [DownloadManager::CONCURRENT_DOWNLOADS]. Returns the limit that was applied.
+    pub fn set_concurrent_downloads(&mut self, concurrent_downloads: usize) -> usize {
+        self.concurrent_downloads = concurrent_downloads.clamp(
+            *Self::CONCURRENT_DOWNLOADS.start(),
+            *Self::CONCURRENT_DOWNLOADS.end(),
+        );
+        self.schedule();
+        self.concurrent_downloads
+    }
+
+    /// Time left until downloads continue after the server rate limited us
+    pub fn throttled_for(&self) -> Option<Duration> {
+        self.limiter.backoff_remaining()
+    }
+
+    /// Limits the combined speed of all downloads in KB/s, applies to running downloads too
+    pub fn set_speed_limit(&mut self, kilobytes_per_second: Option<u32>) {
+        self.limiter.set_limit(kilobytes_per_second);
+    }
+
+    /// Downloads queued from now on go through this client
+    pub fn set_client(&mut self, client: reqwest::Client) {
+        self.client = client;
+    }
+}
+
+/// Commands sent into a running download stream
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum DownloadControl {
+    Run,
+    Pause,
+    Cancel,
+}
+
+/// A download in the queue along with how it's going
+#[derive(Debug)]
+pub struct QueuedDownload {
+    download: ImageDownload,
+    control: watch::Sender<DownloadControl>,
+    /// Holds a download slot and has a subscription, only set by [DownloadManager::schedule]
+    running: bool,
+    /// Percentage downloaded, None until the download starts
+    progress: Option<f32>,
+    /// Bytes downloaded so far
+    downloaded: u64,
+    /// Why the download failed, kept until it's retried
+    failure: Option<String>,
+    /// Bytes per second
+    speed: Option<f32>,
+    /// When the speed was last measured and how many bytes were downloaded at the time
+    speed_sample: Option<(Instant, u64)>,
+}
+
+impl QueuedDownload {
+    pub fn is_paused(&self) -> bool {
+        *self.control.borrow() == DownloadControl::Pause
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failure.is_some()
+    }
+
+    /// Why the download failed, None unless it did
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    /// Percentage downloaded, None until the download starts
+    pub fn progress(&self) -> Option<f32> {
+        self.progress
+    }
+
+    /// Bytes downloaded so far
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// Bytes per second, None until it's been measured
+    pub fn speed(&self) -> Option<f32> {
+        self.speed
+    }
+
+    /// Full size in bytes, None when the server didn't say
+    pub fn expected_size(&self) -> Option<u64> {
+        self.download.expected_size
+    }
+
+    pub fn save_path(&self) -> &Path {
+        &self.download.save_path
+    }
+
+    fn reset_speed(&mut self) {
+        self.speed = None;
+        self.speed_sample = None;
+    }
+
+    /// The old stream is gone along with its receiver, the next one needs a new channel
+    fn reset_control(&mut self, control: DownloadControl) {
+        let (sender, receiver) = watch::channel(control);
+        self.control = sender;
+        self.download.control = receiver;
+    }
+}
+
+/// A single wallpaper being downloaded, [ImageDownload::stream] does the downloading
+#[derive(Debug, Clone)]
+pub struct ImageDownload {
+    /// URL of the image we're downloading
+    url: String,
+    /// ID of the message
+    id: String,
+    /// Location to store the image
+    save_path: PathBuf,
+    /// The file at `save_path` already exists, a free numbered name is picked before downloading
+    rename_existing: bool,
+    /// Pauses, resumes or cancels the download
+    control: watch::Receiver<DownloadControl>,
+    /// Size reported by a previous attempt, checked before resuming a partial file
+    expected_size: Option<u64>,
+    /// Shared with every other download to enforce the speed limit
+    limiter: BandwidthLimiter,
+    /// Set as the modification time of the finished file
+    uploaded: Option<SystemTime>,
+    client: reqwest::Client,
+}
+
+/// The most a whole download may take, it's longer than the timeout of other requests so big
+/// wallpapers on a slow or limited connection still finish
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 2);
+
+/// How long a download may go without receiving anything before it's treated as a lost connection
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait after being throttled when the server doesn't say
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Reads `Retry-After` when it's given in seconds, the date form isn't used by wallhaven
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Parses a `Content-Range` header such as `bytes 100-199/200` or `bytes */200`, returning the
+/// first and last byte sent and the full size
+fn parse_content_range(header: &str) -> Option<(Option<(u64, u64)>, u64)> {
+    let (range, total) = header.strip_prefix("bytes ")?.split_once('/')?;
+    let total = total.parse().ok()?;
+    match range {
+        "*" => Some((None, total)),
+        range => {
+            let (start, end) = range.split_once('-')?;
+            Some((Some((start.parse().ok()?, end.parse().ok()?)), total))
+        }
+    }
+}
+
+/// Whether the range the server sent picks up right where the partial file ends and runs to the
+/// end of the image. Anything else means the partial file doesn't match the image on the server.
+fn continues_part(existing: u64, (start, end): (u64, u64), total: u64) -> bool {
+    start == existing && existing < total && end + 1 == total
+}
+
+/// Partial files older than this are assumed to belong to downloads that won't be resumed
+const STALE_PART_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Downloads are written next to their destination with a `.part` suffix and only renamed once
+/// complete, so an interrupted download never looks like a finished wallpaper
+pub fn part_path(save_path: &Path) -> PathBuf {
+    let mut file_name = save_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+    save_path.with_file_name(file_name)
+}
+
+/// Adds a number to the file name, `wallhaven-j3m8y5.jpg` becomes `wallhaven-j3m8y5-2.jpg`
+pub fn numbered_path(save_path: &Path, number: u32) -> PathBuf {
+    let stem = save_path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match save_path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, number),
+    };
+    save_path.with_file_name(file_name)
+}
+
+/// Numbered names tried by [free_numbered_path] before giving up
+const MAX_NUMBERED_NAMES: u32 = 1000;
+
+/// The first [numbered_path] that's neither a file yet nor being downloaded to
+pub async fn free_numbered_path(save_path: &Path) -> std::io::Result<PathBuf> {
+    for number in 2..=MAX_NUMBERED_NAMES {
+        let path = numbered_path(save_path, number);
+        let taken =
+            tokio::fs::try_exists(&path).await? || tokio::fs::try_exists(part_path(&path)).await?;
+        if !taken {
+            return Ok(path);
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("Ran out of file names for {:?}", save_path),
+    ))
+}
+
+/// Removes partial files left behind by downloads that were interrupted a while ago. Recent ones
+/// are kept so queueing the same wallpaper again resumes it.
+// Function left sync intentionally, it runs before the UI starts
+pub fn clean_partial_downloads(directory: &Path) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to read {:?} {:?}", directory, e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().map(|ext| ext != "part").unwrap_or(true) {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age > STALE_PART_AGE)
+            .unwrap_or_default();
+        if stale {
+            info!("Removing stale partial download {:?}", &path);
+            if let Err(e) = std::fs::remove_file(&path) {
+                error!("Failed to delete file {:?} {:?}", &path, e);
+            }
+        }
+    }
+}
+
+/// Describes a file error the way it matters to whoever is downloading, a full disk most of all
+fn io_reason(e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::StorageFull => "The disk is full".to_string(),
+        std::io::ErrorKind::PermissionDenied => {
+            "Not allowed to write to the save directory".to_string()
+        }
+        _ => e.to_string(),
+    }
+}
+
+fn percentage_of(downloaded: u64, total: Option<u64>) -> Option<f32> {
+    total.map(|total| (downloaded as f32 / total as f32) * 100.0)
+}
+
+impl ImageDownload {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Moves the completed partial file to its real name
+    async fn finish(save_path: &Path, uploaded: Option<SystemTime>) -> std::io::Result<()> {
+        let part_path = part_path(save_path);
+        if let Some(uploaded) = uploaded {
+            let file = File::options().write(true).open(&part_path).await?;
+            file.into_std().await.set_modified(uploaded)?;
+        }
+        tokio::fs::rename(&part_path, save_path).await
+    }
+
+    /// Picks the numbered name the wallpaper is saved under since its file already exists
+    async fn rename(mut download: ImageDownload) -> (DownloadStatus, DownloadState) {
+        match free_numbered_path(&download.save_path).await {
+            Ok(save_path) => {
+                info!(
+                    "{:?} already exists, saving {} as {:?}",
+                    &download.save_path, &download.id, &save_path
+                );
+                download.save_path = save_path.clone();
+                download.rename_existing = false;
+                (
+                    DownloadStatus::Renamed {
+                        id: download.id.clone(),
+                        save_path,
+                    },
+                    DownloadState::Started(download),
+                )
+            }
+            Err(e) => {
+                error!("Failed to rename {:?} {:?}", &download.save_path, e);
+                (
+                    DownloadStatus::failed(download.id, io_reason(&e)),
+                    DownloadState::Completed,
+                )
+            }
+        }
+    }
+
+    /// Requests the image, resuming from whatever is already saved to disk when the server
+    /// supports range requests
+    async fn open(download: ImageDownload) -> (DownloadStatus, DownloadState) {
+        if download.rename_existing {
+            return ImageDownload::rename(download).await;
+        }
+        let ImageDownload {
+            url,
+            id,
+            save_path,
+            rename_existing,
+            control,
+            expected_size,
+            limiter,
+            uploaded,
+            client,
+        } = download;
+        let part_path = part_path(&save_path);
+        let existing = match tokio::fs::metadata(&part_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        limiter.wait_for_backoff().await;
+        let mut request = client.get(&url).timeout(DOWNLOAD_TIMEOUT);
+        if existing > 0 {
+            info!("Resuming {} from {} bytes", &id, existing);
+            request = request.header(RANGE, format!("bytes={}-", existing));
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) if is_connection_error(&e) => {
+                info!("Lost the connection requesting {} {:?}", &url, e);
+                return (DownloadStatus::Offline(id), DownloadState::Completed);
+            }
+            Err(e) => {
+                error!("Failed to request {} {:?}", &url, e);
+                let reason = format!("Couldn't request it, {}", e);
+                return (DownloadStatus::failed(id, reason), DownloadState::Completed);
+            }
+        };
+        let content_range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|header| header.to_str().ok())
+            .and_then(parse_content_range);
+        // the file changed on the server since the last attempt, the saved bytes are useless
+        let changed = |total: u64| expected_size.map(|size| size != total).unwrap_or_default();
+        let (file, downloaded, total) = match (response.status(), content_range) {
+            (StatusCode::RANGE_NOT_SATISFIABLE, Some((_, total)))
+                if total == existing && !changed(total) =>
+            {
+                info!("{} was already downloaded", &id);
+                return match ImageDownload::finish(&save_path, uploaded).await {
+                    Ok(_) => (DownloadStatus::Finished(id), DownloadState::Completed),
+                    Err(e) => {
+                        error!("Failed to finish {:?} {:?}", &save_path, e);
+                        (
+                            DownloadStatus::failed(id, io_reason(&e)),
+                            DownloadState::Completed,
+                        )
+                    }
+                };
+            }
+            (StatusCode::PARTIAL_CONTENT, Some((Some(range), total)))
+                if continues_part(existing, range, total) && !changed(total) =>
+            {
+                let file = tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&part_path)
+                    .await;
+                (file, existing, Some(total))
+            }
+            (StatusCode::OK, _) => (File::create(&part_path).await, 0, response.content_length()),
+            (StatusCode::TOO_MANY_REQUESTS, _) => {
+                let retry_after = parse_retry_after(response.headers()).unwrap_or(DEFAULT_BACKOFF);
+                info!(
+                    "Throttled downloading {}, retrying in {:?}",
+                    &id, retry_after
+                );
+                // the whole queue waits, not just this download
+                limiter.back_off(retry_after);
+                return (
+                    DownloadStatus::Throttled(id.clone()),
+                    DownloadState::Started(ImageDownload {
+                        url,
+                        id,
+                        save_path,
+                        rename_existing,
+                        control,
+                        limiter,
+                        uploaded,
+                        expected_size,
+                        client,
+                    }),
+                );
+            }
+            (status, _) if existing > 0 => {
+                info!("Couldn't resume {} ({}), downloading it again", &id, status);
+                if let Err(e) = tokio::fs::remove_file(&part_path).await {
+                    error!("Failed to delete file {:?} {:?}", &part_path, e);
+                    return (
+                        DownloadStatus::failed(id, io_reason(&e)),
+                        DownloadState::Completed,
+                    );
+                }
+                return (
+                    DownloadStatus::Progress {
+                        id: id.clone(),
+                        percentage: None,
+                        downloaded: 0,
+                        total: None,
+                    },
+                    DownloadState::Started(ImageDownload {
+                        url,
+                        id,
+                        save_path,
+                        rename_existing,
+                        control,
+                        limiter,
+                        uploaded,
+                        expected_size: None,
+                        client,
+                    }),
+                );
+            }
+            (status, _) => {
+                error!("Failed to download {} {}", &url, status);
+                let reason = format!("wallhaven answered {}", status);
+                return (DownloadStatus::failed(id, reason), DownloadState::Completed);
+            }
+        };
+        match file {
+            Ok(file) => (
+                DownloadStatus::Progress {
+                    id: id.clone(),
+                    percentage: percentage_of(downloaded, total),
+                    downloaded,
+                    total,
+                },
+                DownloadState::Downloading {
+                    response: Box::new(response),
+                    file: Box::new(file),
+                    total,
+                    downloaded,
+                    id,
+                    save_path,
+                    control,
+                    limiter,
+                    uploaded,
+                },
+            ),
+            Err(e) => {
+                error!("Failed to create file {:?} {:?}", &part_path, e);
+                (
+                    DownloadStatus::failed(id, io_reason(&e)),
+                    DownloadState::Completed,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DownloadState {
+    Started(ImageDownload),
+    Downloading {
+        response: Box<Response>,
+        file: Box<File>,
+        /// Chunked responses don't always come with a length, those are written until they end
+        total: Option<u64>,
+        downloaded: u64,
+        id: String,
+        save_path: PathBuf,
+        control: watch::Receiver<DownloadControl>,
+        limiter: BandwidthLimiter,
+        uploaded: Option<SystemTime>,
+    },
+    Completed,
+}
+
+#[derive(Clone, Debug)]
+pub enum DownloadStatus {
+    Progress {
+        id: String,
+        /// None when the server didn't tell us the size
+        percentage: Option<f32>,
+        /// Bytes downloaded so far
+        downloaded: u64,
+        /// Full size in bytes
+        total: Option<u64>,
+    },
+    Failed {
+        id: String,
+        /// Shown on the result and in the downloads list
+        reason: String,
+    },
+    Finished(String),
+    /// The file already existed, the wallpaper is saved under this numbered name instead
+    Renamed {
+        id: String,
+        save_path: PathBuf,
+    },
+    /// The download was stopped and its partial file removed
+    Cancelled(String),
+    /// The server rate limited us, the download is retried once the backoff is over
+    Throttled(String),
+    /// The connection was lost, the download waits in the queue until it's back
+    Offline(String),
+}
+
+impl DownloadStatus {
+    fn failed(id: String, reason: impl Into<String>) -> Self {
+        DownloadStatus::Failed {
+            id,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl ImageDownload {
+    /// Downloads the wallpaper, reporting how it goes until it's finished, failed or cancelled
+    pub fn stream(self) -> BoxStream<'static, DownloadStatus> {
+        Box::pin(futures::stream::unfold(
+            DownloadState::Started(self),
+            |state| async move {
+                match state {
+                    DownloadState::Started(download) => Some(ImageDownload::open(download).await),
+                    DownloadState::Downloading {
+                        mut response,
+                        mut file,
+                        total,
+                        downloaded,
+                        id,
+                        save_path,
+                        mut control,
+                        limiter,
+                        uploaded,
+                    } => {
+                        let percentage = percentage_of(downloaded, total);
+                        let command = *control.borrow_and_update();
+                        match command {
+                            DownloadControl::Cancel => {
+                                info!("Cancelled download {}", &id);
+                                drop(file);
+                                let part_path = part_path(&save_path);
+                                if let Err(e) = tokio::fs::remove_file(&part_path).await {
+                                    error!("Failed to delete file {:?} {:?}", &part_path, e);
+                                }
+                                return Some((
+                                    DownloadStatus::Cancelled(id),
+                                    DownloadState::Completed,
+                                ));
+                            }
+                            DownloadControl::Pause => {
+                                debug!("Paused download {}", &id);
+                                // nobody is left to resume us if the sender is gone
+                                if control.changed().await.is_err() {
+                                    return Some((
+                                        DownloadStatus::failed(id, "It was abandoned while paused"),
+                                        DownloadState::Completed,
+                                    ));
+                                }
+                                return Some((
+                                    DownloadStatus::Progress {
+                                        id: id.clone(),
+                                        percentage,
+                                        downloaded,
+                                        total,
+                                    },
+                                    DownloadState::Downloading {
+                                        response,
+                                        file,
+                                        total,
+                                        downloaded,
+                                        id,
+                                        save_path,
+                                        control,
+                                        limiter,
+                                        uploaded,
+                                    },
+                                ));
+                            }
+                            DownloadControl::Run => {}
+                        }
+                        let chunk = tokio::select! {
+                            chunk = tokio::time::timeout(STALL_TIMEOUT, response.chunk()) => match chunk {
+                                Ok(chunk) => chunk,
+                                Err(_) => {
+                                    // the partial file is kept, the download resumes from it
+                                    info!("Download {} stalled", &id);
+                                    return Some((DownloadStatus::Offline(id), DownloadState::Completed));
+                                }
+                            },
+                            Ok(()) = control.changed() => {
+                                // handle the new command on the next iteration
+                                return Some((
+                                    DownloadStatus::Progress {
+                                        id: id.clone(),
+                                        percentage,
+                                        downloaded,
+                                        total,
+                                    },
+                                    DownloadState::Downloading {
+                                        response,
+                                        file,
+                                        total,
+                                        downloaded,
+                                        id,
+                                        save_path,
+                                        control,
+                                        limiter,
+                                        uploaded,
+                                    },
+                                ));
+                            }
+                        };
+                        match chunk {
+                            Ok(Some(chunk)) => {
+                                debug!("Downloaded chunk {} bytes {}", &id, chunk.len());
+                                limiter.acquire(chunk.len() as u64).await;
+                                let downloaded = downloaded + chunk.len() as u64;
+                                let percentage = percentage_of(downloaded, total);
+                                match file.write_all(&chunk).await {
+                                    Ok(()) => Some((
+                                        DownloadStatus::Progress {
+                                            id: id.clone(),
+                                            percentage,
+                                            downloaded,
+                                            total,
+                                        },
+                                        DownloadState::Downloading {
+                                            response,
+                                            file,
+                                            total,
+                                            downloaded,
+                                            id,
+                                            save_path,
+                                            control,
+                                            limiter,
+                                            uploaded,
+                                        },
+                                    )),
+                                    Err(e) => {
+                                        let part_path = part_path(&save_path);
+                                        error!("Failed to write file! {:?} {:?}", &part_path, e);
+                                        if let Err(e) = tokio::fs::remove_file(&part_path).await {
+                                            error!(
+                                                "Failed to delete file {:?} {:?}",
+                                                &part_path, e
+                                            );
+                                        }
+                                        Some((
+                                            DownloadStatus::failed(id, io_reason(&e)),
+                                            DownloadState::Completed,
+                                        ))
+                                    }
+                                }
+                            }
+                            Ok(None) if total.map(|total| total == downloaded).unwrap_or(true) => {
+                                // make sure everything is on disk before it takes the real name
+                                let finished = match file.flush().await {
+                                    Ok(_) => ImageDownload::finish(&save_path, uploaded).await,
+                                    Err(e) => Err(e),
+                                };
+                                match finished {
+                                    Ok(_) => Some((
+                                        DownloadStatus::Finished(id),
+                                        DownloadState::Completed,
+                                    )),
+                                    Err(e) => {
+                                        error!("Failed to finish {:?} {:?}", &save_path, e);
+                                        Some((
+                                            DownloadStatus::failed(id, io_reason(&e)),
+                                            DownloadState::Completed,
+                                        ))
+                                    }
+                                }
+                            }
+                            Ok(None) => {
+                                // keep what we have so a retry can pick up from there
+                                error!(
+                                    "Download {} ended early, got {} of {:?} bytes",
+                                    &id, downloaded, total
+                                );
+                                let reason = format!(
+                                    "It ended early, after {}",
+                                    file_size_format(downloaded as i64)
+                                );
+                                Some((DownloadStatus::failed(id, reason), DownloadState::Completed))
+                            }
+                            // the partial file is kept, the download resumes from it
+                            Err(e) if is_connection_error(&e) => {
+                                info!("Lost the connection downloading {} {:?}", &id, e);
+                                Some((DownloadStatus::Offline(id), DownloadState::Completed))
+                            }
+                            Err(e) => {
+                                error!("Failed to download {} {:?}", &id, e);
+                                Some((
+                                    DownloadStatus::failed(id, e.to_string()),
+                                    DownloadState::Completed,
+                                ))
+                            }
+                        }
+                    }
+                    DownloadState::Completed => {
+                        debug!("Closing download");
+                        None
+                    }
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::download_manager::{
+        continues_part, free_numbered_path, io_reason, numbered_path, parse_content_range,
+        parse_retry_after, part_path, BatchProgress, DownloadManager,
+    };
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use std::io::ErrorKind;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn scheduling_keeps_running_downloads() {
+        let mut manager = DownloadManager::default();
+        manager.set_concurrent_downloads(2);
+        for id in ["a", "b", "c"] {
+            manager.queue_download(id, id, id.into(), None);
+        }
+        let running = |manager: &DownloadManager| -> Vec<String> {
+            manager
+                .downloads
+                .values()
+                .filter(|d| d.running)
+                .map(|d| d.download.id.clone())
+                .collect()
+        };
+        assert_eq!(running(&manager), ["a", "b"]);
+        // lowering the limit doesn't stop anything
+        manager.set_concurrent_downloads(1);
+        assert_eq!(running(&manager), ["a", "b"]);
+        manager.remove_download("a");
+        assert_eq!(running(&manager), ["b"]);
+        manager.set_concurrent_downloads(3);
+        assert_eq!(running(&manager), ["b", "c"]);
+        assert_eq!(manager.set_concurrent_downloads(0), 1);
+        assert_eq!(manager.set_concurrent_downloads(100), 32);
+    }
+
+    #[test]
+    fn estimates_from_recent_speed() {
+        let mut manager = DownloadManager::default();
+        assert_eq!(manager.estimated_time(1_000_000), None);
+        manager.recent_speed = Some(500_000.0);
+        assert_eq!(manager.estimated_time(1_000_000), Some(2));
+        assert_eq!(manager.estimated_time(1_200_000), Some(3));
+    }
+
+    #[test]
+    fn failures_keep_their_reason() {
+        let mut manager = DownloadManager::default();
+        manager.queue_download("a", "a", "a".into(), None);
+        manager.mark_failed("a", io_reason(&ErrorKind::StorageFull.into()));
+        assert_eq!(manager.failure("a"), Some("The disk is full"));
+        assert_eq!(manager.failed_count(), 1);
+        manager.retry_download("a");
+        assert_eq!(manager.failure("a"), None);
+        assert!(manager.downloads["a"].running);
+    }
+
+    #[test]
+    fn progress_of_the_batch() {
+        let mut manager = DownloadManager::default();
+        assert_eq!(manager.batch_progress(), BatchProgress::Hidden);
+        for id in ["a", "b", "c", "d"] {
+            manager.queue_download(id, id, id.into(), None);
+        }
+        manager.remove_download("a");
+        manager.set_progress("b", Some(50.0), 500, Some(1000));
+        assert_eq!(manager.batch_progress(), BatchProgress::Running(375));
+        manager.set_paused(true);
+        assert_eq!(manager.batch_progress(), BatchProgress::Paused(375));
+        manager.set_paused(false);
+        manager.mark_failed("c", "Not found".to_string());
+        assert_eq!(manager.batch_progress(), BatchProgress::Failed(375));
+        for id in ["b", "c", "d"] {
+            manager.remove_download(id);
+        }
+        assert_eq!(manager.batch_progress(), BatchProgress::Hidden);
+        // the next batch starts from nothing
+        manager.queue_download("e", "e", "e".into(), None);
+        assert_eq!(manager.batch_progress(), BatchProgress::Running(0));
+    }
+
+    #[test]
+    fn offline_holds_the_queue() {
+        let mut manager = DownloadManager::default();
+        manager.set_concurrent_downloads(1);
+        for id in ["a", "b"] {
+            manager.queue_download(id, id, id.into(), None);
+        }
+        manager.mark_offline("a");
+        assert!(manager.is_offline());
+        assert!(manager
+            .downloads
+            .values()
+            .all(|d| !d.running && !d.is_failed()));
+        manager.set_offline(false);
+        assert!(manager.downloads["a"].running);
+        assert!(!manager.downloads["b"].running);
+    }
+
+    #[test]
+    fn content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200"),
+            Some((Some((100, 199)), 200))
+        );
+        assert_eq!(parse_content_range("bytes */200"), Some((None, 200)));
+        assert_eq!(parse_content_range("bytes 100-199/*"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn resumes_matching_parts() {
+        assert!(continues_part(100, (100, 199), 200));
+        // the server ignored where the partial file ends
+        assert!(!continues_part(100, (0, 199), 200));
+        // the partial file is as long as the image or longer, it's a different one
+        assert!(!continues_part(200, (200, 199), 200));
+        assert!(!continues_part(300, (300, 199), 200));
+        // only part of the rest was sent
+        assert!(!continues_part(100, (100, 149), 200));
+    }
+
+    #[test]
+    fn retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn part_file_names() {
+        assert_eq!(
+            part_path(Path::new("walls/wallhaven-j3m8y5.jpg")),
+            Path::new("walls/wallhaven-j3m8y5.jpg.part")
+        );
+        assert_eq!(
+            numbered_path(Path::new("walls/wallhaven-j3m8y5.jpg"), 2),
+            Path::new("walls/wallhaven-j3m8y5-2.jpg")
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_taken_names() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-names-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("wallhaven-j3m8y5.jpg");
+        std::fs::write(dir.join("wallhaven-j3m8y5-2.jpg"), "").unwrap();
+        // another download is already writing to it
+        std::fs::write(dir.join("wallhaven-j3m8y5-3.jpg.part"), "").unwrap();
+        assert_eq!(
+            free_numbered_path(&save_path).await.unwrap(),
+            dir.join("wallhaven-j3m8y5-4.jpg")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
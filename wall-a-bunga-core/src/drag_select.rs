@@ -0,0 +1,47 @@
+/// A rectangle dragged over the results grid, from the tile it started on to the tile under the
+/// cursor
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DragSelection {
+    /// Position among the visible results of the tile the drag started on
+    pub start: usize,
+    pub end: usize,
+    /// Whether the touched results get selected or deselected, picked from the first tile
+    pub select: bool,
+}
+
+impl DragSelection {
+    pub fn new(start: usize, select: bool) -> Self {
+        Self {
+            start,
+            end: start,
+            select,
+        }
+    }
+
+    /// Whether the tile at the position is inside the rectangle, in a grid of `columns` tiles
+    /// per row
+    pub fn contains(&self, position: usize, columns: usize) -> bool {
+        let (start_row, start_column) = (self.start / columns, self.start % columns);
+        let (end_row, end_column) = (self.end / columns, self.end % columns);
+        let rows = start_row.min(end_row)..=start_row.max(end_row);
+        let tile_columns = start_column.min(end_column)..=start_column.max(end_column);
+        rows.contains(&(position / columns)) && tile_columns.contains(&(position % columns))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::drag_select::DragSelection;
+
+    #[test]
+    fn covers_tiles_in_rectangle() {
+        // dragged up and to the left, from the third tile of the third row to the second tile
+        // of the second row
+        let mut drag = DragSelection::new(12, true);
+        drag.end = 6;
+        let covered: Vec<usize> = (0..15).filter(|p| drag.contains(*p, 5)).collect();
+        assert_eq!(covered, [6, 7, 11, 12]);
+        assert!(DragSelection::new(3, false).contains(3, 5));
+        assert!(!DragSelection::new(3, false).contains(8, 5));
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use platform_dirs::AppDirs;
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Tells apart the temporary files of writes that are running at the same time
+static WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// A temporary file next to `path` that no other write uses
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    Ok(path.with_file_name(format!(
+        "{}.{}-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+/// The directory `path` is in, when it has one
+fn parent_dir(path: &Path) -> Option<&Path> {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+}
+
+/// Replaces the file with `contents` through a temporary file next to it, so a failed write can't
+/// leave half a file behind. Every write has a temporary file of its own, writes running at the
+/// same time don't trip over each other and the last one to finish is kept. The directory is
+/// created when it doesn't exist yet.
+pub async fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    if let Some(parent) = parent_dir(path) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temp_file = temp_path(path)?;
+    let result = match tokio::fs::write(&temp_file, contents).await {
+        Ok(()) => tokio::fs::rename(&temp_file, path).await,
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        if let Err(e) = tokio::fs::remove_file(&temp_file).await {
+            // there's nothing to clean up when it couldn't even be created
+            debug!("Failed to remove {:?} {}", temp_file, e);
+        }
+    }
+    result
+}
+
+/// Same as [write_atomically] for code that can't wait on it, like loading the settings at startup
+pub fn write_atomically_blocking(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    if let Some(parent) = parent_dir(path) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp_file = temp_path(path)?;
+    let result = match std::fs::write(&temp_file, contents) {
+        Ok(()) => std::fs::rename(&temp_file, path),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        if let Err(e) = std::fs::remove_file(&temp_file) {
+            debug!("Failed to remove {:?} {}", temp_file, e);
+        }
+    }
+    result
+}
+
+/// Saves the value as json in the config directory under `name`, like `history.json`. The path
+/// it was saved to.
+pub async fn save_json(name: &str, value: &impl Serialize) -> Result<PathBuf> {
+    let app_dirs = AppDirs::new(Some("wall-a-bunga"), true)
+        .ok_or_else(|| anyhow!("No config directory to save {} in", name))?;
+    let path = app_dirs.config_dir.join(name);
+    let json = serde_json::to_string(value)?;
+    write_atomically(&path, json)
+        .await
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::files::write_atomically;
+
+    #[tokio::test]
+    async fn writes_at_the_same_time() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-files-{}", std::process::id()));
+        let path = dir.join("nested").join("file.json");
+        let (a, b) = tokio::join!(write_atomically(&path, "a"), write_atomically(&path, "b"));
+        a.unwrap();
+        b.unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written == "a" || written == "b");
+        // only the file is left, no temporary files
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use crate::files::save_json;
+use anyhow::Result;
+use log::info;
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use wallapi::types::SearchOptions;
+
+/// Maximum number of searches we keep around
+const MAX_HISTORY_ENTRIES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Options used for the search, never contains the api key
+    pub options: SearchOptions,
+    /// Seconds since the unix epoch
+    pub searched_at: u64,
+}
+
+impl HistoryEntry {
+    pub fn query(&self) -> &str {
+        self.options.query.as_deref().unwrap_or_default()
+    }
+
+    /// Short summary of the non default filters used, e.g. `sketchy, Top List, 1920x1080`
+    pub fn describe_filters(&self) -> String {
+        let options = &self.options;
+        let mut filters = vec![];
+        if let Some(purity) = &options.purity {
+            let names = [
+                (purity.clean, "clean"),
+                (purity.sketchy, "sketchy"),
+                (purity.nsfw, "nsfw"),
+            ];
+            filters.extend(
+                names
+                    .iter()
+                    .filter(|(on, _)| *on)
+                    .map(|(_, n)| n.to_string()),
+            );
+        }
+        if let Some(categories) = &options.categories {
+            let names = [
+                (categories.general, "general"),
+                (categories.anime, "anime"),
+                (categories.people, "people"),
+            ];
+            filters.extend(
+                names
+                    .iter()
+                    .filter(|(on, _)| *on)
+                    .map(|(_, n)| n.to_string()),
+            );
+        }
+        if let Some(sorting) = &options.sorting {
+            filters.push(sorting.to_string());
+        }
+        if let Some(minimum) = &options.minimum_resolution {
+            filters.push(format!("≥{}", minimum));
+        }
+        if let Some(resolutions) = &options.resolutions {
+            filters.extend(resolutions.iter().map(|r| r.to_string()));
+        }
+        if let Some(ratios) = &options.ratios {
+            filters.extend(ratios.iter().map(|r| format!("{}:{}", r.x, r.y)));
+        }
+        filters.join(", ")
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SearchHistory {
+    /// Most recent search first
+    pub entries: Vec<HistoryEntry>,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl SearchHistory {
+    /// Records a search, moving it to the top if the same search was already made
+    pub fn record(&mut self, options: &SearchOptions) {
+        let options = SearchOptions {
+            api_key: None,
+            page: None,
+            seed: None,
+            ..options.clone()
+        };
+        self.entries.retain(|e| e.options != options);
+        self.entries.insert(
+            0,
+            HistoryEntry {
+                options,
+                searched_at: unix_now(),
+            },
+        );
+        self.entries.truncate(MAX_HISTORY_ENTRIES);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+    }
+
+    pub async fn save_history(history: SearchHistory) -> Result<()> {
+        let history_file = save_json("history.json", &history).await?;
+        info!("Saved search history to {:?}", history_file);
+        Ok(())
+    }
+
+    // Function left sync intentionally, same as the settings
+    pub fn load_history() -> Option<Self> {
+        // without a config directory there's no history to load, searching still works
+        let app_dirs = AppDirs::new(Some("wall-a-bunga"), true)?;
+        let history_file = app_dirs.config_dir.join("history.json");
+        let json = std::fs::read_to_string(history_file.clone()).ok()?;
+        info!("Loaded search history from {:?}", history_file);
+        serde_json::from_str(&json).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_dedupes_and_strips_api_key() {
+        let mut history = SearchHistory::default();
+        let options = SearchOptions {
+            query: Some("cats".to_string()),
+            api_key: Some("secret".to_string()),
+            page: Some(3),
+            ..Default::default()
+        };
+        history.record(&options);
+        history.record(&SearchOptions {
+            query: Some("dogs".to_string()),
+            ..Default::default()
+        });
+        history.record(&options);
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.entries[0].query(), "cats");
+        assert_eq!(history.entries[0].options.api_key, None);
+        assert_eq!(history.entries[0].options.page, None);
+    }
+}
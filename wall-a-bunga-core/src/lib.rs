@@ -0,0 +1,21 @@
+//! Everything wall-a-bunga does that doesn't need a window: the settings, searching, picking
+//! wallpapers and the download queue. The gui and the headless modes both run on it.
+
+pub mod bandwidth;
+pub mod blacklist;
+pub mod cart;
+pub mod connectivity;
+pub mod download_manager;
+pub mod drag_select;
+pub mod files;
+pub mod history;
+pub mod paginator;
+pub mod reddit;
+pub mod result_filter;
+pub mod settings;
+pub mod source;
+pub mod tags;
+pub mod undo;
+pub mod unsplash;
+pub mod updates;
+pub mod utils;
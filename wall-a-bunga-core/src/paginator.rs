@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+use wallapi::types::SearchMetaData;
+
+/// Shortest time between two requests for the next page, scrolling sends a burst of events near
+/// the end of the results
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Decides when a tab asks for its next page, for both scrolling to the end and the next page
+/// button
+#[derive(Debug, Default)]
+pub struct Paginator {
+    /// A page was asked for and hasn't arrived yet, nothing else is asked for until it does
+    in_flight: bool,
+    last_request: Option<Instant>,
+    /// Counts the searches of the tab, pages that arrive for an earlier one are dropped
+    generation: u64,
+}
+
+impl Paginator {
+    /// The page after the last one received, None while a page is being fetched, once the last
+    /// page arrived, or when the previous page was asked for too recently
+    pub fn next_page(&mut self, meta: Option<&SearchMetaData>, now: Instant) -> Option<i32> {
+        if self
+            .last_request
+            .is_some_and(|last| now.duration_since(last) < DEBOUNCE)
+        {
+            return None;
+        }
+        self.advance(meta, now)
+    }
+
+    /// Same as [Paginator::next_page] without the debounce, for going through every page one
+    /// after another
+    pub fn advance(&mut self, meta: Option<&SearchMetaData>, now: Instant) -> Option<i32> {
+        if self.in_flight {
+            return None;
+        }
+        let meta = meta?;
+        let page = meta.current_page as i32 + 1;
+        if page > meta.last_page as i32 {
+            return None;
+        }
+        self.in_flight = true;
+        self.last_request = Some(now);
+        Some(page)
+    }
+
+    /// A page arrived or failed to, the next one can be asked for
+    pub fn received(&mut self) {
+        self.in_flight = false;
+    }
+
+    /// A new search replaced the results, whatever the one before asked for no longer counts
+    pub fn restart(&mut self) {
+        self.generation += 1;
+        self.in_flight = false;
+        self.last_request = None;
+    }
+
+    /// The search the pages asked for now belong to, see [Paginator::restart]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::paginator::{Paginator, DEBOUNCE};
+    use std::time::Instant;
+    use wallapi::types::SearchMetaData;
+
+    #[test]
+    fn asks_for_one_page_at_a_time() {
+        let mut paginator = Paginator::default();
+        let now = Instant::now();
+        let meta = SearchMetaData {
+            current_page: 1,
+            last_page: 3,
+            ..Default::default()
+        };
+        assert_eq!(paginator.next_page(None, now), None);
+        assert_eq!(paginator.next_page(Some(&meta), now), Some(2));
+        // still in flight
+        assert_eq!(paginator.next_page(Some(&meta), now + DEBOUNCE), None);
+        paginator.received();
+        // arrived too quickly after asking
+        assert_eq!(paginator.next_page(Some(&meta), now), None);
+        let meta = SearchMetaData {
+            current_page: 2,
+            ..meta
+        };
+        assert_eq!(paginator.advance(Some(&meta), now), Some(3));
+        paginator.received();
+        // that was the last page
+        let meta = SearchMetaData {
+            current_page: 3,
+            ..meta
+        };
+        assert_eq!(paginator.next_page(Some(&meta), now + DEBOUNCE), None);
+    }
+
+    #[test]
+    fn restarting_forgets_the_page_in_flight() {
+        let mut paginator = Paginator::default();
+        let now = Instant::now();
+        let meta = SearchMetaData {
+            current_page: 1,
+            last_page: 3,
+            ..Default::default()
+        };
+        assert_eq!(paginator.next_page(Some(&meta), now), Some(2));
+        let generation = paginator.generation();
+        paginator.restart();
+        assert_ne!(paginator.generation(), generation);
+        // nothing is in flight for the new search, nor was anything asked for too recently
+        assert_eq!(paginator.next_page(Some(&meta), now), Some(2));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::utils::format_utc;
+use anyhow::Result;
+use log::info;
+use serde::Deserialize;
+use std::time::{Duration, UNIX_EPOCH};
+use wallapi::rate_limit::RateLimiter;
+use wallapi::types::{ListingData, PurityLevel, SearchMetaData, Thumbs};
+
+/// Posts asked for at once, most of a page are image posts in wallpaper subreddits
+const PAGE_SIZE: u32 = 25;
+
+/// Reddit allows clients that aren't logged in 10 requests a minute
+pub static RATE_LIMIT: RateLimiter = RateLimiter::new(10, Duration::from_secs(60));
+
+/// Thumbnails narrower than this look blurry in the results
+const THUMBNAIL_WIDTH: i64 = 300;
+
+/// Only posts linking straight to one of these are wallpapers, galleries and videos are skipped
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    data: ListingPage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListingPage {
+    children: Vec<Child>,
+    /// Name of the last post, the next page starts after it
+    after: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Child {
+    data: Post,
+}
+
+#[derive(Debug, Deserialize)]
+struct Post {
+    id: String,
+    url: String,
+    permalink: String,
+    score: i64,
+    created_utc: f64,
+    over_18: bool,
+    preview: Option<Preview>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Preview {
+    images: Vec<PreviewImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviewImage {
+    source: PreviewSize,
+    /// Scaled down copies, smallest first
+    resolutions: Vec<PreviewSize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviewSize {
+    url: String,
+    width: i64,
+    height: i64,
+}
+
+/// A page of a subreddit's wallpapers
+#[derive(Debug, Default)]
+pub struct RedditPage {
+    pub wallpapers: Vec<ListingData>,
+    /// Where the next page starts, None on the last page
+    pub after: Option<String>,
+}
+
+impl RedditPage {
+    /// Reddit pages by where the last one ended instead of by number, so there's one more page
+    /// for as long as there's somewhere to continue from
+    pub fn meta(&self, page: i64) -> SearchMetaData {
+        SearchMetaData {
+            current_page: page,
+            last_page: page + i64::from(self.after.is_some()),
+            per_page: PAGE_SIZE as i64,
+            total: page * PAGE_SIZE as i64,
+            ..Default::default()
+        }
+    }
+}
+
+/// The newest posts of the subreddit, or the ones matching `query` when it isn't empty.
+/// `after` comes from the previous page's [RedditPage::after].
+fn listing_url(subreddit: &str, query: &str, after: Option<&str>) -> Result<reqwest::Url> {
+    let limit = PAGE_SIZE.to_string();
+    // raw_json keeps reddit from escaping the preview urls into html entities
+    let mut params = vec![("limit", limit.as_str()), ("raw_json", "1")];
+    let path = match query.trim() {
+        "" => "hot",
+        query => {
+            params.extend([("q", query), ("restrict_sr", "on"), ("sort", "relevance")]);
+            "search"
+        }
+    };
+    if let Some(after) = after {
+        params.push(("after", after));
+    }
+    Ok(reqwest::Url::parse_with_params(
+        &format!("https://www.reddit.com/r/{}/{}.json", subreddit, path),
+        &params,
+    )?)
+}
+
+/// Turns an image post into a result, anything else is None
+fn to_listing(post: Post) -> Option<ListingData> {
+    let extension = post.url.rsplit('.').next()?.to_lowercase();
+    if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let image = post.preview?.images.into_iter().next()?;
+    let thumbnail = image
+        .resolutions
+        .iter()
+        .find(|size| size.width >= THUMBNAIL_WIDTH)
+        .unwrap_or(&image.source);
+    let (width, height) = (image.source.width, image.source.height);
+    let created = UNIX_EPOCH + Duration::from_secs_f64(post.created_utc.max(0.0));
+    let permalink = format!("https://www.reddit.com{}", post.permalink);
+    Some(ListingData {
+        // reddit's ids could be taken for wallhaven's
+        id: format!("reddit-{}", post.id),
+        url: permalink.clone(),
+        short_url: permalink.clone(),
+        favorites: post.score,
+        source: permalink,
+        purity: match post.over_18 {
+            true => PurityLevel::Nsfw,
+            false => PurityLevel::Sfw,
+        },
+        dimension_x: width,
+        dimension_y: height,
+        resolution: format!("{}x{}", width, height),
+        ratio: format!("{:.2}", width as f64 / height.max(1) as f64),
+        file_type: match extension.as_str() {
+            "png" => "image/png".to_string(),
+            _ => "image/jpeg".to_string(),
+        },
+        created_at: format_utc(created),
+        thumbs: Thumbs {
+            large: image.source.url.clone(),
+            original: image.source.url.clone(),
+            small: thumbnail.url.clone(),
+        },
+        path: post.url,
+        ..Default::default()
+    })
+}
+
+/// Fetches a page of the subreddit's image posts, see [listing_url]
+pub async fn fetch_subreddit(
+    client: &reqwest::Client,
+    subreddit: &str,
+    query: &str,
+    after: Option<&str>,
+) -> Result<RedditPage> {
+    let url = listing_url(subreddit, query, after)?;
+    RATE_LIMIT.acquire().await;
+    info!("Fetching {}", url);
+    let listing: Listing = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(RedditPage {
+        wallpapers: listing
+            .data
+            .children
+            .into_iter()
+            .filter_map(|child| to_listing(child.data))
+            .collect(),
+        after: listing.data.after,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::reddit::{listing_url, to_listing, Listing, RedditPage};
+    use wallapi::types::PurityLevel;
+
+    #[test]
+    fn maps_image_posts() {
+        let listing: Listing = serde_json::from_str(
+            r#"{"data": {"after": "t3_b", "children": [
+                {"data": {"id": "a", "url": "https://i.redd.it/a.png",
+                    "permalink": "/r/wallpapers/comments/a/mountains/", "score": 120,
+                    "created_utc": 1632141296.0, "over_18": false,
+                    "preview": {"images": [{
+                        "source": {"url": "https://preview.redd.it/a.png", "width": 3840, "height": 2160},
+                        "resolutions": [
+                            {"url": "https://preview.redd.it/a-108.png", "width": 108, "height": 60},
+                            {"url": "https://preview.redd.it/a-320.png", "width": 320, "height": 180}
+                        ]}]}}},
+                {"data": {"id": "b", "url": "https://www.reddit.com/gallery/b",
+                    "permalink": "/r/wallpapers/comments/b/gallery/", "score": 5,
+                    "created_utc": 1632141296.0, "over_18": true}}
+            ]}}"#,
+        )
+        .unwrap();
+        let mut posts = listing.data.children.into_iter();
+        let wallpaper = to_listing(posts.next().unwrap().data).unwrap();
+        assert_eq!(wallpaper.id, "reddit-a");
+        assert_eq!(wallpaper.path, "https://i.redd.it/a.png");
+        assert_eq!(wallpaper.thumbs.small, "https://preview.redd.it/a-320.png");
+        assert_eq!(wallpaper.resolution, "3840x2160");
+        assert_eq!(wallpaper.ratio, "1.78");
+        assert_eq!(wallpaper.file_type, "image/png");
+        assert_eq!(wallpaper.created_at, "2021-09-20 12:34:56");
+        assert_eq!(wallpaper.purity, PurityLevel::Sfw);
+        // galleries aren't a single image
+        assert_eq!(to_listing(posts.next().unwrap().data), None);
+
+        let page = RedditPage {
+            after: Some("t3_b".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(page.meta(2).last_page, 3);
+        assert_eq!(RedditPage::default().meta(2).last_page, 2);
+    }
+
+    #[test]
+    fn listing_urls() {
+        assert_eq!(
+            listing_url("wallpapers", "", None).unwrap().as_str(),
+            "https://www.reddit.com/r/wallpapers/hot.json?limit=25&raw_json=1"
+        );
+        assert_eq!(
+            listing_url("wallpapers", "night sky", Some("t3_b"))
+                .unwrap()
+                .as_str(),
+            "https://www.reddit.com/r/wallpapers/search.json?limit=25&raw_json=1&q=night+sky&restrict_sr=on&sort=relevance&after=t3_b"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use wallapi::types::{ListingData, XYCombo};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileType {
+    Jpeg,
+    Png,
+}
+
+impl FileType {
+    pub const LIST: [FileType; 2] = [FileType::Jpeg, FileType::Png];
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            FileType::Jpeg => "image/jpeg",
+            FileType::Png => "image/png",
+        }
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileType::Jpeg => write!(f, "jpeg"),
+            FileType::Png => write!(f, "png"),
+        }
+    }
+}
+
+/// Order of the results that have already been fetched, without searching again. The largest,
+/// most popular or newest come first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LocalSort {
+    Favorites,
+    Views,
+    Resolution,
+    FileSize,
+    UploadDate,
+}
+
+impl LocalSort {
+    pub const LIST: [LocalSort; 5] = [
+        LocalSort::Favorites,
+        LocalSort::Views,
+        LocalSort::Resolution,
+        LocalSort::FileSize,
+        LocalSort::UploadDate,
+    ];
+
+    pub fn compare(self, a: &ListingData, b: &ListingData) -> Ordering {
+        let ordering = match self {
+            LocalSort::Favorites => a.favorites.cmp(&b.favorites),
+            LocalSort::Views => a.views.cmp(&b.views),
+            LocalSort::Resolution => {
+                (a.dimension_x * a.dimension_y).cmp(&(b.dimension_x * b.dimension_y))
+            }
+            LocalSort::FileSize => a.file_size.cmp(&b.file_size),
+            // `2021-09-20 12:34:56` sorts the same as a string
+            LocalSort::UploadDate => a.created_at.cmp(&b.created_at),
+        };
+        ordering.reverse()
+    }
+}
+
+impl Display for LocalSort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalSort::Favorites => write!(f, "favorites"),
+            LocalSort::Views => write!(f, "views"),
+            LocalSort::Resolution => write!(f, "resolution"),
+            LocalSort::FileSize => write!(f, "file size"),
+            LocalSort::UploadDate => write!(f, "upload date"),
+        }
+    }
+}
+
+/// Tags wallhaven files AI generated wallpapers under, compared in lowercase
+const AI_TAGS: [&str; 2] = ["ai art", "ai generated"];
+
+/// Filters applied locally to results that have already been fetched
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResultFilter {
+    pub min_favorites: Option<i64>,
+    pub min_views: Option<i64>,
+    pub min_resolution: Option<XYCombo>,
+    pub file_type: Option<FileType>,
+    /// Hides wallpapers tagged as AI art. Search results only have their tags once their details
+    /// were loaded, so the details of those already shown are fetched when it's turned on.
+    pub exclude_ai: bool,
+}
+
+/// An unset filter lets everything through
+fn passes<T>(filter: Option<T>, test: impl FnOnce(T) -> bool) -> bool {
+    match filter {
+        Some(value) => test(value),
+        None => true,
+    }
+}
+
+fn is_ai_art(listing: &ListingData) -> bool {
+    listing
+        .tags
+        .iter()
+        .any(|tag| AI_TAGS.contains(&tag.name.to_lowercase().as_str()))
+}
+
+impl ResultFilter {
+    pub fn is_active(&self) -> bool {
+        self != &ResultFilter::default()
+    }
+
+    pub fn matches(&self, listing: &ListingData) -> bool {
+        passes(self.min_favorites, |favorites| {
+            listing.favorites >= favorites
+        }) && passes(self.min_views, |views| listing.views >= views)
+            && passes(self.min_resolution, |resolution| {
+                listing.dimension_x >= resolution.x as i64
+                    && listing.dimension_y >= resolution.y as i64
+            })
+            && passes(self.file_type, |file_type| {
+                listing.file_type == file_type.mime_type()
+            })
+            && !(self.exclude_ai && is_ai_art(listing))
+    }
+}
+
+/// Parses the contents of a numeric filter input, where empty means no filter
+pub fn parse_minimum(text: &str) -> Option<i64> {
+    let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wallapi::types::Tag;
+
+    #[test]
+    fn filter_matches() {
+        let listing = ListingData {
+            favorites: 50,
+            views: 2000,
+            dimension_x: 2560,
+            dimension_y: 1440,
+            file_type: "image/png".to_string(),
+            ..Default::default()
+        };
+        assert!(ResultFilter::default().matches(&listing));
+        let filter = ResultFilter {
+            min_views: Some(1000),
+            min_resolution: Some(XYCombo { x: 1920, y: 1080 }),
+            file_type: Some(FileType::Png),
+            ..Default::default()
+        };
+        assert!(filter.matches(&listing));
+        assert!(!ResultFilter {
+            min_favorites: Some(100),
+            ..filter.clone()
+        }
+        .matches(&listing));
+        assert!(!ResultFilter {
+            file_type: Some(FileType::Jpeg),
+            ..filter.clone()
+        }
+        .matches(&listing));
+        let exclude_ai = ResultFilter {
+            exclude_ai: true,
+            ..filter
+        };
+        assert!(exclude_ai.matches(&listing));
+        let ai_art = ListingData {
+            tags: vec![Tag {
+                name: "AI Art".to_string(),
+                ..Default::default()
+            }],
+            ..listing
+        };
+        assert!(!exclude_ai.matches(&ai_art));
+    }
+
+    #[test]
+    fn local_sort_order() {
+        let listing = |favorites: i64, dimension_x: i64, created_at: &str| ListingData {
+            favorites,
+            dimension_x,
+            dimension_y: 1080,
+            created_at: created_at.to_string(),
+            ..Default::default()
+        };
+        let mut results = vec![
+            listing(5, 1920, "2021-09-20 12:34:56"),
+            listing(50, 1280, "2019-01-01 00:00:00"),
+            listing(20, 3840, "2020-03-01 08:00:00"),
+        ];
+        results.sort_by(|a, b| LocalSort::Favorites.compare(a, b));
+        let favorites =
+            |results: &[ListingData]| -> Vec<i64> { results.iter().map(|l| l.favorites).collect() };
+        assert_eq!(favorites(&results), [50, 20, 5]);
+        results.sort_by(|a, b| LocalSort::Resolution.compare(a, b));
+        assert_eq!(favorites(&results), [20, 5, 50]);
+        results.sort_by(|a, b| LocalSort::UploadDate.compare(a, b));
+        assert_eq!(favorites(&results), [5, 20, 50]);
+    }
+
+    #[test]
+    fn parse_minimum_input() {
+        assert_eq!(parse_minimum(""), None);
+        assert_eq!(parse_minimum("1,000"), Some(1000));
+        assert_eq!(parse_minimum("abc"), None);
+    }
+}
@@ -0,0 +1,994 @@
+use crate::blacklist::Blacklist;
+use crate::files::{write_atomically, write_atomically_blocking};
+use crate::source::SourceKind;
+use log::{error, info, warn};
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use wallapi::types::{Categories, Purity, SearchOptions, XYCombo};
+
+/// Held while the settings file is read and written, so saves that load the file, change a part
+/// of it and write it back don't lose each other's change when they run at the same time
+static SETTINGS_FILE: Mutex<()> = Mutex::const_new(());
+
+/// Controls which results are shown based on whether they already exist in the save directory
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadedFilter {
+    #[default]
+    ShowAll,
+    HideDownloaded,
+    OnlyDownloaded,
+}
+
+impl DownloadedFilter {
+    pub const LIST: [DownloadedFilter; 3] = [
+        DownloadedFilter::ShowAll,
+        DownloadedFilter::HideDownloaded,
+        DownloadedFilter::OnlyDownloaded,
+    ];
+
+    pub fn shows(&self, downloaded: bool) -> bool {
+        match self {
+            DownloadedFilter::ShowAll => true,
+            DownloadedFilter::HideDownloaded => !downloaded,
+            DownloadedFilter::OnlyDownloaded => downloaded,
+        }
+    }
+}
+
+impl Display for DownloadedFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadedFilter::ShowAll => write!(f, "show all"),
+            DownloadedFilter::HideDownloaded => write!(f, "hide downloaded"),
+            DownloadedFilter::OnlyDownloaded => write!(f, "only downloaded"),
+        }
+    }
+}
+
+/// What to do when a wallpaper is downloaded to a file that already exists
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExistingFilePolicy {
+    Skip,
+    Overwrite,
+    /// Saves the new file with a number appended to its name
+    Rename,
+    #[default]
+    Ask,
+}
+
+impl ExistingFilePolicy {
+    pub const LIST: [ExistingFilePolicy; 4] = [
+        ExistingFilePolicy::Skip,
+        ExistingFilePolicy::Overwrite,
+        ExistingFilePolicy::Rename,
+        ExistingFilePolicy::Ask,
+    ];
+}
+
+impl Display for ExistingFilePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExistingFilePolicy::Skip => write!(f, "skip"),
+            ExistingFilePolicy::Overwrite => write!(f, "overwrite"),
+            ExistingFilePolicy::Rename => write!(f, "keep both"),
+            ExistingFilePolicy::Ask => write!(f, "ask"),
+        }
+    }
+}
+
+/// Size and position the window had when the app was last closed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: u32,
+    pub height: u32,
+    /// Not every platform tells where the window is, Wayland doesn't
+    pub position: Option<(i32, i32)>,
+}
+
+impl WindowGeometry {
+    /// Smallest the window can be made while the controls still fit
+    pub const MIN_WIDTH: u32 = 800;
+    pub const MIN_HEIGHT: u32 = 500;
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: 1800,
+            height: 800,
+            position: None,
+        }
+    }
+}
+
+/// The PIN asked for before sketchy or nsfw wallpapers can be shown, only its salted hash is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurityPin {
+    pub salt: String,
+    pub hash: String,
+}
+
+/// A named set of the settings that change with where the app is used, like "work SFW" or
+/// "home". The active profile's values are the ones in `SavedSettings`, the list only holds them
+/// while another profile is active.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub save_directory: Option<String>,
+    pub api_key: Option<String>,
+    pub default_purity: Option<Purity>,
+    pub default_categories: Option<Categories>,
+    /// Filters the profile's searches start from
+    pub last_search: Option<SearchOptions>,
+}
+
+impl Profile {
+    fn capture(name: &str, settings: &SavedSettings) -> Self {
+        Self {
+            name: name.to_string(),
+            save_directory: settings.save_directory.clone(),
+            api_key: settings.api_key.clone(),
+            default_purity: settings.default_purity.clone(),
+            default_categories: settings.default_categories.clone(),
+            last_search: settings.last_search.clone(),
+        }
+    }
+}
+
+/// Colors the app is shown in
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    /// Follows the desktop's dark mode
+    System,
+}
+
+impl ThemeMode {
+    pub const LIST: [ThemeMode; 3] = [ThemeMode::Dark, ThemeMode::Light, ThemeMode::System];
+}
+
+impl Display for ThemeMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeMode::Dark => write!(f, "dark"),
+            ThemeMode::Light => write!(f, "light"),
+            ThemeMode::System => write!(f, "follow the system"),
+        }
+    }
+}
+
+/// How much room buttons and result tiles get
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Density {
+    #[default]
+    Comfortable,
+    /// Fits more results on screen
+    Compact,
+}
+
+impl Density {
+    pub const LIST: [Density; 2] = [Density::Comfortable, Density::Compact];
+
+    /// Padding inside buttons
+    pub fn padding(&self) -> u16 {
+        match self {
+            Density::Comfortable => 10,
+            Density::Compact => 5,
+        }
+    }
+
+    /// Room between result tiles
+    pub fn spacing(&self) -> u16 {
+        match self {
+            Density::Comfortable => 5,
+            Density::Compact => 1,
+        }
+    }
+}
+
+impl Display for Density {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Density::Comfortable => write!(f, "comfortable"),
+            Density::Compact => write!(f, "compact"),
+        }
+    }
+}
+
+/// How search results are laid out
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResultLayout {
+    /// Thumbnails in rows
+    #[default]
+    Grid,
+    /// One line of details per result, for going through lots of them
+    List,
+}
+
+/// When the app holds back on what it downloads to look at results
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataSaver {
+    #[default]
+    Off,
+    On,
+    /// Only while the network connection is metered, like a phone's hotspot
+    WhenMetered,
+}
+
+impl DataSaver {
+    pub const LIST: [DataSaver; 3] = [DataSaver::Off, DataSaver::On, DataSaver::WhenMetered];
+
+    /// Whether data is being saved right now, `metered` is what the connection was last seen as
+    pub fn is_saving(self, metered: bool) -> bool {
+        match self {
+            DataSaver::Off => false,
+            DataSaver::On => true,
+            DataSaver::WhenMetered => metered,
+        }
+    }
+}
+
+impl Display for DataSaver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataSaver::Off => write!(f, "off"),
+            DataSaver::On => write!(f, "always"),
+            DataSaver::WhenMetered => write!(f, "on metered connections"),
+        }
+    }
+}
+
+/// How the app starts when it's launched at login
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartMode {
+    /// Opens the window
+    Window,
+    /// Starts hidden in the tray
+    #[default]
+    Minimized,
+    /// Starts without a window, only rotating wallpapers and checking watched searches
+    Headless,
+}
+
+impl StartMode {
+    pub const LIST: [StartMode; 3] = [StartMode::Window, StartMode::Minimized, StartMode::Headless];
+
+    /// Arguments the app is launched with
+    pub fn args(&self) -> &'static [&'static str] {
+        match self {
+            StartMode::Window => &[],
+            StartMode::Minimized => &["--minimized"],
+            StartMode::Headless => &["--watch"],
+        }
+    }
+}
+
+impl Display for StartMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartMode::Window => write!(f, "open the window"),
+            StartMode::Minimized => write!(f, "minimized to the tray"),
+            StartMode::Headless => write!(f, "without a window"),
+        }
+    }
+}
+
+/// What happens when the save directory grows past the disk quota
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuotaAction {
+    #[default]
+    Warn,
+    /// Deletes the oldest downloads first
+    PruneOldest,
+    /// Deletes the downloads with the fewest wallhaven favorites first
+    PruneLeastFavorite,
+}
+
+impl QuotaAction {
+    pub const LIST: [QuotaAction; 3] = [
+        QuotaAction::Warn,
+        QuotaAction::PruneOldest,
+        QuotaAction::PruneLeastFavorite,
+    ];
+}
+
+impl Display for QuotaAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaAction::Warn => write!(f, "warn"),
+            QuotaAction::PruneOldest => write!(f, "delete oldest"),
+            QuotaAction::PruneLeastFavorite => write!(f, "delete least favorited"),
+        }
+    }
+}
+
+/// What draws the window, read once at startup
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Renderer {
+    /// The graphics card, falling back to software when it can't be used
+    #[default]
+    Auto,
+    Gpu,
+    /// Drawn on the processor, for virtual machines and graphics cards that show a blank window
+    Software,
+}
+
+impl Renderer {
+    pub const LIST: [Renderer; 3] = [Renderer::Auto, Renderer::Gpu, Renderer::Software];
+
+    /// Parses the value of `--renderer`
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "auto" => Some(Renderer::Auto),
+            "gpu" => Some(Renderer::Gpu),
+            "software" => Some(Renderer::Software),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Renderer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Renderer::Auto => write!(f, "automatic"),
+            Renderer::Gpu => write!(f, "graphics card"),
+            Renderer::Software => write!(f, "software"),
+        }
+    }
+}
+
+/// Least severe messages written to the log file
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub const LIST: [LogLevel; 5] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+    ];
+
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Off => write!(f, "off"),
+            LogLevel::Error => write!(f, "errors"),
+            LogLevel::Warn => write!(f, "warnings"),
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+/// Bumped whenever a change to the settings needs `SavedSettings::migrate` to carry older files
+/// over
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Folders remembered in the source dropdown
+pub const MAX_SOURCE_FOLDERS: usize = 5;
+
+/// Why the settings couldn't be loaded
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("Failed to read {0:?}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("{0:?} is malformed: {1}")]
+    Toml(PathBuf, toml::de::Error),
+    #[error("{0:?} is malformed: {1}")]
+    Json(PathBuf, serde_json::Error),
+    #[error("Failed to write {0:?}: {1}")]
+    Write(PathBuf, std::io::Error),
+    #[error("Failed to serialize the settings: {0}")]
+    Serialize(toml::ser::Error),
+    #[error("No config directory to keep the settings in")]
+    NoConfigDirectory,
+}
+
+/// Where the settings are kept, like `~/.config/wall-a-bunga`
+fn config_dir() -> Result<PathBuf, SettingsError> {
+    AppDirs::new(Some("wall-a-bunga"), true)
+        .map(|dirs| dirs.config_dir)
+        .ok_or(SettingsError::NoConfigDirectory)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedSettings {
+    /// 0 for settings from before the file was versioned
+    #[serde(default)]
+    pub version: u32,
+    pub save_directory: Option<String>,
+    /// Kept in the credential store instead when there is one
+    pub api_key: Option<String>,
+    /// Replaced by `downloaded_filter`, only read to carry over older configs
+    #[serde(default, skip_serializing)]
+    ignore_downloaded: bool,
+    #[serde(default)]
+    pub downloaded_filter: DownloadedFilter,
+    /// Resolution of the user's screen, used to show how wallpapers will be cropped
+    #[serde(default)]
+    pub monitor_resolution: Option<XYCombo>,
+    /// Combined download speed limit in KB/s, unlimited when None
+    #[serde(default)]
+    pub download_speed_limit: Option<u32>,
+    #[serde(default)]
+    pub existing_file_policy: ExistingFilePolicy,
+    /// Dates downloaded files by when the wallpaper was uploaded instead of when it was downloaded
+    #[serde(default)]
+    pub use_upload_date: bool,
+    /// Most wallpapers "download all results" queues, no limit when None
+    #[serde(default = "default_download_all_count")]
+    pub download_all_count: Option<u32>,
+    /// Most megabytes "download all results" queues, no limit when None
+    #[serde(default)]
+    pub download_all_megabytes: Option<u32>,
+    /// Most megabytes the save directory may use, no limit when None
+    #[serde(default)]
+    pub max_disk_usage_megabytes: Option<u32>,
+    #[serde(default)]
+    pub quota_action: QuotaAction,
+    /// Runs this instead of the platform default to set the wallpaper, `{path}` is replaced by
+    /// the image
+    #[serde(default)]
+    pub wallpaper_command: Option<String>,
+    /// Minutes between switching to another downloaded wallpaper, never when None
+    #[serde(default)]
+    pub rotation_minutes: Option<u32>,
+    /// Closing the window leaves the app running in the tray
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    /// Minutes between checks of the watched searches
+    #[serde(default = "default_watch_interval_minutes")]
+    pub watch_interval_minutes: u32,
+    /// Sets the day's top wallpaper as the desktop background once a day
+    #[serde(default)]
+    pub daily_wallpaper: bool,
+    /// Registers the app to start when the user logs in
+    #[serde(default)]
+    pub start_at_login: bool,
+    /// Asks github for a newer release at startup, off until it's turned on
+    #[serde(default)]
+    pub check_for_updates: bool,
+    #[serde(default)]
+    pub start_mode: StartMode,
+    #[serde(default)]
+    pub theme_mode: ThemeMode,
+    /// Color of the main buttons and selections like `#de6b1c`, the app's orange when None
+    #[serde(default)]
+    pub accent_color: Option<String>,
+    #[serde(default)]
+    pub density: Density,
+    #[serde(default)]
+    pub result_layout: ResultLayout,
+    /// Keeps previews to small thumbnails and pages from loading ahead of time
+    #[serde(default)]
+    pub data_saver: DataSaver,
+    /// Filters and query of the last search, restored at startup. Never contains the api key.
+    #[serde(default)]
+    pub last_search: Option<SearchOptions>,
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
+    /// Purity new tabs start with, wallhaven's default when None
+    #[serde(default)]
+    pub default_purity: Option<Purity>,
+    /// Categories new tabs start with, all of them when None
+    #[serde(default)]
+    pub default_categories: Option<Categories>,
+    /// Name of the active profile, None until one is added
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Resolutions added to the resolution submenu
+    #[serde(default)]
+    pub custom_resolutions: Vec<XYCombo>,
+    /// Aspect ratios added to the aspect ratio submenu
+    #[serde(default)]
+    pub custom_ratios: Vec<XYCombo>,
+    /// Written to the log file in the data directory, the console follows `RUST_LOG` instead
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// Wallpapers and tags kept out of the search results
+    #[serde(default)]
+    pub blacklist: Blacklist,
+    /// Proxy every request goes through, like `http://host:port`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Takes effect on the next start, `--renderer` overrides it
+    #[serde(default)]
+    pub renderer: Renderer,
+    /// Access key of an unsplash.com app, searching unsplash needs one. Kept in the credential
+    /// store instead when there is one.
+    #[serde(default)]
+    pub unsplash_access_key: Option<String>,
+    /// Sources left out of the source dropdown
+    #[serde(default)]
+    pub disabled_sources: Vec<SourceKind>,
+    /// Locks sketchy and nsfw wallpapers away until the PIN is entered, for every profile
+    #[serde(default)]
+    pub purity_pin: Option<PurityPin>,
+    /// Folders picked as a search source, most recently picked first
+    #[serde(default)]
+    pub source_folders: Vec<PathBuf>,
+}
+
+fn default_download_all_count() -> Option<u32> {
+    Some(100)
+}
+
+fn default_watch_interval_minutes() -> u32 {
+    60
+}
+
+impl Default for SavedSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            save_directory: None,
+            api_key: None,
+            ignore_downloaded: false,
+            downloaded_filter: Default::default(),
+            monitor_resolution: None,
+            download_speed_limit: None,
+            existing_file_policy: Default::default(),
+            use_upload_date: false,
+            download_all_count: default_download_all_count(),
+            download_all_megabytes: None,
+            max_disk_usage_megabytes: None,
+            quota_action: Default::default(),
+            wallpaper_command: None,
+            rotation_minutes: None,
+            minimize_to_tray: false,
+            watch_interval_minutes: default_watch_interval_minutes(),
+            daily_wallpaper: false,
+            start_at_login: false,
+            check_for_updates: false,
+            start_mode: Default::default(),
+            theme_mode: Default::default(),
+            accent_color: None,
+            density: Default::default(),
+            result_layout: Default::default(),
+            data_saver: Default::default(),
+            last_search: None,
+            window: None,
+            default_purity: None,
+            default_categories: None,
+            profile: None,
+            profiles: Vec::new(),
+            custom_resolutions: Vec::new(),
+            custom_ratios: Vec::new(),
+            log_level: LogLevel::default(),
+            blacklist: Blacklist::default(),
+            proxy: None,
+            renderer: Renderer::default(),
+            unsplash_access_key: None,
+            disabled_sources: Vec::new(),
+            purity_pin: None,
+            source_folders: Vec::new(),
+        }
+    }
+}
+
+impl SavedSettings {
+    /// Saves every setting, replacing what's in the file
+    pub async fn save_settings(settings: SavedSettings) -> Result<(), SettingsError> {
+        let _file = SETTINGS_FILE.lock().await;
+        SavedSettings::save_settings_to(&config_dir()?, &settings).await
+    }
+
+    async fn save_settings_to(dir: &Path, settings: &SavedSettings) -> Result<(), SettingsError> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| SettingsError::Write(dir.to_path_buf(), e))?;
+        let config_file = dir.join("config.toml");
+        let toml = toml::to_string_pretty(settings).map_err(SettingsError::Serialize)?;
+        write_atomically(&config_file, toml)
+            .await
+            .map_err(|e| SettingsError::Write(config_file.clone(), e))?;
+        info!("Saved settings to {:?}", config_file);
+        Ok(())
+    }
+
+    /// Loads the saved settings, changes them and saves them again, leaving alone the settings
+    /// that were changed in the window but not saved yet. A file that can't be loaded isn't
+    /// replaced before it's fixed.
+    async fn update_settings(change: impl FnOnce(&mut SavedSettings)) -> Result<(), SettingsError> {
+        SavedSettings::update_settings_in(&config_dir()?, change).await
+    }
+
+    async fn update_settings_in(
+        dir: &Path,
+        change: impl FnOnce(&mut SavedSettings),
+    ) -> Result<(), SettingsError> {
+        let _file = SETTINGS_FILE.lock().await;
+        let mut settings = SavedSettings::load_settings_from(dir)?.unwrap_or_default();
+        change(&mut settings);
+        SavedSettings::save_settings_to(dir, &settings).await
+    }
+
+    /// Strips what shouldn't outlive the session from a search before it's kept as `last_search`
+    pub fn remembered_search(options: &SearchOptions) -> SearchOptions {
+        SearchOptions {
+            api_key: None,
+            page: None,
+            seed: None,
+            ..options.clone()
+        }
+    }
+
+    /// Moves the folder to the front of `source_folders`, forgetting the oldest past
+    /// [MAX_SOURCE_FOLDERS]
+    pub fn add_source_folder(&mut self, folder: PathBuf) {
+        self.source_folders.retain(|known| *known != folder);
+        self.source_folders.insert(0, folder);
+        self.source_folders.truncate(MAX_SOURCE_FOLDERS);
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles
+            .iter()
+            .map(|profile| profile.name.clone())
+            .collect()
+    }
+
+    /// Copies the active profile's values into its entry of `profiles`
+    fn store_profile(&mut self) {
+        let name = match &self.profile {
+            Some(name) => name.clone(),
+            None => return,
+        };
+        let profile = Profile::capture(&name, self);
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(stored) => *stored = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Adds a profile with the current values and makes it active, the first one just names the
+    /// current settings. False when the name is empty or taken.
+    pub fn add_profile(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.profiles.iter().any(|p| p.name == name) {
+            return false;
+        }
+        self.store_profile();
+        self.profile = Some(name.to_string());
+        self.store_profile();
+        true
+    }
+
+    /// Keeps the active profile's values and loads the ones of `name`, false when there's no such
+    /// profile
+    pub fn switch_profile(&mut self, name: &str) -> bool {
+        let profile = match self.profiles.iter().find(|p| p.name == name) {
+            Some(profile) => profile.clone(),
+            None => return false,
+        };
+        self.store_profile();
+        self.save_directory = profile.save_directory;
+        self.api_key = profile.api_key;
+        self.default_purity = profile.default_purity;
+        self.default_categories = profile.default_categories;
+        self.last_search = profile.last_search;
+        self.profile = Some(profile.name);
+        true
+    }
+
+    /// Removes the active profile, its values stay as the settings without a profile name
+    pub fn remove_profile(&mut self) {
+        if let Some(name) = self.profile.take() {
+            self.profiles.retain(|p| p.name != name);
+        }
+    }
+
+    /// Remembers the search so the next start picks up where this one left off
+    pub async fn save_last_search(options: SearchOptions) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.last_search = Some(options)).await
+    }
+
+    /// Saves the resolutions and aspect ratios added to the pickers as soon as they're added
+    pub async fn save_custom_combos(
+        resolutions: Vec<XYCombo>,
+        ratios: Vec<XYCombo>,
+    ) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| {
+            settings.custom_resolutions = resolutions;
+            settings.custom_ratios = ratios;
+        })
+        .await
+    }
+
+    /// Saves the folders recently picked as a source, for the source dropdown
+    pub async fn save_source_folders(folders: Vec<PathBuf>) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.source_folders = folders).await
+    }
+
+    /// Saves the purity lock right away, it shouldn't wait for the save button to take effect
+    pub async fn save_purity_pin(pin: Option<PurityPin>) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.purity_pin = pin).await
+    }
+
+    /// Saves the blacklist as soon as a tag is added or removed, the next search uses it
+    pub async fn save_blacklist(blacklist: Blacklist) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.blacklist = blacklist).await
+    }
+
+    /// Saves the result layout as soon as it's switched
+    pub async fn save_result_layout(layout: ResultLayout) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.result_layout = layout).await
+    }
+
+    /// Saves data saver as soon as it's toggled from the status row
+    pub async fn save_data_saver(data_saver: DataSaver) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.data_saver = data_saver).await
+    }
+
+    /// Saves where the window was and how big it was, for the next start
+    pub async fn save_window(window: WindowGeometry) -> Result<(), SettingsError> {
+        SavedSettings::update_settings(|settings| settings.window = Some(window)).await
+    }
+
+    /// Brings settings saved by an older version up to date
+    pub fn migrate(mut self) -> Self {
+        if self.version < 1 {
+            // version 0 was json and hid downloaded wallpapers with a bool
+            if self.ignore_downloaded {
+                self.downloaded_filter = DownloadedFilter::HideDownloaded;
+            }
+        }
+        if self.version > SETTINGS_VERSION {
+            warn!(
+                "The settings are from a newer version {}, unknown settings are dropped on save",
+                self.version
+            );
+        }
+        self.version = SETTINGS_VERSION;
+        self
+    }
+
+    /// Carries the settings over from the `config.json` used before they moved to toml, the json
+    /// file is kept as `config.json.bak` once the toml file was written
+    fn migrate_json(config_dir: &Path) -> Result<Option<Self>, SettingsError> {
+        let json_file = config_dir.join("config.json");
+        let json = match std::fs::read_to_string(&json_file) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(SettingsError::Io(json_file, e)),
+        };
+        let settings: SavedSettings =
+            serde_json::from_str(&json).map_err(|e| SettingsError::Json(json_file.clone(), e))?;
+        let settings = settings.migrate();
+        let toml_file = config_dir.join("config.toml");
+        let written = toml::to_string_pretty(&settings)
+            .map_err(|e| e.to_string())
+            .and_then(|toml| {
+                write_atomically_blocking(&toml_file, toml).map_err(|e| e.to_string())
+            });
+        match written {
+            Ok(()) => {
+                info!("Moved settings from {:?} to {:?}", json_file, toml_file);
+                if let Err(e) = std::fs::rename(&json_file, json_file.with_extension("json.bak")) {
+                    warn!("Failed to rename {:?} {:?}", json_file, e);
+                }
+            }
+            // tried again next time
+            Err(e) => error!("Failed to write {:?} {}", toml_file, e),
+        }
+        Ok(Some(settings))
+    }
+
+    // Function left sync intentionally
+    /// The saved settings, None when nothing has been saved yet
+    pub fn load_settings() -> Result<Option<Self>, SettingsError> {
+        SavedSettings::load_settings_from(&config_dir()?)
+    }
+
+    fn load_settings_from(dir: &Path) -> Result<Option<Self>, SettingsError> {
+        let config_file = dir.join("config.toml");
+        match std::fs::read_to_string(&config_file) {
+            Ok(toml) => {
+                info!("Loaded settings from {:?}", config_file);
+                let settings: SavedSettings =
+                    toml::from_str(&toml).map_err(|e| SettingsError::Toml(config_file, e))?;
+                Ok(Some(settings.migrate()))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => SavedSettings::migrate_json(dir),
+            Err(e) => Err(SettingsError::Io(config_file, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::settings::{
+        DataSaver, DownloadedFilter, ResultLayout, SavedSettings, SettingsError, WindowGeometry,
+        SETTINGS_VERSION,
+    };
+    use std::path::PathBuf;
+    use wallapi::types::{Purity, SearchOptions, Sorting};
+
+    #[test]
+    fn remembers_last_search() {
+        let options = SearchOptions {
+            query: Some("cats -dogs".to_string()),
+            api_key: Some("secret".to_string()),
+            page: Some(3),
+            purity: Some(Purity {
+                clean: true,
+                sketchy: true,
+                nsfw: false,
+            }),
+            sorting: Some(Sorting::TopList),
+            ..Default::default()
+        };
+        let settings = SavedSettings {
+            last_search: Some(SavedSettings::remembered_search(&options)),
+            window: Some(WindowGeometry {
+                width: 1200,
+                height: 900,
+                position: Some((-20, 40)),
+            }),
+            ..Default::default()
+        };
+        let toml = toml::to_string_pretty(&settings).unwrap();
+        assert!(!toml.contains("secret"));
+        let loaded: SavedSettings = toml::from_str(&toml).unwrap();
+        assert_eq!(loaded.window.unwrap().position, Some((-20, 40)));
+        let last_search = loaded.last_search.unwrap();
+        assert_eq!(
+            last_search,
+            SearchOptions {
+                api_key: None,
+                page: None,
+                ..options
+            }
+        );
+        // configs from before it was remembered
+        let old: SavedSettings =
+            serde_json::from_str(r#"{"save_directory": null, "api_key": null}"#).unwrap();
+        assert!(old.last_search.is_none());
+    }
+
+    #[test]
+    fn switches_profiles() {
+        let mut settings = SavedSettings {
+            save_directory: Some("/work".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.add_profile("work"));
+        assert!(!settings.add_profile("work"));
+        assert!(settings.add_profile(" home "));
+        settings.save_directory = Some("/home".to_string());
+        settings.api_key = Some("secret".to_string());
+        assert_eq!(settings.profile_names(), ["work", "home"]);
+
+        assert!(settings.switch_profile("work"));
+        assert_eq!(settings.save_directory.as_deref(), Some("/work"));
+        assert_eq!(settings.api_key, None);
+        assert!(settings.switch_profile("home"));
+        assert_eq!(settings.save_directory.as_deref(), Some("/home"));
+        assert_eq!(settings.api_key.as_deref(), Some("secret"));
+        assert!(!settings.switch_profile("school"));
+
+        settings.remove_profile();
+        assert_eq!(settings.profile, None);
+        assert_eq!(settings.profile_names(), ["work"]);
+        let toml = toml::to_string_pretty(&settings).unwrap();
+        let loaded: SavedSettings = toml::from_str(&toml).unwrap();
+        assert_eq!(loaded.profiles, settings.profiles);
+    }
+
+    #[test]
+    fn remembers_source_folders() {
+        let mut settings = SavedSettings::default();
+        for folder in ["a", "b", "c", "d", "e", "f"] {
+            settings.add_source_folder(PathBuf::from(folder));
+        }
+        settings.add_source_folder(PathBuf::from("d"));
+        assert_eq!(
+            settings.source_folders,
+            [
+                PathBuf::from("d"),
+                "f".into(),
+                "e".into(),
+                "c".into(),
+                "b".into()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_persistence_failures() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // a file where the config directory should be
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, "").unwrap();
+        let result = SavedSettings::save_settings_to(&blocked, &SavedSettings::default()).await;
+        assert!(matches!(result, Err(SettingsError::Write(path, _)) if path == blocked));
+
+        SavedSettings::save_settings_to(&dir, &SavedSettings::default())
+            .await
+            .unwrap();
+        assert!(SavedSettings::load_settings_from(&dir).unwrap().is_some());
+        // saves of different settings running together keep each other's change
+        let (layout, data_saver) = tokio::join!(
+            SavedSettings::update_settings_in(&dir, |settings| {
+                settings.result_layout = ResultLayout::List
+            }),
+            SavedSettings::update_settings_in(&dir, |settings| {
+                settings.data_saver = DataSaver::On
+            }),
+        );
+        layout.unwrap();
+        data_saver.unwrap();
+        let saved = SavedSettings::load_settings_from(&dir).unwrap().unwrap();
+        assert_eq!(saved.result_layout, ResultLayout::List);
+        assert_eq!(saved.data_saver, DataSaver::On);
+        std::fs::write(dir.join("config.toml"), "version = ").unwrap();
+        let result = SavedSettings::load_settings_from(&dir);
+        assert!(matches!(result, Err(SettingsError::Toml(..))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_json_settings() {
+        let old: SavedSettings = serde_json::from_str(
+            r#"{"save_directory": "/wallpapers", "api_key": null, "ignore_downloaded": true}"#,
+        )
+        .unwrap();
+        assert_eq!(old.version, 0);
+        let migrated = old.migrate();
+        assert_eq!(migrated.version, SETTINGS_VERSION);
+        assert_eq!(migrated.downloaded_filter, DownloadedFilter::HideDownloaded);
+        let toml = toml::to_string_pretty(&migrated).unwrap();
+        assert!(toml.starts_with(&format!("version = {}\n", SETTINGS_VERSION)));
+        assert!(toml::from_str::<SavedSettings>("version = 1\nsave_directory = [").is_err());
+    }
+
+    #[test]
+    fn moves_the_json_file_aside_once_migrated() {
+        let dir = std::env::temp_dir().join(format!("wall-a-bunga-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("config.json"),
+            r#"{"save_directory": "/wallpapers"}"#,
+        )
+        .unwrap();
+        let settings = SavedSettings::load_settings_from(&dir).unwrap().unwrap();
+        assert_eq!(settings.save_directory.as_deref(), Some("/wallpapers"));
+        assert!(dir.join("config.toml").exists());
+        assert!(dir.join("config.json.bak").exists());
+        assert!(!dir.join("config.json").exists());
+        // the toml is read from now on
+        let settings = SavedSettings::load_settings_from(&dir).unwrap().unwrap();
+        assert_eq!(settings.save_directory.as_deref(), Some("/wallpapers"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{reddit, unsplash};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use wallapi::rate_limit::RateLimiter;
+
+/// Subreddits offered in the source dropdown
+pub const SUBREDDITS: [&str; 4] = [
+    "wallpapers",
+    "wallpaper",
+    "wallpaperengine",
+    "WidescreenWallpaper",
+];
+
+/// Where a search tab finds its wallpapers
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Wallhaven,
+    /// Photos of unsplash.com, searching them needs an access key
+    Unsplash,
+    /// Image posts of a subreddit, where String == the subreddit's name without `r/`
+    Reddit(String),
+    /// Images in a folder on disk and its subfolders
+    Folder(PathBuf),
+}
+
+impl Source {
+    /// Every source that can be picked in the order they're listed, `folders` are the ones picked
+    /// before. Sources of the `disabled` kinds are left out.
+    pub fn choices(folders: &[PathBuf], disabled: &[SourceKind]) -> Vec<Source> {
+        [Source::Wallhaven, Source::Unsplash]
+            .into_iter()
+            .chain(
+                SUBREDDITS
+                    .into_iter()
+                    .map(|subreddit| Source::Reddit(subreddit.to_string())),
+            )
+            .chain(folders.iter().cloned().map(Source::Folder))
+            .filter(|source| !disabled.contains(&source.kind()))
+            .collect()
+    }
+
+    pub fn kind(&self) -> SourceKind {
+        match self {
+            Source::Wallhaven => SourceKind::Wallhaven,
+            Source::Unsplash => SourceKind::Unsplash,
+            Source::Reddit(_) => SourceKind::Reddit,
+            Source::Folder(_) => SourceKind::Folder,
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Wallhaven => write!(f, "wallhaven.cc"),
+            Source::Unsplash => write!(f, "unsplash.com"),
+            Source::Reddit(subreddit) => write!(f, "r/{}", subreddit),
+            // the whole path would stretch the dropdown
+            Source::Folder(folder) => match folder.file_name() {
+                Some(name) => write!(f, "{}", name.to_string_lossy()),
+                None => write!(f, "{}", folder.display()),
+            },
+        }
+    }
+}
+
+/// Sources grouped by the service behind them, each kind can be turned off in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SourceKind {
+    Wallhaven,
+    Unsplash,
+    Reddit,
+    Folder,
+}
+
+impl SourceKind {
+    pub const LIST: [SourceKind; 4] = [
+        SourceKind::Wallhaven,
+        SourceKind::Unsplash,
+        SourceKind::Reddit,
+        SourceKind::Folder,
+    ];
+
+    /// Wallhaven is what new tabs search and what watches and the daily wallpaper use, so it
+    /// can't be turned off
+    pub fn can_disable(self) -> bool {
+        self != SourceKind::Wallhaven
+    }
+
+    /// What keeps requests to the service under its limit, None for folders that make none
+    pub fn rate_limit(self) -> Option<&'static RateLimiter> {
+        match self {
+            SourceKind::Wallhaven => Some(&wallapi::RATE_LIMIT),
+            SourceKind::Unsplash => Some(&unsplash::RATE_LIMIT),
+            SourceKind::Reddit => Some(&reddit::RATE_LIMIT),
+            SourceKind::Folder => None,
+        }
+    }
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Wallhaven => write!(f, "wallhaven.cc"),
+            SourceKind::Unsplash => write!(f, "unsplash.com"),
+            SourceKind::Reddit => write!(f, "reddit"),
+            SourceKind::Folder => write!(f, "folders"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::source::{Source, SourceKind};
+    use std::path::PathBuf;
+
+    #[test]
+    fn leaves_out_disabled_sources() {
+        let folders = [PathBuf::from("/home/me/Pictures")];
+        let choices = Source::choices(&folders, &[SourceKind::Reddit]);
+        assert_eq!(
+            choices,
+            [
+                Source::Wallhaven,
+                Source::Unsplash,
+                Source::Folder(folders[0].clone())
+            ]
+        );
+        assert_eq!(choices[2].to_string(), "Pictures");
+        assert_eq!(Source::choices(&[], &[]).len(), 6);
+    }
+}
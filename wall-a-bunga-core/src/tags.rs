@@ -0,0 +1,160 @@
+use crate::files::save_json;
+use crate::history::unix_now;
+use anyhow::Result;
+use log::info;
+use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
+use wallapi::types::Tag;
+
+/// Tags kept for suggestions, the ones that weren't seen for the longest are dropped past this
+const MAX_TAGS: usize = 2000;
+
+/// Typed text shorter than this matches too many tags to be worth suggesting
+const MIN_TYPED_LENGTH: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedTag {
+    pub tag: Tag,
+    /// How many wallpapers it was seen on, the popular ones are suggested first
+    pub seen: u32,
+    /// Seconds since the unix epoch, 0 for tags cached before it was kept
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+/// Tags of the wallpapers whose details were fetched. Wallhaven has no tag search in its api, so
+/// the search box suggests tags from here.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TagCache {
+    pub tags: Vec<CachedTag>,
+}
+
+impl TagCache {
+    /// Counts the tags of a wallpaper, adding the ones that weren't seen before
+    pub fn record(&mut self, tags: &[Tag]) {
+        self.record_at(tags, unix_now());
+    }
+
+    fn record_at(&mut self, tags: &[Tag], now: u64) {
+        for tag in tags {
+            match self.tags.iter_mut().find(|cached| cached.tag.id == tag.id) {
+                Some(cached) => {
+                    cached.tag = tag.clone();
+                    cached.seen += 1;
+                    cached.last_seen = now;
+                }
+                None => self.tags.push(CachedTag {
+                    tag: tag.clone(),
+                    seen: 1,
+                    last_seen: now,
+                }),
+            }
+        }
+        if self.tags.len() > MAX_TAGS {
+            // by when they were last seen, a tag that was just added would always lose to the
+            // counts built up by the older ones otherwise
+            self.tags
+                .sort_by_key(|cached| std::cmp::Reverse((cached.last_seen, cached.seen)));
+            self.tags.truncate(MAX_TAGS);
+        }
+    }
+
+    /// Up to `limit` tags whose name or alias contains `typed`, names starting with it first and
+    /// then the most seen
+    pub fn suggest(&self, typed: &str, limit: usize) -> Vec<&Tag> {
+        let typed = typed.trim().to_lowercase();
+        if typed.chars().count() < MIN_TYPED_LENGTH {
+            return vec![];
+        }
+        let mut matches: Vec<(bool, &CachedTag)> = self
+            .tags
+            .iter()
+            .filter_map(|cached| {
+                let name = cached.tag.name.to_lowercase();
+                let prefix = name.starts_with(&typed);
+                let matches = prefix
+                    || name.contains(&typed)
+                    || cached.tag.alias.to_lowercase().contains(&typed);
+                matches.then_some((prefix, cached))
+            })
+            .collect();
+        matches.sort_by(|(a_prefix, a), (b_prefix, b)| {
+            b_prefix
+                .cmp(a_prefix)
+                .then(b.seen.cmp(&a.seen))
+                .then(a.tag.name.cmp(&b.tag.name))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, cached)| &cached.tag)
+            .collect()
+    }
+
+    pub async fn save_tags(tags: TagCache) -> Result<()> {
+        let tags_file = save_json("tags.json", &tags).await?;
+        info!("Saved tags to {:?}", tags_file);
+        Ok(())
+    }
+
+    // Function left sync intentionally, same as the settings
+    pub fn load_tags() -> Option<Self> {
+        // without a config directory the suggestions start out empty
+        let app_dirs = AppDirs::new(Some("wall-a-bunga"), true)?;
+        let tags_file = app_dirs.config_dir.join("tags.json");
+        let json = std::fs::read_to_string(tags_file.clone()).ok()?;
+        info!("Loaded tags from {:?}", tags_file);
+        serde_json::from_str(&json).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tags::{TagCache, MAX_TAGS};
+    use wallapi::types::Tag;
+
+    #[test]
+    fn suggests_seen_tags() {
+        let tag = |id: i64, name: &str, alias: &str| Tag {
+            id,
+            name: name.to_string(),
+            alias: alias.to_string(),
+            category: "Nature".to_string(),
+        };
+        let mut tags = TagCache::default();
+        tags.record(&[tag(1, "mountains", ""), tag(2, "snowy mountain", "")]);
+        tags.record(&[tag(2, "snowy mountain", ""), tag(3, "landscape", "scenery")]);
+
+        let names = |typed: &str| -> Vec<String> {
+            tags.suggest(typed, 5)
+                .into_iter()
+                .map(|tag| tag.name.clone())
+                .collect()
+        };
+        // names starting with it come first, even when seen less
+        assert_eq!(names("Mountain"), ["mountains", "snowy mountain"]);
+        assert_eq!(names("scen"), ["landscape"]);
+        assert!(names("m").is_empty());
+        assert_eq!(tags.tags.len(), 3);
+        assert_eq!(tags.tags[1].seen, 2);
+    }
+
+    #[test]
+    fn keeps_new_tags_when_full() {
+        let tag = |id: i64| Tag {
+            id,
+            name: format!("tag {}", id),
+            alias: String::new(),
+            category: "Nature".to_string(),
+        };
+        let mut tags = TagCache::default();
+        let old: Vec<Tag> = (0..MAX_TAGS as i64).map(tag).collect();
+        // seen a lot, but a while ago
+        for _ in 0..3 {
+            tags.record_at(&old, 100);
+        }
+        tags.record_at(&[tag(-1)], 200);
+        assert_eq!(tags.tags.len(), MAX_TAGS);
+        assert!(tags.tags.iter().any(|cached| cached.tag.id == -1));
+    }
+}
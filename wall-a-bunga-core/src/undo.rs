@@ -0,0 +1,87 @@
+/// Changes kept to undo, the oldest are forgotten past this
+const UNDO_LIMIT: usize = 100;
+
+/// Changes that can be undone, and the undone ones that can be redone until something new changes
+#[derive(Debug)]
+pub struct UndoStack<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> Default for UndoStack<T> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl<T> UndoStack<T> {
+    /// Remembers a new change, which leaves nothing to redo
+    pub fn record(&mut self, edit: T) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Takes the last change, it's handed back with [UndoStack::undone] once reverted
+    pub fn undo(&mut self) -> Option<T> {
+        self.undo.pop()
+    }
+
+    pub fn undone(&mut self, edit: T) {
+        self.redo.push(edit);
+    }
+
+    /// Takes the last undone change, it's handed back with [UndoStack::redone] once applied again
+    pub fn redo(&mut self) -> Option<T> {
+        self.redo.pop()
+    }
+
+    pub fn redone(&mut self, edit: T) {
+        self.undo.push(edit);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::undo::{UndoStack, UNDO_LIMIT};
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut stack = UndoStack::default();
+        stack.record(1);
+        stack.record(2);
+        let edit = stack.undo().unwrap();
+        assert_eq!(edit, 2);
+        stack.undone(edit);
+        assert!(stack.can_redo());
+        let edit = stack.redo().unwrap();
+        assert_eq!(edit, 2);
+        stack.redone(edit);
+        let edit = stack.undo().unwrap();
+        stack.undone(edit);
+        // a new change forgets what was undone
+        stack.record(3);
+        assert!(!stack.can_redo());
+        assert_eq!(stack.undo(), Some(3));
+        assert_eq!(stack.undo(), Some(1));
+        assert!(!stack.can_undo());
+        for edit in 0..UNDO_LIMIT + 5 {
+            stack.record(edit);
+        }
+        assert_eq!(stack.undo.len(), UNDO_LIMIT);
+        assert_eq!(stack.undo[0], 5);
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use log::info;
+use serde::Deserialize;
+use std::time::Duration;
+use wallapi::rate_limit::RateLimiter;
+use wallapi::types::{Avatar, ListingData, PurityLevel, SearchMetaData, Thumbs, Uploader};
+
+/// Most photos unsplash hands out at once
+const PAGE_SIZE: i64 = 30;
+
+const API_URL: &str = "https://api.unsplash.com";
+
+/// Unsplash allows apps that haven't been approved for production 50 requests an hour
+pub static RATE_LIMIT: RateLimiter = RateLimiter::new(50, Duration::from_secs(60 * 60));
+
+/// Unsplash asks for links back to it to say where they came from
+/// https://help.unsplash.com/en/articles/2511315-guideline-attribution
+const UTM: &str = "utm_source=wall-a-bunga&utm_medium=referral";
+
+/// Results of unsplash have ids starting with this, so they can't be taken for wallhaven's
+const ID_PREFIX: &str = "unsplash-";
+
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    total: i64,
+    total_pages: i64,
+    results: Vec<Photo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Photo {
+    id: String,
+    width: i64,
+    height: i64,
+    /// Like `2016-05-03T11:00:28-04:00`
+    created_at: String,
+    likes: i64,
+    color: Option<String>,
+    urls: PhotoUrls,
+    links: PhotoLinks,
+    user: Photographer,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotoUrls {
+    /// Largest size as a jpg
+    full: String,
+    /// 1080 pixels wide
+    regular: String,
+    /// 400 pixels wide
+    small: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotoLinks {
+    html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Photographer {
+    name: String,
+    links: PhotographerLinks,
+    profile_image: Option<ProfileImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotographerLinks {
+    html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileImage {
+    /// 32 pixels
+    small: String,
+    /// 128 pixels
+    large: String,
+}
+
+/// The unsplash id of a result, None for results of other sources
+pub fn photo_id(id: &str) -> Option<&str> {
+    id.strip_prefix(ID_PREFIX)
+}
+
+/// "Photo by someone on Unsplash" for results of unsplash, None for the others
+pub fn attribution(listing: &ListingData) -> Option<String> {
+    photo_id(&listing.id)?;
+    let photographer = listing.uploader.as_ref()?;
+    Some(format!("Photo by {} on Unsplash", photographer.username))
+}
+
+fn with_utm(link: &str) -> String {
+    match link.contains('?') {
+        true => format!("{}&{}", link, UTM),
+        false => format!("{}?{}", link, UTM),
+    }
+}
+
+/// The photographer is the uploader, their profile is the source. Unsplash is only photography
+/// and doesn't allow nudity, so everything is general and sfw.
+fn to_listing(photo: Photo) -> ListingData {
+    let (width, height) = (photo.width, photo.height);
+    let link = with_utm(&photo.links.html);
+    let avatar = photo
+        .user
+        .profile_image
+        .map(|image| Avatar {
+            large: image.large.clone(),
+            medium: image.large,
+            small: image.small.clone(),
+            tiny: image.small,
+        })
+        .unwrap_or_default();
+    ListingData {
+        id: format!("{}{}", ID_PREFIX, photo.id),
+        url: link.clone(),
+        short_url: link,
+        favorites: photo.likes,
+        source: with_utm(&photo.user.links.html),
+        purity: PurityLevel::Sfw,
+        dimension_x: width,
+        dimension_y: height,
+        resolution: format!("{}x{}", width, height),
+        ratio: format!("{:.2}", width as f64 / height.max(1) as f64),
+        file_type: "image/jpeg".to_string(),
+        // the offset is dropped, wallhaven's dates don't have one either
+        created_at: photo
+            .created_at
+            .replacen('T', " ", 1)
+            .chars()
+            .take(19)
+            .collect(),
+        colors: photo.color.into_iter().collect(),
+        thumbs: Thumbs {
+            large: photo.urls.regular.clone(),
+            original: photo.urls.full.clone(),
+            small: photo.urls.small,
+        },
+        path: photo.urls.full,
+        uploader: Some(Uploader {
+            username: photo.user.name,
+            group: "Unsplash".to_string(),
+            avatar,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Photos matching `query`, or the latest ones when it's empty
+fn photos_url(query: &str, page: i64) -> Result<reqwest::Url> {
+    let page = page.to_string();
+    let per_page = PAGE_SIZE.to_string();
+    let mut params = vec![("page", page.as_str()), ("per_page", per_page.as_str())];
+    let path = match query.trim() {
+        "" => "photos",
+        query => {
+            params.extend([("query", query), ("orientation", "landscape")]);
+            "search/photos"
+        }
+    };
+    Ok(reqwest::Url::parse_with_params(
+        &format!("{}/{}", API_URL, path),
+        &params,
+    )?)
+}
+
+/// A page of photos along with how many pages there are, see [photos_url]
+pub async fn fetch_photos(
+    client: &reqwest::Client,
+    access_key: &str,
+    query: &str,
+    page: i64,
+) -> Result<(Vec<ListingData>, SearchMetaData)> {
+    let url = photos_url(query, page)?;
+    RATE_LIMIT.acquire().await;
+    info!("Fetching {}", url);
+    let response = client
+        .get(url)
+        .header("Authorization", format!("Client-ID {}", access_key))
+        .header("Accept-Version", "v1")
+        .send()
+        .await?
+        .error_for_status()?;
+    let (photos, total, last_page) = match query.trim() {
+        // the latest photos go on for as long as there are full pages
+        "" => {
+            let photos: Vec<Photo> = response.json().await?;
+            let last_page = page + i64::from(photos.len() as i64 == PAGE_SIZE);
+            (photos, page * PAGE_SIZE, last_page)
+        }
+        _ => {
+            let search: SearchPage = response.json().await?;
+            (search.results, search.total, search.total_pages.max(1))
+        }
+    };
+    let meta = SearchMetaData {
+        current_page: page,
+        last_page,
+        per_page: PAGE_SIZE,
+        total,
+        ..Default::default()
+    };
+    Ok((photos.into_iter().map(to_listing).collect(), meta))
+}
+
+/// Tells unsplash the photo was downloaded, which their guidelines ask for
+/// https://help.unsplash.com/en/articles/2511258-guideline-triggering-a-download
+pub async fn track_download(client: &reqwest::Client, access_key: &str, id: &str) -> Result<()> {
+    RATE_LIMIT.acquire().await;
+    client
+        .get(format!("{}/photos/{}/download", API_URL, id))
+        .header("Authorization", format!("Client-ID {}", access_key))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::unsplash::{attribution, photo_id, photos_url, to_listing, SearchPage};
+
+    #[test]
+    fn maps_photos() {
+        let page: SearchPage = serde_json::from_str(
+            r##"{"total": 1, "total_pages": 1, "results": [{
+                "id": "Dwu85P9SOIk", "width": 6000, "height": 4000,
+                "created_at": "2016-05-03T11:00:28-04:00", "likes": 286, "color": "#6E633A",
+                "urls": {"raw": "https://images.unsplash.com/photo-1?ixid=a",
+                    "full": "https://images.unsplash.com/photo-1?ixid=a&fm=jpg",
+                    "regular": "https://images.unsplash.com/photo-1?ixid=a&w=1080",
+                    "small": "https://images.unsplash.com/photo-1?ixid=a&w=400"},
+                "links": {"html": "https://unsplash.com/photos/Dwu85P9SOIk"},
+                "user": {"name": "Jane Doe", "links": {"html": "https://unsplash.com/@jane"},
+                    "profile_image": {"small": "s", "medium": "m", "large": "l"}}
+            }]}"##,
+        )
+        .unwrap();
+        let photo = to_listing(page.results.into_iter().next().unwrap());
+        assert_eq!(photo.id, "unsplash-Dwu85P9SOIk");
+        assert_eq!(photo_id(&photo.id), Some("Dwu85P9SOIk"));
+        assert_eq!(photo_id("j3m8y5"), None);
+        assert_eq!(photo.ratio, "1.50");
+        assert_eq!(photo.created_at, "2016-05-03 11:00:28");
+        assert_eq!(photo.colors, ["#6E633A"]);
+        assert_eq!(
+            photo.thumbs.small,
+            "https://images.unsplash.com/photo-1?ixid=a&w=400"
+        );
+        assert_eq!(
+            photo.url,
+            "https://unsplash.com/photos/Dwu85P9SOIk?utm_source=wall-a-bunga&utm_medium=referral"
+        );
+        assert_eq!(
+            photo.source,
+            "https://unsplash.com/@jane?utm_source=wall-a-bunga&utm_medium=referral"
+        );
+        assert_eq!(
+            attribution(&photo).as_deref(),
+            Some("Photo by Jane Doe on Unsplash")
+        );
+        assert_eq!(attribution(&Default::default()), None);
+    }
+
+    #[test]
+    fn photos_urls() {
+        assert_eq!(
+            photos_url(" ", 2).unwrap().as_str(),
+            "https://api.unsplash.com/photos?page=2&per_page=30"
+        );
+        assert_eq!(
+            photos_url("northern lights", 1).unwrap().as_str(),
+            "https://api.unsplash.com/search/photos?page=1&per_page=30&query=northern+lights&orientation=landscape"
+        );
+    }
+}
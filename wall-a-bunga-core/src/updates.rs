@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/akarras/wall-a-bunga/releases/latest";
+
+/// The windows installer of every release, no other asset is ever started
+const INSTALLER_NAME: &str = "wall-a-bunga.msi";
+
+/// The installer's SHA256, published next to it the way `sha256sum` prints it
+const CHECKSUM_NAME: &str = "wall-a-bunga.msi.sha256";
+
+/// A release on github, the binaries are its assets
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    /// Like `v0.5.0`
+    pub tag_name: String,
+    pub name: Option<String>,
+    /// The changelog, in markdown
+    pub body: Option<String>,
+    /// The release's page
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: u64,
+}
+
+/// Major, minor and patch of `0.4.0` or `v0.4.0`, anything after a `-` is ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+impl Release {
+    /// The version without the `v` of the tag
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// Whether it's a later version than `current`, releases that aren't tagged with a version
+    /// never are
+    pub fn is_newer_than(&self, current: &str) -> bool {
+        match (parse_version(&self.tag_name), parse_version(current)) {
+            (Some(release), Some(current)) => release > current,
+            _ => false,
+        }
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+
+    /// The windows installer, None when the release doesn't publish its checksum alongside it
+    pub fn installer(&self) -> Option<&ReleaseAsset> {
+        self.asset(CHECKSUM_NAME)?;
+        self.asset(INSTALLER_NAME)
+    }
+}
+
+/// The hash of a `sha256sum` line, lowercased
+fn parse_checksum(text: &str) -> Option<String> {
+    let hash = text.split_whitespace().next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then(|| hash.to_lowercase())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The release that's marked as the latest, drafts and pre-releases never are
+pub async fn latest_release(client: &reqwest::Client) -> Result<Release> {
+    info!("Checking for updates");
+    Ok(client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Saves the release's installer into `directory`, streaming it to disk. It's only kept when its
+/// SHA256 matches the published one, since it's started right after.
+pub async fn download_installer(
+    client: &reqwest::Client,
+    release: &Release,
+    directory: &Path,
+) -> Result<PathBuf> {
+    let (installer, checksum) = match (release.asset(INSTALLER_NAME), release.asset(CHECKSUM_NAME))
+    {
+        (Some(installer), Some(checksum)) => (installer, checksum),
+        _ => return Err(anyhow!("{} has no checksummed installer", release.tag_name)),
+    };
+    let text = client
+        .get(&checksum.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let expected =
+        parse_checksum(&text).ok_or_else(|| anyhow!("{} isn't a SHA256", CHECKSUM_NAME))?;
+
+    info!("Downloading {}", installer.browser_download_url);
+    let mut response = client
+        .get(&installer.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?;
+    let part = directory.join(format!("{}.part", INSTALLER_NAME));
+    let mut file = tokio::fs::File::create(&part).await?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    drop(file);
+    let actual = hex(&hasher.finalize());
+    if actual != expected {
+        if let Err(e) = tokio::fs::remove_file(&part).await {
+            error!("Failed to remove {:?} {}", part, e);
+        }
+        return Err(anyhow!(
+            "The downloaded installer doesn't match its checksum, expected {} but got {}",
+            expected,
+            actual
+        ));
+    }
+    let path = directory.join(INSTALLER_NAME);
+    tokio::fs::rename(&part, &path).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::updates::{parse_checksum, parse_version, Release};
+
+    #[test]
+    fn compares_versions() {
+        assert_eq!(parse_version("v0.4.0"), Some((0, 4, 0)));
+        assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("v1.0.0-beta.1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("nightly"), None);
+        let release: Release = serde_json::from_str(
+            r#"{"tag_name": "v0.10.1", "name": "0.10.1", "body": "* faster downloads",
+                "html_url": "https://github.com/akarras/wall-a-bunga/releases/tag/v0.10.1",
+                "assets": [
+                    {"name": "wall-a-bunga-linux", "size": 1, "browser_download_url": "https://example.com/linux"},
+                    {"name": "wall-a-bunga.exe", "size": 2, "browser_download_url": "https://example.com/exe"},
+                    {"name": "wall-a-bunga.msi", "size": 3, "browser_download_url": "https://example.com/msi"}
+                ]}"#,
+        )
+        .unwrap();
+        // never started without a checksum to check it against
+        assert_eq!(release.installer(), None);
+        let mut release = release;
+        release.assets.push(serde_json::from_str(
+            r#"{"name": "wall-a-bunga.msi.sha256", "size": 4, "browser_download_url": "https://example.com/sha256"}"#,
+        ).unwrap());
+        assert_eq!(release.version(), "0.10.1");
+        assert!(release.is_newer_than("0.4.0"));
+        assert!(release.is_newer_than("0.10.0"));
+        assert!(!release.is_newer_than("0.10.1"));
+        assert!(!release.is_newer_than("1.0.0"));
+        assert_eq!(release.installer().unwrap().size, 3);
+
+        let hash = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        assert_eq!(
+            parse_checksum(&format!("{}  wall-a-bunga.msi\n", hash)),
+            Some(hash.to_lowercase())
+        );
+        assert_eq!(parse_checksum("not a hash"), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+}
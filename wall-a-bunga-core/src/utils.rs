@@ -0,0 +1,239 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wallapi::types::XYCombo;
+
+/// Returns a number formatted with a suffix of k, or m.
+/// Will return a decimal point if applicable
+pub fn trendy_number_format(val: f64) -> String {
+    let log = val.log10();
+    let val_suff = if (3.0..6.0).contains(&log) {
+        Some((val / 1000.0, "k"))
+    } else if (6.0..9.0).contains(&log) {
+        Some((val / 1000000.0, "m"))
+    } else if log >= 9.0 {
+        Some((val / 1000000000.0, "b"))
+    } else {
+        None
+    };
+    match val_suff {
+        // no suffix
+        None => format!("{:.0}", val),
+        // suffix, use the applied units
+        Some((sig, suffix)) => format!("{:.1}{}", sig, suffix),
+    }
+}
+
+/// Bytes in a megabyte. The megabyte limits in the settings use the same decimal units as
+/// [file_size_format], so a limit reads the same as the sizes shown next to it.
+pub const MEGABYTE: u64 = 1_000_000;
+
+/// Returns a byte count formatted with a unit, e.g. 1.5 MB
+pub fn file_size_format(bytes: i64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1_000_000_000.0 {
+        format!("{:.1} GB", bytes / 1_000_000_000.0)
+    } else if bytes >= 1_000_000.0 {
+        format!("{:.1} MB", bytes / 1_000_000.0)
+    } else if bytes >= 1_000.0 {
+        format!("{:.0} KB", bytes / 1_000.0)
+    } else {
+        format!("{:.0} B", bytes)
+    }
+}
+
+/// Returns a short human readable duration like 5m ago or 3d ago
+pub fn time_ago_format(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Returns a short duration for an estimate like 45s, 3m 20s or 1h 5m
+pub fn remaining_time_format(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Parses the `created_at` of a wallpaper, e.g. `2021-09-20 12:34:56` in UTC
+pub fn parse_upload_date(created_at: &str) -> Option<SystemTime> {
+    let (date, time) = created_at.trim().split_once(' ')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146097 + day_of_era - 719468).ok()?;
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Formats a time the way wallhaven does, e.g. `2021-09-20 12:34:56` in UTC
+pub fn format_utc(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, seconds_of_day) = ((seconds / 86400) as i64, seconds % 86400);
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Pixels of an image cut off horizontally (x) and vertically (y) when it's scaled to cover a
+/// screen, the way desktop backgrounds are usually filled
+pub fn cropped_pixels(image: XYCombo, screen: XYCombo) -> XYCombo {
+    if image.x <= 0 || image.y <= 0 || screen.x <= 0 || screen.y <= 0 {
+        return XYCombo::default();
+    }
+    let scale = f64::max(
+        screen.x as f64 / image.x as f64,
+        screen.y as f64 / image.y as f64,
+    );
+    XYCombo {
+        x: (image.x as f64 - screen.x as f64 / scale).round() as i32,
+        y: (image.y as f64 - screen.y as f64 / scale).round() as i32,
+    }
+}
+
+/// A seed for random sorting, in the six letters and digits wallhaven uses itself
+pub fn random_seed() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(6)
+        .map(char::from)
+        .collect()
+}
+
+/// Whether wallhaven accepts the seed, it only takes six letters or digits
+pub fn is_valid_seed(seed: &str) -> bool {
+    seed.len() == 6 && seed.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Whether an image is smaller than the screen in either direction, so it would be upscaled to
+/// fill it
+pub fn is_upscaled(image: XYCombo, screen: XYCombo) -> bool {
+    image.x < screen.x || image.y < screen.y
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn num_format_test() {
+        assert_eq!(trendy_number_format(12300.0), "12.3k");
+        assert_eq!(trendy_number_format(12012.0), "12.0k");
+        assert_eq!(trendy_number_format(1200000.0), "1.2m");
+        assert_eq!(trendy_number_format(10001.0), "10.0k");
+        assert_eq!(trendy_number_format(1.0), "1");
+    }
+
+    #[test]
+    fn file_size_test() {
+        assert_eq!(file_size_format(512), "512 B");
+        assert_eq!(file_size_format(2_400), "2 KB");
+        assert_eq!(file_size_format(1_500_000), "1.5 MB");
+        assert_eq!(file_size_format(3_210_000_000), "3.2 GB");
+    }
+
+    #[test]
+    fn time_ago_test() {
+        assert_eq!(time_ago_format(5), "just now");
+        assert_eq!(time_ago_format(125), "2m ago");
+        assert_eq!(time_ago_format(7200), "2h ago");
+        assert_eq!(time_ago_format(86400 * 3), "3d ago");
+    }
+
+    #[test]
+    fn remaining_time_test() {
+        assert_eq!(remaining_time_format(45), "45s");
+        assert_eq!(remaining_time_format(200), "3m 20s");
+        assert_eq!(remaining_time_format(3900), "1h 5m");
+    }
+
+    #[test]
+    fn upload_date_test() {
+        assert_eq!(
+            parse_upload_date("2021-09-20 12:34:56"),
+            Some(UNIX_EPOCH + Duration::from_secs(1632141296))
+        );
+        assert_eq!(parse_upload_date("1970-01-01 00:00:00"), Some(UNIX_EPOCH));
+        assert_eq!(parse_upload_date("yesterday"), None);
+        for date in [
+            "2021-09-20 12:34:56",
+            "2000-02-29 23:59:59",
+            "1970-01-01 00:00:00",
+        ] {
+            assert_eq!(format_utc(parse_upload_date(date).unwrap()), date);
+        }
+    }
+
+    #[test]
+    fn seed_test() {
+        assert!(is_valid_seed(&random_seed()));
+        assert!(is_valid_seed("aB3dE9"));
+        assert!(!is_valid_seed("abc"));
+        assert!(!is_valid_seed("abc-de"));
+    }
+
+    #[test]
+    fn cropped_pixels_test() {
+        let screen = XYCombo { x: 1920, y: 1080 };
+        // 16:10 on 16:9 loses some height
+        assert_eq!(
+            cropped_pixels(XYCombo { x: 2560, y: 1600 }, screen),
+            XYCombo { x: 0, y: 160 }
+        );
+        // ultrawide on 16:9 loses width
+        assert_eq!(
+            cropped_pixels(XYCombo { x: 3440, y: 1440 }, screen),
+            XYCombo { x: 880, y: 0 }
+        );
+        assert_eq!(cropped_pixels(screen, screen), XYCombo::default());
+    }
+
+    #[test]
+    fn upscaled_test() {
+        let screen = XYCombo { x: 2560, y: 1440 };
+        assert!(is_upscaled(XYCombo { x: 1920, y: 1080 }, screen));
+        // wide enough but too short
+        assert!(is_upscaled(XYCombo { x: 3440, y: 1080 }, screen));
+        assert!(!is_upscaled(screen, screen));
+        assert!(!is_upscaled(XYCombo { x: 3840, y: 2160 }, screen));
+    }
+}
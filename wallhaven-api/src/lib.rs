@@ -1,8 +1,14 @@
-use crate::types::{GenericResponse, ListingData, SearchOptions};
-use log::{debug, info};
+use crate::rate_limit::RateLimiter;
+use crate::types::{Collection, GenericResponse, ListingData, SearchOptions, UserSettings};
+use log::{debug, info, warn};
+use reqwest::{Request, Response, StatusCode, Url};
+use std::time::Duration;
 use thiserror::Error;
 
+pub mod query;
+pub mod rate_limit;
 pub mod types;
+pub mod url;
 
 #[derive(Error, Debug)]
 pub enum WallhavenApiClientError {
@@ -10,16 +16,117 @@ pub enum WallhavenApiClientError {
     Reqwest(#[from] reqwest::Error),
     #[error("Invalid content supplied")]
     InvalidContent,
+    #[error("Not a wallhaven.cc url")]
+    InvalidUrl,
+    #[error("The api key isn't valid")]
+    InvalidApiKey,
 }
 
 pub type WHResult<T> = Result<T, WallhavenApiClientError>;
 
+/// Wallhaven allows 45 api requests a minute, shared by every client since they go to the same
+/// server
+pub static RATE_LIMIT: RateLimiter = RateLimiter::new(45, Duration::from_secs(60));
+
+/// How requests that failed for a reason that usually passes on its own are retried, which is
+/// timing out or wallhaven answering with a server error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one, 0 never retries
+    pub retries: u32,
+    /// Waited before each retry
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 1,
+            delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// The url with the api key swapped out, so it can be logged
+fn redacted(url: &Url) -> Url {
+    let mut redacted = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| match name == "apikey" {
+            true => (name.to_string(), "redacted".to_string()),
+            false => (name.to_string(), value.to_string()),
+        })
+        .collect();
+    if !pairs.is_empty() {
+        redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    redacted
+}
+
 /// Provides a client that provides async access to the Wallhaven api
 /// No blocking client is provided, because I don't want to :)
 #[derive(Default, Debug, Clone)]
-pub struct WallhavenClient {}
+pub struct WallhavenClient {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
 
 impl WallhavenClient {
+    /// Sends every request through the given client, sharing its connections, proxy and user agent
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Replaces the default policy of retrying once
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The client requests are sent through, to fetch images with the same connections
+    pub fn http(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Sends the request, retrying it by the [RetryPolicy] when it times out or wallhaven answers
+    /// with a server error. A server error that's still there after the retries is returned as
+    /// an error.
+    async fn execute(&self, mut request: Request) -> WHResult<Response> {
+        let mut attempt = 0;
+        loop {
+            let next = request.try_clone();
+            RATE_LIMIT.acquire().await;
+            let result = self.client.execute(request).await;
+            let transient = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_timeout(),
+            };
+            match next {
+                Some(next) if transient && attempt < self.retry.retries => {
+                    attempt += 1;
+                    // the query can hold the api key, so only the path is logged
+                    warn!(
+                        "Request to {} failed, retrying in {:?}",
+                        next.url().path(),
+                        self.retry.delay
+                    );
+                    futures_timer::Delay::new(self.retry.delay).await;
+                    request = next;
+                }
+                _ => {
+                    let response = result?;
+                    return match response.status().is_server_error() {
+                        true => Ok(response.error_for_status()?),
+                        false => Ok(response),
+                    };
+                }
+            }
+        }
+    }
+
     /// Searches wallhaven.cc using the given search options
     ///
     /// # Arguments
@@ -30,7 +137,7 @@ impl WallhavenClient {
     /// use wallhaven_api::{WallhavenClient, types::SearchOptions};
     ///
     /// async fn search_example() {
-    ///     let results = WallhavenClient::search(&SearchOptions {
+    ///     let results = WallhavenClient::default().search(&SearchOptions {
     ///         query: Some("Cats".to_string()),
     ///         ..Default::default()
     ///     }).await;
@@ -38,29 +145,160 @@ impl WallhavenClient {
     ///     println!("received wallpapers: {:?}", results);
     /// }
     /// ```
-    pub async fn search(options: &SearchOptions) -> WHResult<GenericResponse<Vec<ListingData>>> {
+    pub async fn search(
+        &self,
+        options: &SearchOptions,
+    ) -> WHResult<GenericResponse<Vec<ListingData>>> {
         let search_url_base = "https://wallhaven.cc/api/v1/search";
-        let client = reqwest::Client::builder().build()?;
+        let client = &self.client;
         let request = client.get(search_url_base).query(&options).build()?;
-        info!("Requesting from url: {:?}", &request);
-        let response = client.execute(request).await?;
+        info!("Requesting from url: {}", redacted(request.url()));
+        let response = self.execute(request).await?;
+        let content = response.json().await?;
+        debug!("Received content {:?}", content);
+        Ok(content)
+    }
+
+    /// Fetches the details of a single wallpaper
+    ///
+    /// # Arguments
+    /// * `id` - Id of the wallpaper, as seen in `https://wallhaven.cc/w/{id}`
+    /// * `api_key` - Required to view NSFW wallpapers
+    pub async fn wallpaper(
+        &self,
+        id: &str,
+        api_key: Option<&str>,
+    ) -> WHResult<GenericResponse<ListingData>> {
+        let wallpaper_url = format!("https://wallhaven.cc/api/v1/w/{}", id);
+        let client = &self.client;
+        let mut request = client.get(&wallpaper_url);
+        if let Some(api_key) = api_key {
+            request = request.query(&[("apikey", api_key)]);
+        }
+        let request = request.build()?;
+        info!("Requesting from url: {}", redacted(request.url()));
+        let response = self.execute(request).await?;
         let content = response.json().await?;
         debug!("Received content {:?}", content);
         Ok(content)
     }
+
+    /// Fetches the account settings of the api key's owner, which is also how a key is checked
+    ///
+    /// # Arguments
+    /// * `api_key` - Fails with [WallhavenApiClientError::InvalidApiKey] when wallhaven rejects it
+    pub async fn user_settings(&self, api_key: &str) -> WHResult<UserSettings> {
+        let client = &self.client;
+        let request = client
+            .get("https://wallhaven.cc/api/v1/settings")
+            .query(&[("apikey", api_key)])
+            .build()?;
+        // the url holds the key, so it isn't logged
+        info!("Requesting user settings");
+        let response = self.execute(request).await?;
+        // anything else, like being rate limited, says nothing about the key
+        if matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(WallhavenApiClientError::InvalidApiKey);
+        }
+        let content: GenericResponse<UserSettings> = response.error_for_status()?.json().await?;
+        debug!("Received content {:?}", content);
+        content.data.ok_or(WallhavenApiClientError::InvalidContent)
+    }
+
+    /// Lists the public collections of a user, or every collection of the api key's owner
+    ///
+    /// # Arguments
+    /// * `username` - Whose collections to list, leave out to list your own
+    /// * `api_key` - Required when no username is given, private collections are listed with it
+    pub async fn collections(
+        &self,
+        username: Option<&str>,
+        api_key: Option<&str>,
+    ) -> WHResult<GenericResponse<Vec<Collection>>> {
+        let mut url = Url::parse("https://wallhaven.cc/api/v1/collections")
+            .map_err(|_| WallhavenApiClientError::InvalidUrl)?;
+        if let Some(username) = username {
+            url.path_segments_mut()
+                .map_err(|_| WallhavenApiClientError::InvalidUrl)?
+                .push(username);
+        }
+        let client = &self.client;
+        let mut request = client.get(url);
+        if let Some(api_key) = api_key {
+            request = request.query(&[("apikey", api_key)]);
+        }
+        let request = request.build()?;
+        info!("Requesting collections of {:?}", username);
+        let response = self.execute(request).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(WallhavenApiClientError::InvalidApiKey);
+        }
+        let content = response.error_for_status()?.json().await?;
+        debug!("Received content {:?}", content);
+        Ok(content)
+    }
+
+    /// Fetches a page of the wallpapers in a collection
+    ///
+    /// # Arguments
+    /// * `username` - Owner of the collection
+    /// * `id` - Id of the collection, as listed by [WallhavenClient::collections]
+    /// * `options` - Only the page, purity and api key are used, the rest is ignored by wallhaven
+    pub async fn collection_wallpapers(
+        &self,
+        username: &str,
+        id: i64,
+        options: &SearchOptions,
+    ) -> WHResult<GenericResponse<Vec<ListingData>>> {
+        let mut url = Url::parse("https://wallhaven.cc/api/v1/collections")
+            .map_err(|_| WallhavenApiClientError::InvalidUrl)?;
+        url.path_segments_mut()
+            .map_err(|_| WallhavenApiClientError::InvalidUrl)?
+            .push(username)
+            .push(&id.to_string());
+        let options = SearchOptions {
+            page: options.page,
+            purity: options.purity.clone(),
+            api_key: options.api_key.clone(),
+            ..Default::default()
+        };
+        let client = &self.client;
+        let request = client.get(url).query(&options).build()?;
+        info!("Requesting collection {} of {}", id, username);
+        let response = self.execute(request).await?;
+        let content = response.error_for_status()?.json().await?;
+        debug!("Received content {:?}", content);
+        Ok(content)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{SearchOptions, WallhavenClient};
+    use crate::{redacted, SearchOptions, WallhavenClient};
+    use reqwest::Url;
+
+    #[test]
+    fn redacts_api_keys() {
+        let url = Url::parse("https://wallhaven.cc/api/v1/search?q=cats&apikey=secret").unwrap();
+        assert_eq!(
+            redacted(&url).as_str(),
+            "https://wallhaven.cc/api/v1/search?q=cats&apikey=redacted"
+        );
+        let url = Url::parse("https://wallhaven.cc/api/v1/w/abc").unwrap();
+        assert_eq!(redacted(&url), url);
+    }
 
     #[tokio::test]
     async fn search_test() {
-        let results = WallhavenClient::search(&SearchOptions::new())
+        let results = WallhavenClient::default()
+            .search(&SearchOptions::new())
             .await
             .expect("No failure");
         let values = results.data.unwrap();
 
-        assert_eq!(values.len() > 0, true);
+        assert!(!values.is_empty());
     }
 }
@@ -0,0 +1,92 @@
+/// Builds a search query using the wallhaven query syntax, see https://wallhaven.cc/help/api#search
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QueryBuilder {
+    terms: Vec<String>,
+    excluded_tags: Vec<String>,
+}
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Splits a raw query into plain terms and excluded tags (`-tag`)
+    ///
+    /// # Example Usage
+    /// ```
+    /// use wallhaven_api::query::QueryBuilder;
+    ///
+    /// let query = QueryBuilder::parse("nature -city 4k");
+    /// assert_eq!(query.terms(), &["nature", "4k"]);
+    /// assert_eq!(query.excluded_tags(), &["city"]);
+    /// ```
+    pub fn parse(query: &str) -> Self {
+        let mut builder = QueryBuilder::new();
+        for token in query.split_whitespace() {
+            match token.strip_prefix('-') {
+                Some(tag) if !tag.is_empty() => builder.exclude_tag(tag),
+                _ => builder.add_term(token),
+            };
+        }
+        builder
+    }
+
+    pub fn add_term(&mut self, term: &str) -> &mut QueryBuilder {
+        let term = term.trim();
+        if !term.is_empty() {
+            self.terms.push(term.to_string());
+        }
+        self
+    }
+
+    /// Excludes results with the given tag, duplicates are ignored
+    pub fn exclude_tag(&mut self, tag: &str) -> &mut QueryBuilder {
+        let tag = tag.trim();
+        if !tag.is_empty() && !self.excluded_tags.iter().any(|t| t == tag) {
+            self.excluded_tags.push(tag.to_string());
+        }
+        self
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    pub fn excluded_tags(&self) -> &[String] {
+        &self.excluded_tags
+    }
+
+    /// Returns the query string to be used for `SearchOptions::query`
+    pub fn build(&self) -> String {
+        self.terms
+            .iter()
+            .cloned()
+            .chain(self.excluded_tags.iter().map(|tag| format!("-{}", tag)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::QueryBuilder;
+
+    #[test]
+    fn build_query() {
+        let mut query = QueryBuilder::new();
+        query
+            .add_term("nature")
+            .exclude_tag("city")
+            .exclude_tag("city")
+            .exclude_tag(" ");
+        assert_eq!(query.build(), "nature -city");
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let query = QueryBuilder::parse("  cats -dogs  - -birds");
+        assert_eq!(query.terms(), &["cats", "-"]);
+        assert_eq!(query.excluded_tags(), &["dogs", "birds"]);
+        assert_eq!(query.build(), "cats - -dogs -birds");
+    }
+}
@@ -0,0 +1,82 @@
+use log::info;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeps requests to an api under what it allows, `requests` in any `period`. Once they're used
+/// up, the next request waits until the oldest one is a `period` ago.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests: usize,
+    period: Duration,
+    /// When the requests in the last `period` were sent, oldest first
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub const fn new(requests: usize, period: Duration) -> Self {
+        Self {
+            requests,
+            period,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Takes a request for `now`, or how long until one is free
+    fn reserve(&self, now: Instant) -> Option<Duration> {
+        let mut sent = self.sent.lock().unwrap();
+        while sent
+            .front()
+            .is_some_and(|oldest| now.duration_since(*oldest) >= self.period)
+        {
+            sent.pop_front();
+        }
+        match sent.front() {
+            Some(oldest) if sent.len() >= self.requests => Some(*oldest + self.period - now),
+            _ => {
+                sent.push_back(now);
+                None
+            }
+        }
+    }
+
+    /// Waits until a request can be sent without going over the limit
+    pub async fn acquire(&self) {
+        while let Some(wait) = self.reserve(Instant::now()) {
+            info!("Rate limited, waiting {:?}", wait);
+            futures_timer::Delay::new(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rate_limit::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn limits_requests_per_period() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(limiter.reserve(start), None);
+        assert_eq!(limiter.reserve(start + Duration::from_secs(10)), None);
+        // the third waits for the first to be a minute old
+        assert_eq!(
+            limiter.reserve(start + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(limiter.reserve(start + Duration::from_secs(60)), None);
+        assert_eq!(
+            limiter.reserve(start + Duration::from_secs(61)),
+            Some(Duration::from_secs(9))
+        );
+    }
+}
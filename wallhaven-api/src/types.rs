@@ -8,8 +8,9 @@ use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Purity {
     pub clean: bool,
     pub sketchy: bool,
@@ -37,6 +38,16 @@ impl Serialize for Purity {
     }
 }
 
+impl<'de> Deserialize<'de> for Purity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        Purity::try_from(str.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
 impl Serialize for Categories {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
     where
@@ -47,6 +58,16 @@ impl Serialize for Categories {
     }
 }
 
+impl<'de> Deserialize<'de> for Categories {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        Categories::try_from(str.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
 fn explicit_char_bool(character: char) -> WHResult<bool> {
     match character {
         '0' => Ok(false),
@@ -78,17 +99,45 @@ fn bool_to_bit_char(val: bool) -> char {
     }
 }
 
-impl Into<String> for &Purity {
-    fn into(self) -> String {
+fn serialize_optional_bit_bool<S>(
+    value: &Option<bool>,
+    serializer: S,
+) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(value) => serializer.serialize_char(bool_to_bit_char(*value)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_optional_bit_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) => match value.chars().next() {
+            Some(character) => explicit_char_bool(character)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+impl From<&Purity> for String {
+    fn from(purity: &Purity) -> String {
         let mut string = String::with_capacity(3);
-        string.push(bool_to_bit_char(self.clean));
-        string.push(bool_to_bit_char(self.sketchy));
-        string.push(bool_to_bit_char(self.nsfw));
+        string.push(bool_to_bit_char(purity.clean));
+        string.push(bool_to_bit_char(purity.sketchy));
+        string.push(bool_to_bit_char(purity.nsfw));
         string
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Categories {
     pub general: bool,
     pub anime: bool,
@@ -105,12 +154,12 @@ impl Default for Categories {
     }
 }
 
-impl Into<String> for &Categories {
-    fn into(self) -> String {
+impl From<&Categories> for String {
+    fn from(categories: &Categories) -> String {
         let mut str = String::with_capacity(3);
-        str.push(bool_to_bit_char(self.general));
-        str.push(bool_to_bit_char(self.anime));
-        str.push(bool_to_bit_char(self.people));
+        str.push(bool_to_bit_char(categories.general));
+        str.push(bool_to_bit_char(categories.anime));
+        str.push(bool_to_bit_char(categories.people));
         str
     }
 }
@@ -131,14 +180,17 @@ impl TryFrom<&str> for Categories {
     }
 }
 
-#[derive(Serialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Sorting {
     DateAdded,
     Relevance,
+    #[default]
     Random,
     Views,
     Favorites,
+    /// Saved as `top_list` before it was named the way the api expects
+    #[serde(rename = "toplist", alias = "top_list")]
     TopList,
 }
 
@@ -166,24 +218,32 @@ impl Display for Sorting {
     }
 }
 
-impl Default for Sorting {
-    fn default() -> Self {
-        Sorting::Random
-    }
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
 pub enum SortingOrder {
     #[serde(rename = "asc")]
     Ascending,
+    #[default]
     #[serde(rename = "desc")]
     Descending,
 }
 
-impl Default for SortingOrder {
-    fn default() -> Self {
-        SortingOrder::Descending
-    }
+/// Time span the toplist is ranked over, the API defaults to a month
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TopRange {
+    #[serde(rename = "1d")]
+    OneDay,
+    #[serde(rename = "3d")]
+    ThreeDays,
+    #[serde(rename = "1w")]
+    OneWeek,
+    #[serde(rename = "1M")]
+    OneMonth,
+    #[serde(rename = "3M")]
+    ThreeMonths,
+    #[serde(rename = "6M")]
+    SixMonths,
+    #[serde(rename = "1y")]
+    OneYear,
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
@@ -198,6 +258,25 @@ impl Display for XYCombo {
     }
 }
 
+impl FromStr for XYCombo {
+    type Err = WallhavenApiClientError;
+
+    /// Parses the `1920x1080` format used by the API
+    fn from_str(s: &str) -> WHResult<Self> {
+        let (x, y) = s
+            .split_once('x')
+            .ok_or(WallhavenApiClientError::InvalidContent)?;
+        Ok(XYCombo {
+            x: x.trim()
+                .parse()
+                .map_err(|_| WallhavenApiClientError::InvalidContent)?,
+            y: y.trim()
+                .parse()
+                .map_err(|_| WallhavenApiClientError::InvalidContent)?,
+        })
+    }
+}
+
 pub static RESOLUTION_POSSIBILITIES: [XYCombo; 22] = [
     XYCombo { x: 2560, y: 1080 },
     XYCombo { x: 3440, y: 1440 },
@@ -265,9 +344,20 @@ impl Serialize for XYCombo {
     }
 }
 
+impl<'de> Deserialize<'de> for XYCombo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        str.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
 pub struct SearchOptions {
     #[serde(rename = "q")]
     pub query: Option<String>,
@@ -279,6 +369,9 @@ pub struct SearchOptions {
     /// Optional order that results will be sorted in, API defaults this to desc if not provided
     #[serde(rename = "order")]
     pub sorting_order: Option<SortingOrder>,
+    /// Only used when sorting by the toplist
+    #[serde(rename = "topRange")]
+    pub top_range: Option<TopRange>,
     #[serde(rename = "apikey")]
     pub api_key: Option<String>,
     pub seed: Option<String>,
@@ -288,6 +381,13 @@ pub struct SearchOptions {
     pub minimum_resolution: Option<XYCombo>,
     #[serde_as(as = "Option<StringWithSeparator::<CommaSeparator, XYCombo>>")]
     pub ratios: Option<HashSet<XYCombo>>,
+    /// Hides wallpapers flagged as AI generated
+    #[serde(
+        rename = "ai_art_filter",
+        serialize_with = "serialize_optional_bit_bool",
+        deserialize_with = "deserialize_optional_bit_bool"
+    )]
+    pub hide_ai_art: Option<bool>,
 }
 
 impl SearchOptions {
@@ -344,7 +444,7 @@ pub struct ListingData {
     pub views: i64,
     pub favorites: i64,
     pub source: String,
-    pub purity: String,
+    pub purity: PurityLevel,
     pub category: Category,
     pub dimension_x: i64,
     pub dimension_y: i64,
@@ -356,20 +456,106 @@ pub struct ListingData {
     pub colors: Vec<String>,
     pub path: String,
     pub thumbs: Thumbs,
+    /// Only filled in when fetching a single wallpaper, search results leave this empty
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    /// Only filled in when fetching a single wallpaper
+    #[serde(default)]
+    pub uploader: Option<Uploader>,
+}
+
+/// User who uploaded a wallpaper, their uploads are searched for with `@username`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Uploader {
+    pub username: String,
+    /// ## example: `User`
+    pub group: String,
+    pub avatar: Avatar,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Links to an uploader's avatar in a few sizes
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Avatar {
+    /// ## example: `https://wallhaven.cc/images/user/avatar/200/11_3339efb2a813.png`
+    #[serde(rename = "200px")]
+    pub large: String,
+    #[serde(rename = "128px")]
+    pub medium: String,
+    #[serde(rename = "32px")]
+    pub small: String,
+    #[serde(rename = "20px")]
+    pub tiny: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Category {
     Anime,
     People,
+    #[default]
     General,
 }
 
-impl Default for Category {
-    fn default() -> Self {
-        Self::General
-    }
+/// Purity rating of a single wallpaper
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PurityLevel {
+    #[default]
+    Sfw,
+    Sketchy,
+    Nsfw,
+}
+
+/// Tag attached to a wallpaper
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    /// ## example: `landscape`
+    pub name: String,
+    /// Other names of the tag separated by commas
+    /// ## example: `landscapes, scenery`
+    #[serde(default)]
+    pub alias: String,
+    /// ## example: `Nature`
+    #[serde(default)]
+    pub category: String,
+}
+
+/// Account settings belonging to an api key, see https://wallhaven.cc/help/api#settings
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// ## example: `orig`
+    pub thumb_size: String,
+    /// Sent as a string
+    /// ## example: `24`
+    pub per_page: String,
+    /// ## example: `["sfw", "sketchy"]`
+    pub purity: Vec<String>,
+    /// ## example: `["general", "anime", "people"]`
+    pub categories: Vec<String>,
+    pub resolutions: Vec<String>,
+    pub aspect_ratios: Vec<String>,
+    /// ## example: `6M`
+    pub toplist_range: String,
+    pub tag_blacklist: Vec<String>,
+    pub user_blacklist: Vec<String>,
+}
+
+/// A user's collection of favorited wallpapers, see https://wallhaven.cc/help/api#collections
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collection {
+    pub id: i64,
+    /// ## example: `Default`
+    pub label: String,
+    pub views: i64,
+    /// `1` when anyone can see the collection, private ones are only listed for their owner
+    pub public: i64,
+    /// Number of wallpapers in the collection
+    pub count: i64,
 }
 
 /// Contains URLs to various sized thumbnails
@@ -446,7 +632,10 @@ pub struct SearchMetaData {
 
 #[cfg(test)]
 mod tests {
-    use crate::types::{Categories, Purity, Sorting, SortingOrder, XYCombo};
+    use crate::types::{
+        Categories, Collection, GenericResponse, ListingData, Purity, Sorting, SortingOrder,
+        TopRange, UserSettings, XYCombo,
+    };
     use crate::SearchOptions;
 
     // ensure that the search options query string serializes properly
@@ -470,18 +659,20 @@ mod tests {
             }),
             sorting: Some(Sorting::Views),
             sorting_order: Some(SortingOrder::Descending),
+            top_range: Some(TopRange::OneWeek),
             api_key: Some("supersecretapikey".to_string()),
             seed: Some("seedyroots".to_string()),
             resolutions: Some(vec![XYCombo { x: 1920, y: 1280 }].into_iter().collect()),
             minimum_resolution: Some(XYCombo { x: 1920, y: 1280 }),
             ratios: Some(vec![XYCombo { x: 16, y: 9 }].into_iter().collect()),
+            hide_ai_art: Some(true),
         };
         let request = client
             .get("http://test.test/")
             .query(&query_options)
             .build()
             .unwrap();
-        assert_eq!(&request.url().to_string(), "http://test.test/?q=Zero+Two&page=2&purity=011&categories=010&sorting=views&order=desc&apikey=supersecretapikey&seed=seedyroots&resolutions=1920x1280&atleast=1920x1280&ratios=16x9");
+        assert_eq!(&request.url().to_string(), "http://test.test/?q=Zero+Two&page=2&purity=011&categories=010&sorting=views&order=desc&topRange=1w&apikey=supersecretapikey&seed=seedyroots&resolutions=1920x1280&atleast=1920x1280&ratios=16x9&ai_art_filter=1");
     }
 
     #[test]
@@ -542,6 +733,83 @@ mod tests {
         );
     }
 
+    #[test]
+    fn search_options_round_trip() {
+        let query_options = SearchOptions {
+            query: Some("Zero Two".to_string()),
+            purity: Some(Purity {
+                clean: true,
+                sketchy: true,
+                nsfw: false,
+            }),
+            sorting: Some(Sorting::TopList),
+            resolutions: Some(
+                vec![XYCombo { x: 1920, y: 1080 }, XYCombo { x: 2560, y: 1440 }]
+                    .into_iter()
+                    .collect(),
+            ),
+            minimum_resolution: Some(XYCombo { x: 3840, y: 2160 }),
+            hide_ai_art: Some(false),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&query_options).unwrap();
+        let parsed: SearchOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, query_options);
+    }
+
+    #[test]
+    fn xy_combo_from_str() {
+        assert_eq!(
+            "1920x1080".parse::<XYCombo>().unwrap(),
+            XYCombo { x: 1920, y: 1080 }
+        );
+        assert!("1920".parse::<XYCombo>().is_err());
+        assert!("axb".parse::<XYCombo>().is_err());
+    }
+
+    #[test]
+    fn user_settings() {
+        let response: GenericResponse<UserSettings> = serde_json::from_str(
+            r#"{"data":{"thumb_size":"orig","per_page":"24","purity":["sfw","sketchy","nsfw"],"categories":["general","anime","people"],"resolutions":[],"aspect_ratios":[],"toplist_range":"6M","tag_blacklist":[],"user_blacklist":[""]}}"#,
+        )
+        .unwrap();
+        let settings = response.data.unwrap();
+        assert_eq!(settings.purity, ["sfw", "sketchy", "nsfw"]);
+        assert_eq!(settings.per_page, "24");
+        // fields wallhaven might add or drop don't break parsing
+        let sparse: UserSettings = serde_json::from_str(r#"{"purity":["sfw"]}"#).unwrap();
+        assert!(sparse.categories.is_empty());
+    }
+
+    #[test]
+    fn collections() {
+        let response: GenericResponse<Vec<Collection>> = serde_json::from_str(
+            r#"{"data":[{"id":15,"label":"Default","views":38,"public":1,"count":10},{"id":17,"label":"Cats","views":2,"public":0,"count":1}]}"#,
+        )
+        .unwrap();
+        let collections = response.data.unwrap();
+        assert_eq!(collections.len(), 2);
+        assert_eq!(collections[0].label, "Default");
+        assert_eq!(collections[0].count, 10);
+        assert_eq!(collections[1].public, 0);
+    }
+
+    #[test]
+    fn wallpaper_uploader() {
+        let response: GenericResponse<ListingData> = serde_json::from_str(
+            r##"{"data":{"id":"94x38z","url":"https://wallhaven.cc/w/94x38z","short_url":"https://whvn.cc/94x38z","views":6,"favorites":0,"source":"","purity":"sfw","category":"anime","dimension_x":6742,"dimension_y":3534,"resolution":"6742x3534","ratio":"1.91","file_size":5070446,"file_type":"image/jpeg","created_at":"2018-10-31 01:23:10","colors":["#000000"],"path":"https://w.wallhaven.cc/full/94/wallhaven-94x38z.jpg","thumbs":{"large":"","original":"","small":""},"uploader":{"username":"test-user","group":"User","avatar":{"200px":"https://wallhaven.cc/images/user/avatar/200/11_3339efb2a813.png","128px":"https://wallhaven.cc/images/user/avatar/128/11_3339efb2a813.png","32px":"https://wallhaven.cc/images/user/avatar/32/11_3339efb2a813.png","20px":"https://wallhaven.cc/images/user/avatar/20/11_3339efb2a813.png"}},"tags":[{"id":1,"name":"anime"}]}}"##,
+        )
+        .unwrap();
+        let listing = response.data.unwrap();
+        let uploader = listing.uploader.unwrap();
+        assert_eq!(uploader.username, "test-user");
+        assert_eq!(
+            uploader.avatar.medium,
+            "https://wallhaven.cc/images/user/avatar/128/11_3339efb2a813.png"
+        );
+        assert_eq!(listing.tags[0].name, "anime");
+    }
+
     #[test]
     fn sorting_order() {
         let query_options = SearchOptions {
@@ -560,4 +828,11 @@ mod tests {
             "http://test.test/?sorting=views&order=asc"
         );
     }
+
+    #[test]
+    fn saved_top_list_sorting() {
+        let sorting: Sorting = serde_json::from_str(r#""top_list""#).unwrap();
+        assert_eq!(sorting, Sorting::TopList);
+        assert_eq!(serde_json::to_string(&sorting).unwrap(), r#""toplist""#);
+    }
 }
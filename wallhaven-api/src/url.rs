@@ -0,0 +1,193 @@
+use crate::types::{SearchOptions, Sorting};
+use crate::{WHResult, WallhavenApiClientError};
+use reqwest::Url;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A link to wallhaven.cc that was understood by the parser
+#[derive(Debug, Clone, PartialEq)]
+pub enum WallhavenUrl {
+    /// Search page such as `https://wallhaven.cc/search?q=cats&purity=100`
+    Search(SearchOptions),
+    /// Single wallpaper page such as `https://wallhaven.cc/w/j3m8y5`, holds the wallpaper id
+    Wallpaper(String),
+}
+
+impl FromStr for WallhavenUrl {
+    type Err = WallhavenApiClientError;
+
+    /// Parses a wallhaven.cc link, the scheme may be left out
+    ///
+    /// # Example Usage
+    /// ```
+    /// use wallhaven_api::url::WallhavenUrl;
+    ///
+    /// let url: WallhavenUrl = "https://wallhaven.cc/w/j3m8y5".parse().unwrap();
+    /// assert_eq!(url, WallhavenUrl::Wallpaper("j3m8y5".to_string()));
+    /// ```
+    fn from_str(s: &str) -> WHResult<Self> {
+        let s = s.trim();
+        let url = match Url::parse(s) {
+            Ok(url) => url,
+            Err(_) => Url::parse(&format!("https://{}", s))
+                .map_err(|_| WallhavenApiClientError::InvalidUrl)?,
+        };
+        let host = url.host_str().ok_or(WallhavenApiClientError::InvalidUrl)?;
+        let segments: Vec<_> = url
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        match (host.trim_start_matches("www."), segments.as_slice()) {
+            ("wallhaven.cc", ["w", id]) | ("whvn.cc", [id]) => {
+                Ok(WallhavenUrl::Wallpaper(id.to_string()))
+            }
+            // direct link to the full size image, `/full/j3/wallhaven-j3m8y5.jpg`
+            ("w.wallhaven.cc", ["full", _, file]) => file
+                .strip_prefix("wallhaven-")
+                .and_then(|file| file.split('.').next())
+                .map(|id| WallhavenUrl::Wallpaper(id.to_string()))
+                .ok_or(WallhavenApiClientError::InvalidUrl),
+            ("wallhaven.cc", path) => {
+                let mut options: SearchOptions =
+                    serde_urlencoded::from_str(url.query().unwrap_or_default())
+                        .map_err(|_| WallhavenApiClientError::InvalidUrl)?;
+                match path {
+                    [] | ["search"] => {}
+                    ["toplist"] => options.sorting = Some(Sorting::TopList),
+                    ["latest"] => options.sorting = Some(Sorting::DateAdded),
+                    ["random"] => options.sorting = Some(Sorting::Random),
+                    ["tag", id] => options.query = Some(format!("id:{}", id)),
+                    _ => return Err(WallhavenApiClientError::InvalidUrl),
+                }
+                Ok(WallhavenUrl::Search(options))
+            }
+            _ => Err(WallhavenApiClientError::InvalidUrl),
+        }
+    }
+}
+
+impl Display for WallhavenUrl {
+    /// The page on wallhaven.cc, a search leaves out the api key so the link can be shared
+    ///
+    /// # Example Usage
+    /// ```
+    /// use wallhaven_api::url::WallhavenUrl;
+    /// use wallhaven_api::types::SearchOptions;
+    ///
+    /// let url = WallhavenUrl::Search(SearchOptions {
+    ///     query: Some("zero two".to_string()),
+    ///     api_key: Some("secret".to_string()),
+    ///     ..Default::default()
+    /// });
+    /// assert_eq!(url.to_string(), "https://wallhaven.cc/search?q=zero+two");
+    /// ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WallhavenUrl::Wallpaper(id) => write!(f, "https://wallhaven.cc/w/{}", id),
+            WallhavenUrl::Search(options) => {
+                let options = SearchOptions {
+                    api_key: None,
+                    ..options.clone()
+                };
+                let query = serde_urlencoded::to_string(&options).map_err(|_| std::fmt::Error)?;
+                match query.is_empty() {
+                    true => write!(f, "https://wallhaven.cc/search"),
+                    false => write!(f, "https://wallhaven.cc/search?{}", query),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Purity, SearchOptions, Sorting, TopRange, XYCombo};
+    use crate::url::WallhavenUrl;
+
+    #[test]
+    fn parse_wallpaper_urls() {
+        let expected = WallhavenUrl::Wallpaper("j3m8y5".to_string());
+        for url in [
+            "https://wallhaven.cc/w/j3m8y5",
+            "wallhaven.cc/w/j3m8y5/",
+            "https://whvn.cc/j3m8y5",
+            "https://w.wallhaven.cc/full/j3/wallhaven-j3m8y5.jpg",
+        ] {
+            assert_eq!(url.parse::<WallhavenUrl>().unwrap(), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn parse_search_urls() {
+        let url: WallhavenUrl =
+            "https://wallhaven.cc/search?q=zero+two&purity=110&sorting=toplist&atleast=1920x1080&topRange=1M"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            url,
+            WallhavenUrl::Search(SearchOptions {
+                query: Some("zero two".to_string()),
+                purity: Some(Purity {
+                    clean: true,
+                    sketchy: true,
+                    nsfw: false
+                }),
+                sorting: Some(Sorting::TopList),
+                top_range: Some(TopRange::OneMonth),
+                minimum_resolution: Some(XYCombo { x: 1920, y: 1080 }),
+                ..Default::default()
+            })
+        );
+        let url: WallhavenUrl = "https://wallhaven.cc/latest".parse().unwrap();
+        assert_eq!(
+            url,
+            WallhavenUrl::Search(SearchOptions {
+                sorting: Some(Sorting::DateAdded),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn search_url_round_trip() {
+        let options = SearchOptions {
+            query: Some("cats -dogs".to_string()),
+            page: Some(3),
+            purity: Some(Purity {
+                clean: true,
+                sketchy: true,
+                nsfw: false,
+            }),
+            sorting: Some(Sorting::Random),
+            seed: Some("aB3dE9".to_string()),
+            resolutions: Some([XYCombo { x: 1920, y: 1080 }].into()),
+            ..Default::default()
+        };
+        let link = WallhavenUrl::Search(SearchOptions {
+            api_key: Some("secret".to_string()),
+            ..options.clone()
+        })
+        .to_string();
+        assert!(!link.contains("secret"), "{}", link);
+        assert_eq!(
+            link.parse::<WallhavenUrl>().unwrap(),
+            WallhavenUrl::Search(options)
+        );
+        let wallpaper = WallhavenUrl::Wallpaper("j3m8y5".to_string());
+        assert_eq!(
+            wallpaper.to_string().parse::<WallhavenUrl>().unwrap(),
+            wallpaper
+        );
+    }
+
+    #[test]
+    fn reject_other_urls() {
+        assert!("cats".parse::<WallhavenUrl>().is_err());
+        assert!("https://example.com/w/j3m8y5"
+            .parse::<WallhavenUrl>()
+            .is_err());
+        assert!("https://wallhaven.cc/user/someone"
+            .parse::<WallhavenUrl>()
+            .is_err());
+    }
+}